@@ -0,0 +1,400 @@
+//! A persistent key-value store backed by a directory of sstable files.
+//!
+//! # Layout
+//!
+//! ```log
+//! <dir>
+//! ├── MANIFEST        the ids of the live sstables, from the oldest to the newest
+//! ├── 000001.sst
+//! ├── 000002.sst
+//! └── ...
+//! ```
+//!
+//! New writes are buffered in the mem table until [DirKvStore::flush] is called,
+//! which writes them into a new sstable file. Opening the store only reads the
+//! manifest and the block meta of each sstable; the blocks are read from the files
+//! when they are accessed.
+//!
+//! When the number of sstables exceeds [DirKvConfig::max_table_num], all the tables
+//! are merged into a single one.
+use std::{
+    fs,
+    io::Write,
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+
+use bytes::{Buf, BufMut, Bytes};
+use loro_common::{LoroError, LoroResult};
+
+use crate::{
+    compress::CompressionType,
+    mem_store::MemKvConfig,
+    sstable::{SsTable, XXH_SEED},
+    utils::io_err,
+    MemKvStore,
+};
+
+const MANIFEST_FILE: &str = "MANIFEST";
+const MANIFEST_TMP_FILE: &str = "MANIFEST.tmp";
+const SSTABLE_EXT: &str = "sst";
+const MANIFEST_MAGIC_BYTES: [u8; 4] = *b"LRKV";
+const MANIFEST_VERSION: u8 = 0;
+
+#[derive(Debug)]
+pub struct DirKvStore {
+    dir: PathBuf,
+    store: MemKvStore,
+    /// The file ids of the sstables in `store`, from the oldest to the newest
+    table_ids: Vec<u64>,
+    next_table_id: u64,
+    max_table_num: usize,
+}
+
+pub struct DirKvConfig {
+    block_size: usize,
+    compression_type: CompressionType,
+    max_table_num: usize,
+}
+
+impl Default for DirKvConfig {
+    fn default() -> Self {
+        Self {
+            block_size: MemKvStore::DEFAULT_BLOCK_SIZE,
+            compression_type: CompressionType::LZ4,
+            max_table_num: DirKvStore::DEFAULT_MAX_TABLE_NUM,
+        }
+    }
+}
+
+impl DirKvConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn compression_type(mut self, compression_type: CompressionType) -> Self {
+        self.compression_type = compression_type;
+        self
+    }
+
+    /// The max number of sstables before they are compacted into one
+    pub fn max_table_num(mut self, max_table_num: usize) -> Self {
+        self.max_table_num = max_table_num.max(1);
+        self
+    }
+
+    pub fn open(self, dir: impl AsRef<Path>) -> LoroResult<DirKvStore> {
+        DirKvStore::open_with_config(dir, self)
+    }
+}
+
+impl DirKvStore {
+    pub const DEFAULT_MAX_TABLE_NUM: usize = 8;
+
+    /// Open the store in the given directory. The directory is created if it doesn't exist.
+    pub fn open(dir: impl AsRef<Path>) -> LoroResult<Self> {
+        Self::open_with_config(dir, DirKvConfig::default())
+    }
+
+    pub fn open_with_config(dir: impl AsRef<Path>, config: DirKvConfig) -> LoroResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(io_err)?;
+        let table_ids = match fs::read(dir.join(MANIFEST_FILE)) {
+            Ok(bytes) => decode_manifest(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io_err(e)),
+        };
+
+        let mut store = MemKvConfig::default()
+            .block_size(config.block_size)
+            .compression_type(config.compression_type)
+            .build();
+        let mut tables = Vec::with_capacity(table_ids.len());
+        for &id in table_ids.iter() {
+            tables.push(SsTable::open_file(&table_path(&dir, id), false)?);
+        }
+        store.replace_sstables(tables);
+
+        let mut this = Self {
+            next_table_id: table_ids.iter().max().map_or(1, |x| x + 1),
+            dir,
+            store,
+            table_ids,
+            max_table_num: config.max_table_num,
+        };
+        this.remove_stale_files()?;
+        Ok(this)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn get(&self, key: &[u8]) -> LoroResult<Option<Bytes>> {
+        self.store.try_get(key)
+    }
+
+    pub fn set(&mut self, key: &[u8], value: Bytes) {
+        self.store.set(key, value)
+    }
+
+    pub fn compare_and_swap(
+        &mut self,
+        key: &[u8],
+        old: Option<Bytes>,
+        new: Bytes,
+    ) -> LoroResult<bool> {
+        if self.store.try_get(key)? != old {
+            return Ok(false);
+        }
+
+        self.store.set(key, new);
+        Ok(true)
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        self.store.remove(key)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> LoroResult<bool> {
+        self.store.try_contains_key(key)
+    }
+
+    /// Scan the entries in the range. The iteration stops after yielding the first error.
+    pub fn scan(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = LoroResult<(Bytes, Bytes)>> + '_> {
+        self.store.try_scan(start, end)
+    }
+
+    /// The number of valid keys in the store, it's expensive to call
+    pub fn len(&self) -> LoroResult<usize> {
+        self.scan(Bound::Unbounded, Bound::Unbounded)
+            .try_fold(0, |acc, x| x.map(|_| acc + 1))
+    }
+
+    pub fn is_empty(&self) -> LoroResult<bool> {
+        match self.scan(Bound::Unbounded, Bound::Unbounded).next() {
+            Some(x) => x.map(|_| false),
+            None => Ok(true),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.store.size()
+    }
+
+    /// The number of sstable files in the store
+    pub fn table_num(&self) -> usize {
+        self.table_ids.len()
+    }
+
+    /// Whether there are writes that have not been flushed to the disk
+    pub fn has_unflushed(&self) -> bool {
+        !self.store.mem_table_is_empty()
+    }
+
+    /// Export all the entries as the bytes of a single sstable.
+    ///
+    /// Unlike [MemKvStore::export_all], it doesn't change the layout of the store.
+    pub fn export_all(&self) -> LoroResult<Bytes> {
+        match self.store.merge_tables(true, false)? {
+            Some(table) => table.export_all(),
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    /// Write the bytes of a sstable into the directory as the newest table.
+    ///
+    /// We can import several times, the latter will override the former.
+    pub fn import_all(&mut self, bytes: Bytes) -> LoroResult<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        // Validate the bytes before writing them to the disk
+        SsTable::import_all(bytes.clone(), true)?;
+        let id = self.write_table(&bytes)?;
+        let table = SsTable::open_file(&table_path(&self.dir, id), false)?;
+        let mut table_ids = self.table_ids.clone();
+        table_ids.push(id);
+        if let Err(e) = self.write_manifest(&table_ids) {
+            let _ = fs::remove_file(table_path(&self.dir, id));
+            return Err(e);
+        }
+
+        self.table_ids = table_ids;
+        self.store.push_sstable(table);
+        self.compact_if_needed()
+    }
+
+    /// Persist the unflushed writes into a new sstable file.
+    pub fn flush(&mut self) -> LoroResult<()> {
+        let Some(table) = self.store.mem_table_to_sstable() else {
+            return Ok(());
+        };
+
+        let id = self.write_table(&table.export_all()?)?;
+        let table = SsTable::open_file(&table_path(&self.dir, id), false)?;
+        let mut table_ids = self.table_ids.clone();
+        table_ids.push(id);
+        if let Err(e) = self.write_manifest(&table_ids) {
+            let _ = fs::remove_file(table_path(&self.dir, id));
+            return Err(e);
+        }
+
+        self.table_ids = table_ids;
+        self.store.push_mem_table_as(table);
+        self.compact_if_needed()
+    }
+
+    fn compact_if_needed(&mut self) -> LoroResult<()> {
+        if self.table_ids.len() > self.max_table_num {
+            self.compact()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Merge all the sstable files into one. The unflushed writes are not included.
+    pub fn compact(&mut self) -> LoroResult<()> {
+        if self.table_ids.len() <= 1 {
+            return Ok(());
+        }
+
+        let (table_ids, tables) = match self.store.merge_tables(false, false)? {
+            Some(merged) => {
+                let id = self.write_table(&merged.export_all()?)?;
+                let table = SsTable::open_file(&table_path(&self.dir, id), false)?;
+                (vec![id], vec![table])
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+
+        if let Err(e) = self.write_manifest(&table_ids) {
+            for &id in table_ids.iter() {
+                let _ = fs::remove_file(table_path(&self.dir, id));
+            }
+            return Err(e);
+        }
+
+        let old_ids = std::mem::replace(&mut self.table_ids, table_ids);
+        self.store.replace_sstables(tables);
+        for id in old_ids {
+            // It may fail if the file is still opened by a forked store on some platforms.
+            // The stale file will be removed the next time the store is opened.
+            let _ = fs::remove_file(table_path(&self.dir, id));
+        }
+
+        Ok(())
+    }
+
+    /// Get an in-memory copy of the store.
+    ///
+    /// The flushed tables are shared with this store and still read lazily from the files.
+    pub fn to_mem_store(&self) -> MemKvStore {
+        self.store.clone()
+    }
+
+    fn write_table(&mut self, bytes: &[u8]) -> LoroResult<u64> {
+        let id = self.next_table_id;
+        self.next_table_id += 1;
+        write_file_synced(&table_path(&self.dir, id), bytes).map_err(io_err)?;
+        Ok(id)
+    }
+
+    fn write_manifest(&self, table_ids: &[u64]) -> LoroResult<()> {
+        let tmp = self.dir.join(MANIFEST_TMP_FILE);
+        write_file_synced(&tmp, &encode_manifest(table_ids)).map_err(io_err)?;
+        fs::rename(&tmp, self.dir.join(MANIFEST_FILE)).map_err(io_err)
+    }
+
+    /// Remove the sstable files that are not referenced by the manifest.
+    /// They are left by a failed flush or compaction.
+    fn remove_stale_files(&mut self) -> LoroResult<()> {
+        for entry in fs::read_dir(&self.dir).map_err(io_err)? {
+            let path = entry.map_err(io_err)?.path();
+            if path.extension().and_then(|x| x.to_str()) != Some(SSTABLE_EXT) {
+                continue;
+            }
+
+            let Some(id) = path
+                .file_stem()
+                .and_then(|x| x.to_str())
+                .and_then(|x| x.parse::<u64>().ok())
+            else {
+                continue;
+            };
+
+            self.next_table_id = self.next_table_id.max(id + 1);
+            if !self.table_ids.contains(&id) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn table_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{id:06}.{SSTABLE_EXT}"))
+}
+
+fn write_file_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// ```log
+/// ┌──────────────────────────────────────────────────────────────────────┐
+/// │ Manifest                                                             │
+/// │┌ ─ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─ ─┌ ─ ─ ─ ─ ─┌ ─ ─ ─┌ ─ ─ ─ ─ ─ │
+/// │  Magic Number │  Version   │ Table Num  │ Table Id │ ...  │ checksum ││
+/// ││     u32      │     u8     │    u32     │   u64    │      │   u32     │
+/// │ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ┘─ ─ ─ ┘─ ─ ─ ─ ─ ┘│
+/// └──────────────────────────────────────────────────────────────────────┘
+/// ```
+fn encode_manifest(table_ids: &[u64]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(4 + 1 + 4 + table_ids.len() * 8 + 4);
+    buf.put_slice(&MANIFEST_MAGIC_BYTES);
+    buf.put_u8(MANIFEST_VERSION);
+    buf.put_u32_le(table_ids.len() as u32);
+    for &id in table_ids {
+        buf.put_u64_le(id);
+    }
+    let checksum = xxhash_rust::xxh32::xxh32(&buf, XXH_SEED);
+    buf.put_u32_le(checksum);
+    buf
+}
+
+fn decode_manifest(bytes: &[u8]) -> LoroResult<Vec<u64>> {
+    if bytes.len() < 4 + 1 + 4 + 4 {
+        return Err(LoroError::DecodeError("Invalid manifest".into()));
+    }
+    let (body, mut checksum) = bytes.split_at(bytes.len() - 4);
+    if checksum.get_u32_le() != xxhash_rust::xxh32::xxh32(body, XXH_SEED) {
+        return Err(LoroError::DecodeChecksumMismatchError);
+    }
+    if body[..4] != MANIFEST_MAGIC_BYTES {
+        return Err(LoroError::DecodeError("Invalid magic number".into()));
+    }
+    if body[4] != MANIFEST_VERSION {
+        return Err(LoroError::DecodeError(
+            format!("Invalid manifest version {}", body[4]).into(),
+        ));
+    }
+    let mut body = &body[5..];
+    let len = body.get_u32_le() as usize;
+    if body.len() != len * 8 {
+        return Err(LoroError::DecodeError("Invalid manifest".into()));
+    }
+    Ok((0..len).map(|_| body.get_u64_le()).collect())
+}
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
 use bytes::Bytes;
+use loro_common::LoroError;
 
 /// When you need peek next key and value and next back key and value, use this trait.
 pub trait KvIterator: Debug + DoubleEndedIterator<Item = (Bytes, Bytes)> {
@@ -12,6 +13,10 @@ pub trait KvIterator: Debug + DoubleEndedIterator<Item = (Bytes, Bytes)> {
     fn peek_next_back_value(&self) -> Option<Bytes>;
     fn next_back_(&mut self);
     fn has_next_back(&self) -> bool;
+    /// Take the error that stopped the iterator, if any.
+    fn take_error(&mut self) -> Option<LoroError> {
+        None
+    }
 }
 
 /// Merge multiple iterators into one.
@@ -24,11 +29,28 @@ pub trait KvIterator: Debug + DoubleEndedIterator<Item = (Bytes, Bytes)> {
 #[derive(Debug)]
 pub struct MergeIterator<T: KvIterator> {
     iters: Vec<T>,
+    error: Option<LoroError>,
 }
 
 impl<T: KvIterator> MergeIterator<T> {
     pub fn new(iters: Vec<T>) -> Self {
-        Self { iters }
+        Self { iters, error: None }
+    }
+
+    /// Take the first error that stopped one of the iterators.
+    ///
+    /// The merged result is incomplete once it's set.
+    pub fn take_error(&mut self) -> Option<LoroError> {
+        self.collect_errors();
+        self.error.take()
+    }
+
+    fn collect_errors(&mut self) {
+        for iter in self.iters.iter_mut() {
+            if let Some(e) = iter.take_error() {
+                self.error.get_or_insert(e);
+            }
+        }
     }
 }
 
@@ -69,6 +91,7 @@ impl<T: KvIterator> Iterator for MergeIterator<T> {
         };
 
         if has_to_remove {
+            self.collect_errors();
             self.iters.retain(|x| x.has_next());
         }
         ans
@@ -109,6 +132,7 @@ impl<T: KvIterator> DoubleEndedIterator for MergeIterator<T> {
         };
 
         if has_to_remove {
+            self.collect_errors();
             self.iters.retain(|x| x.has_next_back());
         }
         ans
@@ -133,13 +157,15 @@ mod tests {
         sstable1.add(a.clone(), a.clone());
         sstable1.add(c.clone(), c.clone());
         let sstable1 = sstable1.build();
-        let iter1 = sstable::SsTableIter::new_scan(&sstable1, Bound::Unbounded, Bound::Unbounded);
+        let iter1 =
+            sstable::SsTableIter::new_scan(&sstable1, Bound::Unbounded, Bound::Unbounded).unwrap();
 
         let mut sstable2 = sstable::SsTableBuilder::new(10, CompressionType::LZ4, true);
         sstable2.add(b.clone(), b.clone());
         sstable2.add(d.clone(), d.clone());
         let sstable2 = sstable2.build();
-        let iter2 = sstable::SsTableIter::new_scan(&sstable2, Bound::Unbounded, Bound::Unbounded);
+        let iter2 =
+            sstable::SsTableIter::new_scan(&sstable2, Bound::Unbounded, Bound::Unbounded).unwrap();
 
         let merged_iter = MergeIterator::new(vec![iter1.clone(), iter2.clone()]);
         let ans = merged_iter.collect::<Vec<_>>();
@@ -169,13 +195,15 @@ mod tests {
         sstable1.add(a.clone(), a.clone());
         sstable1.add(c.clone(), c.clone());
         let sstable1 = sstable1.build();
-        let iter1 = sstable::SsTableIter::new_scan(&sstable1, Bound::Unbounded, Bound::Unbounded);
+        let iter1 =
+            sstable::SsTableIter::new_scan(&sstable1, Bound::Unbounded, Bound::Unbounded).unwrap();
 
         let mut sstable2 = sstable::SsTableBuilder::new(10, CompressionType::LZ4, true);
         sstable2.add(a.clone(), a2.clone());
         sstable2.add(d.clone(), d.clone());
         let sstable2 = sstable2.build();
-        let iter2 = sstable::SsTableIter::new_scan(&sstable2, Bound::Unbounded, Bound::Unbounded);
+        let iter2 =
+            sstable::SsTableIter::new_scan(&sstable2, Bound::Unbounded, Bound::Unbounded).unwrap();
 
         let merged_iter = MergeIterator::new(vec![iter1.clone(), iter2.clone()]);
         let ans = merged_iter.collect::<Vec<_>>();
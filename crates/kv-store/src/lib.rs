@@ -135,9 +135,11 @@
 #![allow(clippy::uninlined_format_args)]
 pub mod block;
 pub mod compress;
+pub mod dir_store;
 pub mod iter;
 pub mod mem_store;
 pub mod sstable;
 mod utils;
+pub use dir_store::{DirKvConfig, DirKvStore};
 pub use iter::{KvIterator, MergeIterator};
 pub use mem_store::{MemKvStore, MemStoreIterator};
@@ -3,10 +3,16 @@ use crate::compress::CompressionType;
 use crate::sstable::{SsTable, SsTableBuilder, SsTableIter};
 use crate::{KvIterator, MergeIterator};
 use bytes::Bytes;
+use loro_common::LoroResult;
 
 use std::ops::Bound;
 use std::{cmp::Ordering, collections::BTreeMap};
 
+/// The sstables of a [MemKvStore] live in memory unless they are shared with a
+/// [crate::DirKvStore], so the reads can only fail in that case.
+const READ_ERR: &str =
+    "Failed to read the sstable file. Use the `try_` methods to handle the error";
+
 #[derive(Debug, Clone)]
 pub struct MemKvStore {
     mem_table: BTreeMap<Bytes, Bytes>,
@@ -72,12 +78,21 @@ impl MemKvStore {
         }
     }
 
+    /// # Panics
+    ///
+    /// If a table shared with a [crate::DirKvStore] fails to be read. See [MemKvStore::try_get].
     pub fn get(&self, key: &[u8]) -> Option<Bytes> {
+        self.try_get(key).expect(READ_ERR)
+    }
+
+    /// # Errors
+    /// - [loro_common::LoroError::IoError] if a table shared with a [crate::DirKvStore] fails to be read
+    pub fn try_get(&self, key: &[u8]) -> LoroResult<Option<Bytes>> {
         if let Some(v) = self.mem_table.get(key) {
             if v.is_empty() {
-                return None;
+                return Ok(None);
             }
-            return Some(v.clone());
+            return Ok(Some(v.clone()));
         }
 
         for table in self.ss_table.iter().rev() {
@@ -86,16 +101,16 @@ impl MemKvStore {
             }
             // table.
             let idx = table.find_block_idx(key);
-            let block = table.read_block_cached(idx);
+            let block = table.read_block_cached(idx)?;
             let block_iter = BlockIter::new_seek_to_key(block, key);
             if let Some(k) = block_iter.peek_next_curr_key() {
                 let v = block_iter.peek_next_curr_value().unwrap();
                 if k == key {
-                    return if v.is_empty() { None } else { Some(v) };
+                    return Ok(if v.is_empty() { None } else { Some(v) });
                 }
             }
         }
-        None
+        Ok(None)
     }
 
     pub fn set(&mut self, key: &[u8], value: Bytes) {
@@ -130,48 +145,74 @@ impl MemKvStore {
     /// Check if the key exists in the mem table or the sstable
     ///
     /// If the value is empty, it means the key is deleted
+    ///
+    /// # Panics
+    ///
+    /// If a table shared with a [crate::DirKvStore] fails to be read.
     pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.try_contains_key(key).expect(READ_ERR)
+    }
+
+    pub fn try_contains_key(&self, key: &[u8]) -> LoroResult<bool> {
         if self.mem_table.contains_key(key) {
-            return !self.mem_table.get(key).unwrap().is_empty();
+            return Ok(!self.mem_table.get(key).unwrap().is_empty());
         }
 
         for table in self.ss_table.iter().rev() {
-            if table.contains_key(key) {
-                if let Some(v) = table.get(key) {
-                    return !v.is_empty();
+            if table.contains_key(key)? {
+                if let Some(v) = table.get(key)? {
+                    return Ok(!v.is_empty());
                 }
             }
         }
-        false
+        Ok(false)
     }
 
+    /// # Panics
+    ///
+    /// If a table shared with a [crate::DirKvStore] fails to be read. See [MemKvStore::try_scan].
     pub fn scan(
         &self,
         start: std::ops::Bound<&[u8]>,
         end: std::ops::Bound<&[u8]>,
     ) -> Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> {
+        Box::new(self.try_scan(start, end).map(|x| x.expect(READ_ERR)))
+    }
+
+    /// Scan the entries in the range. The iteration stops after yielding the first error.
+    pub fn try_scan(
+        &self,
+        start: std::ops::Bound<&[u8]>,
+        end: std::ops::Bound<&[u8]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = LoroResult<(Bytes, Bytes)>> + '_> {
         if self.ss_table.is_empty() {
             return Box::new(
                 self.mem_table
                     .range::<[u8], _>((start, end))
                     .filter(|(_, v)| !v.is_empty())
-                    .map(|(k, v)| (k.clone(), v.clone())),
+                    .map(|(k, v)| Ok((k.clone(), v.clone()))),
             );
         }
 
-        Box::new(MemStoreIterator::new(
-            self.mem_table
-                .range::<[u8], _>((start, end))
-                .map(|(k, v)| (k.clone(), v.clone())),
-            MergeIterator::new(
-                self.ss_table
-                    .iter()
-                    .rev()
-                    .map(|table| SsTableIter::new_scan(table, start, end))
-                    .collect(),
-            ),
-            true,
-        ))
+        let iters = match self
+            .ss_table
+            .iter()
+            .rev()
+            .map(|table| SsTableIter::new_scan(table, start, end))
+            .collect::<LoroResult<Vec<_>>>()
+        {
+            Ok(iters) => iters,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        Box::new(TryMemStoreIterator {
+            iter: Some(MemStoreIterator::new(
+                self.mem_table
+                    .range::<[u8], _>((start, end))
+                    .map(|(k, v)| (k.clone(), v.clone())),
+                MergeIterator::new(iters),
+                true,
+            )),
+        })
     }
 
     /// The number of valid keys in the mem table and sstable, it's expensive to call
@@ -195,7 +236,14 @@ impl MemKvStore {
                 .sum::<usize>()
     }
 
+    /// # Panics
+    ///
+    /// If a table shared with a [crate::DirKvStore] fails to be read. See [MemKvStore::try_export_all].
     pub fn export_all(&mut self) -> Bytes {
+        self.try_export_all().expect(READ_ERR)
+    }
+
+    pub fn try_export_all(&mut self) -> LoroResult<Bytes> {
         if self.mem_table.is_empty() && self.ss_table.len() == 1 {
             return self.ss_table[0].export_all();
         }
@@ -204,38 +252,91 @@ impl MemKvStore {
             return self.export_with_encoded_block();
         }
 
-        let mut builder = SsTableBuilder::new(
-            self.block_size,
-            self.compression_type,
-            self.should_encode_none,
-        );
+        let Some(ss) = self.merge_tables(true, self.should_encode_none)? else {
+            return Ok(Bytes::new());
+        };
+        self.mem_table.clear();
+        let ans = ss.export_all()?;
+        let _ = std::mem::replace(&mut self.ss_table, vec![ss]);
+        Ok(ans)
+    }
+
+    /// Merge the sstables (and the mem table if `include_mem_table` is true) into a new sstable.
+    ///
+    /// Return `None` if there is nothing to write.
+    pub(crate) fn merge_tables(
+        &self,
+        include_mem_table: bool,
+        include_none: bool,
+    ) -> LoroResult<Option<SsTable>> {
+        let mut builder = SsTableBuilder::new(self.block_size, self.compression_type, include_none);
+        let mem: Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> = if include_mem_table {
+            Box::new(
+                self.mem_table
+                    .range::<[u8], _>((Bound::Unbounded, Bound::Unbounded))
+                    .map(|(k, v)| (k.clone(), v.clone())),
+            )
+        } else {
+            Box::new(std::iter::empty())
+        };
         // we could use scan() here, we should keep the empty value
-        let iter = MemStoreIterator::new(
-            self.mem_table
-                .range::<[u8], _>((Bound::Unbounded, Bound::Unbounded))
-                .map(|(k, v)| (k.clone(), v.clone())),
+        let mut iter = MemStoreIterator::new(
+            mem,
             MergeIterator::new(
                 self.ss_table
                     .iter()
                     .rev()
                     .map(|table| SsTableIter::new_scan(table, Bound::Unbounded, Bound::Unbounded))
-                    .collect(),
+                    .collect::<LoroResult<_>>()?,
             ),
             false,
         );
 
-        for (k, v) in iter {
+        for (k, v) in iter.by_ref() {
             builder.add(k, v);
         }
 
+        if let Some(e) = iter.sst.take_error() {
+            return Err(e);
+        }
+
         if builder.is_empty() {
-            return Bytes::new();
+            return Ok(None);
         }
+
+        Ok(Some(builder.build()))
+    }
+
+    /// Encode the mem table into a sstable. The deleted keys are kept so that
+    /// the new table can shadow the older ones.
+    pub(crate) fn mem_table_to_sstable(&self) -> Option<SsTable> {
+        if self.mem_table.is_empty() {
+            return None;
+        }
+
+        let mut builder = SsTableBuilder::new(self.block_size, self.compression_type, true);
+        for (k, v) in self.mem_table.iter() {
+            builder.add(k.clone(), v.clone());
+        }
+        Some(builder.build())
+    }
+
+    pub(crate) fn mem_table_is_empty(&self) -> bool {
+        self.mem_table.is_empty()
+    }
+
+    /// Replace the content of the mem table with the given sstable
+    pub(crate) fn push_mem_table_as(&mut self, table: SsTable) {
         self.mem_table.clear();
-        let ss = builder.build();
-        let ans = ss.export_all();
-        let _ = std::mem::replace(&mut self.ss_table, vec![ss]);
-        ans
+        self.ss_table.push(table);
+    }
+
+    pub(crate) fn push_sstable(&mut self, table: SsTable) {
+        self.ss_table.push(table);
+    }
+
+    pub(crate) fn replace_sstables(&mut self, tables: Vec<SsTable>) {
+        self.ss_table = tables;
     }
 
     /// We can import several times, the latter will override the former.
@@ -252,10 +353,10 @@ impl MemKvStore {
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    fn export_with_encoded_block(&mut self) -> Bytes {
+    fn export_with_encoded_block(&mut self) -> LoroResult<Bytes> {
         ensure_cov::notify_cov("kv-store::mem_store::export_with_encoded_block");
         let mut mem_iter = self.mem_table.iter().peekable();
-        let mut sstable_iter = self.ss_table[0].iter();
+        let mut sstable_iter = self.ss_table[0].iter()?;
         let mut builder = SsTableBuilder::new(
             self.block_size,
             self.compression_type,
@@ -327,16 +428,20 @@ impl MemKvStore {
             sstable_iter.next_block();
         }
 
+        if let Some(e) = sstable_iter.take_error() {
+            return Err(e);
+        }
+
         if builder.is_empty() {
-            return Bytes::new();
+            return Ok(Bytes::new());
         }
 
         drop(mem_iter);
         self.mem_table.clear();
         let ss = builder.build();
-        let ans = ss.export_all();
+        let ans = ss.export_all()?;
         let _ = std::mem::replace(&mut self.ss_table, vec![ss]);
-        ans
+        Ok(ans)
     }
 
     #[allow(unused)]
@@ -351,6 +456,44 @@ impl MemKvStore {
     }
 }
 
+/// Yields the error of the sstables and stops, once a read of them fails.
+struct TryMemStoreIterator<'a, T> {
+    iter: Option<MemStoreIterator<T, MergeIterator<SsTableIter<'a>>>>,
+}
+
+impl<T> TryMemStoreIterator<'_, T> {
+    fn check<U>(&mut self, ans: Option<U>) -> Option<LoroResult<U>> {
+        match self.iter.as_mut()?.sst.take_error() {
+            Some(e) => {
+                self.iter = None;
+                Some(Err(e))
+            }
+            None => ans.map(Ok),
+        }
+    }
+}
+
+impl<T> Iterator for TryMemStoreIterator<'_, T>
+where
+    T: DoubleEndedIterator<Item = (Bytes, Bytes)>,
+{
+    type Item = LoroResult<(Bytes, Bytes)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ans = self.iter.as_mut()?.next();
+        self.check(ans)
+    }
+}
+
+impl<T> DoubleEndedIterator for TryMemStoreIterator<'_, T>
+where
+    T: DoubleEndedIterator<Item = (Bytes, Bytes)>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let ans = self.iter.as_mut()?.next_back();
+        self.check(ans)
+    }
+}
+
 #[derive(Debug)]
 pub struct MemStoreIterator<T, S> {
     mem: T,
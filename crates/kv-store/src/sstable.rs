@@ -3,12 +3,19 @@ use crate::{
     block::{Block, BlockBuilder},
     compress::CompressionType,
     iter::KvIterator,
-    utils::{get_u16_le, get_u32_le, get_u8_le},
+    utils::{get_u16_le, get_u32_le, get_u8_le, io_err},
 };
 use bytes::{Buf, BufMut, Bytes};
 use ensure_cov::*;
 use loro_common::{LoroError, LoroResult};
-use std::{fmt::Debug, ops::Bound, sync::Arc};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::{Bound, Range},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 pub(crate) const XXH_SEED: u32 = u32::from_le_bytes(*b"LORO");
 const MAGIC_BYTES: [u8; 4] = *b"LORO";
@@ -284,7 +291,7 @@ impl SsTableBuilder {
             })
             .unwrap_or_default();
         SsTable {
-            data: Bytes::from(buf),
+            data: SsTableData::Mem(Bytes::from(buf)),
             first_key,
             last_key,
             meta: self.meta,
@@ -296,10 +303,52 @@ impl SsTableBuilder {
 
 type BlockCache = quick_cache::sync::Cache<usize, Arc<Block>>;
 
+/// The backing bytes of a [SsTable].
+///
+/// A file-backed table only keeps the block meta in memory and reads the
+/// blocks from the file on demand.
+#[derive(Debug, Clone)]
+enum SsTableData {
+    Mem(Bytes),
+    File(Arc<SsTableFile>),
+}
+
+#[derive(Debug)]
+struct SsTableFile {
+    file: Mutex<File>,
+    len: usize,
+}
+
+impl SsTableFile {
+    fn read(&self, range: Range<usize>) -> std::io::Result<Bytes> {
+        let mut buf = vec![0; range.len()];
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(range.start as u64))?;
+        file.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+}
+
+impl SsTableData {
+    fn len(&self) -> usize {
+        match self {
+            SsTableData::Mem(bytes) => bytes.len(),
+            SsTableData::File(file) => file.len,
+        }
+    }
+
+    fn slice(&self, range: Range<usize>) -> LoroResult<Bytes> {
+        match self {
+            SsTableData::Mem(bytes) => Ok(bytes.slice(range)),
+            SsTableData::File(file) => file.read(range).map_err(io_err),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SsTable {
     // TODO: mmap?
-    data: Bytes,
+    data: SsTableData,
     pub(crate) first_key: Bytes,
     pub(crate) last_key: Bytes,
     meta: Vec<BlockMeta>,
@@ -321,11 +370,13 @@ impl Clone for SsTable {
 }
 
 impl SsTable {
-    pub fn export_all(&self) -> Bytes {
-        self.data.clone()
+    /// # Errors
+    /// - [LoroError::IoError] if the table is backed by a file that fails to be read
+    pub fn export_all(&self) -> LoroResult<Bytes> {
+        self.data.slice(0..self.data.len())
     }
 
-    pub fn iter(&self) -> SsTableIter<'_> {
+    pub fn iter(&self) -> LoroResult<SsTableIter<'_>> {
         SsTableIter::new(self)
     }
 
@@ -341,22 +392,7 @@ impl SsTable {
         if bytes.len() < SIZE_OF_U32 + SIZE_OF_U8 + SIZE_OF_U32 {
             return Err(LoroError::DecodeError("Invalid sstable bytes".into()));
         }
-        let magic_number = u32::from_le_bytes((&bytes[..SIZE_OF_U32]).try_into().unwrap());
-        if magic_number != u32::from_le_bytes(MAGIC_BYTES) {
-            return Err(LoroError::DecodeError("Invalid magic number".into()));
-        }
-        let schema_version = bytes[SIZE_OF_U32];
-        match schema_version {
-            CURRENT_SCHEMA_VERSION => {}
-            _ => {
-                return Err(LoroError::DecodeError(
-                    format!(
-                        "Invalid schema version {schema_version}, current support max version is {CURRENT_SCHEMA_VERSION}"
-                    )
-                    .into(),
-                ))
-            }
-        }
+        Self::check_header(&bytes[..SIZE_OF_U32 + SIZE_OF_U8])?;
         let data_len = bytes.len();
         let meta_offset = (&bytes[data_len - SIZE_OF_U32..]).get_u32_le() as usize;
         if meta_offset >= data_len - SIZE_OF_U32 {
@@ -367,6 +403,72 @@ impl SsTable {
         if check_checksum {
             Self::check_block_checksum(&meta, &bytes, meta_offset)?;
         }
+        Ok(Self::new_with_meta(
+            SsTableData::Mem(bytes),
+            meta,
+            meta_offset,
+        ))
+    }
+
+    /// Open a sstable file without loading its blocks into memory.
+    ///
+    /// Only the header and the block meta are read eagerly. The blocks are read
+    /// from the file when they are accessed.
+    ///
+    /// # Errors
+    /// - [LoroError::IoError]
+    /// - [LoroError::DecodeChecksumMismatchError]
+    /// - [LoroError::DecodeError]
+    pub fn open_file(path: &Path, check_checksum: bool) -> LoroResult<Self> {
+        let file = File::open(path).map_err(io_err)?;
+        let len = file.metadata().map_err(io_err)?.len() as usize;
+        let file = SsTableFile {
+            file: Mutex::new(file),
+            len,
+        };
+        if len < SIZE_OF_U32 + SIZE_OF_U8 + SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid sstable bytes".into()));
+        }
+        let header = file.read(0..SIZE_OF_U32 + SIZE_OF_U8).map_err(io_err)?;
+        Self::check_header(&header)?;
+        let meta_offset = file
+            .read(len - SIZE_OF_U32..len)
+            .map_err(io_err)?
+            .get_u32_le() as usize;
+        if meta_offset >= len - SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
+        let raw_meta = file.read(meta_offset..len - SIZE_OF_U32).map_err(io_err)?;
+        let meta = BlockMeta::decode_meta(&raw_meta)?;
+        if check_checksum {
+            let bytes = file.read(0..meta_offset).map_err(io_err)?;
+            Self::check_block_checksum(&meta, &bytes, meta_offset)?;
+        }
+        Ok(Self::new_with_meta(
+            SsTableData::File(Arc::new(file)),
+            meta,
+            meta_offset,
+        ))
+    }
+
+    fn check_header(header: &[u8]) -> LoroResult<()> {
+        let magic_number = u32::from_le_bytes((&header[..SIZE_OF_U32]).try_into().unwrap());
+        if magic_number != u32::from_le_bytes(MAGIC_BYTES) {
+            return Err(LoroError::DecodeError("Invalid magic number".into()));
+        }
+        let schema_version = header[SIZE_OF_U32];
+        match schema_version {
+            CURRENT_SCHEMA_VERSION => Ok(()),
+            _ => Err(LoroError::DecodeError(
+                format!(
+                    "Invalid schema version {schema_version}, current support max version is {CURRENT_SCHEMA_VERSION}"
+                )
+                .into(),
+            )),
+        }
+    }
+
+    fn new_with_meta(data: SsTableData, meta: Vec<BlockMeta>, meta_offset: usize) -> Self {
         let first_key = meta
             .first()
             .map(|m| m.first_key.clone())
@@ -379,15 +481,14 @@ impl SsTable {
                     .unwrap_or(meta.last().map(|m| m.first_key.clone()).unwrap_or_default())
             })
             .unwrap_or_default();
-        let ans = Self {
-            data: bytes,
+        Self {
+            data,
             first_key,
             last_key,
             meta,
             meta_offset,
             block_cache: BlockCache::new(DEFAULT_CACHE_SIZE),
-        };
-        Ok(ans)
+        }
     }
 
     fn check_block_checksum(
@@ -401,18 +502,19 @@ impl SsTable {
             if offset_end > bytes.len() {
                 return Err(LoroError::DecodeError("Invalid bytes".into()));
             }
-            let raw_block_and_check = bytes.slice(offset..offset_end);
-            let checksum = raw_block_and_check
-                .slice(raw_block_and_check.len() - SIZE_OF_U32..)
-                .get_u32_le();
-            if checksum
-                != xxhash_rust::xxh32::xxh32(
-                    &raw_block_and_check[..raw_block_and_check.len() - SIZE_OF_U32],
-                    XXH_SEED,
-                )
-            {
-                return Err(LoroError::DecodeChecksumMismatchError);
-            }
+            Self::check_raw_block_checksum(&bytes[offset..offset_end])?;
+        }
+        Ok(())
+    }
+
+    fn check_raw_block_checksum(raw_block_and_check: &[u8]) -> LoroResult<()> {
+        if raw_block_and_check.len() < SIZE_OF_U32 {
+            return Err(LoroError::DecodeError("Invalid bytes".into()));
+        }
+        let (raw_block, mut check) =
+            raw_block_and_check.split_at(raw_block_and_check.len() - SIZE_OF_U32);
+        if check.get_u32_le() != xxhash_rust::xxh32::xxh32(raw_block, XXH_SEED) {
+            return Err(LoroError::DecodeChecksumMismatchError);
         }
         Ok(())
     }
@@ -429,54 +531,58 @@ impl SsTable {
             .min(self.meta.len() - 1)
     }
 
-    fn read_block(&self, block_idx: usize) -> Arc<Block> {
+    fn read_block(&self, block_idx: usize) -> LoroResult<Arc<Block>> {
         let offset = self.meta[block_idx].offset;
         let offset_end = self
             .meta
             .get(block_idx + 1)
             .map_or(self.meta_offset, |m| m.offset);
-        let raw_block_and_check = self.data.slice(offset..offset_end);
-        Arc::new(Block::decode(
+        let raw_block_and_check = self.data.slice(offset..offset_end)?;
+        // The blocks of a file-backed table are not verified when the table is opened,
+        // so we verify each of them when it is first loaded from the disk.
+        if matches!(self.data, SsTableData::File(_)) {
+            Self::check_raw_block_checksum(&raw_block_and_check)?;
+        }
+        Ok(Arc::new(Block::decode(
             raw_block_and_check,
             self.meta[block_idx].is_large,
             self.meta[block_idx].first_key.clone(),
             self.meta[block_idx].compression_type,
-        ))
+        )))
     }
 
-    pub(crate) fn read_block_cached(&self, block_idx: usize) -> Arc<Block> {
+    /// # Errors
+    /// - [LoroError::IoError] if the table is backed by a file that fails to be read
+    /// - [LoroError::DecodeChecksumMismatchError] if a block read from the file is corrupted
+    pub(crate) fn read_block_cached(&self, block_idx: usize) -> LoroResult<Arc<Block>> {
         self.block_cache
-            .get_or_insert_with(&block_idx, || {
-                Ok::<_, LoroError>(self.read_block(block_idx))
-            })
-            .unwrap()
+            .get_or_insert_with(&block_idx, || self.read_block(block_idx))
     }
 
-    pub fn contains_key(&self, key: &[u8]) -> bool {
+    pub fn contains_key(&self, key: &[u8]) -> LoroResult<bool> {
         if self.first_key > key || self.last_key < key {
-            return false;
+            return Ok(false);
         }
         let idx = self.find_block_idx(key);
-        let block = self.read_block_cached(idx);
+        let block = self.read_block_cached(idx)?;
         let block_iter = BlockIter::new_seek_to_key(block, key);
-        block_iter.peek_next_curr_key() == Some(Bytes::copy_from_slice(key))
+        Ok(block_iter.peek_next_curr_key() == Some(Bytes::copy_from_slice(key)))
     }
 
-    #[allow(unused)]
-    pub fn get(&self, key: &[u8]) -> Option<Bytes> {
+    pub fn get(&self, key: &[u8]) -> LoroResult<Option<Bytes>> {
         if self.first_key > key || self.last_key < key {
-            return None;
+            return Ok(None);
         }
         let idx = self.find_block_idx(key);
-        let block = self.read_block_cached(idx);
+        let block = self.read_block_cached(idx)?;
         let block_iter = BlockIter::new_seek_to_key(block, key);
-        block_iter.peek_next_curr_key().and_then(|k| {
+        Ok(block_iter.peek_next_curr_key().and_then(|k| {
             if k == key {
                 block_iter.peek_next_curr_value()
             } else {
                 None
             }
-        })
+        }))
     }
 
     pub fn data_size(&self) -> usize {
@@ -494,6 +600,8 @@ pub struct SsTableIter<'a> {
     iter: SsTableIterInner,
     next_block_idx: usize,
     back_block_idx: isize,
+    /// The error of the last failed block read. The iterator is finished once it's set.
+    error: Option<LoroError>,
 }
 
 impl Debug for SsTableIter<'_> {
@@ -507,29 +615,35 @@ impl Debug for SsTableIter<'_> {
 }
 
 impl<'a> SsTableIter<'a> {
-    fn new(table: &'a SsTable) -> Self {
+    fn new(table: &'a SsTable) -> LoroResult<Self> {
         Self::new_scan(table, Bound::Unbounded, Bound::Unbounded)
     }
 
-    pub fn new_scan(table: &'a SsTable, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self {
+    /// # Errors
+    /// - [LoroError::IoError] if the table is backed by a file that fails to be read
+    pub fn new_scan(
+        table: &'a SsTable,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> LoroResult<Self> {
         let (table_idx, mut iter, excluded) = match start {
             Bound::Included(start) => {
                 notify_cov("kv-store::SstableIter::new_scan::start included");
                 let idx = table.find_block_idx(start);
-                let block = table.read_block_cached(idx);
+                let block = table.read_block_cached(idx)?;
                 let iter = BlockIter::new_seek_to_key(block, start);
                 (idx, iter, None)
             }
             Bound::Excluded(start) => {
                 notify_cov("kv-store::SstableIter::new_scan::start excluded");
                 let idx = table.find_block_idx(start);
-                let block = table.read_block_cached(idx);
+                let block = table.read_block_cached(idx)?;
                 let iter = BlockIter::new_seek_to_key(block, start);
                 (idx, iter, Some(start))
             }
             Bound::Unbounded => {
                 notify_cov("kv-store::SstableIter::new_scan::start unbounded");
-                let block = table.read_block_cached(0);
+                let block = table.read_block_cached(0)?;
                 let iter = BlockIter::new(block);
                 (0, iter, None)
             }
@@ -546,7 +660,7 @@ impl<'a> SsTableIter<'a> {
                     }
                     (end_idx, None, None)
                 } else {
-                    let block = table.read_block_cached(end_idx);
+                    let block = table.read_block_cached(end_idx)?;
                     let iter = BlockIter::new_back_to_key(block, end);
                     (end_idx, Some(iter), None)
                 }
@@ -562,7 +676,7 @@ impl<'a> SsTableIter<'a> {
                     }
                     (end_idx, None, Some(end))
                 } else {
-                    let block = table.read_block_cached(end_idx);
+                    let block = table.read_block_cached(end_idx)?;
                     let iter = BlockIter::new_back_to_key(block, end);
                     (end_idx, Some(iter), Some(end))
                 }
@@ -574,7 +688,7 @@ impl<'a> SsTableIter<'a> {
                     notify_cov("kv-store::SstableIter::new_scan::unbounded equal");
                     (end_idx, None, None)
                 } else {
-                    let block = table.read_block_cached(end_idx);
+                    let block = table.read_block_cached(end_idx)?;
                     let iter = BlockIter::new(block);
                     (end_idx, Some(iter), None)
                 }
@@ -591,6 +705,7 @@ impl<'a> SsTableIter<'a> {
                 },
                 next_block_idx: table_idx,
                 back_block_idx: end_idx as isize,
+                error: None,
             }
         } else {
             debug_assert!(end_idx == table_idx);
@@ -599,6 +714,7 @@ impl<'a> SsTableIter<'a> {
                 iter: SsTableIterInner::Same(iter),
                 next_block_idx: table_idx,
                 back_block_idx: end_idx as isize,
+                error: None,
             }
         };
         // the current iter may be empty, but has next iter. we need to skip the empty iter
@@ -615,7 +731,32 @@ impl<'a> SsTableIter<'a> {
                 ans.next_back();
             }
         }
-        ans
+        match ans.error.take() {
+            Some(e) => Err(e),
+            None => Ok(ans),
+        }
+    }
+
+    /// Take the error of the last failed block read.
+    ///
+    /// The iterator stops at the first failed read, so it should be checked
+    /// when the iterator ends.
+    pub fn take_error(&mut self) -> Option<LoroError> {
+        self.error.take()
+    }
+
+    /// Read the block at `block_idx`. On failure, the error is kept and the iterator is finished.
+    fn read_block_or_finish(&mut self, block_idx: usize) -> Option<Arc<Block>> {
+        match self.table.read_block_cached(block_idx) {
+            Ok(block) => Some(block),
+            Err(e) => {
+                self.error = Some(e);
+                self.iter.front_iter_mut().finish();
+                self.iter.back_iter_mut().finish();
+                self.back_block_idx = self.next_block_idx as isize;
+                None
+            }
+        }
     }
 
     fn skip_next_empty(&mut self) {
@@ -680,7 +821,9 @@ impl<'a> SsTableIter<'a> {
             if this.next_block_idx == this.back_block_idx as usize && !this.iter.is_same() {
                 this.iter.convert_back_as_same();
             } else if this.next_block_idx < this.table.meta.len() {
-                let block = this.table.read_block_cached(this.next_block_idx);
+                let Some(block) = this.read_block_or_finish(this.next_block_idx) else {
+                    return;
+                };
                 this.iter.reset_front(BlockIter::new(block));
                 this.skip_next_empty();
             } else {
@@ -701,7 +844,9 @@ impl<'a> SsTableIter<'a> {
         if self.next_block_idx == self.back_block_idx as usize && !self.iter.is_same() {
             self.iter.convert_back_as_same();
         } else if self.next_block_idx < self.table.meta.len() {
-            let block = self.table.read_block_cached(self.next_block_idx);
+            let Some(block) = self.read_block_or_finish(self.next_block_idx) else {
+                return;
+            };
             self.iter.reset_front(BlockIter::new(block));
             self.skip_next_empty();
         } else {
@@ -720,7 +865,9 @@ impl<'a> SsTableIter<'a> {
             if self.next_block_idx == self.back_block_idx as usize && !self.iter.is_same() {
                 self.iter.convert_front_as_same();
             } else if self.back_block_idx > 0 {
-                let block = self.table.read_block_cached(self.back_block_idx as usize);
+                let Some(block) = self.read_block_or_finish(self.back_block_idx as usize) else {
+                    return;
+                };
                 self.iter.reset_back(BlockIter::new(block));
                 self.skip_next_back_empty();
             }
@@ -768,6 +915,10 @@ impl KvIterator for SsTableIter<'_> {
     fn has_next_back(&self) -> bool {
         self.has_next_back()
     }
+
+    fn take_error(&mut self) -> Option<LoroError> {
+        self.take_error()
+    }
 }
 
 impl Iterator for SsTableIter<'_> {
@@ -1047,7 +1198,7 @@ mod test {
         builder.add(Bytes::from_static(b"key2"), Bytes::from_static(b"value2"));
         builder.add(Bytes::from_static(b"key3"), Bytes::from_static(b"value3"));
         let table = builder.build();
-        let mut iter = table.iter();
+        let mut iter = table.iter().unwrap();
         let (k1, v1) = Iterator::next(&mut iter).unwrap();
         let (k3, v3) = DoubleEndedIterator::next_back(&mut iter).unwrap();
         let (k2, v2) = Iterator::next(&mut iter).unwrap();
@@ -1070,7 +1221,7 @@ mod test {
         builder.add(Bytes::from_static(b"key5"), Bytes::new());
         builder.add(Bytes::from_static(b"key3"), Bytes::from_static(b"value3"));
         let table = builder.build();
-        let mut iter = table.iter();
+        let mut iter = table.iter().unwrap();
         let (k1, v1) = Iterator::next(&mut iter).unwrap();
         let (k3, v3) = DoubleEndedIterator::next_back(&mut iter).unwrap();
         let (k4, v4) = Iterator::next(&mut iter).unwrap();
@@ -1099,8 +1250,9 @@ mod test {
         builder.add(Bytes::from_static(b"key5"), Bytes::new());
         builder.add(Bytes::from_static(b"key3"), Bytes::from_static(b"value3"));
         let table = builder.build();
-        assert!(table.contains_key(b"key1"));
-        let mut iter = SsTableIter::new_scan(&table, Bound::Excluded(b"key1"), Bound::Unbounded);
+        assert!(table.contains_key(b"key1").unwrap());
+        let mut iter =
+            SsTableIter::new_scan(&table, Bound::Excluded(b"key1"), Bound::Unbounded).unwrap();
         let (k3, v3) = DoubleEndedIterator::next_back(&mut iter).unwrap();
         let (k4, v4) = Iterator::next(&mut iter).unwrap();
         let (k5, v5) = DoubleEndedIterator::next_back(&mut iter).unwrap();
@@ -1125,7 +1277,7 @@ mod test {
         builder.add(Bytes::from_static(b"key2"), Bytes::from_static(b"value2"));
         builder.add(Bytes::from_static(b"key3"), Bytes::from_static(b"value3"));
         let original_table = builder.build();
-        let mut buffer = original_table.export_all().to_vec();
+        let mut buffer = original_table.export_all().unwrap().to_vec();
         buffer[11] = 123;
         assert!(SsTable::import_all(buffer.into(), true).is_err());
     }
@@ -22,3 +22,7 @@ pub(crate) fn get_u16_le(bytes: &[u8]) -> LoroResult<(u16, &[u8])> {
     let ans = u16::from_le_bytes(bytes[..2].try_into().unwrap());
     Ok((ans, &bytes[2..]))
 }
+
+pub(crate) fn io_err(e: std::io::Error) -> LoroError {
+    LoroError::IoError(e.to_string().into_boxed_str())
+}
@@ -1,6 +1,7 @@
 #![allow(unexpected_cfgs)]
 use bytes::Bytes;
-use loro_kv_store::{mem_store::MemKvConfig, MemKvStore};
+use loro_common::{LoroError, LoroResult};
+use loro_kv_store::{mem_store::MemKvConfig, DirKvConfig, DirKvStore, MemKvStore};
 
 #[ctor::ctor]
 fn init() {
@@ -131,3 +132,156 @@ fn export_import_edit_export() {
     assert_eq!(new_new_store.get(b"b99"), Some(Bytes::from_static(b"2")));
     assert_eq!(new_new_store.get(b"a"), Some(Bytes::from_static(b"2")));
 }
+
+fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("loro-kv-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn dir_store_flush_reopen() {
+    let dir = test_dir("flush_reopen");
+    let mut store = DirKvStore::open(&dir).unwrap();
+    store.set(b"a", Bytes::from_static(b"1"));
+    store.set(b"b", Bytes::from_static(b"2"));
+    store.flush().unwrap();
+    store.remove(b"a");
+    store.set(b"c", Bytes::from_static(b"3"));
+    store.flush().unwrap();
+    store.set(b"d", Bytes::from_static(b"4"));
+    assert_eq!(store.table_num(), 2);
+    // not flushed
+    drop(store);
+
+    let store = DirKvStore::open(&dir).unwrap();
+    assert_eq!(store.get(b"a").unwrap(), None);
+    assert_eq!(store.get(b"b").unwrap(), Some(Bytes::from_static(b"2")));
+    assert_eq!(store.get(b"c").unwrap(), Some(Bytes::from_static(b"3")));
+    assert_eq!(store.get(b"d").unwrap(), None);
+    assert_eq!(
+        store
+            .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+            .collect::<LoroResult<Vec<_>>>()
+            .unwrap(),
+        vec![
+            (Bytes::from_static(b"b"), Bytes::from_static(b"2")),
+            (Bytes::from_static(b"c"), Bytes::from_static(b"3")),
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dir_store_compaction() {
+    let dir = test_dir("compaction");
+    let mut store = DirKvConfig::new().max_table_num(3).open(&dir).unwrap();
+    for i in 0..10u32 {
+        store.set(&i.to_be_bytes(), Bytes::from(i.to_string()));
+        if i % 2 == 0 {
+            store.remove(&(i / 2).to_be_bytes());
+        }
+        store.flush().unwrap();
+        assert!(store.table_num() <= 3);
+    }
+
+    let expected = store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .collect::<LoroResult<Vec<_>>>()
+        .unwrap();
+    store.compact().unwrap();
+    assert_eq!(store.table_num(), 1);
+    drop(store);
+    let sst_num = std::fs::read_dir(&dir)
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension() == Some("sst".as_ref()))
+        .count();
+    assert_eq!(sst_num, 1);
+
+    let store = DirKvStore::open(&dir).unwrap();
+    let actual = store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .collect::<LoroResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(actual, expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dir_store_import_export() {
+    let mut mem = MemKvStore::new(MemKvConfig::default());
+    for i in 0..3000 {
+        let s = format!("b{i}");
+        mem.set(s.as_bytes(), Bytes::from_static(b"2"));
+    }
+    let bytes = mem.export_all();
+
+    let dir = test_dir("import_export");
+    let mut store = DirKvStore::open(&dir).unwrap();
+    store.import_all(bytes).unwrap();
+    store.set(b"a", Bytes::from_static(b"1"));
+    let exported = store.export_all().unwrap();
+    drop(store);
+
+    let store = DirKvStore::open(&dir).unwrap();
+    assert_eq!(store.get(b"a").unwrap(), None);
+    assert_eq!(store.get(b"b2999").unwrap(), Some(Bytes::from_static(b"2")));
+
+    let mut new_store = MemKvStore::new(MemKvConfig::default());
+    new_store.import_all(exported).unwrap();
+    assert_eq!(new_store.get(b"a"), Some(Bytes::from_static(b"1")));
+    assert_eq!(new_store.len(), 3001);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dir_store_read_errors() {
+    let dir = test_dir("read_errors");
+    let mut store = DirKvConfig::new().block_size(64).open(&dir).unwrap();
+    for i in 0..100u32 {
+        store.set(&i.to_be_bytes(), Bytes::from(i.to_string()));
+    }
+    store.flush().unwrap();
+    drop(store);
+    let table = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.extension() == Some("sst".as_ref()))
+        .unwrap();
+
+    let mut bytes = std::fs::read(&table).unwrap();
+    let len = bytes.len() as u64;
+
+    // The blocks are read lazily, so a file truncated after opening fails on reads
+    let store = DirKvStore::open(&dir).unwrap();
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&table)
+        .unwrap();
+    file.set_len(len / 2).unwrap();
+    assert!(matches!(
+        store.get(&99u32.to_be_bytes()),
+        Err(LoroError::IoError(_))
+    ));
+    let scanned = store
+        .scan(std::ops::Bound::Unbounded, std::ops::Bound::Unbounded)
+        .collect::<Vec<_>>();
+    assert!(matches!(scanned.last(), Some(Err(LoroError::IoError(_)))));
+    assert!(store.export_all().is_err());
+    drop(store);
+
+    // A corrupted block is rejected when it is first read
+    bytes[10] ^= 1;
+    std::fs::write(&table, &bytes).unwrap();
+    let store = DirKvStore::open(&dir).unwrap();
+    assert!(matches!(
+        store.get(&0u32.to_be_bytes()),
+        Err(LoroError::DecodeChecksumMismatchError)
+    ));
+    assert_eq!(
+        store.get(&99u32.to_be_bytes()).unwrap(),
+        Some(Bytes::from("99"))
+    );
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -5,7 +5,7 @@ use crate::{ContainerID, InternalString, PeerID, TreeID, ID};
 
 pub type LoroResult<T> = Result<T, LoroError>;
 
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum LoroError {
     #[error("Context's client_id({found:?}) does not match Container's client_id({expected:?})")]
     UnmatchedContext { expected: PeerID, found: PeerID },
@@ -100,9 +100,17 @@ pub enum LoroError {
     ContainersNotFound { containers: Box<Vec<ContainerID>> },
     #[error("Import failed: Deprecated encoding mode")]
     ImportUnsupportedEncodingMode,
+    #[error("IO error ({0})")]
+    IoError(Box<str>),
+    #[error("The blob is encrypted. Use `import_encrypted` to import it")]
+    ImportEncryptedBlob,
+    #[error("Decryption failed ({0})")]
+    DecryptionError(Box<str>),
+    #[error("The signature of the change {0} is invalid")]
+    InvalidSignature(ID),
 }
 
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum LoroTreeError {
     #[error("`Cycle move` occurs when moving tree nodes.")]
     CyclicMoveError,
@@ -129,6 +137,10 @@ pub enum LoroEncodeError {
     ShallowSnapshotIncompatibleWithOldFormat,
     #[error("Cannot export shallow snapshot with unknown container type. Please upgrade the Loro version.")]
     UnknownContainer,
+    #[error("IO error ({0})")]
+    IoError(String),
+    #[error("Encryption failed ({0})")]
+    EncryptionError(String),
 }
 
 #[cfg(feature = "wasm")]
@@ -35,6 +35,8 @@ pub struct Change<O = Op> {
     /// It is the number of seconds that have elapsed since 00:00:00 UTC on 1 January 1970.
     pub(crate) timestamp: Timestamp,
     pub(crate) commit_msg: Option<Arc<str>>,
+    /// The signature of the change, see [crate::signature]
+    pub(crate) signature: Option<Arc<[u8]>>,
     pub(crate) ops: RleVec<[O; 1]>,
 }
 
@@ -75,6 +77,7 @@ impl<O> Change<O> {
             lamport,
             timestamp,
             commit_msg: None,
+            signature: None,
         }
     }
 
@@ -120,6 +123,10 @@ impl<O> Change<O> {
     pub fn message(&self) -> Option<&Arc<str>> {
         self.commit_msg.as_ref()
     }
+
+    pub fn signature(&self) -> Option<&Arc<[u8]>> {
+        self.signature.as_ref()
+    }
 }
 
 impl<O: EstimatedSize> EstimatedSize for Change<O> {
@@ -135,7 +142,8 @@ impl<O: EstimatedSize> EstimatedSize for Change<O> {
             .iter()
             .map(|op| op.estimate_storage_size())
             .sum::<usize>();
-        id_size + lamport_size + timestamp_size + ops_size + deps_size
+        let signature_size = self.signature.as_ref().map_or(0, |s| s.len() + 1);
+        id_size + lamport_size + timestamp_size + ops_size + deps_size + signature_size
     }
 }
 
@@ -254,6 +262,12 @@ impl<O: Mergable + HasLength + HasIndex + Sliceable + HasCounter + Debug> Slicea
             lamport: self.lamport + from as Lamport,
             timestamp: self.timestamp,
             commit_msg: self.commit_msg.clone(),
+            // The signature is only valid for the whole change
+            signature: if from == 0 && to == self.atom_len() {
+                self.signature.clone()
+            } else {
+                None
+            },
         }
     }
 }
@@ -272,6 +286,8 @@ impl Change {
             && other.deps.as_single().unwrap().peer == self.id.peer
             && other.timestamp - self.timestamp <= merge_interval
             && self.commit_msg == other.commit_msg
+            && self.signature.is_none()
+            && other.signature.is_none()
         {
             debug_assert!(other.timestamp >= self.timestamp);
             debug_assert!(other.lamport == self.lamport + self.len() as Lamport);
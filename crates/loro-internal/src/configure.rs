@@ -1,7 +1,8 @@
 use rustc_hash::FxHashSet;
 use loro_common::ContainerID;
 
-pub use crate::container::richtext::config::{StyleConfig, StyleConfigMap};
+pub use crate::container::richtext::config::{OverlapPolicy, StyleConfig, StyleConfigMap};
+use crate::signature::{InvalidSignaturePolicy, SignatureConfig, Signer, Verifier};
 use crate::LoroDoc;
 use std::sync::atomic::{AtomicBool, AtomicI64};
 use std::sync::RwLock;
@@ -15,6 +16,7 @@ pub struct Configure {
     pub(crate) editable_detached_mode: Arc<AtomicBool>,
     pub(crate) deleted_root_containers: Arc<Mutex<FxHashSet<ContainerID>>>,
     pub(crate) hide_empty_root_containers: Arc<AtomicBool>,
+    pub(crate) signature: Arc<RwLock<SignatureConfig>>,
 }
 
 impl LoroDoc {
@@ -23,6 +25,7 @@ impl LoroDoc {
         self.set_record_timestamp(config.record_timestamp());
         self.set_change_merge_interval(config.merge_interval());
         self.set_detached_editing(config.detached_editing());
+        *self.config.signature.write().unwrap() = config.signature_config();
     }
 }
 
@@ -35,6 +38,7 @@ impl Default for Configure {
             merge_interval_in_s: Arc::new(AtomicI64::new(1000)),
             deleted_root_containers: Arc::new(Mutex::new(Default::default())),
             hide_empty_root_containers: Arc::new(AtomicBool::new(false)),
+            signature: Arc::new(RwLock::new(SignatureConfig::default())),
         }
     }
 }
@@ -64,6 +68,7 @@ impl Configure {
                 self.hide_empty_root_containers
                     .load(std::sync::atomic::Ordering::Relaxed),
             )),
+            signature: Arc::new(RwLock::new(self.signature.read().unwrap().clone())),
         }
     }
 
@@ -105,6 +110,24 @@ impl Configure {
         self.hide_empty_root_containers
             .store(hide, std::sync::atomic::Ordering::Relaxed);
     }
+
+    pub fn set_signer(&self, signer: Option<Arc<dyn Signer>>) {
+        self.signature.write().unwrap().signer = signer;
+    }
+
+    pub fn set_verifier(
+        &self,
+        verifier: Option<Arc<dyn Verifier>>,
+        policy: InvalidSignaturePolicy,
+    ) {
+        let mut config = self.signature.write().unwrap();
+        config.verifier = verifier;
+        config.policy = policy;
+    }
+
+    pub(crate) fn signature_config(&self) -> SignatureConfig {
+        self.signature.read().unwrap().clone()
+    }
 }
 
 #[derive(Debug)]
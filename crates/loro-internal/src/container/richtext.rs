@@ -9,6 +9,28 @@
 //! In [crate::op::Op], we always use entity index to persist richtext ops.
 //!
 //! The users of this type can only operate on unicode index or utf16 index, but calculated entity index will be provided.
+//!
+//! # Embeds
+//!
+//! An inline embed (an image, a mention, a child container...) occupies one position in
+//! the text. It's an item of its own in the deltas and the events
+//! ([crate::handler::TextDelta::Embed]) and in the richtext value (`{ "embed": value }`).
+//!
+//! Internally it's stored as a single [EMBED_CHAR] marked over its own range with the
+//! reserved style key [EMBED_STYLE_KEY] whose value is the embed, so it moves with the
+//! text around it under concurrent edits and needs no change to the op or encoding
+//! formats. The reserved style is never exposed as an attribute. A container embed's id
+//! is the id of the style op that embeds it.
+//!
+//! # Annotations
+//!
+//! An annotation (a comment, a suggestion...) is stored as a mark `"$annotation:id"`
+//! ([ANNOTATION_STYLE_PREFIX]), where `id` is the id of its style start op. Deleting text
+//! never deletes style anchors, so when the annotated text is deleted the anchors end up
+//! next to each other and the annotation collapses to an empty range instead of vanishing.
+//! The anchors are addressed by [crate::cursor::Cursor]s with the ids of the style start
+//! and end ops. An annotation is removed by unmarking its key, only the latest style of the
+//! key counts.
 
 pub(crate) mod config;
 mod fugue_span;
@@ -19,7 +41,7 @@ mod tracker;
 
 use crate::{change::Lamport, delta::StyleMeta, utils::string_slice::StringSlice, InternalString};
 use fugue_span::*;
-use loro_common::{Counter, IdFull, IdLp, LoroValue, PeerID, ID};
+use loro_common::{ContainerID, Counter, IdFull, IdLp, LoroValue, PeerID, ID};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
@@ -54,6 +76,21 @@ pub struct StyleOp {
     pub(crate) info: TextStyleInfoFlag,
 }
 
+/// The reserved style key that marks an [EMBED_CHAR] as an inline embed.
+pub const EMBED_STYLE_KEY: &str = "$embed";
+/// The placeholder char of an inline embed in the text.
+pub(crate) const EMBED_CHAR: char = '\u{FFFC}';
+pub(crate) const EMBED_STR: &str = "\u{FFFC}";
+/// The prefix of the reserved style keys of annotations.
+pub const ANNOTATION_STYLE_PREFIX: &str = "$annotation:";
+
+/// Whether the style key is reserved for embeds or annotations.
+///
+/// These styles are built in and never expand.
+pub(crate) fn is_reserved_style_key(key: &str) -> bool {
+    key == EMBED_STYLE_KEY || key.starts_with(ANNOTATION_STYLE_PREFIX)
+}
+
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub(crate) enum StyleKey {
     Key(InternalString),
@@ -83,6 +120,15 @@ impl StyleOp {
         StyleKey::Key(self.key.clone())
     }
 
+    /// The container embedded by this style, if it's a container embed.
+    pub(crate) fn embedded_container(&self) -> Option<&ContainerID> {
+        if &*self.key != EMBED_STYLE_KEY {
+            return None;
+        }
+
+        self.value.as_container()
+    }
+
     #[cfg(test)]
     pub fn new_for_test(n: isize, key: &str, value: LoroValue, info: TextStyleInfoFlag) -> Self {
         Self {
@@ -280,6 +326,7 @@ impl TextStyleInfoFlag {
     pub const BOLD: TextStyleInfoFlag = TextStyleInfoFlag::new(ExpandType::After);
     pub const LINK: TextStyleInfoFlag = TextStyleInfoFlag::new(ExpandType::None);
     pub const COMMENT: TextStyleInfoFlag = TextStyleInfoFlag::new(ExpandType::None);
+    pub const EMBED: TextStyleInfoFlag = TextStyleInfoFlag::new(ExpandType::None);
 
     pub const fn to_byte(&self) -> u8 {
         self.data
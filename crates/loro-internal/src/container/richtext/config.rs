@@ -1,12 +1,16 @@
+use std::sync::Arc;
+
 use rustc_hash::FxHashMap;
 use loro_common::InternalString;
 
-use super::{ExpandType, TextStyleInfoFlag};
+use super::{is_reserved_style_key, ExpandType, TextStyleInfoFlag};
 
 #[derive(Debug, Default, Clone)]
 pub struct StyleConfigMap {
     pub(crate) map: FxHashMap<InternalString, StyleConfig>,
     pub(crate) default_style: Option<StyleConfig>,
+    /// Cached overlap rules of the configs, rebuilt whenever the configs change
+    rules: Option<Arc<OverlapRules>>,
 }
 
 impl StyleConfigMap {
@@ -14,6 +18,7 @@ impl StyleConfigMap {
         Self {
             map: FxHashMap::default(),
             default_style: None,
+            rules: None,
         }
     }
 
@@ -23,10 +28,21 @@ impl StyleConfigMap {
         }
 
         self.map.insert(key, value);
+        self.update_overlap_rules();
+    }
+
+    pub(crate) fn set_map(&mut self, map: FxHashMap<InternalString, StyleConfig>) {
+        self.map = map;
+        self.update_overlap_rules();
+    }
+
+    pub(crate) fn set_default_style(&mut self, style: Option<StyleConfig>) {
+        self.default_style = style;
+        self.update_overlap_rules();
     }
 
     pub fn get(&self, key: &InternalString) -> Option<StyleConfig> {
-        self.map.get(key).copied().or(self.default_style)
+        self.map.get(key).or(self.default_style.as_ref()).cloned()
     }
 
     pub fn get_style_flag(&self, key: &InternalString) -> Option<TextStyleInfoFlag> {
@@ -38,85 +54,130 @@ impl StyleConfigMap {
     }
 
     fn _get_style_flag(&self, key: &InternalString, is_del: bool) -> Option<TextStyleInfoFlag> {
-        let f = |x: StyleConfig| {
+        let f = |x: &StyleConfig| {
             TextStyleInfoFlag::new(if is_del { x.expand.reverse() } else { x.expand })
         };
-        if let Some(index) = key.find(':') {
-            let key = key[..index].into();
-            self.map.get(&key).copied().or(self.default_style).map(f)
-        } else {
-            self.map.get(key).copied().or(self.default_style).map(f)
+        if is_reserved_style_key(key) {
+            return Some(f(&StyleConfig::new()));
         }
+
+        self.get_config_of_key(key).map(f)
+    }
+
+    fn get_config_of_key(&self, key: &str) -> Option<&StyleConfig> {
+        config_of_key(&self.map, self.default_style.as_ref(), key)
+    }
+
+    /// Get the overlap rules of the configs, or `None` if no style has any.
+    ///
+    /// The rules are enforced when the styles are annotated on the text, see
+    /// [OverlapRules::conflicts]. They are cached, so the same `Arc` is returned until
+    /// the configs change.
+    pub(crate) fn overlap_rules(&self) -> Option<Arc<OverlapRules>> {
+        self.rules.clone()
+    }
+
+    fn update_overlap_rules(&mut self) {
+        let has_rules =
+            |x: &StyleConfig| x.overlap == OverlapPolicy::Exclusive || !x.incompatible.is_empty();
+        if !self
+            .map
+            .values()
+            .chain(self.default_style.iter())
+            .any(has_rules)
+        {
+            self.rules = None;
+            return;
+        }
+
+        self.rules = Some(Arc::new(OverlapRules {
+            map: self.map.clone(),
+            default_style: self.default_style.clone(),
+        }));
     }
 
     pub fn default_rich_text_config() -> Self {
-        let mut map = Self {
-            map: FxHashMap::default(),
-            default_style: None,
-        };
+        let mut map = Self::new();
 
-        map.map.insert(
-            "bold".into(),
-            StyleConfig {
-                expand: ExpandType::After,
-            },
-        );
+        map.map
+            .insert("bold".into(), StyleConfig::new().expand(ExpandType::After));
 
         map.map.insert(
             "italic".into(),
-            StyleConfig {
-                expand: ExpandType::After,
-            },
+            StyleConfig::new().expand(ExpandType::After),
         );
 
         map.map.insert(
             "underline".into(),
-            StyleConfig {
-                expand: ExpandType::After,
-            },
+            StyleConfig::new().expand(ExpandType::After),
         );
 
-        map.map.insert(
-            "link".into(),
-            StyleConfig {
-                expand: ExpandType::None,
-            },
-        );
+        map.map
+            .insert("link".into(), StyleConfig::new().expand(ExpandType::None));
 
         map.map.insert(
             "highlight".into(),
-            StyleConfig {
-                expand: ExpandType::None,
-            },
+            StyleConfig::new().expand(ExpandType::None),
         );
 
         map.map.insert(
             "comment".into(),
-            StyleConfig {
-                expand: ExpandType::None,
-            },
+            StyleConfig::new().expand(ExpandType::None),
         );
 
-        map.map.insert(
-            "code".into(),
-            StyleConfig {
-                expand: ExpandType::None,
-            },
-        );
+        map.map
+            .insert("code".into(), StyleConfig::new().expand(ExpandType::None));
 
         map
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The config of a style key.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StyleConfig {
     pub expand: ExpandType,
+    /// Whether the marks of this style can overlap with each other.
+    pub overlap: OverlapPolicy,
+    /// The style keys that can't overlap with this style. Where they overlap, the
+    /// latest one wins and the older ones are trimmed. E.g. `code` can be incompatible
+    /// with `bold`, so marking `code` clears `bold` in its range.
+    ///
+    /// It's symmetric: it doesn't matter which of the two configs lists the other key.
+    pub incompatible: Vec<InternalString>,
+}
+
+/// Whether the marks of a style can overlap with each other.
+///
+/// All the peers must use the same overlap policies and incompatible keys, otherwise
+/// their documents may not converge.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverlapPolicy {
+    /// The marks can overlap. Where marks of the same key overlap, the latest value wins.
+    #[default]
+    Allow,
+    /// The marks whose keys share this config (e.g. `link`, `link:a` and `link:b`)
+    /// can't overlap. Where they overlap, the latest one wins and the older ones are
+    /// trimmed.
+    Exclusive,
+}
+
+impl OverlapPolicy {
+    /// 'allow'|'exclusive'
+    pub fn try_from_str(s: &str) -> Option<Self> {
+        match s {
+            "allow" => Some(OverlapPolicy::Allow),
+            "exclusive" => Some(OverlapPolicy::Exclusive),
+            _ => None,
+        }
+    }
 }
 
 impl StyleConfig {
     pub fn new() -> Self {
         Self {
             expand: ExpandType::None,
+            overlap: OverlapPolicy::Allow,
+            incompatible: Vec::new(),
         }
     }
 
@@ -124,6 +185,19 @@ impl StyleConfig {
         self.expand = expand;
         self
     }
+
+    pub fn overlap(mut self, overlap: OverlapPolicy) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn incompatible<I: Into<InternalString>>(
+        mut self,
+        keys: impl IntoIterator<Item = I>,
+    ) -> Self {
+        self.incompatible = keys.into_iter().map(Into::into).collect();
+        self
+    }
 }
 
 impl Default for StyleConfig {
@@ -131,3 +205,43 @@ impl Default for StyleConfig {
         Self::new()
     }
 }
+
+/// Get the config of the style key. A key with a `:` uses the config of the part
+/// before it, e.g. `comment:alice` uses the config of `comment`.
+fn config_of_key<'a>(
+    map: &'a FxHashMap<InternalString, StyleConfig>,
+    default_style: Option<&'a StyleConfig>,
+    key: &str,
+) -> Option<&'a StyleConfig> {
+    let key: InternalString = key.split(':').next().unwrap().into();
+    map.get(&key).or(default_style)
+}
+
+/// The overlap rules of the styles, derived from the [StyleConfig]s
+#[derive(Debug)]
+pub(crate) struct OverlapRules {
+    map: FxHashMap<InternalString, StyleConfig>,
+    default_style: Option<StyleConfig>,
+}
+
+impl OverlapRules {
+    /// Whether the marks of the two different keys can't overlap.
+    ///
+    /// Where they overlap, only the latest one is kept. The reserved styles never conflict.
+    pub(crate) fn conflicts(&self, a: &str, b: &str) -> bool {
+        if a == b || is_reserved_style_key(a) || is_reserved_style_key(b) {
+            return false;
+        }
+
+        let family_a = a.split(':').next().unwrap();
+        let family_b = b.split(':').next().unwrap();
+        let config_a = config_of_key(&self.map, self.default_style.as_ref(), a);
+        let config_b = config_of_key(&self.map, self.default_style.as_ref(), b);
+        if family_a == family_b {
+            return config_a.is_some_and(|x| x.overlap == OverlapPolicy::Exclusive);
+        }
+
+        config_a.is_some_and(|x| x.incompatible.iter().any(|k| &**k == family_b))
+            || config_b.is_some_and(|x| x.incompatible.iter().any(|k| &**k == family_a))
+    }
+}
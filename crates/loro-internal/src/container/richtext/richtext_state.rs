@@ -4,7 +4,8 @@ use generic_btree::{
     BTree, BTreeTrait, Cursor, LeafIndex,
 };
 use loro_common::{
-    Counter, IdFull, IdLpSpan, IdSpan, Lamport, LoroError, LoroResult, LoroValue, ID,
+    ContainerID, Counter, IdFull, IdLpSpan, IdSpan, InternalString, Lamport, LoroError, LoroResult,
+    LoroValue, ID,
 };
 use query::{ByteQuery, ByteQueryT};
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -19,6 +20,7 @@ use std::{
     sync::Arc,
 };
 use tracing::instrument;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::{
     container::richtext::style_range_map::EMPTY_STYLES,
@@ -27,13 +29,15 @@ use crate::{
 };
 
 use self::query::{
-    EntityQuery, EntityQueryT, EventIndexQuery, EventIndexQueryT, UnicodeQuery, UnicodeQueryT,
-    Utf16Query, Utf16QueryT,
+    EntityQuery, EntityQueryT, EventIndexQuery, EventIndexQueryT, LineBreakQuery, UnicodeQuery,
+    UnicodeQueryT, Utf16Query, Utf16QueryT,
 };
 
 use super::{
+    config::OverlapRules,
     style_range_map::{IterAnchorItem, StyleRangeMap, Styles},
-    AnchorType, RichtextSpan, StyleOp,
+    AnchorType, RichtextSpan, StyleKey, StyleOp, ANNOTATION_STYLE_PREFIX, EMBED_CHAR,
+    EMBED_STYLE_KEY,
 };
 
 pub(crate) use query::PosType;
@@ -257,6 +261,7 @@ mod text_chunk {
         bytes: BytesSlice,
         unicode_len: i32,
         utf16_len: i32,
+        line_breaks: i32,
         id: IdFull,
     }
 
@@ -266,6 +271,7 @@ mod text_chunk {
                 .field("text", &self.as_str())
                 .field("unicode_len", &self.unicode_len)
                 .field("utf16_len", &self.utf16_len)
+                .field("line_breaks", &self.line_breaks)
                 .field("id", &self.id)
                 .finish()
         }
@@ -275,15 +281,20 @@ mod text_chunk {
         pub fn new(bytes: BytesSlice, id: IdFull) -> Self {
             let mut utf16_len = 0;
             let mut unicode_len = 0;
+            let mut line_breaks = 0;
             for c in std::str::from_utf8(&bytes).unwrap().chars() {
                 utf16_len += c.len_utf16();
                 unicode_len += 1;
+                if c == '\n' {
+                    line_breaks += 1;
+                }
             }
 
             Self {
                 unicode_len,
                 bytes,
                 utf16_len: utf16_len as i32,
+                line_breaks,
                 id,
             }
         }
@@ -330,6 +341,12 @@ mod text_chunk {
             self.utf16_len
         }
 
+        /// The number of `\n` in the text
+        #[inline]
+        pub fn line_breaks(&self) -> i32 {
+            self.line_breaks
+        }
+
         #[inline]
         pub fn event_len(&self) -> i32 {
             if cfg!(feature = "wasm") {
@@ -360,6 +377,7 @@ mod text_chunk {
                 unicode_len: 0,
                 bytes: BytesSlice::empty(),
                 utf16_len: 0,
+                line_breaks: 0,
                 // This is a dummy value.
                 // It's fine because the length is 0. We never actually use this value.
                 id: IdFull::NONE_ID,
@@ -395,6 +413,7 @@ mod text_chunk {
             }
 
             self.utf16_len -= (current_utf16_index - start_utf16_index) as i32;
+            self.line_breaks -= count_line_breaks(&self.bytes[start_byte..end_byte]);
 
             let event_len = if cfg!(feature = "wasm") {
                 current_utf16_index - start_utf16_index
@@ -422,6 +441,7 @@ mod text_chunk {
                     let next = Self::new(next, self.id.inc(end_unicode_index as i32));
                     self.unicode_len -= next.unicode_len;
                     self.utf16_len -= next.utf16_len;
+                    self.line_breaks -= next.line_breaks;
                     self.bytes.slice_(..start_byte);
                     Some(next)
                 }
@@ -441,6 +461,7 @@ mod text_chunk {
                     self.utf16_len,
                     self.as_str().chars().map(|c| c.len_utf16()).sum::<usize>() as i32
                 );
+                assert_eq!(self.line_breaks, count_line_breaks(&self.bytes));
             }
         }
 
@@ -477,6 +498,10 @@ mod text_chunk {
         }
     }
 
+    fn count_line_breaks(bytes: &[u8]) -> i32 {
+        bytes.iter().filter(|&&b| b == b'\n').count() as i32
+    }
+
     impl generic_btree::rle::HasLength for TextChunk {
         fn rle_len(&self) -> usize {
             self.unicode_len as usize
@@ -518,6 +543,7 @@ mod text_chunk {
                 unicode_len: range.len() as i32,
                 bytes: self.bytes.slice_clone(start..end),
                 utf16_len: utf16_len as i32,
+                line_breaks: count_line_breaks(&self.bytes[start..end]),
                 id: self.id.inc(range.start as i32),
             };
             ans.check();
@@ -539,11 +565,13 @@ mod text_chunk {
                 unicode_len: self.unicode_len - pos as i32,
                 bytes: self.bytes.slice_clone(byte_offset..),
                 utf16_len: self.utf16_len - utf16_len as i32,
+                line_breaks: count_line_breaks(&self.bytes[byte_offset..]),
                 id: self.id.inc(pos as i32),
             };
 
             self.unicode_len = pos as i32;
             self.utf16_len = utf16_len as i32;
+            self.line_breaks -= right.line_breaks;
             self.bytes.slice_(..byte_offset);
             right.check();
             self.check();
@@ -560,6 +588,7 @@ mod text_chunk {
             self.bytes.try_merge(&rhs.bytes).unwrap();
             self.utf16_len += rhs.utf16_len;
             self.unicode_len += rhs.unicode_len;
+            self.line_breaks += rhs.line_breaks;
             self.check();
         }
 
@@ -569,6 +598,7 @@ mod text_chunk {
             self.bytes = new;
             self.utf16_len += left.utf16_len;
             self.unicode_len += left.unicode_len;
+            self.line_breaks += left.line_breaks;
             self.id = left.id;
             self.check();
         }
@@ -612,10 +642,8 @@ impl RichtextStateChunk {
             }
             RichtextStateChunk::Style { style, anchor_type } => match anchor_type {
                 AnchorType::Start => style.id().into(),
-                AnchorType::End => {
-                    let id = style.id();
-                    id.to_span(1)
-                }
+                // The end anchor is the op right after the start anchor
+                AnchorType::End => style.id().inc(1).into(),
             },
         }
     }
@@ -649,7 +677,7 @@ impl RichtextStateChunk {
             RichtextStateChunk::Text(t) => match pos_type {
                 PosType::Bytes => t.utf8_len() as usize,
                 PosType::Utf16 => t.utf16_len() as usize,
-                PosType::Event => t.unicode_len() as usize,
+                PosType::Event => t.event_len() as usize,
                 PosType::Entity => t.unicode_len() as usize,
                 PosType::Unicode => t.unicode_len() as usize,
             },
@@ -927,6 +955,7 @@ pub(crate) struct PosCache {
     pub(super) bytes: i32,
     pub(super) utf16_len: i32,
     pub(crate) entity_len: i32,
+    pub(super) line_breaks: i32,
 }
 
 impl PosCache {
@@ -956,6 +985,7 @@ impl AddAssign for PosCache {
         self.bytes += rhs.bytes;
         self.utf16_len += rhs.utf16_len;
         self.entity_len += rhs.entity_len;
+        self.line_breaks += rhs.line_breaks;
     }
 }
 
@@ -968,6 +998,7 @@ impl Add for PosCache {
             unicode_len: self.unicode_len + rhs.unicode_len,
             utf16_len: self.utf16_len + rhs.utf16_len,
             entity_len: self.entity_len + rhs.entity_len,
+            line_breaks: self.line_breaks + rhs.line_breaks,
         }
     }
 }
@@ -981,6 +1012,7 @@ impl Sub for PosCache {
             unicode_len: self.unicode_len - rhs.unicode_len,
             utf16_len: self.utf16_len - rhs.utf16_len,
             entity_len: self.entity_len - rhs.entity_len,
+            line_breaks: self.line_breaks - rhs.line_breaks,
         }
     }
 }
@@ -1046,12 +1078,14 @@ impl BTreeTrait for RichtextTreeTrait {
                 unicode_len: s.unicode_len(),
                 utf16_len: s.utf16_len(),
                 entity_len: s.unicode_len(),
+                line_breaks: s.line_breaks(),
             },
             RichtextStateChunk::Style { .. } => PosCache {
                 bytes: 0,
                 unicode_len: 0,
                 utf16_len: 0,
                 entity_len: 1,
+                line_breaks: 0,
             },
         }
     }
@@ -1068,12 +1102,15 @@ impl BTreeTrait for RichtextTreeTrait {
             unicode_len: cache_lhs.unicode_len - cache_rhs.unicode_len,
             utf16_len: cache_lhs.utf16_len - cache_rhs.utf16_len,
             entity_len: cache_lhs.entity_len - cache_rhs.entity_len,
+            line_breaks: cache_lhs.line_breaks - cache_rhs.line_breaks,
         }
     }
 }
 
 // This query implementation will prefer right element when both left element and right element are valid.
 mod query {
+    use generic_btree::{FindResult, Query};
+
     use crate::utils::query_by_len::{IndexQuery, QueryByLen};
 
     use super::*;
@@ -1256,6 +1293,58 @@ mod query {
             cache.entity_len as usize
         }
     }
+
+    /// Find the position right after the n-th (1-based) line break
+    pub(super) struct LineBreakQuery {
+        left: usize,
+    }
+
+    impl Query<RichtextTreeTrait> for LineBreakQuery {
+        type QueryArg = usize;
+
+        fn init(target: &Self::QueryArg) -> Self {
+            Self { left: *target }
+        }
+
+        fn find_node(
+            &mut self,
+            _: &Self::QueryArg,
+            child_caches: &[generic_btree::Child<RichtextTreeTrait>],
+        ) -> FindResult {
+            for (i, cache) in child_caches.iter().enumerate() {
+                let len = cache.cache.line_breaks as usize;
+                if self.left > len {
+                    self.left -= len;
+                } else {
+                    return FindResult::new_found(i, self.left);
+                }
+            }
+
+            FindResult::new_missing(child_caches.len() - 1, self.left)
+        }
+
+        fn confirm_elem(
+            &mut self,
+            _: &Self::QueryArg,
+            elem: &<RichtextTreeTrait as BTreeTrait>::Elem,
+        ) -> (usize, bool) {
+            match elem {
+                RichtextStateChunk::Text(s) => {
+                    for (i, c) in s.as_str().chars().enumerate() {
+                        if c == '\n' {
+                            self.left -= 1;
+                            if self.left == 0 {
+                                return (i + 1, true);
+                            }
+                        }
+                    }
+
+                    (s.len() as usize, false)
+                }
+                RichtextStateChunk::Style { .. } => (0, false),
+            }
+        }
+    }
 }
 
 impl RichtextState {
@@ -1485,10 +1574,19 @@ impl RichtextState {
 
     /// This method only updates `style_ranges`.
     /// When this method is called, the style start anchor and the style end anchor should already have been inserted.
-    pub(crate) fn annotate_style_range(&mut self, range: Range<usize>, style: Arc<StyleOp>) {
+    ///
+    /// The styles conflicting with each other under `rules` are trimmed, only the latest one is kept.
+    pub(crate) fn annotate_style_range(
+        &mut self,
+        range: Range<usize>,
+        style: Arc<StyleOp>,
+        rules: Option<Arc<OverlapRules>>,
+    ) {
         self.check_cache();
         self.clear_cache();
-        self.ensure_style_ranges_mut().annotate(range, style, None);
+        let style_ranges = self.ensure_style_ranges_mut();
+        style_ranges.set_overlap_rules(rules);
+        style_ranges.annotate(range, style, None);
         self.check_cache();
     }
 
@@ -1500,13 +1598,16 @@ impl RichtextState {
         &mut self,
         range: Range<usize>,
         style: Arc<StyleOp>,
+        rules: Option<Arc<OverlapRules>>,
     ) -> impl Iterator<Item = (StyleMeta, usize)> + '_ {
         self.check_cache();
         self.clear_cache();
         let mut ranges_in_entity_index: Vec<(StyleMeta, Range<usize>)> = Vec::new();
         let mut start = range.start;
         let end = range.end;
-        self.ensure_style_ranges_mut().annotate(
+        let style_ranges = self.ensure_style_ranges_mut();
+        style_ranges.set_overlap_rules(rules);
+        style_ranges.annotate(
             range,
             style,
             Some(&mut |s, len| {
@@ -2117,7 +2218,12 @@ impl RichtextState {
         self.check_style_anchors_appear_in_pairs();
     }
 
-    pub(crate) fn mark_with_entity_index(&mut self, range: Range<usize>, style: Arc<StyleOp>) {
+    pub(crate) fn mark_with_entity_index(
+        &mut self,
+        range: Range<usize>,
+        style: Arc<StyleOp>,
+        rules: Option<Arc<OverlapRules>>,
+    ) {
         self.check_cache();
         self.clear_cache();
         if self.tree.is_empty() {
@@ -2135,11 +2241,50 @@ impl RichtextState {
         // end_entity_index + 2, because
         // 1. We inserted a start anchor before end_entity_index, so we need to +1
         // 2. We need to include the end anchor in the range, so we need to +1
-        self.ensure_style_ranges_mut()
-            .annotate(range.start..range.end + 2, style, None);
+        let style_ranges = self.ensure_style_ranges_mut();
+        style_ranges.set_overlap_rules(rules);
+        style_ranges.annotate(range.start..range.end + 2, style, None);
         self.check_cache();
     }
 
+    /// Get the keys of the styles in the entity range that conflict with `key` under
+    /// `rules`. Marking `key` over the range trims them.
+    pub(crate) fn get_conflicting_style_keys(
+        &self,
+        range: Range<usize>,
+        key: &str,
+        rules: &OverlapRules,
+    ) -> Vec<InternalString> {
+        let mut ans: Vec<InternalString> = Vec::new();
+        let Some(style_ranges) = self.style_ranges.as_ref() else {
+            return ans;
+        };
+        if range.is_empty() || !style_ranges.has_style() {
+            return ans;
+        }
+
+        for slice in style_ranges.iter_range(range) {
+            if slice.end.unwrap_or(slice.elem.len) == slice.start.unwrap_or(0) {
+                continue;
+            }
+
+            for (style_key, value) in slice.elem.styles.iter() {
+                let other = style_key.key();
+                if ans.contains(other) || !rules.conflicts(key, other) {
+                    continue;
+                }
+
+                if value.get().is_some_and(|x| !x.value.is_null())
+                    && slice.elem.styles.trimmed_by(style_key).is_none()
+                {
+                    ans.push(other.clone());
+                }
+            }
+        }
+
+        ans
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = RichtextSpan> + '_ {
         let mut entity_index = 0;
         let mut style_range_iter: Box<dyn Iterator<Item = (Range<usize>, &Styles)>> =
@@ -2186,39 +2331,190 @@ impl RichtextState {
         self.tree.iter()
     }
 
+    /// Get the ranges of the styled text overlapping `range`, clipped to `range`, along with
+    /// their styles. `range` and the returned ranges are in `pos_type` index.
+    ///
+    /// The styles are the same as the attributes in the delta, except that the reserved
+    /// styles of embeds and annotations are not included. Text without styles is skipped,
+    /// and neighbor ranges with the same styles are merged.
+    ///
+    /// Only the style ranges overlapping `range` are visited.
+    pub(crate) fn get_style_ranges(
+        &mut self,
+        range: Range<usize>,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        let mut ans: Vec<(Range<usize>, FxHashMap<String, LoroValue>)> = Vec::new();
+        if range.is_empty() || !self.has_styles() {
+            return Ok(ans);
+        }
+
+        let (start, _) = self.get_entity_index_for_text_insert(range.start, pos_type)?;
+        let (end, _) = self.get_entity_index_for_text_insert(range.end, pos_type)?;
+        let mut index = start;
+        for slice in self.style_ranges.as_ref().unwrap().iter_range(start..end) {
+            let len = slice.end.unwrap_or(slice.elem.len) - slice.start.unwrap_or(0);
+            let entity_range = index..index + len;
+            index += len;
+            if entity_range.is_empty() || slice.elem.styles.is_empty() {
+                continue;
+            }
+
+            let mut styles = StyleMeta::from(&slice.elem.styles).to_map_without_null_value();
+            styles.retain(|key, _| !is_reserved_style_key(key));
+            if styles.is_empty() {
+                continue;
+            }
+
+            let span_start = self
+                .entity_index_to_index(entity_range.start, pos_type)
+                .max(range.start);
+            let span_end = self
+                .entity_index_to_index(entity_range.end, pos_type)
+                .min(range.end);
+            if span_start >= span_end {
+                continue;
+            }
+
+            match ans.last_mut() {
+                Some((last_range, last_styles))
+                    if last_range.end == span_start && *last_styles == styles =>
+                {
+                    last_range.end = span_end;
+                }
+                _ => ans.push((span_start..span_end, styles)),
+            }
+        }
+
+        Ok(ans)
+    }
+
+    fn entity_index_to_index(&self, index: usize, pos_type: PosType) -> usize {
+        match self.tree.query::<EntityQuery>(&index) {
+            Some(cursor) => self.get_index_from_cursor(cursor.cursor, pos_type).unwrap(),
+            None => 0,
+        }
+    }
+
+    /// Get the annotation styles with the event ranges between their anchors, sorted by
+    /// their ranges.
+    ///
+    /// Only the latest style of each annotation key is returned, and the range may be
+    /// empty if the annotated text is deleted.
+    pub(crate) fn get_annotation_styles(&self) -> Vec<(Arc<StyleOp>, Range<usize>)> {
+        let Some(style_ranges) = self.style_ranges.as_ref() else {
+            return Vec::new();
+        };
+
+        // The latest style of each key is the latest one wherever it's applied, so its
+        // ranges are continuous. They include the anchors, so they're kept when the text
+        // is deleted.
+        let mut latest: FxHashMap<InternalString, (Arc<StyleOp>, Range<usize>)> =
+            FxHashMap::default();
+        for (range, styles) in style_ranges.iter() {
+            for (key, value) in styles.iter() {
+                if !key.key().starts_with(ANNOTATION_STYLE_PREFIX) {
+                    continue;
+                }
+
+                let Some(style) = value.get() else {
+                    continue;
+                };
+                match latest.get_mut(key.key()) {
+                    Some((op, op_range)) if op.id() == style.id() => {
+                        op_range.end = range.end;
+                    }
+                    Some((op, _)) if **op > **style => {}
+                    _ => {
+                        latest.insert(key.key().clone(), (style.clone(), range.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut ans: Vec<_> = latest
+            .into_values()
+            .map(|(op, range)| {
+                let start = self.entity_index_to_event_index(range.start);
+                let end = self.entity_index_to_event_index(range.end);
+                (op, start..end)
+            })
+            .collect();
+        ans.sort_by_key(|(op, range)| (range.start, range.end, op.id()));
+        ans
+    }
+
+    /// Iterate the containers embedded in the text with their event indexes.
+    ///
+    /// Only the style ranges are visited. A container is skipped if its embed char is deleted.
+    pub(crate) fn iter_embedded_containers(
+        &self,
+    ) -> impl Iterator<Item = (&ContainerID, usize)> + '_ {
+        let embed_key = StyleKey::Key(EMBED_STYLE_KEY.into());
+        let mut last: Option<&ContainerID> = None;
+        self.style_ranges
+            .iter()
+            .flat_map(|x| x.iter())
+            .filter_map(move |(range, styles)| {
+                let id = styles.get(&embed_key)?.get()?.embedded_container()?;
+                if last == Some(id) {
+                    return None;
+                }
+
+                // The range includes the anchors, which have no event length
+                let start = self.entity_index_to_event_index(range.start);
+                if self.entity_index_to_event_index(range.end) == start {
+                    return None;
+                }
+
+                last = Some(id);
+                Some((id, start))
+            })
+    }
+
     pub fn get_richtext_value(&self) -> LoroValue {
         self.check_cache();
         let result = {
             let mut ans: Vec<LoroValue> = Vec::new();
             let mut last_attributes: Option<LoroValue> = None;
             for span in self.iter() {
-                let attributes: LoroValue = span.attributes.to_value();
-                if let Some(last) = last_attributes.as_ref() {
-                    if &attributes == last {
-                        let hash_map = ans.last_mut().unwrap().as_map_mut().unwrap();
-                        let s = hash_map
-                            .make_mut()
-                            .get_mut("insert")
-                            .unwrap()
-                            .as_string_mut()
-                            .unwrap();
-                        s.make_mut().push_str(span.text.as_str());
-                        continue;
-                    }
-                }
+                let mut attributes: LoroValue = span.attributes.to_value();
+                let embed = attributes
+                    .as_map_mut()
+                    .unwrap()
+                    .make_mut()
+                    .remove(EMBED_STYLE_KEY);
+                let Some(embed) = embed else {
+                    push_richtext_insert(
+                        &mut ans,
+                        &mut last_attributes,
+                        span.text.as_str(),
+                        attributes,
+                    );
+                    continue;
+                };
 
-                let mut value = FxHashMap::default();
-                value.insert(
-                    "insert".into(),
-                    LoroValue::String(span.text.as_str().into()),
-                );
+                // Embeds are never merged with the text around them
+                for (i, part) in span.text.as_str().split(EMBED_CHAR).enumerate() {
+                    if i > 0 {
+                        let mut value = FxHashMap::default();
+                        value.insert("embed".into(), embed.clone());
+                        if !attributes.as_map().unwrap().is_empty() {
+                            value.insert("attributes".into(), attributes.clone());
+                        }
 
-                if !attributes.as_map().unwrap().is_empty() {
-                    value.insert("attributes".into(), attributes.clone());
+                        ans.push(LoroValue::Map(value.into()));
+                        last_attributes = None;
+                    }
+                    if !part.is_empty() {
+                        push_richtext_insert(
+                            &mut ans,
+                            &mut last_attributes,
+                            part,
+                            attributes.clone(),
+                        );
+                    }
                 }
-
-                ans.push(LoroValue::Map(value.into()));
-                last_attributes = Some(attributes);
             }
 
             LoroValue::List(ans.into())
@@ -2264,6 +2560,157 @@ impl RichtextState {
         self.tree.root_cache().entity_len as usize
     }
 
+    /// The number of the grapheme clusters, in O(n).
+    pub fn len_grapheme(&self) -> usize {
+        let mut len = 0;
+        self.for_each_grapheme(|_| {
+            len += 1;
+            true
+        });
+        len
+    }
+
+    /// Convert the grapheme cluster range to a unicode range.
+    ///
+    /// Only the clusters before `range.end` are segmented, so it takes O(range.end).
+    /// The clusters are segmented on the current text, so a cluster split by concurrent
+    /// edits is just counted as several clusters.
+    pub(crate) fn grapheme_range_to_unicode(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Range<usize>> {
+        debug_assert!(range.start <= range.end);
+        let mut start = (range.start == 0).then_some(0);
+        let mut end = (range.end == 0).then_some(0);
+        let mut count = 0;
+        self.for_each_grapheme(|index| {
+            count += 1;
+            if count == range.start {
+                start = Some(index);
+            }
+            if count == range.end {
+                end = Some(index);
+                return false;
+            }
+            true
+        });
+
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(start..end),
+            _ => Err(LoroError::OutOfBound {
+                pos: range.end,
+                len: count,
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            }),
+        }
+    }
+
+    /// Visit the grapheme clusters in order with the unicode index of their ends, until
+    /// `f` returns false.
+    ///
+    /// The text is segmented chunk by chunk without building the whole string. The last
+    /// cluster of a chunk may continue in the next chunk, so it's segmented again along
+    /// with the next chunk.
+    fn for_each_grapheme(&self, mut f: impl FnMut(usize) -> bool) {
+        let mut pending = String::new();
+        let mut index = 0;
+        for chunk in self.tree.iter() {
+            let RichtextStateChunk::Text(s) = chunk else {
+                continue;
+            };
+
+            pending.push_str(s.as_str());
+            let mut last_start = 0;
+            let mut graphemes = pending.grapheme_indices(true).peekable();
+            while let Some((start, g)) = graphemes.next() {
+                if graphemes.peek().is_none() {
+                    last_start = start;
+                    break;
+                }
+
+                index += g.chars().count();
+                if !f(index) {
+                    return;
+                }
+            }
+
+            pending.replace_range(..last_start, "");
+        }
+
+        if !pending.is_empty() {
+            index += pending.chars().count();
+            f(index);
+        }
+    }
+
+    /// The number of lines, which is the number of `\n` plus one.
+    #[inline(always)]
+    pub fn line_count(&self) -> usize {
+        self.tree.root_cache().line_breaks as usize + 1
+    }
+
+    /// Get the start position of the `line` (0-based), or None if there is no such line.
+    pub(crate) fn get_line_start(&self, line: usize, pos_type: PosType) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+
+        if line >= self.line_count() {
+            return None;
+        }
+
+        let cursor = self.tree.query::<LineBreakQuery>(&line)?.cursor;
+        self.get_index_from_cursor(cursor, pos_type)
+    }
+
+    /// Get the line (0-based) of `pos` and the column of `pos` in the line, or None if
+    /// `pos` is out of bound.
+    ///
+    /// The column is in the same unit as `pos`.
+    pub(crate) fn get_line_col(&self, pos: usize, pos_type: PosType) -> Option<(usize, usize)> {
+        if pos > self.len(pos_type) {
+            return None;
+        }
+
+        if self.tree.is_empty() {
+            return Some((0, 0));
+        }
+
+        let cursor = match pos_type {
+            PosType::Bytes => self.tree.query::<ByteQuery>(&pos),
+            PosType::Unicode => self.tree.query::<UnicodeQuery>(&pos),
+            PosType::Utf16 => self.tree.query::<Utf16Query>(&pos),
+            PosType::Entity => self.tree.query::<EntityQuery>(&pos),
+            PosType::Event => self.tree.query::<EventIndexQuery>(&pos),
+        }?
+        .cursor;
+        let mut line = 0;
+        self.tree
+            .visit_previous_caches(cursor, |cache| match cache {
+                generic_btree::PreviousCache::NodeCache(c) => {
+                    line += c.line_breaks as usize;
+                }
+                generic_btree::PreviousCache::PrevSiblingElem(c) => {
+                    if let RichtextStateChunk::Text(t) = c {
+                        line += t.line_breaks() as usize;
+                    }
+                }
+                generic_btree::PreviousCache::ThisElemAndOffset { elem, offset } => {
+                    if let RichtextStateChunk::Text(t) = elem {
+                        line += t
+                            .as_str()
+                            .chars()
+                            .take(offset)
+                            .filter(|&c| c == '\n')
+                            .count();
+                    }
+                }
+            });
+
+        let start = self.get_line_start(line, pos_type)?;
+        Some((line, pos - start))
+    }
+
     pub fn diagnose(&self) {
         println!(
             "rope_nodes: {}, style_nodes: {}, text_len: {}",
@@ -2673,6 +3120,35 @@ mod converter {
     }
 }
 
+/// Push the text to the richtext value, merging it into the last insert if they have the same attributes.
+fn push_richtext_insert(
+    ans: &mut Vec<LoroValue>,
+    last_attributes: &mut Option<LoroValue>,
+    text: &str,
+    attributes: LoroValue,
+) {
+    if last_attributes.as_ref() == Some(&attributes) {
+        let hash_map = ans.last_mut().unwrap().as_map_mut().unwrap();
+        let s = hash_map
+            .make_mut()
+            .get_mut("insert")
+            .unwrap()
+            .as_string_mut()
+            .unwrap();
+        s.make_mut().push_str(text);
+        return;
+    }
+
+    let mut value = FxHashMap::default();
+    value.insert("insert".into(), LoroValue::String(text.into()));
+    if !attributes.as_map().unwrap().is_empty() {
+        value.insert("attributes".into(), attributes.clone());
+    }
+
+    ans.push(LoroValue::Map(value.into()));
+    *last_attributes = Some(attributes);
+}
+
 #[cfg(test)]
 mod test {
     use append_only_bytes::AppendOnlyBytes;
@@ -2725,7 +3201,7 @@ mod test {
                 .state
                 .get_entity_index_for_text_insert(range.end, PosType::Unicode)
                 .unwrap();
-            self.state.mark_with_entity_index(start..end, style);
+            self.state.mark_with_entity_index(start..end, style, None);
         }
     }
 
@@ -2901,6 +3377,32 @@ mod test {
         );
     }
 
+    #[test]
+    fn event_index_of_cursor() {
+        let mut wrapper = SimpleWrapper::default();
+        // The chunks can't be merged because their ids are not continuous
+        wrapper.insert(0, "a😀");
+        wrapper.insert(2, "b😀");
+        wrapper.insert(4, "c");
+        let state = &wrapper.state;
+        let last = state.tree.end_cursor().unwrap();
+        let cursor = Cursor {
+            leaf: last.leaf,
+            offset: 0,
+        };
+        // The event index is in utf16 in wasm and in unicode otherwise
+        let expected = if cfg!(feature = "wasm") { 6 } else { 4 };
+        assert_eq!(
+            state.get_index_from_cursor(cursor, PosType::Event),
+            Some(expected)
+        );
+        assert_eq!(
+            state.get_index_from_cursor(cursor, PosType::Unicode),
+            Some(4)
+        );
+        assert_eq!(state.get_index_from_cursor(cursor, PosType::Utf16), Some(6));
+    }
+
     #[test]
     fn link_should_not_expand() {
         let mut wrapper = SimpleWrapper::default();
@@ -17,7 +17,7 @@ use once_cell::sync::Lazy;
 
 use crate::delta::StyleMeta;
 
-use super::{AnchorType, StyleKey, StyleOp};
+use super::{config::OverlapRules, AnchorType, StyleKey, StyleOp};
 
 /// This struct keep the mapping of ranges to numbers
 ///
@@ -26,6 +26,7 @@ use super::{AnchorType, StyleKey, StyleOp};
 pub(super) struct StyleRangeMap {
     pub(super) tree: BTree<RangeNumMapTrait>,
     has_style: bool,
+    rules: Option<Arc<OverlapRules>>,
 }
 
 #[derive(Debug, Clone)]
@@ -34,11 +35,19 @@ pub(super) struct RangeNumMapTrait;
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub(crate) struct Styles {
     pub(crate) styles: FxHashMap<StyleKey, StyleValue>,
+    /// The keys whose styles are trimmed by newer conflicting styles, mapped to the
+    /// newest of them. See [OverlapRules].
+    trimmed: FxHashMap<StyleKey, Arc<StyleOp>>,
 }
 
 impl Styles {
     pub(crate) fn has_key_value(&self, key: &str, value: &loro_common::LoroValue) -> bool {
-        match self.get(&StyleKey::Key(key.into())) {
+        let key = StyleKey::Key(key.into());
+        if self.trimmed.contains_key(&key) {
+            return false;
+        }
+
+        match self.get(&key) {
             Some(v) => match v.get() {
                 Some(v) => &v.value == value,
                 _ => false,
@@ -47,6 +56,41 @@ impl Styles {
         }
     }
 
+    /// Get the newest style that trims the style of `key`
+    pub(crate) fn trimmed_by(&self, key: &StyleKey) -> Option<&Arc<StyleOp>> {
+        self.trimmed.get(key)
+    }
+
+    /// Update the trimmed styles by the overlap rules.
+    ///
+    /// The latest style of a key is trimmed if there is a newer non-null style of a
+    /// conflicting key. It only depends on the set of styles, so all peers get the same
+    /// result no matter in which order they apply the styles. Unmarking the newer style
+    /// doesn't bring the trimmed one back.
+    fn update_trimmed(&mut self, rules: Option<&OverlapRules>) {
+        self.trimmed.clear();
+        let Some(rules) = rules else {
+            return;
+        };
+
+        for (key, value) in self.styles.iter() {
+            let Some(latest) = value.get() else {
+                continue;
+            };
+
+            let winner = self
+                .styles
+                .iter()
+                .filter(|(other, _)| rules.conflicts(key.key(), other.key()))
+                .filter_map(|(_, other)| other.set.iter().rev().find(|x| !x.value.is_null()))
+                .filter(|x| *x > latest)
+                .max();
+            if let Some(winner) = winner {
+                self.trimmed.insert(key.clone(), winner.clone());
+            }
+        }
+    }
+
     /// Infer the anchors between the neighbor styles.
     /// Returns the last anchor of the left style and the first anchor of the right style.
     fn infer_anchors(&self, next: &Self) -> (Option<Arc<StyleOp>>, Option<Arc<StyleOp>>) {
@@ -131,7 +175,36 @@ impl StyleRangeMap {
         Self {
             tree,
             has_style: false,
+            rules: None,
+        }
+    }
+
+    /// Set the overlap rules of the styles, the trimmed styles are updated if they change.
+    ///
+    /// The rules are cached by the config, so they are compared by pointer.
+    pub fn set_overlap_rules(&mut self, rules: Option<Arc<OverlapRules>>) {
+        let unchanged = match (&self.rules, &rules) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            return;
+        }
+
+        self.rules = rules;
+        if !self.has_style {
+            return;
         }
+
+        let rules = self.rules.as_deref();
+        let len = *self.tree.root_cache() as usize;
+        let range = self.tree.range::<LengthFinder>(0..len).unwrap();
+        self.tree
+            .update(range.start.cursor..range.end.cursor, &mut |x| {
+                x.styles.update_trimmed(rules);
+                None
+            });
     }
 
     pub fn annotate(
@@ -147,6 +220,7 @@ impl StyleRangeMap {
 
         self.has_style = true;
         let range = range.unwrap();
+        let rules = self.rules.as_deref();
         self.tree
             .update(range.start.cursor..range.end.cursor, &mut |x| {
                 if let Some(set) = x.styles.get_mut(&style.get_style_key()) {
@@ -157,6 +231,7 @@ impl StyleRangeMap {
                     value.insert(style.clone());
                     x.styles.insert(key, value);
                 }
+                x.styles.update_trimmed(rules);
 
                 if let Some(y) = yield_style.as_mut() {
                     y(&x.styles, x.len);
@@ -245,6 +320,7 @@ impl StyleRangeMap {
 
             false
         });
+        styles.update_trimmed(self.rules.as_deref());
 
         let (target, _) = self.tree.insert_by_path(right, Elem { len, styles });
         &self.tree.get_elem(target.leaf).unwrap().styles
@@ -276,6 +352,7 @@ impl StyleRangeMap {
 
                 false
             });
+            styles.update_trimmed(self.rules.as_deref());
 
             styles.into()
         }
@@ -391,6 +468,7 @@ impl StyleRangeMap {
         last_index: usize,
     ) -> usize {
         let mut removed_len = 0;
+        let rules = self.rules.clone();
         self.update_styles_scanning_backward(last_index, |elem| {
             removed_len += elem.len;
             let styles = &mut elem.styles;
@@ -402,6 +480,9 @@ impl StyleRangeMap {
                     styles.remove(&key);
                 }
             }
+            if has_removed {
+                styles.update_trimmed(rules.as_deref());
+            }
 
             if has_removed {
                 ControlFlow::Continue(())
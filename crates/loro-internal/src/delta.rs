@@ -11,4 +11,5 @@ pub use text::{StyleMeta, StyleMetaItem};
 mod tree;
 pub use tree::{
     TreeDelta, TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff, TreeInternalDiff,
+    TreeMoveRecord,
 };
@@ -37,14 +37,20 @@ impl From<&Styles> for StyleMeta {
         let mut map = FxHashMap::with_capacity_and_hasher(styles.len(), Default::default());
         for (key, value) in styles.iter() {
             if let Some(value) = value.get() {
-                map.insert(
-                    key.key().clone(),
-                    StyleMetaItem {
+                let item = match styles.trimmed_by(key) {
+                    // A trimmed style is as if it's unmarked by the style trimming it
+                    Some(by) => StyleMetaItem {
+                        value: LoroValue::Null,
+                        lamport: by.lamport,
+                        peer: by.peer,
+                    },
+                    None => StyleMetaItem {
                         value: value.to_value(),
                         lamport: value.lamport,
                         peer: value.peer,
                     },
-                );
+                };
+                map.insert(key.key().clone(), item);
             }
         }
         Self { map }
@@ -126,7 +132,7 @@ impl StyleMeta {
         LoroValue::Map(self.to_map_without_null_value().into())
     }
 
-    fn to_map_without_null_value(&self) -> FxHashMap<String, LoroValue> {
+    pub(crate) fn to_map_without_null_value(&self) -> FxHashMap<String, LoroValue> {
         self.map
             .iter()
             .filter_map(|(key, value)| {
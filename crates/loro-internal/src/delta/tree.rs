@@ -1,7 +1,7 @@
 use fractional_index::FractionalIndex;
 use rustc_hash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
-use loro_common::{IdFull, TreeID};
+use loro_common::{IdFull, Lamport, TreeID, ID};
 use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
@@ -91,6 +91,22 @@ impl TreeDiff {
 #[derive(Clone, Default)]
 pub struct TreeDelta {
     pub(crate) diff: Vec<TreeDeltaItem>,
+    /// The imported moves that are rejected because they would create cycles
+    pub(crate) rejected: Vec<TreeMoveRecord>,
+}
+
+/// An op that creates, moves or deletes a tree node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeMoveRecord {
+    pub id: ID,
+    pub lamport: Lamport,
+    pub target: TreeID,
+    /// The parent that the op moves the node to, which is [TreeParentId::Deleted] for a
+    /// deletion
+    pub parent: TreeParentId,
+    /// Whether the op takes effect. The concurrent moves are applied in the order of their
+    /// lamports and peers, and a move that would create a cycle is rejected.
+    pub applied: bool,
 }
 
 impl Debug for TreeDelta {
@@ -191,6 +207,7 @@ impl TreeDelta {
     // TODO: cannot handle this for now
     pub(crate) fn compose(mut self, x: TreeDelta) -> TreeDelta {
         self.diff.extend(x.diff);
+        self.rejected.extend(x.rejected);
         self
     }
 }
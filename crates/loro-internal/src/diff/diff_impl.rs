@@ -15,20 +15,24 @@
 //! The implementation of this algorithm is based on the implementation by
 //! Brandon Williams.
 use crate::change::get_sys_timestamp;
+use loro_common::{LoroEncodeError, LoroError};
 use rustc_hash::FxHashMap;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::iter::zip;
-use std::ops::{Index, IndexMut};
+use std::ops::{Index, IndexMut, Range};
 
 /// Options for controlling the text update behavior.
 ///
 /// - `timeout_ms`: Optional timeout in milliseconds for the diff computation
 /// - `use_refined_diff`: Whether to use a more refined but slower diff algorithm. Defaults to true.
+/// - `granularity`: The unit of the diff. Defaults to [UpdateGranularity::Char].
+///   It's ignored by `update_by_line`.
 #[derive(Clone, Debug)]
 pub struct UpdateOptions {
     pub timeout_ms: Option<f64>,
     pub use_refined_diff: bool,
+    pub granularity: UpdateGranularity,
 }
 
 impl Default for UpdateOptions {
@@ -36,16 +40,61 @@ impl Default for UpdateOptions {
         Self {
             timeout_ms: None,
             use_refined_diff: true,
+            granularity: UpdateGranularity::Char,
         }
     }
 }
 
+impl UpdateOptions {
+    pub fn timeout_ms(mut self, timeout_ms: Option<f64>) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    pub fn use_refined_diff(mut self, use_refined_diff: bool) -> Self {
+        self.use_refined_diff = use_refined_diff;
+        self
+    }
+
+    pub fn granularity(mut self, granularity: UpdateGranularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+}
+
+/// The unit of the diff when updating a text.
+///
+/// The word and sentence modes diff the tokens split by the
+/// [Unicode Text Segmentation](https://www.unicode.org/reports/tr29/) rules. Then the tiny
+/// unchanged parts between the changes are merged into the changes, so that a changed
+/// phrase is replaced as a whole instead of being interleaved with concurrent edits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpdateGranularity {
+    #[default]
+    Char,
+    /// A word, a run of whitespaces or a punctuation is a token.
+    Word,
+    /// A sentence, with its trailing whitespaces, is a token.
+    Sentence,
+}
+
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum UpdateTimeoutError {
     #[error("Timeout")]
     Timeout,
 }
 
+/// The error of [crate::handler::TextHandler::update_from_base].
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum UpdateFromBaseError {
+    #[error(transparent)]
+    Loro(#[from] LoroError),
+    #[error(transparent)]
+    Encode(#[from] LoroEncodeError),
+    #[error(transparent)]
+    Timeout(#[from] UpdateTimeoutError),
+}
+
 /// Utility function to check if a range is empty that works on older rust versions
 #[inline(always)]
 fn is_empty_range(start: usize, end: usize) -> bool {
@@ -109,7 +158,6 @@ impl<D: DiffHandler> OperateProxy<D> {
         self.handler.insert(old_index, new_index, new_len);
     }
 
-    #[allow(unused)]
     fn unwrap(self) -> D {
         self.handler
     }
@@ -146,6 +194,91 @@ pub(crate) fn diff<D: DiffHandler>(
     )
 }
 
+/// Diff `old` and `new` like [diff], then merge the unchanged parts that are not longer
+/// than the changes on both of their sides into the changes.
+///
+/// `token_len` is the weight of a token when comparing the lengths. The changes are
+/// only applied to `proxy` after the diff finishes, so nothing is applied on timeout.
+pub(crate) fn diff_with_cleanup<D: DiffHandler>(
+    proxy: &mut OperateProxy<D>,
+    options: UpdateOptions,
+    old: &[u32],
+    new: &[u32],
+    token_len: impl Fn(u32) -> usize,
+) -> Result<(), UpdateTimeoutError> {
+    let mut recorder = OperateProxy::new(EditRecorder::default());
+    diff(&mut recorder, options, old, new)?;
+    let edits = recorder.unwrap().edits;
+    let weight = |tokens: &[u32]| tokens.iter().map(|x| token_len(*x)).sum::<usize>();
+    let edit_weight =
+        |edit: &Edit| weight(&old[edit.old.clone()]).max(weight(&new[edit.new.clone()]));
+    let mut merged: Vec<Edit> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if let Some(last) = merged.last_mut() {
+            let equality = weight(&old[last.old.end..edit.old.start]);
+            if equality <= edit_weight(last) && equality <= edit_weight(&edit) {
+                last.old.end = edit.old.end;
+                last.new.end = edit.new.end;
+                continue;
+            }
+        }
+
+        merged.push(edit);
+    }
+
+    for edit in merged {
+        if !edit.old.is_empty() {
+            proxy.delete(edit.old.start, edit.old.len());
+        }
+        if !edit.new.is_empty() {
+            proxy.insert(edit.old.end, edit.new.start, edit.new.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// A change that replaces `old` tokens with `new` tokens
+#[derive(Debug)]
+struct Edit {
+    old: Range<usize>,
+    new: Range<usize>,
+}
+
+/// Record the changes, merging the adjacent deletions and insertions
+#[derive(Debug, Default)]
+struct EditRecorder {
+    edits: Vec<Edit>,
+    /// The new index minus the old index after the recorded changes
+    shift: isize,
+}
+
+impl EditRecorder {
+    fn push(&mut self, old: Range<usize>, new: Range<usize>) {
+        self.shift += new.len() as isize - old.len() as isize;
+        if let Some(last) = self.edits.last_mut() {
+            if last.new.end == new.start && last.old.end >= old.start {
+                last.old.end = last.old.end.max(old.end);
+                last.new.end = new.end;
+                return;
+            }
+        }
+
+        self.edits.push(Edit { old, new });
+    }
+}
+
+impl DiffHandler for EditRecorder {
+    fn insert(&mut self, old_index: usize, new_index: usize, new_len: usize) {
+        self.push(old_index..old_index, new_index..new_index + new_len);
+    }
+
+    fn delete(&mut self, old_index: usize, old_len: usize) {
+        let new_index = (old_index as isize + self.shift) as usize;
+        self.push(old_index..old_index + old_len, new_index..new_index);
+    }
+}
+
 struct OffsetVec(isize, Vec<usize>);
 
 impl OffsetVec {
@@ -854,4 +987,68 @@ mod tests {
         let result = diff(&mut proxy, options, &old, &new);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_diff_with_cleanup() {
+        let mut proxy = OperateProxy::new(RecordingDiffHandler::default());
+        let old = vec![1, 2, 3, 2, 4];
+        let new = vec![5, 2, 6, 2, 4];
+        diff_with_cleanup(&mut proxy, Default::default(), &old, &new, |_| 1).unwrap();
+        assert_eq!(
+            proxy.unwrap().ops,
+            vec![
+                DiffOperation::Delete {
+                    old_index: 0,
+                    length: 3
+                },
+                DiffOperation::Insert {
+                    old_index: 3,
+                    new_index: 0,
+                    length: 3
+                },
+            ]
+        );
+
+        // The long unchanged token is kept
+        let mut proxy = OperateProxy::new(RecordingDiffHandler::default());
+        let token_len = |x: u32| if x == 2 { 10 } else { 1 };
+        diff_with_cleanup(&mut proxy, Default::default(), &old, &new, token_len).unwrap();
+        assert_eq!(
+            proxy.unwrap().ops,
+            vec![
+                DiffOperation::Delete {
+                    old_index: 0,
+                    length: 1
+                },
+                DiffOperation::Insert {
+                    old_index: 1,
+                    new_index: 0,
+                    length: 1
+                },
+                DiffOperation::Delete {
+                    old_index: 2,
+                    length: 1
+                },
+                DiffOperation::Insert {
+                    old_index: 3,
+                    new_index: 2,
+                    length: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_with_cleanup_timeout() {
+        let mut proxy = OperateProxy::new(RecordingDiffHandler::default());
+        let old = vec![1; 10000];
+        let new = vec![2; 10000];
+        let options = UpdateOptions {
+            timeout_ms: Some(0.1),
+            ..Default::default()
+        };
+        let result = diff_with_cleanup(&mut proxy, options, &old, &new, |_| 1);
+        assert!(result.is_err());
+        assert_eq!(proxy.unwrap().ops, vec![]);
+    }
 }
@@ -1,4 +1,5 @@
 pub mod diff_impl;
 pub(crate) use diff_impl::diff;
+pub(crate) use diff_impl::diff_with_cleanup;
 pub(crate) use diff_impl::DiffHandler;
 pub(crate) use diff_impl::OperateProxy;
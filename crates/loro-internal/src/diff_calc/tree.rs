@@ -1,14 +1,13 @@
 use std::{collections::BTreeSet, sync::Arc};
 
 use fractional_index::FractionalIndex;
-use rustc_hash::FxHashMap;
 use itertools::Itertools;
 use loro_common::{ContainerID, IdFull, IdLp, Lamport, PeerID, TreeID, ID};
 
 use crate::{
     container::{idx::ContainerIdx, tree::tree_op::TreeOp},
     dag::DagUtils,
-    delta::{TreeDelta, TreeDeltaItem, TreeInternalDiff},
+    delta::{TreeDelta, TreeDeltaItem, TreeInternalDiff, TreeMoveRecord},
     event::InternalDiff,
     state::TreeParentId,
     version::Frontiers,
@@ -21,6 +20,8 @@ use super::{DiffCalcVersionInfo, DiffCalculatorTrait, DiffMode};
 pub(crate) struct TreeDiffCalculator {
     container: ContainerIdx,
     mode: TreeDiffCalculatorMode,
+    /// Whether the ops are imported, in which case the newly rejected moves are reported
+    is_import: bool,
 }
 
 #[derive(Debug)]
@@ -32,6 +33,7 @@ enum TreeDiffCalculatorMode {
 
 impl DiffCalculatorTrait for TreeDiffCalculator {
     fn start_tracking(&mut self, _oplog: &OpLog, _vv: &crate::VersionVector, mode: DiffMode) {
+        self.is_import = matches!(mode, DiffMode::Import);
         match mode {
             DiffMode::Checkout => {
                 self.mode = TreeDiffCalculatorMode::Crdt;
@@ -104,6 +106,7 @@ impl DiffCalculatorTrait for TreeDiffCalculator {
 
     fn finish_this_round(&mut self) {
         self.mode = TreeDiffCalculatorMode::Crdt;
+        self.is_import = false;
     }
 
     fn calculate_diff(
@@ -152,9 +155,51 @@ impl TreeDiffCalculator {
         Self {
             container,
             mode: TreeDiffCalculatorMode::Crdt,
+            is_import: false,
         }
     }
 
+    /// Get the ops that create, move or delete the node in the version, in the order they
+    /// are applied, including the moves rejected because of cycles.
+    ///
+    /// The history cache starts from the shallow root, where the node only has the op of
+    /// its state at that version.
+    pub(crate) fn get_history_of_node(
+        &mut self,
+        oplog: &OpLog,
+        vv: &VersionVector,
+        frontiers: &Frontiers,
+        target: TreeID,
+    ) -> Vec<TreeMoveRecord> {
+        let has_ops = oplog.with_history_cache(|h| {
+            let mark = h.ensure_importing_caches_exist();
+            h.get_tree(&self.container, mark).is_some()
+        });
+        if !has_ops {
+            return Vec::new();
+        }
+
+        self.checkout(vv, frontiers, oplog);
+        oplog.with_history_cache(|h| {
+            let mark = h.ensure_importing_caches_exist();
+            let tree_ops = h.get_tree(&self.container, mark).unwrap();
+            let tree_cache = tree_ops.tree().lock().unwrap();
+            let Some(ops) = tree_cache.tree.get(&target) else {
+                return Vec::new();
+            };
+
+            ops.iter()
+                .map(|op| TreeMoveRecord {
+                    id: op.id.id(),
+                    lamport: op.id.lamport,
+                    target,
+                    parent: op.op.parent_id(),
+                    applied: op.effected,
+                })
+                .collect()
+        })
+    }
+
     fn diff(&mut self, oplog: &OpLog, info: DiffCalcVersionInfo) -> TreeDelta {
         self.checkout(info.from_vv, info.from_frontiers, oplog);
         self.checkout_diff(info, oplog)
@@ -247,6 +292,9 @@ impl TreeDiffCalculator {
 
             // retreat for diff
             let mut diffs = vec![];
+            let mut rejected = vec![];
+            // The moves that were rejected before, which are not reported again
+            let mut rejected_before = FxHashSet::default();
 
             if !(tree_cache.current_vv == lca_vv && &lca_vv == info.from_vv) {
                 let mut retreat_ops = vec![];
@@ -263,6 +311,9 @@ impl TreeDiffCalculator {
 
                 for op in retreat_ops.into_iter().sorted().rev() {
                     tree_cache.retreat_op(&op);
+                    if !op.effected {
+                        rejected_before.insert(op.id.id());
+                    }
                     let (old_parent, position, last_effective_move_op_id) =
                         tree_cache.get_parent_with_id(op.op.target());
                     if op.effected {
@@ -386,12 +437,23 @@ impl TreeDiffCalculator {
                                 s.extend(children.iter().map(|x| x.0));
                             }
                         }
+                    } else if self.is_import && !rejected_before.contains(&id) {
+                        rejected.push(TreeMoveRecord {
+                            id,
+                            lamport: idlp.lamport,
+                            target: op.op.target(),
+                            parent: op.op.parent_id(),
+                            applied: false,
+                        });
                     }
                 }
             }
 
             tree_cache.current_vv = info.to_vv.clone();
-            TreeDelta { diff: diffs }
+            TreeDelta {
+                diff: diffs,
+                rejected,
+            }
         })
     }
 
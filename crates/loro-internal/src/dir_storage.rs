@@ -0,0 +1,157 @@
+//! Persist a [LoroDoc] into a directory.
+//!
+//! # Layout
+//!
+//! ```log
+//! <dir>
+//! ├── oplog/   the change store, stored by [DirKvStore]
+//! └── state    the state of the latest version
+//! ```
+//!
+//! The history is loaded lazily: opening the doc only reads the block meta of
+//! the sstable files, and a block of changes is read from the disk when it's
+//! accessed. The state file is read eagerly, but the containers are still
+//! decoded lazily.
+//!
+//! The state file contains the frontiers of the state, so a state file that
+//! is out of date with the oplog (e.g. the process exits between writing the
+//! oplog and the state) is ignored and the state is recalculated from the history.
+use std::{fs, io::Write, path::Path, sync::Arc};
+
+use bytes::{Buf, Bytes};
+use loro_common::{LoroError, LoroResult};
+
+use crate::{
+    encoding::fast_snapshot::{decode_snapshot_with_oplog_loader, encode_latest_state},
+    kv_store::{DirKvAdapter, DirKvStore},
+    sync::Mutex,
+    version::Frontiers,
+    LoroDoc, OpLog,
+};
+
+const OPLOG_DIR: &str = "oplog";
+const STATE_FILE: &str = "state";
+const STATE_TMP_FILE: &str = "state.tmp";
+
+fn io_err(e: std::io::Error) -> LoroError {
+    LoroError::IoError(e.to_string().into_boxed_str())
+}
+
+impl LoroDoc {
+    /// Open the doc persisted in the given directory.
+    ///
+    /// An empty doc is created if the directory doesn't exist or is empty.
+    /// The changes are read from the disk lazily when they are accessed.
+    ///
+    /// The new changes are not written to the directory until [LoroDoc::flush] is called.
+    pub fn open_dir(dir: impl AsRef<Path>) -> LoroResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let kv = DirKvStore::open(dir.join(OPLOG_DIR))?;
+        let doc = LoroDoc::new_with_oplog(
+            OpLog::new_with_kv(Arc::new(Mutex::new(DirKvAdapter::new(kv)))),
+            Some(dir.clone()),
+        );
+        let Some(frontiers) = doc
+            .oplog()
+            .lock()
+            .unwrap()
+            .change_store()
+            .external_kv_frontiers()?
+        else {
+            return Ok(doc);
+        };
+
+        let state_bytes = read_state_file(&dir)?.and_then(|(state_frontiers, bytes)| {
+            if state_frontiers == frontiers {
+                Some(bytes)
+            } else {
+                None
+            }
+        });
+        decode_snapshot_with_oplog_loader(
+            &doc,
+            |oplog| oplog.load_change_store_from_kv(),
+            state_bytes,
+            Bytes::new(),
+            Default::default(),
+        )?;
+        Ok(doc)
+    }
+
+    /// The directory the doc is persisted to, if it's opened by [LoroDoc::open_dir].
+    pub fn storage_dir(&self) -> Option<&Path> {
+        self.storage_dir.as_deref()
+    }
+
+    /// Write the changes and the latest state to the directory opened by [LoroDoc::open_dir].
+    ///
+    /// The pending transaction is committed first.
+    ///
+    /// # Errors
+    ///
+    /// - [LoroError::ArgErr] if the doc is not opened from a directory
+    /// - [LoroError::NotImplemented] if the doc is a shallow doc
+    /// - [LoroError::IoError]
+    pub fn flush(&self) -> LoroResult<()> {
+        let Some(dir) = self.storage_dir.as_ref() else {
+            return Err(LoroError::ArgErr(
+                "The doc is not opened from a directory".into(),
+            ));
+        };
+
+        self.with_barrier(|| {
+            let oplog = self.oplog().lock().unwrap();
+            if oplog.is_shallow() {
+                return Err(LoroError::NotImplemented(
+                    "Persisting a shallow doc into a directory",
+                ));
+            }
+
+            oplog.persist_change_store()?;
+            let frontiers = oplog.frontiers().clone();
+            drop(oplog);
+            let state_bytes = encode_latest_state(self);
+            write_state_file(dir, &frontiers, &state_bytes)
+        })
+    }
+}
+
+/// ```log
+/// ┌───────────────────────────────────────────────────┐
+/// │ State File                                        │
+/// │┌ ─ ─ ─ ─ ─ ─ ─ ─ ┌ ─ ─ ─ ─ ─ ─ ┌ ─ ─ ─ ─ ─ ─ ─ ─ ─ │
+/// │  Frontiers Len  │  Frontiers  │  State KV Bytes  ││
+/// ││     u32        │    bytes    │      bytes        │
+/// │ ─ ─ ─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ┘─ ─ ─ ─ ─ ─ ─ ─ ─ ┘│
+/// └───────────────────────────────────────────────────┘
+/// ```
+fn write_state_file(dir: &Path, frontiers: &Frontiers, state_bytes: &[u8]) -> LoroResult<()> {
+    let frontiers_bytes = frontiers.encode();
+    let tmp = dir.join(STATE_TMP_FILE);
+    let mut file = fs::File::create(&tmp).map_err(io_err)?;
+    file.write_all(&(frontiers_bytes.len() as u32).to_le_bytes())
+        .map_err(io_err)?;
+    file.write_all(&frontiers_bytes).map_err(io_err)?;
+    file.write_all(state_bytes).map_err(io_err)?;
+    file.sync_all().map_err(io_err)?;
+    fs::rename(&tmp, dir.join(STATE_FILE)).map_err(io_err)
+}
+
+fn read_state_file(dir: &Path) -> LoroResult<Option<(Frontiers, Bytes)>> {
+    let bytes = match fs::read(dir.join(STATE_FILE)) {
+        Ok(bytes) => Bytes::from(bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_err(e)),
+    };
+
+    let mut r = bytes;
+    if r.len() < 4 {
+        return Err(LoroError::DecodeError("Invalid state file".into()));
+    }
+    let frontiers_len = r.get_u32_le() as usize;
+    if r.len() < frontiers_len {
+        return Err(LoroError::DecodeError("Invalid state file".into()));
+    }
+    let frontiers = Frontiers::decode(&r.split_to(frontiers_len))?;
+    Ok(Some((frontiers, r)))
+}
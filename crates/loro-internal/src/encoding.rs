@@ -1,4 +1,6 @@
 pub(crate) mod arena;
+mod compression;
+mod encryption;
 pub(crate) mod fast_snapshot;
 pub(crate) mod json_schema;
 mod outdated_encode_reordered;
@@ -11,12 +13,28 @@ pub(crate) use outdated_encode_reordered::{
 use outdated_encode_reordered::{import_changes_to_oplog, ImportChangesResult};
 pub(crate) use value::OwnedValue;
 
+pub(crate) use compression::{compress_blob, decompress_blob};
+pub use compression::{Compression, ExportOptions};
+#[cfg(feature = "encryption")]
+pub use encryption::ChaCha20Poly1305Cipher;
+pub use encryption::Cipher;
+pub(crate) use encryption::{decrypt_blob, encrypt_blob};
+
+use crate::change::Change;
+use crate::signature::verify_changes;
 use crate::version::{Frontiers, VersionRange};
 use crate::LoroDoc;
-use crate::{oplog::OpLog, LoroError, VersionVector};
+use crate::{
+    oplog::{ExportedBlocks, OpLog},
+    LoroError, VersionVector,
+};
+use bytes::Bytes;
+use fast_snapshot::Snapshot;
 use loro_common::{HasIdSpan, IdSpan, InternalString, LoroEncodeError, LoroResult, ID};
 use num_traits::{FromPrimitive, ToPrimitive};
 use std::borrow::Cow;
+use std::io::{Read, Write};
+use xxhash_rust::xxh32::Xxh32;
 
 /// The mode of the export.
 ///
@@ -227,6 +245,11 @@ impl TryFrom<[u8; 2]> for EncodeMode {
 pub struct ImportStatus {
     pub success: VersionRange,
     pub pending: Option<VersionRange>,
+    /// The changes skipped because of their invalid signatures.
+    ///
+    /// It's only set when the verifier is configured with
+    /// [crate::signature::InvalidSignaturePolicy::Quarantine].
+    pub quarantined: Option<VersionRange>,
 }
 
 pub(crate) fn decode_oplog(
@@ -242,6 +265,21 @@ pub(crate) fn decode_oplog(
         EncodeMode::FastUpdates => fast_snapshot::decode_updates(oplog, body.to_vec().into()),
         EncodeMode::Auto => unreachable!(),
     }?;
+    import_changes(oplog, changes)
+}
+
+/// Import the changes in the oplog bytes of a fast snapshot into a non-empty oplog.
+pub(crate) fn decode_snapshot_oplog(
+    oplog: &mut OpLog,
+    oplog_bytes: Bytes,
+) -> Result<ImportStatus, LoroError> {
+    let changes = fast_snapshot::decode_oplog_bytes(oplog, oplog_bytes)?;
+    import_changes(oplog, changes)
+}
+
+fn import_changes(oplog: &mut OpLog, changes: Vec<Change>) -> Result<ImportStatus, LoroError> {
+    let (changes, quarantined) =
+        verify_changes(&oplog.configure.signature_config(), changes, &oplog.arena)?;
     let ImportChangesResult {
         mut imported,
         latest_ids,
@@ -262,6 +300,7 @@ pub(crate) fn decode_oplog(
     Ok(ImportStatus {
         success: imported,
         pending: (!pending.is_empty()).then_some(pending),
+        quarantined: (!quarantined.is_empty()).then_some(quarantined),
     })
 }
 
@@ -363,6 +402,14 @@ pub(crate) fn export_fast_updates_in_range(oplog: &OpLog, spans: &[IdSpan]) -> V
     .unwrap()
 }
 
+pub(crate) fn export_pending_changes(oplog: &OpLog) -> Vec<u8> {
+    encode_with(EncodeMode::FastUpdates, &mut |ans| {
+        oplog.export_pending_changes(ans);
+        Ok(())
+    })
+    .unwrap()
+}
+
 pub(crate) fn export_shallow_snapshot(
     doc: &LoroDoc,
     f: &Frontiers,
@@ -415,6 +462,269 @@ fn encode_with(
     Ok(ans)
 }
 
+/// Export the doc into the writer.
+///
+/// The snapshot modes write the parts of the snapshot into the writer one by one,
+/// so they are not concatenated into a single blob. The checksum in the header is
+/// calculated over the parts before they are written.
+///
+/// The updates modes write the change blocks into the writer one block at a time.
+/// The blocks are hashed in a first pass, because the checksum must be known before
+/// writing the header.
+pub(crate) fn export_to_writer<W: Write>(
+    doc: &LoroDoc,
+    mode: ExportMode,
+    w: &mut W,
+) -> Result<(), LoroEncodeError> {
+    let snapshot = match mode {
+        ExportMode::Snapshot => fast_snapshot::encode_snapshot_inner(doc),
+        ExportMode::Updates { from } => {
+            let blocks = doc.oplog.lock().unwrap().collect_blocks_from(&from);
+            return write_fast_updates(blocks, w);
+        }
+        ExportMode::UpdatesInRange { spans } => {
+            let blocks = doc
+                .oplog
+                .lock()
+                .unwrap()
+                .collect_blocks_in_range(spans.as_ref());
+            return write_fast_updates(blocks, w);
+        }
+        ExportMode::ShallowSnapshot(f) => {
+            check_target_version_reachable(doc, &f)?;
+            shallow_snapshot::export_shallow_snapshot_inner(doc, &f)?.0
+        }
+        ExportMode::StateOnly(f) => {
+            let f = match f {
+                Some(f) => f.into_owned(),
+                None => doc.oplog_frontiers(),
+            };
+            check_target_version_reachable(doc, &f)?;
+            shallow_snapshot::export_state_only_snapshot_inner(doc, &f)?.0
+        }
+        ExportMode::SnapshotAt { version } => {
+            check_target_version_reachable(doc, &version)?;
+            shallow_snapshot::encode_snapshot_at_inner(doc, &version)?
+        }
+    };
+
+    write_fast_snapshot(snapshot, w)
+}
+
+fn write_all<W: Write>(w: &mut W, bytes: &[u8]) -> Result<(), LoroEncodeError> {
+    w.write_all(bytes)
+        .map_err(|e| LoroEncodeError::IoError(e.to_string()))
+}
+
+fn write_fast_updates<W: Write>(blocks: ExportedBlocks, w: &mut W) -> Result<(), LoroEncodeError> {
+    let mode = EncodeMode::FastUpdates.to_bytes();
+    let mut hasher = HashWriter(Xxh32::new(XXH_SEED));
+    hasher.0.update(&mode);
+    blocks.write_to(&mut hasher).unwrap();
+
+    let mut checksum = [0; 16];
+    checksum[12..16].copy_from_slice(&hasher.0.digest().to_le_bytes());
+    write_all(w, &MAGIC_BYTES)?;
+    write_all(w, &checksum)?;
+    write_all(w, &mode)?;
+    blocks
+        .write_to(w)
+        .map_err(|e| LoroEncodeError::IoError(e.to_string()))
+}
+
+/// A writer that only calculates the checksum of the written bytes.
+struct HashWriter(Xxh32);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_fast_snapshot<W: Write>(snapshot: Snapshot, w: &mut W) -> Result<(), LoroEncodeError> {
+    let mode = EncodeMode::FastSnapshot.to_bytes();
+    let parts = [
+        snapshot.oplog_bytes,
+        snapshot
+            .state_bytes
+            .unwrap_or_else(|| Bytes::from_static(fast_snapshot::EMPTY_MARK)),
+        snapshot.shallow_root_state_bytes,
+    ];
+    let mut hasher = Xxh32::new(XXH_SEED);
+    hasher.update(&mode);
+    for part in parts.iter() {
+        hasher.update(&(part.len() as u32).to_le_bytes());
+        hasher.update(part);
+    }
+
+    let mut checksum = [0; 16];
+    checksum[12..16].copy_from_slice(&hasher.digest().to_le_bytes());
+    write_all(w, &MAGIC_BYTES)?;
+    write_all(w, &checksum)?;
+    write_all(w, &mode)?;
+    for part in parts.iter() {
+        write_all(w, &(part.len() as u32).to_le_bytes())?;
+        write_all(w, part)?;
+    }
+
+    Ok(())
+}
+
+/// The blob read by [read_blob_from].
+pub(crate) enum ReadBlob {
+    /// A fast snapshot whose checksum has been checked
+    Snapshot(Snapshot),
+    /// The header of a fast updates blob. The blocks are left in the reader
+    /// and should be read by [decode_updates_from_reader].
+    Updates { checksum: u32 },
+    /// A blob in the other modes, including the header
+    Other(Vec<u8>),
+}
+
+/// Read a blob from the reader.
+///
+/// The parts of a fast snapshot are read into separate buffers, and the reader
+/// is not read beyond the end of the snapshot. Only the header of fast updates
+/// is read, so that the blocks can be decoded one by one. The blobs in the other
+/// modes are read until the end of the reader.
+pub(crate) fn read_blob_from<R: Read>(r: &mut R) -> LoroResult<ReadBlob> {
+    let mut header = [0; MIN_HEADER_SIZE];
+    read_exact(r, &mut header)?;
+    if header[..4] != MAGIC_BYTES {
+        return Err(LoroError::DecodeError("Invalid magic bytes".into()));
+    }
+
+    let expected = u32::from_le_bytes(header[16..20].try_into().unwrap());
+    // The compressed blobs are decompressed as a whole
+    let mode: Option<EncodeMode> = [header[20], header[21]].try_into().ok();
+    if header[20] == 0 && mode == Some(EncodeMode::FastUpdates) {
+        return Ok(ReadBlob::Updates { checksum: expected });
+    }
+    if header[20] != 0 || mode != Some(EncodeMode::FastSnapshot) {
+        let mut bytes = header.to_vec();
+        r.read_to_end(&mut bytes).map_err(io_err)?;
+        return Ok(ReadBlob::Other(bytes));
+    }
+
+    let mut hasher = Xxh32::new(XXH_SEED);
+    hasher.update(&header[20..22]);
+    let oplog_bytes = read_part(r, &mut hasher)?;
+    let state_bytes = read_part(r, &mut hasher)?;
+    let shallow_root_state_bytes = read_part(r, &mut hasher)?;
+    if hasher.digest() != expected {
+        return Err(LoroError::DecodeChecksumMismatchError);
+    }
+
+    Ok(ReadBlob::Snapshot(Snapshot {
+        oplog_bytes,
+        state_bytes: (state_bytes != fast_snapshot::EMPTY_MARK).then_some(state_bytes),
+        shallow_root_state_bytes,
+    }))
+}
+
+fn read_part<R: Read>(r: &mut R, hasher: &mut Xxh32) -> LoroResult<Bytes> {
+    let mut len = [0; 4];
+    read_exact(r, &mut len)?;
+    hasher.update(&len);
+    let len = u32::from_le_bytes(len) as u64;
+    // Don't trust the length before the data is actually read
+    let mut buf = Vec::new();
+    r.by_ref().take(len).read_to_end(&mut buf).map_err(io_err)?;
+    if buf.len() as u64 != len {
+        return Err(LoroError::DecodeError(
+            "Unexpected end of import data".into(),
+        ));
+    }
+
+    hasher.update(&buf);
+    Ok(buf.into())
+}
+
+/// Read the blocks of a fast updates blob from the reader one by one, and import them
+/// into the oplog.
+///
+/// The blocks are kept in separate buffers, and each of them is released once it's
+/// decoded. They are decoded after the checksum of all the blocks is checked, because
+/// the block decoder assumes valid input.
+///
+/// The header should have been read by [read_blob_from].
+pub(crate) fn decode_updates_from_reader<R: Read>(
+    oplog: &mut OpLog,
+    r: &mut R,
+    checksum: u32,
+) -> Result<ImportStatus, LoroError> {
+    let mut hasher = Xxh32::new(XXH_SEED);
+    hasher.update(&EncodeMode::FastUpdates.to_bytes());
+    let mut blocks = Vec::new();
+    while let Some(len) = read_block_len(r, &mut hasher)? {
+        let mut buf = Vec::new();
+        r.by_ref().take(len).read_to_end(&mut buf).map_err(io_err)?;
+        if buf.len() as u64 != len {
+            return Err(LoroError::DecodeError(
+                "Unexpected end of import data".into(),
+            ));
+        }
+
+        hasher.update(&buf);
+        blocks.push(Bytes::from(buf));
+    }
+
+    if hasher.digest() != checksum {
+        return Err(LoroError::DecodeChecksumMismatchError);
+    }
+
+    let mut changes = Vec::new();
+    for block in blocks {
+        changes.extend(fast_snapshot::decode_block(oplog, block)?);
+    }
+
+    changes.sort_unstable_by_key(|x| x.lamport);
+    import_changes(oplog, changes)
+}
+
+/// Read the leb128 length of the next block. Return `None` at the end of the reader.
+fn read_block_len<R: Read>(r: &mut R, hasher: &mut Xxh32) -> LoroResult<Option<u64>> {
+    let mut len = 0u64;
+    for i in 0..10 {
+        let mut byte = [0; 1];
+        if r.read(&mut byte).map_err(io_err)? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+
+            return Err(LoroError::DecodeError(
+                "Unexpected end of import data".into(),
+            ));
+        }
+
+        hasher.update(&byte);
+        len |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(len));
+        }
+    }
+
+    Err(LoroError::DecodeError("Invalid block length".into()))
+}
+
+fn read_exact<R: Read>(r: &mut R, buf: &mut [u8]) -> LoroResult<()> {
+    r.read_exact(buf).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => {
+            LoroError::DecodeError("Unexpected end of import data".into())
+        }
+        _ => io_err(e),
+    })
+}
+
+fn io_err(e: std::io::Error) -> LoroError {
+    LoroError::IoError(e.to_string().into_boxed_str())
+}
+
 pub(crate) fn decode_snapshot(
     doc: &LoroDoc,
     mode: EncodeMode,
@@ -433,6 +743,7 @@ pub(crate) fn decode_snapshot(
     Ok(ImportStatus {
         success: VersionRange::from_vv(&doc.oplog_vv()),
         pending: None,
+        quarantined: None,
     })
 }
 
@@ -495,7 +806,12 @@ impl LoroDoc {
         blob: &[u8],
         check_checksum: bool,
     ) -> LoroResult<ImportBlobMetadata> {
-        let parsed = parse_header_and_body(blob, check_checksum)?;
+        if encryption::is_encrypted(blob) {
+            return encryption::decode_encrypted_blob_meta(blob, check_checksum);
+        }
+
+        let blob = decompress_blob(blob)?;
+        let parsed = parse_header_and_body(&blob, check_checksum)?;
         match parsed.mode {
             EncodeMode::Auto => unreachable!(),
             EncodeMode::OutdatedRle | EncodeMode::OutdatedSnapshot => {
@@ -0,0 +1,163 @@
+//! The compression of the exported blobs.
+//!
+//! A compressed blob has the same header as an uncompressed one, except that
+//! the first byte of the encode mode records the compression type:
+//!
+//! ```log
+//! ┌───────┬──────────┬─────────────┬──────┬─────────────────────┐
+//! │ Magic │ Checksum │ Compression │ Mode │   Compressed Body   │
+//! │  4B   │   16B    │     1B      │  1B  │                     │
+//! └───────┴──────────┴─────────────┴──────┴─────────────────────┘
+//! ```
+//!
+//! The checksum is calculated over the compressed bytes, so it can be checked
+//! before decompressing. The compression byte is always 0 in the uncompressed
+//! blobs, so the old versions reject the compressed blobs as an unknown mode.
+use std::borrow::Cow;
+
+use loro_common::{LoroEncodeError, LoroError, LoroResult};
+use loro_kv_store::compress::{compress, decompress, CompressionType};
+
+use super::{MAGIC_BYTES, MIN_HEADER_SIZE, XXH_SEED};
+
+/// The compression algorithm applied to the body of an exported blob.
+///
+/// The compression is recorded in the header of the blob, so the blob can be
+/// imported directly without specifying it.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// The body is not compressed.
+    #[default]
+    None,
+    /// LZ4 frame format. It's fast, with a moderate compression ratio.
+    Lz4,
+    /// Zstandard. It has a higher compression ratio than LZ4, but it's slower.
+    ///
+    /// The level should be in `1..=22`. `0` means the default level of zstd.
+    #[cfg(feature = "zstd")]
+    Zstd {
+        /// The compression level
+        level: i32,
+    },
+}
+
+impl Compression {
+    fn to_u8(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> LoroResult<Self> {
+        match v {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Lz4),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Compression::Zstd { level: 0 }),
+            #[cfg(not(feature = "zstd"))]
+            2 => Err(LoroError::DecodeError(
+                "The blob is compressed by zstd, but the zstd feature is not enabled".into(),
+            )),
+            _ => Err(LoroError::DecodeError(
+                format!("Unknown compression type: {v}").into(),
+            )),
+        }
+    }
+}
+
+/// The options of [`crate::LoroDoc::export_with`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// The compression applied to the exported blob.
+    pub compression: Compression,
+}
+
+impl ExportOptions {
+    /// The default options, which don't compress the blob.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the compression applied to the exported blob.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+/// Compress the body of an encoded blob and record the compression in its header.
+pub(crate) fn compress_blob(
+    blob: Vec<u8>,
+    compression: Compression,
+) -> Result<Vec<u8>, LoroEncodeError> {
+    if compression == Compression::None {
+        return Ok(blob);
+    }
+
+    let body = &blob[MIN_HEADER_SIZE..];
+    let mut ans = Vec::with_capacity(MIN_HEADER_SIZE + body.len() / 2);
+    ans.extend_from_slice(&blob[..MIN_HEADER_SIZE]);
+    ans[20] = compression.to_u8();
+    match compression {
+        Compression::None => unreachable!(),
+        Compression::Lz4 => compress(&mut ans, body, CompressionType::LZ4),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level } => {
+            zstd::stream::copy_encode(body, &mut ans, level)
+                .map_err(|e| LoroEncodeError::IoError(e.to_string()))?;
+        }
+    }
+
+    write_checksum(&mut ans);
+    Ok(ans)
+}
+
+/// Decompress the blob if it's compressed, so that it can be parsed by
+/// [`super::parse_header_and_body`].
+///
+/// The checksum of a compressed blob is checked before decompressing it.
+pub(crate) fn decompress_blob(bytes: &[u8]) -> LoroResult<Cow<'_, [u8]>> {
+    if bytes.len() < MIN_HEADER_SIZE || bytes[..4] != MAGIC_BYTES || bytes[20] == 0 {
+        // Leave the invalid blobs to `parse_header_and_body` to report
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    if bytes[20] == super::encryption::ENCRYPTED_MARK {
+        return Err(LoroError::ImportEncryptedBlob);
+    }
+
+    let compression = Compression::from_u8(bytes[20])?;
+    let expected = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+    if xxhash_rust::xxh32::xxh32(&bytes[20..], XXH_SEED) != expected {
+        return Err(LoroError::DecodeChecksumMismatchError);
+    }
+
+    let body = &bytes[MIN_HEADER_SIZE..];
+    let mut ans = Vec::with_capacity(MIN_HEADER_SIZE + body.len() * 2);
+    ans.extend_from_slice(&bytes[..MIN_HEADER_SIZE]);
+    ans[20] = 0;
+    match compression {
+        Compression::None => unreachable!(),
+        Compression::Lz4 => {
+            decompress(&mut ans, body.to_vec().into(), CompressionType::LZ4)?;
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { .. } => {
+            zstd::stream::copy_decode(body, &mut ans)
+                .map_err(|e| LoroError::DecodeError(e.to_string().into()))?;
+        }
+    }
+
+    write_checksum(&mut ans);
+    Ok(Cow::Owned(ans))
+}
+
+fn write_checksum(blob: &mut [u8]) {
+    let checksum = xxhash_rust::xxh32::xxh32(&blob[20..], XXH_SEED);
+    blob[16..20].copy_from_slice(&checksum.to_le_bytes());
+}
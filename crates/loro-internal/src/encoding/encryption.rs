@@ -0,0 +1,240 @@
+//! The encryption of the exported blobs.
+//!
+//! The body of an encrypted blob is opaque, but its metadata is kept in plain
+//! text so that a relay server can route the blob without being able to read it:
+//!
+//! ```log
+//! ┌───────┬──────────┬───────────┬──────┬──────────┬──────────┬─────────────────────┐
+//! │ Magic │ Checksum │ Encrypted │ Mode │ Meta Len │ Metadata │  Encrypted Blob     │
+//! │  4B   │   16B    │  1B 0x80  │  1B  │  u32 LE  │          │                     │
+//! └───────┴──────────┴───────────┴──────┴──────────┴──────────┴─────────────────────┘
+//! ```
+//!
+//! - The metadata is the [ImportBlobMetadata] of the original blob.
+//! - The encrypted blob is the original blob (including its header) encrypted by
+//!   the [Cipher]. The bytes from the mode to the end of the metadata are passed to
+//!   the cipher as the associated data, so the metadata can't be tampered with.
+//! - The checksum is calculated over the bytes after it, like the other blobs.
+use loro_common::{LoroEncodeError, LoroError, LoroResult};
+use serde::{Deserialize, Serialize};
+
+use super::{EncodedBlobMode, ImportBlobMetadata, MAGIC_BYTES, MIN_HEADER_SIZE, XXH_SEED};
+use crate::version::{Frontiers, VersionVector};
+
+pub(crate) const ENCRYPTED_MARK: u8 = 0x80;
+
+/// The cipher used by [`crate::LoroDoc::export_encrypted`] and
+/// [`crate::LoroDoc::import_encrypted`].
+///
+/// It should be an AEAD cipher, so that the tampered blobs and metadata are
+/// rejected when decrypting. The implementation is responsible for generating
+/// the nonce and storing it in the ciphertext.
+pub trait Cipher: Send + Sync {
+    /// Encrypt the plaintext, authenticating the associated data as well.
+    fn encrypt(&self, plaintext: &[u8], associated_data: &[u8])
+        -> Result<Vec<u8>, LoroEncodeError>;
+    /// Decrypt the ciphertext produced by [`Cipher::encrypt`] with the same associated data.
+    fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> LoroResult<Vec<u8>>;
+}
+
+/// The default [Cipher] implemented with ChaCha20-Poly1305.
+///
+/// A random 12-byte nonce is generated for each encryption and prepended to the ciphertext.
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct ChaCha20Poly1305Cipher {
+    cipher: chacha20poly1305::ChaCha20Poly1305,
+}
+
+#[cfg(feature = "encryption")]
+impl ChaCha20Poly1305Cipher {
+    const NONCE_LEN: usize = 12;
+
+    /// Create a cipher with the 256-bit key.
+    pub fn new(key: &[u8; 32]) -> Self {
+        use chacha20poly1305::KeyInit;
+        Self {
+            cipher: chacha20poly1305::ChaCha20Poly1305::new(key.into()),
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for ChaCha20Poly1305Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChaCha20Poly1305Cipher").finish()
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Cipher for ChaCha20Poly1305Cipher {
+    fn encrypt(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        use chacha20poly1305::aead::{Aead, Payload};
+        let mut nonce = [0; Self::NONCE_LEN];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| LoroEncodeError::EncryptionError(e.to_string()))?;
+        let payload = Payload {
+            msg: plaintext,
+            aad: associated_data,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt((&nonce).into(), payload)
+            .map_err(|e| LoroEncodeError::EncryptionError(e.to_string()))?;
+        let mut ans = Vec::with_capacity(Self::NONCE_LEN + ciphertext.len());
+        ans.extend_from_slice(&nonce);
+        ans.extend_from_slice(&ciphertext);
+        Ok(ans)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> LoroResult<Vec<u8>> {
+        use chacha20poly1305::aead::{Aead, Payload};
+        if ciphertext.len() < Self::NONCE_LEN {
+            return Err(LoroError::DecryptionError(
+                "The ciphertext is too short".into(),
+            ));
+        }
+
+        let (nonce, msg) = ciphertext.split_at(Self::NONCE_LEN);
+        let payload = Payload {
+            msg,
+            aad: associated_data,
+        };
+        self.cipher
+            .decrypt(chacha20poly1305::Nonce::from_slice(nonce), payload)
+            .map_err(|e| LoroError::DecryptionError(e.to_string().into()))
+    }
+}
+
+/// The plain text metadata of an encrypted blob
+#[derive(Serialize, Deserialize)]
+struct EncodedMeta {
+    partial_start_vv: VersionVector,
+    partial_end_vv: VersionVector,
+    start_timestamp: i64,
+    start_frontiers: Vec<u8>,
+    end_timestamp: i64,
+    change_num: u32,
+    mode: u8,
+}
+
+impl EncodedMeta {
+    fn new(meta: ImportBlobMetadata) -> Self {
+        Self {
+            partial_start_vv: meta.partial_start_vv,
+            partial_end_vv: meta.partial_end_vv,
+            start_timestamp: meta.start_timestamp,
+            start_frontiers: meta.start_frontiers.encode(),
+            end_timestamp: meta.end_timestamp,
+            change_num: meta.change_num,
+            mode: match meta.mode {
+                EncodedBlobMode::Snapshot => 0,
+                EncodedBlobMode::OutdatedSnapshot => 1,
+                EncodedBlobMode::ShallowSnapshot => 2,
+                EncodedBlobMode::OutdatedRle => 3,
+                EncodedBlobMode::Updates => 4,
+            },
+        }
+    }
+
+    fn into_meta(self) -> LoroResult<ImportBlobMetadata> {
+        Ok(ImportBlobMetadata {
+            partial_start_vv: self.partial_start_vv,
+            partial_end_vv: self.partial_end_vv,
+            start_timestamp: self.start_timestamp,
+            start_frontiers: Frontiers::decode(&self.start_frontiers)?,
+            end_timestamp: self.end_timestamp,
+            change_num: self.change_num,
+            mode: match self.mode {
+                0 => EncodedBlobMode::Snapshot,
+                1 => EncodedBlobMode::OutdatedSnapshot,
+                2 => EncodedBlobMode::ShallowSnapshot,
+                3 => EncodedBlobMode::OutdatedRle,
+                4 => EncodedBlobMode::Updates,
+                _ => return Err(LoroError::DecodeDataCorruptionError),
+            },
+        })
+    }
+}
+
+pub(crate) fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.len() >= MIN_HEADER_SIZE && bytes[..4] == MAGIC_BYTES && bytes[20] == ENCRYPTED_MARK
+}
+
+/// Encrypt the blob with the cipher, keeping its metadata readable.
+pub(crate) fn encrypt_blob(
+    blob: &[u8],
+    meta: ImportBlobMetadata,
+    cipher: &dyn Cipher,
+) -> Result<Vec<u8>, LoroEncodeError> {
+    let meta = postcard::to_allocvec(&EncodedMeta::new(meta)).unwrap();
+    let mut ans = Vec::with_capacity(MIN_HEADER_SIZE + 4 + meta.len() + blob.len());
+    ans.extend_from_slice(&MAGIC_BYTES);
+    ans.extend_from_slice(&[0; 16]);
+    ans.push(ENCRYPTED_MARK);
+    // The mode of the original blob
+    ans.push(blob[21]);
+    ans.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+    ans.extend_from_slice(&meta);
+    let ciphertext = cipher.encrypt(blob, &ans[20..])?;
+    ans.extend_from_slice(&ciphertext);
+    let checksum = xxhash_rust::xxh32::xxh32(&ans[20..], XXH_SEED);
+    ans[16..20].copy_from_slice(&checksum.to_le_bytes());
+    Ok(ans)
+}
+
+struct ParsedEncryptedBlob<'a> {
+    associated_data: &'a [u8],
+    meta: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+fn parse_encrypted_blob(bytes: &[u8], check_checksum: bool) -> LoroResult<ParsedEncryptedBlob<'_>> {
+    if !is_encrypted(bytes) || bytes.len() < MIN_HEADER_SIZE + 4 {
+        return Err(LoroError::DecodeError("Invalid encrypted blob".into()));
+    }
+
+    if check_checksum {
+        let expected = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+        if xxhash_rust::xxh32::xxh32(&bytes[20..], XXH_SEED) != expected {
+            return Err(LoroError::DecodeChecksumMismatchError);
+        }
+    }
+
+    let meta_len = u32::from_le_bytes(
+        bytes[MIN_HEADER_SIZE..MIN_HEADER_SIZE + 4]
+            .try_into()
+            .unwrap(),
+    );
+    let meta_end = MIN_HEADER_SIZE + 4 + meta_len as usize;
+    if bytes.len() < meta_end {
+        return Err(LoroError::DecodeError("Invalid encrypted blob".into()));
+    }
+
+    Ok(ParsedEncryptedBlob {
+        associated_data: &bytes[20..meta_end],
+        meta: &bytes[MIN_HEADER_SIZE + 4..meta_end],
+        ciphertext: &bytes[meta_end..],
+    })
+}
+
+/// Decode the plain text metadata of an encrypted blob.
+pub(crate) fn decode_encrypted_blob_meta(
+    bytes: &[u8],
+    check_checksum: bool,
+) -> LoroResult<ImportBlobMetadata> {
+    let parsed = parse_encrypted_blob(bytes, check_checksum)?;
+    let meta: EncodedMeta =
+        postcard::from_bytes(parsed.meta).map_err(|_| LoroError::DecodeDataCorruptionError)?;
+    meta.into_meta()
+}
+
+/// Decrypt the encrypted blob into the original blob.
+pub(crate) fn decrypt_blob(bytes: &[u8], cipher: &dyn Cipher) -> LoroResult<Vec<u8>> {
+    let parsed = parse_encrypted_blob(bytes, true)?;
+    cipher.decrypt(parsed.ciphertext, parsed.associated_data)
+}
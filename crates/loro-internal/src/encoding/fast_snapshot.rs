@@ -109,6 +109,25 @@ pub(crate) fn decode_snapshot_inner(
         shallow_root_state_bytes,
     } = snapshot;
     ensure_cov::notify_cov("loro_internal::import::fast_snapshot::decode_snapshot");
+    decode_snapshot_with_oplog_loader(
+        doc,
+        |oplog| oplog.decode_change_store(oplog_bytes),
+        state_bytes,
+        shallow_root_state_bytes,
+        origin,
+    )
+}
+
+/// Decode the snapshot whose oplog is loaded by `load_oplog`.
+///
+/// If `state_bytes` is `None`, the state will be calculated from the oplog.
+pub(crate) fn decode_snapshot_with_oplog_loader(
+    doc: &LoroDoc,
+    load_oplog: impl FnOnce(&mut OpLog) -> LoroResult<()>,
+    state_bytes: Option<Bytes>,
+    shallow_root_state_bytes: Bytes,
+    origin: InternalString,
+) -> Result<(), LoroError> {
     let mut oplog = doc.oplog().lock().map_err(|_| {
         LoroError::DecodeError(
             "decode_snapshot: failed to lock oplog"
@@ -132,7 +151,7 @@ pub(crate) fn decode_snapshot_inner(
 
     assert!(state.frontiers.is_empty());
     assert!(oplog.frontiers().is_empty());
-    oplog.decode_change_store(oplog_bytes)?;
+    load_oplog(&mut oplog)?;
     let need_calc = state_bytes.is_none();
     let state_frontiers;
     if shallow_root_state_bytes.is_empty() {
@@ -243,14 +262,46 @@ pub(crate) fn encode_snapshot_inner(doc: &LoroDoc) -> Snapshot {
     snapshot
 }
 
+/// Encode the state of the latest version of the doc.
+///
+/// The doc will be checked out to the latest version temporarily if it's detached.
+pub(crate) fn encode_latest_state(doc: &LoroDoc) -> Bytes {
+    assert!(doc.drop_pending_events().is_empty());
+    let old_state_frontiers = doc.state_frontiers();
+    let was_detached = doc.is_detached();
+    if was_detached {
+        let latest = doc.oplog_frontiers();
+        doc._checkout_without_emitting(&latest, false, true)
+            .unwrap();
+    }
+
+    let mut state = doc.app_state().lock().unwrap();
+    assert!(!state.is_in_txn());
+    state.ensure_all_alive_containers();
+    let state_bytes = state.store.encode();
+    drop(state);
+    if was_detached {
+        doc._checkout_without_emitting(&old_state_frontiers, false, true)
+            .unwrap();
+        doc.drop_pending_events();
+    }
+
+    state_bytes
+}
+
 pub(crate) fn decode_oplog(oplog: &mut OpLog, bytes: &[u8]) -> Result<Vec<Change>, LoroError> {
     let oplog_len = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
     let oplog_bytes = &bytes[4..4 + oplog_len as usize];
-    let mut changes = ChangeStore::decode_snapshot_for_updates(
-        oplog_bytes.to_vec().into(),
-        &oplog.arena,
-        oplog.vv(),
-    )?;
+    decode_oplog_bytes(oplog, oplog_bytes.to_vec().into())
+}
+
+/// Decode the changes in the oplog bytes of a snapshot that are not included in the oplog.
+pub(crate) fn decode_oplog_bytes(
+    oplog: &mut OpLog,
+    oplog_bytes: Bytes,
+) -> Result<Vec<Change>, LoroError> {
+    let mut changes =
+        ChangeStore::decode_snapshot_for_updates(oplog_bytes, &oplog.arena, oplog.vv())?;
     changes.sort_unstable_by_key(|x| x.lamport);
     Ok(changes)
 }
@@ -268,17 +319,21 @@ pub(crate) fn encode_updates_in_range<W: std::io::Write>(
     oplog.export_blocks_in_range(spans, w);
 }
 
+/// Decode the changes in a single block of the updates body.
+pub(crate) fn decode_block(oplog: &OpLog, block_bytes: Bytes) -> Result<Vec<Change>, LoroError> {
+    ChangeStore::decode_block_bytes(block_bytes, &oplog.arena, oplog.vv())
+}
+
 pub(crate) fn decode_updates(oplog: &mut OpLog, body: Bytes) -> Result<Vec<Change>, LoroError> {
     let mut reader: &[u8] = body.as_ref();
     let mut index = 0;
-    let self_vv = oplog.vv();
     let mut changes = Vec::new();
     while !reader.is_empty() {
         let old_reader_len = reader.len();
         let len = leb128::read::unsigned(&mut reader).unwrap() as usize;
         index += old_reader_len - reader.len();
         let block_bytes = body.slice(index..index + len);
-        let new_changes = ChangeStore::decode_block_bytes(block_bytes, &oplog.arena, self_vv)?;
+        let new_changes = decode_block(oplog, block_bytes)?;
         changes.extend(new_changes);
         index += len;
         reader = &reader[len..];
@@ -13,6 +13,7 @@ use crate::{
     },
     op::{FutureInnerContent, InnerContent, Op, SliceRange},
     oplog::BlockChangeRef,
+    signature::verify_changes,
     version::{Frontiers, VersionRange},
     OpLog, VersionVector,
 };
@@ -116,6 +117,8 @@ pub(crate) fn export_json_in_id_span(oplog: &OpLog, mut id_span: IdSpan) -> Vec<
 
 pub(crate) fn import_json(oplog: &mut OpLog, json: JsonSchema) -> LoroResult<ImportStatus> {
     let changes = decode_changes(json, &oplog.arena)?;
+    let (changes, quarantined) =
+        verify_changes(&oplog.configure.signature_config(), changes, &oplog.arena)?;
     let ImportChangesResult {
         latest_ids,
         pending_changes,
@@ -138,6 +141,7 @@ pub(crate) fn import_json(oplog: &mut OpLog, json: JsonSchema) -> LoroResult<Imp
         } else {
             Some(pending)
         },
+        quarantined: (!quarantined.is_empty()).then_some(quarantined),
     })
 }
 
@@ -435,7 +439,13 @@ pub(crate) fn encode_change(
                         start: *start,
                         end: *end,
                         style_key: key.to_string(),
-                        style_value: value.clone(),
+                        // The value of an embed may be a container
+                        style_value: match value {
+                            LoroValue::Container(id) if id.is_normal() => LoroValue::Container(
+                                register_container_id(id.clone(), peer_register.as_deref_mut()),
+                            ),
+                            _ => value.clone(),
+                        },
                         info: info.to_byte(),
                     },
                     InnerListOp::StyleEnd => json::TextOp::MarkEnd,
@@ -572,6 +582,7 @@ fn decode_changes(json: JsonSchema, arena: &SharedArena) -> LoroResult<Vec<Chang
             lamport,
             ops,
             commit_msg: msg.map(|x| x.into()),
+            signature: None,
         };
         ans.push(change);
     }
@@ -622,7 +633,12 @@ fn decode_op(op: json::JsonOp, arena: &SharedArena, peers: &Option<Vec<PeerID>>)
                     start,
                     end,
                     key: style_key.into(),
-                    value: style_value,
+                    value: match style_value {
+                        LoroValue::Container(id) if id.is_normal() => {
+                            LoroValue::Container(convert_container_id(id, peers))
+                        }
+                        v => v,
+                    },
                     info: TextStyleInfoFlag::from_byte(info),
                 }),
                 json::TextOp::MarkEnd => InnerContent::List(InnerListOp::StyleEnd),
@@ -144,6 +144,15 @@ pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
     start_from: &Frontiers,
     w: &mut W,
 ) -> Result<Frontiers, LoroEncodeError> {
+    let (snapshot, start_from) = export_state_only_snapshot_inner(doc, start_from)?;
+    _encode_snapshot(snapshot, w);
+    Ok(start_from)
+}
+
+pub(crate) fn export_state_only_snapshot_inner(
+    doc: &LoroDoc,
+    start_from: &Frontiers,
+) -> Result<(Snapshot, Frontiers), LoroEncodeError> {
     let oplog = doc.oplog().lock().unwrap();
     let start_from = calc_shallow_doc_start(&oplog, start_from);
     let mut start_vv = oplog.dag().frontiers_to_vv(&start_from).unwrap();
@@ -186,7 +195,6 @@ pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
         state_bytes: None,
         shallow_root_state_bytes: shallow_state_bytes,
     };
-    _encode_snapshot(snapshot, w);
 
     if state_frontiers != start_from {
         doc._checkout_without_emitting(&state_frontiers, false, false)
@@ -198,7 +206,7 @@ pub(crate) fn export_state_only_snapshot<W: std::io::Write>(
     }
 
     doc.drop_pending_events();
-    Ok(start_from)
+    Ok((snapshot, start_from))
 }
 
 fn cids_to_bytes(
@@ -253,6 +261,15 @@ pub(crate) fn encode_snapshot_at<W: std::io::Write>(
     frontiers: &Frontiers,
     w: &mut W,
 ) -> Result<(), LoroEncodeError> {
+    let snapshot = encode_snapshot_at_inner(doc, frontiers)?;
+    _encode_snapshot(snapshot, w);
+    Ok(())
+}
+
+pub(crate) fn encode_snapshot_at_inner(
+    doc: &LoroDoc,
+    frontiers: &Frontiers,
+) -> Result<Snapshot, LoroEncodeError> {
     let was_detached = doc.is_detached();
     let version_before_start = doc.oplog_frontiers();
     doc._checkout_without_emitting(frontiers, true, false)
@@ -290,16 +307,11 @@ pub(crate) fn encode_snapshot_at<W: std::io::Write>(
         let state_kv = state.store.get_kv_clone();
         state_kv.retain_keys(&alive_c_bytes);
         let bytes = state_kv.export();
-        _encode_snapshot(
-            Snapshot {
-                oplog_bytes,
-                state_bytes: Some(bytes),
-                shallow_root_state_bytes: Bytes::new(),
-            },
-            w,
-        );
-
-        Ok(())
+        Ok(Snapshot {
+            oplog_bytes,
+            state_bytes: Some(bytes),
+            shallow_root_state_bytes: Bytes::new(),
+        })
     };
     doc._checkout_without_emitting(&version_before_start, false, false)
         .unwrap();
@@ -355,7 +355,7 @@ impl InternalDiff {
             InternalDiff::ListRaw(s) => s.is_empty(),
             InternalDiff::RichtextRaw(t) => t.is_empty(),
             InternalDiff::Map(m) => m.updated.is_empty(),
-            InternalDiff::Tree(t) => t.is_empty(),
+            InternalDiff::Tree(t) => t.is_empty() && t.rejected.is_empty(),
             InternalDiff::MovableList(t) => t.is_empty(),
             #[cfg(feature = "counter")]
             InternalDiff::Counter(c) => c.abs() < f64::EPSILON,
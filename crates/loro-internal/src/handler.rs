@@ -4,16 +4,24 @@ use crate::{
     container::{
         idx::ContainerIdx,
         list::list_op::{DeleteSpan, DeleteSpanWithId, ListOp},
-        richtext::{richtext_state::PosType, RichtextState, StyleOp, TextStyleInfoFlag},
+        richtext::{
+            is_reserved_style_key, richtext_state::PosType, RichtextState, StyleOp,
+            TextStyleInfoFlag, EMBED_CHAR, EMBED_STR, EMBED_STYLE_KEY,
+        },
     },
     cursor::{Cursor, Side},
     delta::{DeltaItem, Meta, StyleMeta, TreeExternalDiff},
-    diff::{diff, diff_impl::UpdateTimeoutError, OperateProxy},
+    diff::{
+        diff,
+        diff_impl::{UpdateFromBaseError, UpdateGranularity, UpdateTimeoutError},
+        diff_with_cleanup, OperateProxy,
+    },
     event::{Diff, TextDiff, TextDiffItem, TextMeta},
     op::ListSlice,
     state::{IndexType, State, TreeParentId},
     txn::EventHint,
     utils::{string_slice::StringSlice, utf16::count_utf16_len},
+    version::Frontiers,
     LoroDoc, LoroDocInner,
 };
 use append_only_bytes::BytesSlice;
@@ -25,17 +33,48 @@ use loro_common::{
 };
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, cmp::Reverse, collections::BinaryHeap, fmt::Debug, ops::Deref, sync::Arc};
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::Debug,
+    ops::{Deref, Range},
+    sync::Arc,
+};
 use tracing::{error, instrument};
 
 pub use crate::diff::diff_impl::UpdateOptions;
+pub use text_annotation::{Annotation, AnnotationEvent, AnnotationSubscriber};
+pub use text_lines::{LineChange, LineSubscriber};
+pub use text_search::TextPattern;
 pub use tree::TreeHandler;
+pub use tree_history::TreeMoveConflictSubscriber;
+pub use tree_sorted::{SortedChildrenSubscriber, SortedChildrenView};
+pub use tree_traversal::{TreeAncestors, TreeDescendantsBfs, TreeDescendantsDfs};
 mod movable_list_apply_delta;
+mod text_annotation;
+mod text_lines;
+mod text_search;
 mod tree;
+mod tree_history;
+mod tree_sorted;
+mod tree_traversal;
 
 const INSERT_CONTAINER_VALUE_ARG_ERROR: &str =
     "Cannot insert a LoroValue::Container directly. To create child container, use insert_container";
 
+/// The style keys of embeds and annotations are reserved and can't be marked directly.
+fn check_style_key(key: &str) -> LoroResult<()> {
+    if is_reserved_style_key(key) {
+        return Err(LoroError::ArgErr(
+            format!("Style key {key:?} is reserved, use insert_embed or annotate instead")
+                .into_boxed_str(),
+        ));
+    }
+
+    Ok(())
+}
+
 mod text_update;
 
 pub trait HandlerTrait: Clone + Sized {
@@ -274,10 +313,11 @@ impl HandlerTrait for TextHandler {
                 let text = inner.into_text().unwrap();
                 let mut delta: Vec<TextDelta> = Vec::new();
                 for span in t.value.iter() {
-                    delta.push(TextDelta::Insert {
-                        insert: span.text.to_string(),
-                        attributes: span.attributes.to_option_map(),
-                    });
+                    TextDelta::push_insert(
+                        &mut delta,
+                        span.text.as_str(),
+                        span.attributes.to_option_map(),
+                    );
                 }
 
                 text.apply_delta_with_txn(txn, &delta)?;
@@ -289,7 +329,14 @@ impl HandlerTrait for TextHandler {
                 let ans = new_inner.into_text().unwrap();
 
                 let delta = self.get_delta();
-                ans.apply_delta_with_txn(txn, &delta)?;
+                let mut embedded = Vec::new();
+                ans.apply_delta_with_txn_and_remap(txn, &delta, &mut |old, new| {
+                    embedded.push((old, new))
+                })?;
+                let ans_inner = ans.attached_handler().unwrap();
+                for (old, new) in embedded {
+                    create_handler(a, old).attach(txn, ans_inner, new)?;
+                }
                 Ok(ans)
             }
         }
@@ -369,6 +416,11 @@ pub enum TextDelta {
         insert: String,
         attributes: Option<FxHashMap<String, LoroValue>>,
     },
+    /// An inline embed, which occupies one position in the text
+    Embed {
+        embed: LoroValue,
+        attributes: Option<FxHashMap<String, LoroValue>>,
+    },
     Delete {
         delete: usize,
     },
@@ -395,14 +447,15 @@ impl TextDelta {
                     delete,
                 } => {
                     if value.rle_len() > 0 {
-                        ans.push(TextDelta::Insert {
-                            insert: value.to_string(),
-                            attributes: if attr.0.is_empty() {
+                        TextDelta::push_insert(
+                            &mut ans,
+                            value.as_str(),
+                            if attr.0.is_empty() {
                                 None
                             } else {
                                 Some(attr.0.clone())
                             },
-                        });
+                        );
                     }
                     if *delete > 0 {
                         ans.push(TextDelta::Delete { delete: *delete });
@@ -427,6 +480,11 @@ impl TextDelta {
                         TextMeta(attributes.unwrap_or_default()),
                     );
                 }
+                TextDelta::Embed { embed, attributes } => {
+                    let mut attributes = attributes.unwrap_or_default();
+                    attributes.insert(EMBED_STYLE_KEY.to_string(), embed);
+                    delta.push_insert(StringSlice::from(EMBED_STR), TextMeta(attributes));
+                }
                 TextDelta::Delete { delete } => {
                     delta.push_delete(delete);
                 }
@@ -435,6 +493,43 @@ impl TextDelta {
 
         delta
     }
+
+    /// Push the inserted text to `ans`, turning the embed chars marked with
+    /// [EMBED_STYLE_KEY] into [TextDelta::Embed].
+    pub(crate) fn push_insert(
+        ans: &mut Vec<TextDelta>,
+        text: &str,
+        mut attributes: Option<FxHashMap<String, LoroValue>>,
+    ) {
+        let embed = attributes
+            .as_mut()
+            .and_then(|x| x.remove(EMBED_STYLE_KEY))
+            .filter(|x| !x.is_null());
+        let attributes = attributes.filter(|x| !x.is_empty());
+        let Some(embed) = embed else {
+            ans.push(TextDelta::Insert {
+                insert: text.to_string(),
+                attributes,
+            });
+            return;
+        };
+
+        // The embed style only covers embed chars, but be lenient with other text
+        for (i, part) in text.split(EMBED_CHAR).enumerate() {
+            if i > 0 {
+                ans.push(TextDelta::Embed {
+                    embed: embed.clone(),
+                    attributes: attributes.clone(),
+                });
+            }
+            if !part.is_empty() {
+                ans.push(TextDelta::Insert {
+                    insert: part.to_string(),
+                    attributes: attributes.clone(),
+                });
+            }
+        }
+    }
 }
 
 impl From<&DeltaItem<StringSlice, StyleMeta>> for TextDelta {
@@ -1166,7 +1261,10 @@ impl Handler {
             }
             Self::Text(x) => {
                 let delta = diff.into_text().unwrap();
-                x.apply_delta(&TextDelta::from_text_diff(delta.iter()))?;
+                x.apply_delta_with_remap(
+                    &TextDelta::from_text_diff(delta.iter()),
+                    on_container_remap,
+                )?;
             }
             Self::List(x) => {
                 let delta = diff.into_list().unwrap();
@@ -1411,6 +1509,20 @@ impl TextHandler {
         }
     }
 
+    /// Get the number of the grapheme clusters, which are the characters perceived by
+    /// users, like an emoji made of several code points. It takes O(n).
+    pub fn len_grapheme(&self) -> usize {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.lock().unwrap();
+                t.value.len_grapheme()
+            }
+            MaybeDetached::Attached(a) => {
+                a.with_state(|state| state.as_richtext_state_mut().unwrap().len_grapheme())
+            }
+        }
+    }
+
     /// if `wasm` feature is enabled, it is a UTF-16 length
     /// otherwise, it is a Unicode length
     pub fn len_event(&self) -> usize {
@@ -1512,6 +1624,33 @@ impl TextHandler {
         }
     }
 
+    /// `start_index` and `end_index` are grapheme cluster indexes. It takes O(end_index).
+    pub fn slice_grapheme(&self, start_index: usize, end_index: usize) -> LoroResult<String> {
+        if end_index < start_index {
+            return Err(LoroError::EndIndexLessThanStartIndex {
+                start: start_index,
+                end: end_index,
+            });
+        }
+
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.lock().unwrap();
+                let range = t.value.grapheme_range_to_unicode(start_index..end_index)?;
+                let start = t.value.index_to_event_index(range.start, PosType::Unicode);
+                let end = t.value.index_to_event_index(range.end, PosType::Unicode);
+                t.value.get_text_slice_by_event_index(start, end - start)
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                let state = state.as_richtext_state_mut().unwrap();
+                let range = state.grapheme_range_to_unicode(start_index..end_index)?;
+                let start = state.index_to_event_index(range.start, PosType::Unicode);
+                let end = state.index_to_event_index(range.end, PosType::Unicode);
+                state.get_text_slice_by_event_index(start, end - start)
+            }),
+        }
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1598,6 +1737,44 @@ impl TextHandler {
         }
     }
 
+    /// `pos` is a grapheme cluster index. It takes O(pos) to find the position.
+    ///
+    /// The clusters are segmented on the current text, so a cluster split by concurrent
+    /// edits is just counted as several clusters.
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_grapheme(&self, pos: usize, s: &str) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let mut t = t.lock().unwrap();
+                let pos = t.value.grapheme_range_to_unicode(pos..pos)?.start;
+                let (index, _) = t
+                    .value
+                    .get_entity_index_for_text_insert(pos, PosType::Unicode)?;
+                t.value.insert_at_entity_index(
+                    index,
+                    BytesSlice::from_bytes(s.as_bytes()),
+                    IdFull::NONE_ID,
+                );
+                Ok(())
+            }
+            // The txn is locked until the edit is done, so the text can't change after the
+            // conversion
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                let pos = a
+                    .with_state(|state| {
+                        state
+                            .as_richtext_state_mut()
+                            .unwrap()
+                            .grapheme_range_to_unicode(pos..pos)
+                    })?
+                    .start;
+                self.insert_with_txn_and_attr(txn, pos, s, None, PosType::Unicode)?;
+                Ok(())
+            }),
+        }
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1617,6 +1794,96 @@ impl TextHandler {
         Ok(())
     }
 
+    /// Insert an inline embed at `pos`. It occupies one position in the text.
+    ///
+    /// `pos` is a Event Index. To embed a container, use [TextHandler::insert_embed_container].
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_embed(&self, pos: usize, value: impl Into<LoroValue>) -> LoroResult<()> {
+        let value = value.into();
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                if value.is_container() {
+                    return Err(LoroError::ArgErr(
+                        INSERT_CONTAINER_VALUE_ARG_ERROR
+                            .to_string()
+                            .into_boxed_str(),
+                    ));
+                }
+
+                let mut t = t.lock().unwrap();
+                let (index, _) = t
+                    .value
+                    .get_entity_index_for_text_insert(pos, PosType::Event)?;
+                t.value.insert_at_entity_index(
+                    index,
+                    BytesSlice::from_bytes(EMBED_STR.as_bytes()),
+                    IdFull::NONE_ID,
+                );
+                self.mark_for_detached(&mut t.value, EMBED_STYLE_KEY, &value, pos, pos + 1, false)
+            }
+            MaybeDetached::Attached(a) => {
+                a.with_txn(|txn| self.insert_embed_with_txn(txn, pos, value))
+            }
+        }
+    }
+
+    /// `pos` is a Event Index
+    pub fn insert_embed_with_txn(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        value: LoroValue,
+    ) -> LoroResult<()> {
+        if value.is_container() {
+            return Err(LoroError::ArgErr(
+                INSERT_CONTAINER_VALUE_ARG_ERROR
+                    .to_string()
+                    .into_boxed_str(),
+            ));
+        }
+
+        self.insert_with_txn_and_attr(txn, pos, EMBED_STR, None, PosType::Event)?;
+        self.mark_with_txn(txn, pos, pos + 1, EMBED_STYLE_KEY, value, false)
+    }
+
+    /// Insert a child container as an inline embed at `pos`.
+    ///
+    /// `pos` is a Event Index
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn insert_embed_container<H: HandlerTrait>(&self, pos: usize, child: H) -> LoroResult<H> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => Err(LoroError::MisuseDetachedContainer {
+                method: "insert_embed_container",
+            }),
+            MaybeDetached::Attached(a) => {
+                a.with_txn(|txn| self.insert_embed_container_with_txn(txn, pos, child))
+            }
+        }
+    }
+
+    pub fn insert_embed_container_with_txn<H: HandlerTrait>(
+        &self,
+        txn: &mut Transaction,
+        pos: usize,
+        child: H,
+    ) -> LoroResult<H> {
+        let inner = self.inner.try_attached_state()?;
+        self.insert_with_txn_and_attr(txn, pos, EMBED_STR, None, PosType::Event)?;
+        // The id of the embedded container is the id of the style op
+        let container_id = ContainerID::new_normal(txn.next_id(), child.kind());
+        self.mark_with_txn(
+            txn,
+            pos,
+            pos + 1,
+            EMBED_STYLE_KEY,
+            LoroValue::Container(container_id.clone()),
+            false,
+        )?;
+        child.attach(txn, inner, container_id)
+    }
+
     /// `pos` is a Event Index:
     ///
     /// - if feature="wasm", pos is a UTF-16 index
@@ -1675,6 +1942,37 @@ impl TextHandler {
         }
     }
 
+    /// Delete `len` grapheme clusters at the grapheme cluster index `pos`, so that a
+    /// character perceived by users is deleted as a whole. It takes O(pos + len) to find
+    /// the range.
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn delete_grapheme(&self, pos: usize, len: usize) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let mut t = t.lock().unwrap();
+                let range = t.value.grapheme_range_to_unicode(pos..pos + len)?;
+                let ranges =
+                    t.value
+                        .get_text_entity_ranges(range.start, range.len(), PosType::Unicode)?;
+                for range in ranges.iter().rev() {
+                    t.value
+                        .drain_by_entity_index(range.entity_start, range.entity_len(), None);
+                }
+                Ok(())
+            }
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                let range = a.with_state(|state| {
+                    state
+                        .as_richtext_state_mut()
+                        .unwrap()
+                        .grapheme_range_to_unicode(pos..pos + len)
+                })?;
+                self.delete_with_txn_inline(txn, range.start, range.len(), PosType::Unicode)
+            }),
+        }
+    }
+
     /// If attr is specified, it will be used as the attribute of the inserted text.
     /// It will override the existing attribute of the text.
     fn insert_with_txn_and_attr(
@@ -1928,14 +2226,28 @@ impl TextHandler {
         end: usize,
         key: impl Into<InternalString>,
         value: LoroValue,
+    ) -> LoroResult<()> {
+        let key: InternalString = key.into();
+        check_style_key(&key)?;
+        self.mark_unchecked(start, end, key, value, false)
+    }
+
+    /// Mark without checking whether the key is reserved
+    fn mark_unchecked(
+        &self,
+        start: usize,
+        end: usize,
+        key: InternalString,
+        value: LoroValue,
+        is_delete: bool,
     ) -> LoroResult<()> {
         match &self.inner {
             MaybeDetached::Detached(t) => {
                 let mut g = t.lock().unwrap();
-                self.mark_for_detached(&mut g.value, key, &value, start, end, false)
+                self.mark_for_detached(&mut g.value, key, &value, start, end, is_delete)
             }
             MaybeDetached::Attached(a) => {
-                a.with_txn(|txn| self.mark_with_txn(txn, start, end, key, value, false))
+                a.with_txn(|txn| self.mark_with_txn(txn, start, end, key, value, is_delete))
             }
         }
     }
@@ -1972,6 +2284,11 @@ impl TextHandler {
             }
         }
 
+        let flag = if &*key == EMBED_STYLE_KEY {
+            TextStyleInfoFlag::EMBED
+        } else {
+            TextStyleInfoFlag::BOLD
+        };
         let style_op = Arc::new(StyleOp {
             lamport: 0,
             peer: 0,
@@ -1979,13 +2296,9 @@ impl TextHandler {
             key,
             value: value.clone(),
             // TODO: describe this behavior in the document
-            info: if is_delete {
-                TextStyleInfoFlag::BOLD.to_delete()
-            } else {
-                TextStyleInfoFlag::BOLD
-            },
+            info: if is_delete { flag.to_delete() } else { flag },
         });
-        state.mark_with_entity_index(entity_range, style_op);
+        state.mark_with_entity_index(entity_range, style_op, None);
         Ok(())
     }
 
@@ -2001,19 +2314,9 @@ impl TextHandler {
         end: usize,
         key: impl Into<InternalString>,
     ) -> LoroResult<()> {
-        match &self.inner {
-            MaybeDetached::Detached(t) => self.mark_for_detached(
-                &mut t.lock().unwrap().value,
-                key,
-                &LoroValue::Null,
-                start,
-                end,
-                true,
-            ),
-            MaybeDetached::Attached(a) => {
-                a.with_txn(|txn| self.mark_with_txn(txn, start, end, key, LoroValue::Null, true))
-            }
-        }
+        let key: InternalString = key.into();
+        check_style_key(&key)?;
+        self.mark_unchecked(start, end, key, LoroValue::Null, true)
     }
 
     /// `start` and `end` are [Event Index]s:
@@ -2048,21 +2351,27 @@ impl TextHandler {
         let key: InternalString = key.into();
 
         let mut doc_state = inner.doc.state.lock().unwrap();
-        let (entity_range, skip) = doc_state.with_state_mut(inner.container_idx, |state| {
-            let (entity_range, styles) = state
-                .as_richtext_state_mut()
-                .unwrap()
-                .get_entity_range_and_styles_at_range(start..end, PosType::Event);
-
-            let skip = match styles {
-                Some(styles) if styles.has_key_value(&key, &value) => {
-                    // already has the same style, skip
-                    true
-                }
-                _ => false,
-            };
-            (entity_range, skip)
-        });
+        let (entity_range, skip, trimmed_keys) =
+            doc_state.with_state_mut(inner.container_idx, |state| {
+                let state = state.as_richtext_state_mut().unwrap();
+                let (entity_range, styles) =
+                    state.get_entity_range_and_styles_at_range(start..end, PosType::Event);
+
+                let skip = match styles {
+                    Some(styles) if styles.has_key_value(&key, &value) => {
+                        // already has the same style, skip
+                        true
+                    }
+                    _ => false,
+                };
+                // The styles trimmed by this mark are removed in the event
+                let trimmed_keys = if skip || value.is_null() {
+                    Vec::new()
+                } else {
+                    state.get_conflicting_style_keys(entity_range.clone(), &key)
+                };
+                (entity_range, skip, trimmed_keys)
+            });
 
         if skip {
             return Ok(());
@@ -2096,6 +2405,7 @@ impl TextHandler {
                 start: start as u32,
                 end: end as u32,
                 style: crate::container::richtext::Style { key, data: value },
+                trimmed_keys,
             },
             &inner.doc,
         )?;
@@ -2110,6 +2420,166 @@ impl TextHandler {
         Ok(())
     }
 
+    /// Get the styles of the char at `pos`.
+    ///
+    /// `pos` is a Event Index:
+    ///
+    /// - if feature="wasm", pos is a UTF-16 index
+    /// - if feature!="wasm", pos is a Unicode index
+    pub fn get_styles_at(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.get_styles_at_with_pos_type(pos, PosType::Event)
+    }
+
+    /// Get the styles of the char at `pos`. `pos` is a UTF-8 index.
+    pub fn get_styles_at_utf8(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.get_styles_at_with_pos_type(pos, PosType::Bytes)
+    }
+
+    /// Get the styles of the char at `pos`. `pos` is a UTF-16 index.
+    pub fn get_styles_at_utf16(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.get_styles_at_with_pos_type(pos, PosType::Utf16)
+    }
+
+    /// Find the ranges where the style `key` is applied, along with its values.
+    ///
+    /// Neighbor ranges with the same value are merged. The ranges are in Event Index.
+    pub fn find_style_ranges(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.find_style_ranges_with_pos_type(key, PosType::Event)
+    }
+
+    /// Find the ranges where the style `key` is applied, in UTF-8 index.
+    pub fn find_style_ranges_utf8(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.find_style_ranges_with_pos_type(key, PosType::Bytes)
+    }
+
+    /// Find the ranges where the style `key` is applied, in UTF-16 index.
+    pub fn find_style_ranges_utf16(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.find_style_ranges_with_pos_type(key, PosType::Utf16)
+    }
+
+    /// Get the styled spans overlapping `range`, clipped to `range`.
+    ///
+    /// The text without styles is skipped. `range` is in Event Index.
+    pub fn styles_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.styles_in_range_with_pos_type(range, PosType::Event)
+    }
+
+    /// Get the styled spans overlapping `range`, clipped to `range`. `range` is in UTF-8 index.
+    pub fn styles_in_range_utf8(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.styles_in_range_with_pos_type(range, PosType::Bytes)
+    }
+
+    /// Get the styled spans overlapping `range`, clipped to `range`. `range` is in UTF-16 index.
+    pub fn styles_in_range_utf16(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.styles_in_range_with_pos_type(range, PosType::Utf16)
+    }
+
+    fn get_styles_at_with_pos_type(
+        &self,
+        pos: usize,
+        pos_type: PosType,
+    ) -> LoroResult<FxHashMap<String, LoroValue>> {
+        let len = self.len_with_pos_type(pos_type);
+        if pos >= len {
+            return Err(LoroError::OutOfBound {
+                pos,
+                len,
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            });
+        }
+
+        Ok(self
+            .get_style_ranges(pos..pos + 1, pos_type)?
+            .pop()
+            .map(|(_, styles)| styles)
+            .unwrap_or_default())
+    }
+
+    fn find_style_ranges_with_pos_type(
+        &self,
+        key: &str,
+        pos_type: PosType,
+    ) -> Vec<(Range<usize>, LoroValue)> {
+        let mut ans: Vec<(Range<usize>, LoroValue)> = Vec::new();
+        let len = self.len_with_pos_type(pos_type);
+        // The whole text is always a valid range
+        for (range, mut styles) in self.get_style_ranges(0..len, pos_type).unwrap() {
+            let Some(value) = styles.remove(key) else {
+                continue;
+            };
+
+            match ans.last_mut() {
+                Some((last_range, last_value))
+                    if last_range.end == range.start && *last_value == value =>
+                {
+                    last_range.end = range.end;
+                }
+                _ => ans.push((range, value)),
+            }
+        }
+
+        ans
+    }
+
+    fn styles_in_range_with_pos_type(
+        &self,
+        range: Range<usize>,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        if range.end < range.start {
+            return Err(LoroError::EndIndexLessThanStartIndex {
+                start: range.start,
+                end: range.end,
+            });
+        }
+
+        let len = self.len_with_pos_type(pos_type);
+        if range.end > len {
+            return Err(LoroError::OutOfBound {
+                pos: range.end,
+                len,
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            });
+        }
+
+        self.get_style_ranges(range, pos_type)
+    }
+
+    fn get_style_ranges(
+        &self,
+        range: Range<usize>,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => t.lock().unwrap().value.get_style_ranges(range, pos_type),
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .get_style_ranges(range, pos_type)
+            }),
+        }
+    }
+
+    fn len_with_pos_type(&self, pos_type: PosType) -> usize {
+        match pos_type {
+            PosType::Bytes => self.len_utf8(),
+            PosType::Unicode => self.len_unicode(),
+            PosType::Utf16 => self.len_utf16(),
+            PosType::Event => self.len_event(),
+            PosType::Entity => unreachable!(),
+        }
+    }
+
     pub fn check(&self) {
         match &self.inner {
             MaybeDetached::Detached(t) => {
@@ -2138,10 +2608,36 @@ impl TextHandler {
         }
     }
 
+    /// Apply the delta. The embedded containers in the delta are created as new containers,
+    /// and `on_container_remap` is called with their old and new ids.
+    pub fn apply_delta_with_remap(
+        &self,
+        delta: &[TextDelta],
+        on_container_remap: &mut dyn FnMut(ContainerID, ContainerID),
+    ) -> LoroResult<()> {
+        match &self.inner {
+            MaybeDetached::Detached(_) => Err(LoroError::NotImplemented(
+                "`apply_delta` on a detached text container",
+            )),
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                self.apply_delta_with_txn_and_remap(txn, delta, on_container_remap)
+            }),
+        }
+    }
+
     pub fn apply_delta_with_txn(
         &self,
         txn: &mut Transaction,
         delta: &[TextDelta],
+    ) -> LoroResult<()> {
+        self.apply_delta_with_txn_and_remap(txn, delta, &mut |_, _| {})
+    }
+
+    pub fn apply_delta_with_txn_and_remap(
+        &self,
+        txn: &mut Transaction,
+        delta: &[TextDelta],
+        on_container_remap: &mut dyn FnMut(ContainerID, ContainerID),
     ) -> LoroResult<()> {
         let mut index = 0;
         struct PendingMark {
@@ -2173,6 +2669,33 @@ impl TextHandler {
                     marks.push(pending_mark);
                     index = end;
                 }
+                TextDelta::Embed { embed, attributes } => {
+                    let override_styles = self.insert_with_txn_and_attr(
+                        txn,
+                        index,
+                        EMBED_STR,
+                        Some(attributes.as_ref().unwrap_or(&Default::default())),
+                        PosType::Event,
+                    )?;
+                    marks.push(PendingMark {
+                        start: index,
+                        end: index + 1,
+                        attributes: override_styles.into_iter().collect(),
+                    });
+                    // The embed is marked right away, because the id of an embedded
+                    // container is the id of the style op
+                    let value = match embed {
+                        LoroValue::Container(old_id) => {
+                            let new_id =
+                                ContainerID::new_normal(txn.next_id(), old_id.container_type());
+                            on_container_remap(old_id.clone(), new_id.clone());
+                            LoroValue::Container(new_id)
+                        }
+                        v => v.clone(),
+                    };
+                    self.mark_with_txn(txn, index, index + 1, EMBED_STYLE_KEY, value, false)?;
+                    index += 1;
+                }
                 TextDelta::Delete { delete } => {
                     self.delete_with_txn(txn, index, *delete)?;
                 }
@@ -2221,7 +2744,28 @@ impl TextHandler {
         Ok(())
     }
 
+    /// Update the text to `text` with the diff calculated in the unit of
+    /// `options.granularity`.
     pub fn update(&self, text: &str, options: UpdateOptions) -> Result<(), UpdateTimeoutError> {
+        let split: for<'s> fn(&'s str) -> Vec<&'s str> = match options.granularity {
+            UpdateGranularity::Char => return self.update_by_char(text, options),
+            UpdateGranularity::Word => text_update::split_words,
+            UpdateGranularity::Sentence => text_update::split_sentences,
+        };
+        let hook = text_update::DiffHookForTokens::new(self, text, split);
+        let old_tokens = hook.get_old_arr().to_vec();
+        let new_tokens = hook.get_new_arr().to_vec();
+        let token_lens = hook.get_token_lens();
+        diff_with_cleanup(
+            &mut OperateProxy::new(hook),
+            options,
+            &old_tokens,
+            &new_tokens,
+            |x| token_lens[x as usize],
+        )
+    }
+
+    fn update_by_char(&self, text: &str, options: UpdateOptions) -> Result<(), UpdateTimeoutError> {
         let old_str = self.to_string();
         let new = text.chars().map(|x| x as u32).collect::<Vec<u32>>();
         let old = old_str.chars().map(|x| x as u32).collect::<Vec<u32>>();
@@ -2239,7 +2783,7 @@ impl TextHandler {
         text: &str,
         options: UpdateOptions,
     ) -> Result<(), UpdateTimeoutError> {
-        let hook = text_update::DiffHookForLine::new(self, text);
+        let hook = text_update::DiffHookForTokens::new(self, text, text_update::split_lines);
         let old_lines = hook.get_old_arr().to_vec();
         let new_lines = hook.get_new_arr().to_vec();
         diff(
@@ -2250,6 +2794,44 @@ impl TextHandler {
         )
     }
 
+    /// Update the text to `text`, which was edited from the text at the `base` version.
+    ///
+    /// Unlike [TextHandler::update], the diff is calculated against the text at `base`,
+    /// and the edits are applied as if they were made concurrently at `base`. So they
+    /// are merged with the edits after `base` instead of overwriting them.
+    ///
+    /// The edits are made by a doc forked at `base` and then imported into this doc. The
+    /// fork uses a new random peer id, so every call adds a new peer to the version vector.
+    ///
+    /// The pending transaction of the doc is committed first.
+    pub fn update_from_base(
+        &self,
+        base: &Frontiers,
+        text: &str,
+    ) -> Result<(), UpdateFromBaseError> {
+        let inner = self.inner.try_attached_state()?;
+        let doc = &inner.doc;
+        let base_vv = {
+            let oplog = doc.oplog().lock().unwrap();
+            if let Some(id) = base.iter().find(|id| !oplog.dag.contains(*id)) {
+                return Err(LoroError::FrontiersNotFound(id).into());
+            }
+            if oplog.dag.is_before_shallow_root(base) {
+                return Err(LoroError::SwitchToVersionBeforeShallowRoot.into());
+            }
+            oplog.dag.frontiers_to_vv(base).unwrap()
+        };
+
+        doc.commit_then_renew();
+        let fork = doc.fork_at(base);
+        fork.get_text(inner.id.clone())
+            .update(text, Default::default())?;
+        fork.commit_then_renew();
+        let updates = fork.export(crate::loro::ExportMode::updates(&base_vv))?;
+        doc.import(&updates)?;
+        Ok(())
+    }
+
     #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match &self.inner {
@@ -2262,6 +2844,24 @@ impl TextHandler {
         self.get_cursor_internal(event_index, side, true)
     }
 
+    /// Get the cursor at the grapheme cluster index `pos`. It takes O(pos) to find the
+    /// position.
+    pub fn get_cursor_grapheme(&self, pos: usize, side: Side) -> Option<Cursor> {
+        let event_index = match &self.inner {
+            MaybeDetached::Detached(t) => {
+                let t = t.lock().unwrap();
+                let pos = t.value.grapheme_range_to_unicode(pos..pos).ok()?.start;
+                t.value.index_to_event_index(pos, PosType::Unicode)
+            }
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                let state = state.as_richtext_state_mut().unwrap();
+                let pos = state.grapheme_range_to_unicode(pos..pos).ok()?.start;
+                Some(state.index_to_event_index(pos, PosType::Unicode))
+            })?,
+        };
+        self.get_cursor(event_index, side)
+    }
+
     /// Get the stable position representation for the target pos
     pub(crate) fn get_cursor_internal(
         &self,
@@ -2387,6 +2987,14 @@ fn event_len(s: &str) -> usize {
     }
 }
 
+fn char_event_len(c: char) -> usize {
+    if cfg!(feature = "wasm") {
+        c.len_utf16()
+    } else {
+        1
+    }
+}
+
 impl ListHandler {
     /// Create a new container that is detached from the document.
     /// The edits on a detached container will not be persisted.
@@ -0,0 +1,199 @@
+use super::*;
+use crate::{container::richtext::ANNOTATION_STYLE_PREFIX, Subscription};
+
+/// An annotation on a text, see [crate::container::richtext] for how it's stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub id: String,
+    /// The cursor of the start anchor. It always resolves to the start of [Annotation::range].
+    pub start: Cursor,
+    /// The cursor of the end anchor. It always resolves to the end of [Annotation::range].
+    pub end: Cursor,
+    /// The current range in Event Index. It's empty if the annotated text is deleted.
+    pub range: Range<usize>,
+    pub value: LoroValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationEvent {
+    /// The annotation is created, or its range or value is changed
+    Updated(Annotation),
+    /// The annotation with the id is removed
+    Removed(String),
+}
+
+pub type AnnotationSubscriber = Arc<dyn Fn(&[AnnotationEvent]) + Send + Sync>;
+
+impl TextHandler {
+    /// Annotate the text in `range` with `value`, and return the id of the annotation.
+    ///
+    /// Unlike a mark, the annotation is kept when the annotated text is deleted, and its
+    /// range collapses to an empty range. Text inserted at either end of the annotation
+    /// isn't a part of it.
+    ///
+    /// `range` is in Event Index
+    pub fn annotate(&self, range: Range<usize>, value: impl Into<LoroValue>) -> LoroResult<String> {
+        let value = value.into();
+        if value.is_null() {
+            return Err(LoroError::ArgErr(
+                "The value of an annotation can't be null".into(),
+            ));
+        }
+
+        let inner = self.inner.try_attached_state()?;
+        inner.with_txn(|txn| {
+            // The id of the annotation is the id of its style start op
+            let id = txn.next_id().to_string();
+            self.mark_with_txn(
+                txn,
+                range.start,
+                range.end,
+                annotation_style_key(&id),
+                value,
+                false,
+            )?;
+            Ok(id)
+        })
+    }
+
+    /// Remove the annotation with `id`. It's a no-op if there is no such annotation.
+    pub fn remove_annotation(&self, id: &str) -> LoroResult<()> {
+        let Some(annotation) = self.get_annotations().into_iter().find(|x| x.id == id) else {
+            return Ok(());
+        };
+
+        let mut range = annotation.range;
+        if range.is_empty() {
+            // Unmark needs a non-empty range. Only the latest style of the key counts,
+            // so unmarking the char next to the collapsed annotation works too.
+            let len = self.len_event();
+            if len == 0 {
+                return Err(LoroError::ArgErr(
+                    "Cannot remove an annotation from an empty text".into(),
+                ));
+            }
+
+            range = if range.start < len {
+                range.start..range.start + char_event_len(self.char_at(range.start)?)
+            } else {
+                let last = self.slice(0, len)?.chars().next_back().unwrap();
+                len - char_event_len(last)..len
+            };
+        }
+
+        self.mark_unchecked(
+            range.start,
+            range.end,
+            annotation_style_key(id),
+            LoroValue::Null,
+            true,
+        )
+    }
+
+    /// Get the annotations sorted by their ranges, in Event Index.
+    ///
+    /// A detached text has no annotations.
+    pub fn get_annotations(&self) -> Vec<Annotation> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Vec::new();
+        };
+
+        let styles = a.with_state(|state| {
+            state
+                .as_richtext_state_mut()
+                .unwrap()
+                .get_annotation_styles()
+        });
+        to_annotations(&a.id, styles)
+    }
+
+    /// Subscribe the changes of the annotations.
+    ///
+    /// The callback is invoked with the annotations that are created, removed, or whose
+    /// range or value is changed, after the events of the text are emitted.
+    pub fn subscribe_annotations(
+        &self,
+        callback: AnnotationSubscriber,
+    ) -> LoroResult<Subscription> {
+        let inner = self.inner.try_attached_state()?;
+        let container = inner.id.clone();
+        let container_idx = inner.container_idx;
+        // Hold the state weakly, or the doc would never be dropped
+        let state = Arc::downgrade(&inner.doc.state);
+        let last: FxHashMap<String, Annotation> = self
+            .get_annotations()
+            .into_iter()
+            .map(|x| (x.id.clone(), x))
+            .collect();
+        let last = Mutex::new(last);
+        Ok(inner.doc.subscribe(
+            &inner.id,
+            Arc::new(move |_| {
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                let styles = state
+                    .lock()
+                    .unwrap()
+                    .with_state_mut(container_idx, |state| {
+                        state
+                            .as_richtext_state_mut()
+                            .unwrap()
+                            .get_annotation_styles()
+                    });
+                let current = to_annotations(&container, styles);
+
+                let mut events = Vec::new();
+                {
+                    let mut last = last.lock().unwrap();
+                    for annotation in current.iter() {
+                        if last.get(&annotation.id) != Some(annotation) {
+                            events.push(AnnotationEvent::Updated(annotation.clone()));
+                        }
+                    }
+                    for id in last.keys() {
+                        if !current.iter().any(|x| &x.id == id) {
+                            events.push(AnnotationEvent::Removed(id.clone()));
+                        }
+                    }
+                    *last = current.into_iter().map(|x| (x.id.clone(), x)).collect();
+                }
+
+                if !events.is_empty() {
+                    callback(&events);
+                }
+            }),
+        ))
+    }
+}
+
+fn annotation_style_key(id: &str) -> InternalString {
+    format!("{ANNOTATION_STYLE_PREFIX}{id}").into()
+}
+
+fn to_annotations(
+    container: &ContainerID,
+    styles: Vec<(Arc<StyleOp>, Range<usize>)>,
+) -> Vec<Annotation> {
+    styles
+        .into_iter()
+        .filter(|(style, _)| !style.value.is_null())
+        .map(|(style, range)| Annotation {
+            id: style.key[ANNOTATION_STYLE_PREFIX.len()..].to_string(),
+            start: Cursor::new(
+                Some(style.id()),
+                container.clone(),
+                Side::Middle,
+                range.start,
+            ),
+            end: Cursor::new(
+                Some(style.id().inc(1)),
+                container.clone(),
+                Side::Middle,
+                range.end,
+            ),
+            range,
+            value: style.value.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+use super::*;
+use crate::Subscription;
+
+/// The lines `old_lines` before a change are replaced by the lines `new_lines` after it.
+///
+/// The lines are 0-based. The changes in one batch are sorted and don't share lines, so
+/// a binding can apply them from the last one, or apply them in order while tracking the
+/// shift of the line numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChange {
+    pub old_lines: Range<usize>,
+    pub new_lines: Range<usize>,
+}
+
+pub type LineSubscriber = Arc<dyn Fn(&[LineChange]) + Send + Sync>;
+
+impl TextHandler {
+    /// Get the number of lines, which is the number of `\n` plus one.
+    pub fn line_count(&self) -> usize {
+        match &self.inner {
+            MaybeDetached::Detached(t) => t.lock().unwrap().value.line_count(),
+            MaybeDetached::Attached(a) => {
+                a.with_state(|state| state.as_richtext_state_mut().unwrap().line_count())
+            }
+        }
+    }
+
+    /// Get the start of the `line` (0-based) in Event Index, or None if there is no such line.
+    ///
+    /// - if feature="wasm", the position is a UTF-16 index
+    /// - if feature!="wasm", the position is a Unicode index
+    pub fn line_to_pos(&self, line: usize) -> Option<usize> {
+        self.line_to_pos_with_pos_type(line, PosType::Event)
+    }
+
+    /// Get the start of the `line` (0-based) in UTF-8 index.
+    pub fn line_to_pos_utf8(&self, line: usize) -> Option<usize> {
+        self.line_to_pos_with_pos_type(line, PosType::Bytes)
+    }
+
+    /// Get the start of the `line` (0-based) in UTF-16 index.
+    pub fn line_to_pos_utf16(&self, line: usize) -> Option<usize> {
+        self.line_to_pos_with_pos_type(line, PosType::Utf16)
+    }
+
+    /// Get the line (0-based) and the column of the Event Index `pos`, or None if `pos`
+    /// is out of bound. The column is in Event Index too.
+    pub fn pos_to_line_col(&self, pos: usize) -> Option<(usize, usize)> {
+        self.pos_to_line_col_with_pos_type(pos, PosType::Event)
+    }
+
+    /// Get the line (0-based) and the column of the UTF-8 index `pos`, or None if `pos`
+    /// is out of bound. The column is in UTF-8 index too.
+    pub fn pos_to_line_col_utf8(&self, pos: usize) -> Option<(usize, usize)> {
+        self.pos_to_line_col_with_pos_type(pos, PosType::Bytes)
+    }
+
+    /// Get the line (0-based) and the column of the UTF-16 index `pos`, or None if `pos`
+    /// is out of bound. The column is in UTF-16 index too.
+    pub fn pos_to_line_col_utf16(&self, pos: usize) -> Option<(usize, usize)> {
+        self.pos_to_line_col_with_pos_type(pos, PosType::Utf16)
+    }
+
+    /// Get the text of the `lines` (0-based), including their line breaks.
+    pub fn slice_lines(&self, lines: Range<usize>) -> LoroResult<String> {
+        let line_count = self.line_count();
+        if lines.start > lines.end || lines.end > line_count {
+            return Err(LoroError::OutOfBound {
+                pos: lines.end,
+                len: line_count,
+                info: format!("Position: {}:{}", file!(), line!()).into_boxed_str(),
+            });
+        }
+
+        let start = self
+            .line_to_pos(lines.start)
+            .unwrap_or_else(|| self.len_event());
+        let end = self
+            .line_to_pos(lines.end)
+            .unwrap_or_else(|| self.len_event());
+        self.slice(start, end)
+    }
+
+    /// Subscribe the changes of the lines.
+    ///
+    /// The callback is invoked with the changed lines after the events of the text are
+    /// emitted. Changes that only update the styles are skipped.
+    pub fn subscribe_lines(&self, callback: LineSubscriber) -> LoroResult<Subscription> {
+        let inner = self.inner.try_attached_state()?;
+        let container = inner.id.clone();
+        // The deltas don't tell the deleted text, so the old line starts are kept to
+        // know the deleted lines. The pending ops are emitted after they are committed,
+        // so the lines start from the committed text.
+        let text = inner
+            .doc
+            .with_committed_state(inner.container_idx, |state| {
+                state.as_richtext_state_mut().unwrap().to_string_mut()
+            });
+        let line_starts = Mutex::new(line_starts(&text));
+        Ok(inner.doc.subscribe(
+            &inner.id,
+            Arc::new(move |e| {
+                let mut changes = Vec::new();
+                {
+                    let mut line_starts = line_starts.lock().unwrap();
+                    for e in e.events.iter().filter(|e| e.id == container) {
+                        if let Diff::Text(delta) = &e.diff {
+                            changes.extend(apply_delta_to_line_starts(&mut line_starts, delta));
+                        }
+                    }
+                }
+
+                if !changes.is_empty() {
+                    callback(&changes);
+                }
+            }),
+        ))
+    }
+
+    fn line_to_pos_with_pos_type(&self, line: usize, pos_type: PosType) -> Option<usize> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => t.lock().unwrap().value.get_line_start(line, pos_type),
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .get_line_start(line, pos_type)
+            }),
+        }
+    }
+
+    fn pos_to_line_col_with_pos_type(
+        &self,
+        pos: usize,
+        pos_type: PosType,
+    ) -> Option<(usize, usize)> {
+        match &self.inner {
+            MaybeDetached::Detached(t) => t.lock().unwrap().value.get_line_col(pos, pos_type),
+            MaybeDetached::Attached(a) => a.with_state(|state| {
+                state
+                    .as_richtext_state_mut()
+                    .unwrap()
+                    .get_line_col(pos, pos_type)
+            }),
+        }
+    }
+}
+
+/// The Event Index of the start of each line
+fn line_starts(s: &str) -> Vec<usize> {
+    let mut ans = vec![0];
+    let mut index = 0;
+    for c in s.chars() {
+        index += char_event_len(c);
+        if c == '\n' {
+            ans.push(index);
+        }
+    }
+
+    ans
+}
+
+/// Update `line_starts` by `delta` in place, and return the changed lines.
+///
+/// Only the line starts in the changed ranges are replaced, the others are shifted by
+/// the length change before them.
+fn apply_delta_to_line_starts(line_starts: &mut Vec<usize>, delta: &TextDiff) -> Vec<LineChange> {
+    let mut changes: Vec<LineChange> = Vec::new();
+    // The line starts before `shifted` are in the new text. The others are still in the
+    // old text and need to be shifted by `shift`.
+    let mut shifted = 1;
+    let mut shift: isize = 0;
+    let mut line_shift: isize = 0;
+    let mut old_pos = 0;
+    for item in delta.iter() {
+        match item {
+            loro_delta::DeltaItem::Retain { len, .. } => {
+                old_pos += len;
+            }
+            loro_delta::DeltaItem::Replace { value, delete, .. } => {
+                if *delete == 0 && value.rle_len() == 0 {
+                    continue;
+                }
+
+                let line_of = |pos: usize| {
+                    shifted + line_starts[shifted..].partition_point(|&x| x <= pos) - 1
+                };
+                let from = line_of(old_pos);
+                let to = line_of(old_pos + delete);
+                for start in line_starts[shifted..=from].iter_mut() {
+                    *start = (*start as isize + shift) as usize;
+                }
+
+                // The lines starting in the deleted text are replaced by the inserted ones
+                let new_pos = (old_pos as isize + shift) as usize;
+                let mut index = new_pos;
+                let mut inserted = Vec::new();
+                for c in value.as_str().chars() {
+                    index += char_event_len(c);
+                    if c == '\n' {
+                        inserted.push(index);
+                    }
+                }
+
+                let inserted_lines = inserted.len();
+                line_starts.splice(from + 1..to + 1, inserted);
+                let old_from = (from as isize - line_shift) as usize;
+                let old_to = (to as isize - line_shift) as usize;
+                let new_end = from + inserted_lines + 1;
+                match changes.last_mut() {
+                    // The changes share a line
+                    Some(last) if last.old_lines.end > old_from => {
+                        last.old_lines.end = old_to + 1;
+                        last.new_lines.end = new_end;
+                    }
+                    _ => changes.push(LineChange {
+                        old_lines: old_from..old_to + 1,
+                        new_lines: from..new_end,
+                    }),
+                }
+
+                line_shift += inserted_lines as isize - (to - from) as isize;
+                shift += (index - new_pos) as isize - *delete as isize;
+                shifted = from + 1 + inserted_lines;
+                old_pos += delete;
+            }
+        }
+    }
+
+    for start in line_starts[shifted..].iter_mut() {
+        *start = (*start as isize + shift) as usize;
+    }
+
+    changes
+}
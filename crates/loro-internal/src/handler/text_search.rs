@@ -0,0 +1,193 @@
+use super::*;
+
+/// A pattern to search in a text, see [TextHandler::find] and [TextHandler::replace_all].
+///
+/// An inline embed is matched as a single `U+FFFC` char.
+#[derive(Debug, Clone, Copy)]
+pub enum TextPattern<'a> {
+    /// Match the string literally. An empty string matches nothing.
+    Str(&'a str),
+    /// Match the regular expression. The replacement can refer to the capture groups,
+    /// like `$1` or `${name}`, see [regex::Captures::expand].
+    #[cfg(feature = "regex")]
+    Regex(&'a regex::Regex),
+}
+
+impl<'a> From<&'a str> for TextPattern<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<&'a String> for TextPattern<'a> {
+    fn from(value: &'a String) -> Self {
+        Self::Str(value)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl<'a> From<&'a regex::Regex> for TextPattern<'a> {
+    fn from(value: &'a regex::Regex) -> Self {
+        Self::Regex(value)
+    }
+}
+
+impl TextPattern<'_> {
+    /// The non-overlapping matches in `s` and their replacements, in UTF-8 byte ranges.
+    fn matches(&self, s: &str, replacement: Option<&str>) -> Vec<(Range<usize>, String)> {
+        match self {
+            TextPattern::Str(p) => {
+                if p.is_empty() {
+                    return Vec::new();
+                }
+
+                s.match_indices(*p)
+                    .map(|(i, m)| {
+                        (
+                            i..i + m.len(),
+                            replacement.map(|x| x.to_string()).unwrap_or_default(),
+                        )
+                    })
+                    .collect()
+            }
+            #[cfg(feature = "regex")]
+            TextPattern::Regex(re) => re
+                .captures_iter(s)
+                .map(|caps| {
+                    let m = caps.get(0).unwrap();
+                    let mut dst = String::new();
+                    if let Some(replacement) = replacement {
+                        caps.expand(replacement, &mut dst);
+                    }
+                    (m.range(), dst)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TextHandler {
+    /// Find the non-overlapping matches of `pattern`, in Event Index.
+    ///
+    /// - if feature="wasm", the ranges are in UTF-16 index
+    /// - if feature!="wasm", the ranges are in Unicode index
+    pub fn find<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.find_with_pos_type(pattern.into(), PosType::Event)
+    }
+
+    /// Find the non-overlapping matches of `pattern`, in UTF-8 index.
+    pub fn find_utf8<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.find_with_pos_type(pattern.into(), PosType::Bytes)
+    }
+
+    /// Find the non-overlapping matches of `pattern`, in UTF-16 index.
+    pub fn find_utf16<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.find_with_pos_type(pattern.into(), PosType::Utf16)
+    }
+
+    fn find_with_pos_type(&self, pattern: TextPattern, pos_type: PosType) -> Vec<Range<usize>> {
+        let s = self.to_string();
+        let ranges = pattern.matches(&s, None).into_iter().map(|(r, _)| r);
+        if pos_type == PosType::Bytes {
+            return ranges.collect();
+        }
+
+        let char_len = |c: char| match pos_type {
+            PosType::Unicode => 1,
+            PosType::Utf16 => c.len_utf16(),
+            _ => char_event_len(c),
+        };
+        // The matches are sorted, so the offsets are converted in a single pass
+        let mut chars = s.char_indices().peekable();
+        let mut index = 0;
+        let mut to_index = |byte: usize| {
+            while let Some((_, c)) = chars.next_if(|(i, _)| *i < byte) {
+                index += char_len(c);
+            }
+            index
+        };
+        ranges
+            .map(|r| {
+                let start = to_index(r.start);
+                start..to_index(r.end)
+            })
+            .collect()
+    }
+
+    /// Replace all the non-overlapping matches of `pattern` with `replacement` in one
+    /// transaction, and return the number of the matches.
+    ///
+    /// Only the part of a match that differs from its replacement is deleted and
+    /// inserted, so the text and its marks outside of the changed parts are kept.
+    /// The result is the same as replacing on [TextHandler::to_string].
+    ///
+    /// This method requires auto_commit to be enabled.
+    pub fn replace_all<'a>(
+        &self,
+        pattern: impl Into<TextPattern<'a>>,
+        replacement: &str,
+    ) -> LoroResult<usize> {
+        let s = self.to_string();
+        let matches = pattern.into().matches(&s, Some(replacement));
+        let edits: Vec<_> = matches
+            .iter()
+            .filter_map(|(range, replacement)| minimal_edit(&s, range.clone(), replacement))
+            .collect();
+
+        match &self.inner {
+            MaybeDetached::Detached(_) => {
+                // Apply from the end so that the positions of the rest stay valid
+                for (range, insert) in edits.into_iter().rev() {
+                    if !range.is_empty() {
+                        self.delete_utf8(range.start, range.len())?;
+                    }
+                    if !insert.is_empty() {
+                        self.insert_utf8(range.start, insert)?;
+                    }
+                }
+            }
+            MaybeDetached::Attached(a) => a.with_txn(|txn| {
+                for (range, insert) in edits.into_iter().rev() {
+                    self.delete_with_txn_inline(txn, range.start, range.len(), PosType::Bytes)?;
+                    self.insert_with_txn_utf8(txn, range.start, insert)?;
+                }
+                Ok(())
+            })?,
+        }
+
+        Ok(matches.len())
+    }
+}
+
+/// Trim the common prefix and suffix of the matched text and its replacement.
+///
+/// Return the byte range to delete and the str to insert at its start, or None if
+/// they are the same.
+fn minimal_edit<'a>(
+    s: &str,
+    range: Range<usize>,
+    replacement: &'a str,
+) -> Option<(Range<usize>, &'a str)> {
+    let old = &s[range.clone()];
+    if old == replacement {
+        return None;
+    }
+
+    let prefix: usize = old
+        .chars()
+        .zip(replacement.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(replacement[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    Some((
+        range.start + prefix..range.end - suffix,
+        &replacement[prefix..replacement.len() - suffix],
+    ))
+}
@@ -2,6 +2,7 @@ use std::{char, sync::Arc};
 
 use rustc_hash::FxHashMap;
 use itertools::Itertools;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::diff::DiffHandler;
 
@@ -53,53 +54,70 @@ impl DiffHandler for DiffHook<'_> {
     }
 }
 
-pub(super) struct DiffHookForLine<'a> {
+pub(super) fn split_lines(s: &str) -> Vec<&str> {
+    s.split_inclusive('\n').collect()
+}
+
+pub(super) fn split_words(s: &str) -> Vec<&str> {
+    s.split_word_bounds().collect()
+}
+
+pub(super) fn split_sentences(s: &str) -> Vec<&str> {
+    s.split_sentence_bounds().collect()
+}
+
+pub(super) struct DiffHookForTokens<'a> {
     text: &'a TextHandler,
     old: Vec<u32>,
     new: Vec<u32>,
-    lines: Vec<Arc<str>>,
-    lines_lookup: FxHashMap<Arc<str>, usize>,
+    tokens: Vec<Arc<str>>,
+    tokens_lookup: FxHashMap<Arc<str>, usize>,
 
     last_old_index: usize,
     current_index: usize,
 }
 
-impl<'a> DiffHookForLine<'a> {
-    pub(crate) fn new(text: &'a TextHandler, new_str: &str) -> Self {
+impl<'a> DiffHookForTokens<'a> {
+    /// `split` splits a str into tokens, which are diffed as a whole
+    pub(crate) fn new(
+        text: &'a TextHandler,
+        new_str: &str,
+        split: for<'s> fn(&'s str) -> Vec<&'s str>,
+    ) -> Self {
         let mut this = Self {
             text,
             old: Vec::new(),
             new: Vec::new(),
-            lines: Vec::new(),
-            lines_lookup: FxHashMap::default(),
+            tokens: Vec::new(),
+            tokens_lookup: FxHashMap::default(),
             last_old_index: 0,
             current_index: 0,
         };
 
         let text_str = text.to_string();
-        for line in text_str.split_inclusive('\n') {
-            let line: Arc<str> = Arc::from(line);
-            let id = this.register_line(line);
+        for token in split(&text_str) {
+            let token: Arc<str> = Arc::from(token);
+            let id = this.register_token(token);
             this.old.push(id as u32);
         }
 
-        for line in new_str.split_inclusive('\n') {
-            let line: Arc<str> = Arc::from(line);
-            let id = this.register_line(line);
+        for token in split(new_str) {
+            let token: Arc<str> = Arc::from(token);
+            let id = this.register_token(token);
             this.new.push(id as u32);
         }
 
         this
     }
 
-    fn register_line(&mut self, line: Arc<str>) -> usize {
-        if let Some(&index) = self.lines_lookup.get(&line) {
+    fn register_token(&mut self, token: Arc<str>) -> usize {
+        if let Some(&index) = self.tokens_lookup.get(&token) {
             return index;
         }
 
-        self.lines.push(line.clone());
-        self.lines_lookup.insert(line, self.lines.len() - 1);
-        self.lines.len() - 1
+        self.tokens.push(token.clone());
+        self.tokens_lookup.insert(token, self.tokens.len() - 1);
+        self.tokens.len() - 1
     }
 
     pub fn get_old_arr(&self) -> &[u32] {
@@ -109,21 +127,26 @@ impl<'a> DiffHookForLine<'a> {
     pub fn get_new_arr(&self) -> &[u32] {
         &self.new
     }
+
+    /// The unicode lengths of the tokens, indexed by the token ids
+    pub fn get_token_lens(&self) -> Vec<usize> {
+        self.tokens.iter().map(|x| x.chars().count()).collect()
+    }
 }
 
-impl DiffHandler for DiffHookForLine<'_> {
+impl DiffHandler for DiffHookForTokens<'_> {
     fn insert(&mut self, old_index: usize, new_index: usize, new_len: usize) {
         if self.last_old_index < old_index {
             assert!(self.last_old_index < old_index);
             self.current_index += (self.last_old_index..old_index)
-                .map(|x| self.lines[self.old[x] as usize].chars().count())
+                .map(|x| self.tokens[self.old[x] as usize].chars().count())
                 .sum::<usize>();
             self.last_old_index = old_index;
         }
 
         let s = self.new[new_index..new_index + new_len]
             .iter()
-            .map(|x| self.lines[*x as usize].clone())
+            .map(|x| self.tokens[*x as usize].clone())
             .join("");
         self.text.insert_unicode(self.current_index, &s).unwrap();
         self.current_index += s.chars().count();
@@ -133,13 +156,13 @@ impl DiffHandler for DiffHookForLine<'_> {
         if self.last_old_index != old_index {
             assert!(self.last_old_index < old_index);
             self.current_index += (self.last_old_index..old_index)
-                .map(|x| self.lines[self.old[x] as usize].chars().count())
+                .map(|x| self.tokens[self.old[x] as usize].chars().count())
                 .sum::<usize>();
         }
 
         self.last_old_index = old_index + old_len;
         let delete_len = (old_index..old_index + old_len)
-            .map(|x| self.lines[self.old[x] as usize].chars().count())
+            .map(|x| self.tokens[self.old[x] as usize].chars().count())
             .sum::<usize>();
 
         self.text
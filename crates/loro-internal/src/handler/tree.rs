@@ -717,6 +717,68 @@ impl TreeHandler {
         )
     }
 
+    /// Duplicate the `target` node and its descendants as a new subtree under `parent` at
+    /// `index`, in one transaction.
+    ///
+    /// The meta maps of the nodes are deeply copied, including the containers in them.
+    /// Return the mapping from the duplicated nodes to the new nodes.
+    pub fn duplicate_subtree(
+        &self,
+        target: TreeID,
+        parent: TreeParentId,
+        index: usize,
+    ) -> LoroResult<FxHashMap<TreeID, TreeID>> {
+        let inner = self.inner.try_attached_state()?;
+        if self.is_node_deleted(&target)? {
+            return Err(LoroTreeError::TreeNodeDeletedOrNotExist(target).into());
+        }
+        match parent {
+            TreeParentId::Node(p) => {
+                if self.is_node_deleted(&p)? {
+                    return Err(LoroTreeError::TreeNodeDeletedOrNotExist(p).into());
+                }
+            }
+            TreeParentId::Root => {}
+            TreeParentId::Deleted | TreeParentId::Unexist => {
+                return Err(LoroTreeError::InvalidParent.into());
+            }
+        }
+        let len = self.children_num(&parent).unwrap_or(0);
+        if index > len {
+            return Err(LoroTreeError::IndexOutOfBound { len, index }.into());
+        }
+
+        // The nodes are collected before creating the copies, so that duplicating a node
+        // into its own subtree doesn't copy the new nodes again.
+        // (node, the parent of the node, the index of the node)
+        let mut nodes = vec![(target, None, index)];
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = nodes[i].0;
+            let children = self.children(&TreeParentId::Node(node)).unwrap_or_default();
+            for (index, child) in children.into_iter().enumerate() {
+                nodes.push((child, Some(node), index));
+            }
+            i += 1;
+        }
+
+        inner.with_txn(|txn| {
+            let mut mapping = FxHashMap::default();
+            for (node, node_parent, index) in nodes {
+                let new_parent = match node_parent {
+                    Some(p) => TreeParentId::Node(mapping[&p]),
+                    None => parent,
+                };
+                let new_node =
+                    self.create_with_txn(txn, new_parent, index, FiIfNotConfigured::Throw)?;
+                self.get_meta(node)?
+                    .attach(txn, inner, new_node.associated_meta_container())?;
+                mapping.insert(node, new_node);
+            }
+            Ok(mapping)
+        })
+    }
+
     pub fn get_meta(&self, target: TreeID) -> LoroResult<MapHandler> {
         match &self.inner {
             MaybeDetached::Detached(d) => {
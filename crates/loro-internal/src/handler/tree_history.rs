@@ -0,0 +1,44 @@
+use super::*;
+use crate::{delta::TreeMoveRecord, diff_calc::tree::TreeDiffCalculator, Subscription};
+
+pub type TreeMoveConflictSubscriber = Arc<dyn Fn(&[TreeMoveRecord]) + Send + Sync>;
+
+impl TreeHandler {
+    /// Get all the ops that create, move or delete the node at the last commit, in the
+    /// order they are applied, including the moves rejected because of cycles.
+    ///
+    /// The last applied op is the one of [TreeHandler::get_last_move_id]. After a shallow
+    /// snapshot, the history starts from the op of the node at the shallow root. A
+    /// detached tree has no history.
+    pub fn history_of_node(&self, target: &TreeID) -> Vec<TreeMoveRecord> {
+        let MaybeDetached::Attached(a) = &self.inner else {
+            return Vec::new();
+        };
+        let frontiers = a.doc.committed_frontiers();
+        let oplog = a.doc.oplog().lock().unwrap();
+        let Some(vv) = oplog.dag().frontiers_to_vv(&frontiers) else {
+            return Vec::new();
+        };
+        TreeDiffCalculator::new(a.container_idx)
+            .get_history_of_node(&oplog, &vv, &frontiers, *target)
+    }
+
+    /// Subscribe the moves that are rejected because they would create cycles.
+    ///
+    /// Local moves that would create cycles fail directly, so the rejected moves come from
+    /// concurrent moves made by other peers. The callback is invoked with the newly
+    /// rejected moves of an import after its events are emitted.
+    pub fn subscribe_move_conflicts(
+        &self,
+        callback: TreeMoveConflictSubscriber,
+    ) -> LoroResult<Subscription> {
+        let inner = self.inner.try_attached_state()?;
+        Ok(inner.doc.subscribe_tree_move_conflicts(
+            inner.container_idx,
+            Box::new(move |moves| {
+                callback(moves);
+                true
+            }),
+        ))
+    }
+}
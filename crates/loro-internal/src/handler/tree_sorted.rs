@@ -0,0 +1,361 @@
+use std::cmp::Ordering;
+
+use loro_common::LoroTreeError;
+use rustc_hash::FxHashSet;
+
+use super::*;
+use crate::{
+    delta::{TreeDiff, TreeDiffItem},
+    event::Index,
+    state::NodePosition,
+    SubscriberSetWithQueue, Subscription,
+};
+
+pub type SortedChildrenSubscriber = Arc<dyn Fn(&TreeDiff) + Send + Sync>;
+type SortedChildrenCallback = Box<dyn Fn(&TreeDiff) -> bool + Send + Sync>;
+
+/// The children of a node sorted by the value at a path in their metadata maps, see
+/// [TreeHandler::children_sorted_by].
+///
+/// The view is stored and updated incrementally by the events of the tree, so reading it
+/// doesn't sort the children again. It stops updating when it's dropped.
+pub struct SortedChildrenView {
+    view: Arc<Mutex<SortedChildren>>,
+    subscribers: SubscriberSetWithQueue<(), SortedChildrenCallback, TreeDiff>,
+    _sub: Subscription,
+}
+
+impl std::fmt::Debug for SortedChildrenView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SortedChildrenView")
+            .field("children", &self.children())
+            .finish()
+    }
+}
+
+impl SortedChildrenView {
+    /// Get the sorted children
+    pub fn children(&self) -> Vec<TreeID> {
+        self.view
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|x| x.2)
+            .collect()
+    }
+
+    /// Subscribe the changes of the view.
+    ///
+    /// The callback is invoked with the changes of the view after the events of the tree are
+    /// emitted. The indexes in the diff are the indexes in the sorted view, and they should be
+    /// applied in order. A child whose sort value changes is reported as a move within the parent.
+    pub fn subscribe(&self, callback: SortedChildrenSubscriber) -> Subscription {
+        let (sub, activate) = self.subscribers.inner().insert(
+            (),
+            Box::new(move |diff| {
+                callback(diff);
+                true
+            }),
+        );
+        activate();
+        sub
+    }
+}
+
+impl TreeHandler {
+    /// Get the view of the children of `parent` sorted by the value at `path` in their
+    /// metadata maps.
+    ///
+    /// The path is a list of keys, where every key but the last one refers to a child map.
+    /// A missing value is treated as `Null`, and a container is compared by its deep value.
+    /// The values are ordered by type first (`Null` < bool < number < string < binary <
+    /// list < map), and the children with equal values keep their order in the tree.
+    ///
+    /// The view starts from the committed state, so the pending ops are reported when
+    /// they are committed.
+    pub fn children_sorted_by(
+        &self,
+        parent: &TreeParentId,
+        path: &[&str],
+    ) -> LoroResult<SortedChildrenView> {
+        let inner = self.inner.try_attached_state()?;
+        let doc = &inner.doc;
+        let container = inner.id.clone();
+        let container_idx = inner.container_idx;
+        let path: Vec<String> = path.iter().map(|x| x.to_string()).collect();
+        let children = doc.with_committed_state(container_idx, |state| {
+            let tree = state.as_tree_state().unwrap();
+            if let TreeParentId::Node(id) = parent {
+                if tree.is_node_unexist(id) {
+                    return Err(LoroTreeError::TreeNodeNotExist(*id));
+                }
+            }
+
+            Ok(tree
+                .get_children(parent)
+                .into_iter()
+                .flatten()
+                .filter_map(|x| Some((x, tree.get_node_position(&x)?)))
+                .collect::<Vec<_>>())
+        })?;
+        let mut entries: Vec<_> = children
+            .into_iter()
+            .map(|(target, position)| {
+                let meta = doc
+                    .arena()
+                    .id_to_idx(&target.associated_meta_container())
+                    .map(|idx| doc.get_committed_deep_value(idx))
+                    .unwrap_or(LoroValue::Null);
+                (sort_key(&meta, &path), position, target)
+            })
+            .collect();
+        entries.sort_by(compare_entries);
+
+        let view = Arc::new(Mutex::new(SortedChildren {
+            parent: *parent,
+            entries,
+        }));
+        let subscribers = SubscriberSetWithQueue::new();
+        // Hold the state, the view and the subscribers weakly, or the doc would never be dropped
+        let weak_state = Arc::downgrade(&doc.state);
+        let weak_view = Arc::downgrade(&view);
+        let weak_subscribers = subscribers.downgrade();
+        let arena = doc.arena().clone();
+        let parent = *parent;
+        let sub = doc.subscribe(
+            &inner.id,
+            Arc::new(move |e| {
+                let mut targets = Vec::new();
+                for e in e.events.iter() {
+                    if e.id == container {
+                        if let Diff::Tree(diff) = &e.diff {
+                            targets.extend(diff.diff.iter().map(|x| x.target));
+                        }
+                    } else if let Some(target) = e.path.iter().find_map(|(id, index)| match index {
+                        // The changes in the metadata of the nodes
+                        Index::Node(x) if *id == container => Some(*x),
+                        _ => None,
+                    }) {
+                        targets.push(target);
+                    }
+                }
+
+                let (Some(state), Some(view)) = (weak_state.upgrade(), weak_view.upgrade()) else {
+                    return;
+                };
+                let mut visited = FxHashSet::default();
+                targets.retain(|x| visited.insert(*x));
+                // The current entries of the targets, or None if they aren't the children now
+                let new_entries: Vec<_> = {
+                    let mut state = state.lock().unwrap();
+                    let positions: Vec<_> = state.with_state(container_idx, |state| {
+                        let tree = state.as_tree_state().unwrap();
+                        targets
+                            .iter()
+                            .map(|x| {
+                                tree.is_parent(x, &parent)
+                                    .then(|| tree.get_node_position(x))
+                                    .flatten()
+                            })
+                            .collect()
+                    });
+                    targets
+                        .iter()
+                        .zip(positions)
+                        .map(|(&target, position)| {
+                            let entry = position.map(|position| {
+                                let meta = arena
+                                    .id_to_idx(&target.associated_meta_container())
+                                    .map(|idx| state.get_container_deep_value(idx))
+                                    .unwrap_or(LoroValue::Null);
+                                (sort_key(&meta, &path), position, target)
+                            });
+                            (target, entry)
+                        })
+                        .collect()
+                };
+
+                let diff = view.lock().unwrap().update(new_entries);
+                if diff.diff.is_empty() {
+                    return;
+                }
+
+                if let Some(subscribers) = weak_subscribers.clone().upgrade() {
+                    subscribers.emit(&(), diff);
+                }
+            }),
+        );
+
+        Ok(SortedChildrenView {
+            view,
+            subscribers,
+            _sub: sub,
+        })
+    }
+}
+
+/// Get the value at `path` in the deep value of a metadata map, or `Null` if it's missing
+fn sort_key(meta: &LoroValue, path: &[String]) -> LoroValue {
+    if path.is_empty() {
+        return LoroValue::Null;
+    }
+
+    let mut value = meta;
+    for key in path {
+        match value {
+            LoroValue::Map(map) => match map.get(key) {
+                Some(v) => value = v,
+                None => return LoroValue::Null,
+            },
+            _ => return LoroValue::Null,
+        }
+    }
+
+    value.clone()
+}
+
+/// The mirror of a sorted view of the children, used to compute the changes of the view
+struct SortedChildren {
+    parent: TreeParentId,
+    /// Sorted by the sort value, and then by the position in the tree
+    entries: Vec<(LoroValue, NodePosition, TreeID)>,
+}
+
+impl SortedChildren {
+    /// Update the view with the current entries of the nodes that may have changed, and
+    /// return the changes of the view.
+    fn update(
+        &mut self,
+        new_entries: Vec<(TreeID, Option<(LoroValue, NodePosition, TreeID)>)>,
+    ) -> TreeDiff {
+        let mut ans = TreeDiff::default();
+        for (target, new_entry) in new_entries {
+            let old_index = self.entries.iter().position(|x| x.2 == target);
+            if let Some(i) = old_index {
+                self.entries.remove(i);
+            }
+            let new_index = new_entry.map(|entry| {
+                let index = self
+                    .entries
+                    .partition_point(|x| compare_entries(x, &entry) == Ordering::Less);
+                let position = entry.1.position.clone();
+                self.entries.insert(index, entry);
+                (index, position)
+            });
+
+            let action = match (old_index, new_index) {
+                (None, None) => continue,
+                (None, Some((index, position))) => TreeExternalDiff::Create {
+                    parent: self.parent,
+                    index,
+                    position,
+                },
+                (Some(old_index), None) => TreeExternalDiff::Delete {
+                    old_parent: self.parent,
+                    old_index,
+                },
+                (Some(old_index), Some((index, _))) if old_index == index => continue,
+                (Some(old_index), Some((index, position))) => TreeExternalDiff::Move {
+                    parent: self.parent,
+                    index,
+                    position,
+                    old_parent: self.parent,
+                    old_index,
+                },
+            };
+            ans.diff.push(TreeDiffItem { target, action });
+        }
+
+        ans
+    }
+}
+
+fn compare_entries(
+    a: &(LoroValue, NodePosition, TreeID),
+    b: &(LoroValue, NodePosition, TreeID),
+) -> Ordering {
+    compare_values(&a.0, &b.0).then_with(|| a.1.cmp(&b.1))
+}
+
+/// A total order of the values, by their types first and then by their contents
+fn compare_values(a: &LoroValue, b: &LoroValue) -> Ordering {
+    fn rank(v: &LoroValue) -> u8 {
+        match v {
+            LoroValue::Null => 0,
+            LoroValue::Bool(_) => 1,
+            LoroValue::Double(_) | LoroValue::I64(_) => 2,
+            LoroValue::String(_) => 3,
+            LoroValue::Binary(_) => 4,
+            LoroValue::List(_) => 5,
+            LoroValue::Map(_) => 6,
+            LoroValue::Container(_) => 7,
+        }
+    }
+
+    match (a, b) {
+        (LoroValue::Bool(a), LoroValue::Bool(b)) => a.cmp(b),
+        (LoroValue::I64(a), LoroValue::I64(b)) => a.cmp(b),
+        (LoroValue::I64(a), LoroValue::Double(b)) => compare_i64_f64(*a, *b),
+        (LoroValue::Double(a), LoroValue::I64(b)) => compare_i64_f64(*b, *a).reverse(),
+        (LoroValue::Double(a), LoroValue::Double(b)) => a.total_cmp(b),
+        (LoroValue::String(a), LoroValue::String(b)) => a.as_str().cmp(b.as_str()),
+        (LoroValue::Binary(a), LoroValue::Binary(b)) => a.as_slice().cmp(b.as_slice()),
+        (LoroValue::List(a), LoroValue::List(b)) => a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| compare_values(a, b))
+            .find(|x| x.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (LoroValue::Map(a), LoroValue::Map(b)) => {
+            let mut a: Vec<_> = a.iter().collect();
+            let mut b: Vec<_> = b.iter().collect();
+            a.sort_by(|x, y| x.0.cmp(y.0));
+            b.sort_by(|x, y| x.0.cmp(y.0));
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.0.cmp(b.0).then_with(|| compare_values(a.1, b.1)))
+                .find(|x| x.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+        (LoroValue::Container(a), LoroValue::Container(b)) => a.to_string().cmp(&b.to_string()),
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Compare an integer with a double exactly, in the same order as [f64::total_cmp].
+///
+/// Casting the integer to a double would round the integers above 2^53, so the order
+/// wouldn't be transitive.
+fn compare_i64_f64(a: i64, b: f64) -> Ordering {
+    // 2^63, the smallest double above i64::MAX
+    const I64_END: f64 = 9_223_372_036_854_775_808.0;
+    if b.is_nan() {
+        // A negative NaN is below every number, a positive one is above
+        return if b.is_sign_negative() {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+    }
+    if b >= I64_END {
+        return Ordering::Less;
+    }
+    if b < -I64_END {
+        return Ordering::Greater;
+    }
+
+    let trunc = b.trunc();
+    a.cmp(&(trunc as i64)).then_with(|| {
+        if b > trunc {
+            Ordering::Less
+        } else if b < trunc {
+            Ordering::Greater
+        } else if a == 0 && b.is_sign_negative() {
+            // 0 is equal to 0.0, which is above -0.0
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    })
+}
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+
+use rustc_hash::FxHashSet;
+
+use super::*;
+
+impl TreeHandler {
+    /// Iterate the ancestors of the node, from its parent to the root node.
+    ///
+    /// The node itself is not included. If the node does not exist, the iterator is empty.
+    pub fn ancestors(&self, target: &TreeID) -> TreeAncestors {
+        TreeAncestors {
+            tree: self.clone(),
+            current: Some(*target),
+        }
+    }
+
+    /// Iterate the descendants of `parent` in depth-first pre-order, where the children are
+    /// visited in their order in the tree.
+    ///
+    /// `parent` itself is not included. The children of a node are read when the node is
+    /// yielded, so the whole subtree is never collected at once.
+    pub fn descendants_dfs(&self, parent: &TreeParentId) -> TreeDescendantsDfs {
+        TreeDescendantsDfs {
+            tree: self.clone(),
+            stack: vec![self.children(parent).unwrap_or_default().into_iter()],
+        }
+    }
+
+    /// Iterate the descendants of `parent` in breadth-first order, where the children are
+    /// visited in their order in the tree.
+    ///
+    /// `parent` itself is not included.
+    pub fn descendants_bfs(&self, parent: &TreeParentId) -> TreeDescendantsBfs {
+        TreeDescendantsBfs {
+            tree: self.clone(),
+            queue: self.children(parent).unwrap_or_default().into(),
+        }
+    }
+
+    /// Get the number of the ancestors of the node, so a root node has depth 0.
+    ///
+    /// If the node does not exist, return None.
+    pub fn depth(&self, target: &TreeID) -> Option<usize> {
+        if !self.contains(*target) {
+            return None;
+        }
+
+        Some(self.ancestors(target).count())
+    }
+
+    /// Get the number of the nodes in the subtree of the node, including the node itself.
+    ///
+    /// If the node does not exist, return None.
+    pub fn subtree_size(&self, target: &TreeID) -> Option<usize> {
+        if !self.contains(*target) {
+            return None;
+        }
+
+        Some(self.descendants_dfs(&TreeParentId::Node(*target)).count() + 1)
+    }
+
+    /// Get the deepest node that is an ancestor of both `a` and `b`, where a node is
+    /// regarded as an ancestor of itself.
+    ///
+    /// Return None if the nodes are in different trees or one of them does not exist.
+    pub fn lowest_common_ancestor(&self, a: &TreeID, b: &TreeID) -> Option<TreeID> {
+        if !self.contains(*a) || !self.contains(*b) {
+            return None;
+        }
+
+        let a_path: FxHashSet<TreeID> = std::iter::once(*a).chain(self.ancestors(a)).collect();
+        std::iter::once(*b)
+            .chain(self.ancestors(b))
+            .find(|x| a_path.contains(x))
+    }
+}
+
+/// The iterator of the ancestors of a node, see [TreeHandler::ancestors].
+pub struct TreeAncestors {
+    tree: TreeHandler,
+    current: Option<TreeID>,
+}
+
+impl Iterator for TreeAncestors {
+    type Item = TreeID;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current.take()?;
+        match self.tree.get_node_parent(&current)? {
+            TreeParentId::Node(parent) => {
+                self.current = Some(parent);
+                Some(parent)
+            }
+            TreeParentId::Root | TreeParentId::Deleted | TreeParentId::Unexist => None,
+        }
+    }
+}
+
+/// The depth-first iterator of the descendants, see [TreeHandler::descendants_dfs].
+pub struct TreeDescendantsDfs {
+    tree: TreeHandler,
+    /// The children that are not visited yet of each level
+    stack: Vec<std::vec::IntoIter<TreeID>>,
+}
+
+impl Iterator for TreeDescendantsDfs {
+    type Item = TreeID;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.stack.last_mut()?.next();
+            match next {
+                Some(node) => {
+                    let children = self
+                        .tree
+                        .children(&TreeParentId::Node(node))
+                        .unwrap_or_default();
+                    if !children.is_empty() {
+                        self.stack.push(children.into_iter());
+                    }
+                    return Some(node);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// The breadth-first iterator of the descendants, see [TreeHandler::descendants_bfs].
+pub struct TreeDescendantsBfs {
+    tree: TreeHandler,
+    queue: VecDeque<TreeID>,
+}
+
+impl Iterator for TreeDescendantsBfs {
+    type Item = TreeID;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        if let Some(children) = self.tree.children(&TreeParentId::Node(node)) {
+            self.queue.extend(children);
+        }
+        Some(node)
+    }
+}
@@ -1,7 +1,8 @@
 use crate::sync::Mutex;
 use bytes::Bytes;
+use loro_common::{LoroError, LoroResult};
 pub use loro_kv_store::compress::CompressionType;
-pub use loro_kv_store::MemKvStore;
+pub use loro_kv_store::{DirKvStore, MemKvStore};
 use std::sync::Arc;
 use std::{collections::BTreeMap, ops::Bound};
 
@@ -22,6 +23,17 @@ pub trait KvStore: std::fmt::Debug + Send + Sync {
     fn export_all(&mut self) -> Bytes;
     fn import_all(&mut self, bytes: Bytes) -> Result<(), String>;
     fn clone_store(&self) -> Arc<Mutex<dyn KvStore>>;
+    /// Persist the pending writes if the store is backed by a durable storage.
+    fn flush(&mut self) -> LoroResult<()> {
+        Ok(())
+    }
+    /// The error of the first failed read, if any.
+    ///
+    /// The read methods can't return errors, so a store backed by a fallible storage
+    /// reports a failed read as a missing entry and keeps the error here.
+    fn read_error(&self) -> Option<LoroError> {
+        None
+    }
 }
 
 fn get_common_prefix_len_and_strip<'a, T: AsRef<[u8]> + ?Sized>(
@@ -96,6 +108,112 @@ impl KvStore for MemKvStore {
     }
 }
 
+/// Adapts a [DirKvStore] to [KvStore].
+///
+/// A failed read of the sstable files is reported as a missing entry, and the error
+/// is kept in [KvStore::read_error]. The store refuses to flush after that, because
+/// the pending writes may be based on the incomplete reads.
+#[derive(Debug)]
+pub struct DirKvAdapter {
+    store: DirKvStore,
+    read_error: Mutex<Option<LoroError>>,
+}
+
+impl DirKvAdapter {
+    pub fn new(store: DirKvStore) -> Self {
+        Self {
+            store,
+            read_error: Mutex::new(None),
+        }
+    }
+
+    fn ok_or_record<T>(&self, result: LoroResult<T>) -> Option<T> {
+        match result {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::error!("Failed to read the DirKvStore: {}", e);
+                self.read_error.lock().unwrap().get_or_insert(e);
+                None
+            }
+        }
+    }
+}
+
+impl KvStore for DirKvAdapter {
+    fn get(&self, key: &[u8]) -> Option<Bytes> {
+        self.ok_or_record(self.store.get(key)).flatten()
+    }
+
+    fn set(&mut self, key: &[u8], value: Bytes) {
+        self.store.set(key, value)
+    }
+
+    fn compare_and_swap(&mut self, key: &[u8], old: Option<Bytes>, new: Bytes) -> bool {
+        let result = self.store.compare_and_swap(key, old, new);
+        self.ok_or_record(result).unwrap_or(false)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<Bytes> {
+        let ans = KvStore::get(self, key);
+        self.store.remove(key);
+        ans
+    }
+
+    fn contains_key(&self, key: &[u8]) -> bool {
+        self.ok_or_record(self.store.contains_key(key))
+            .unwrap_or(false)
+    }
+
+    fn scan(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> Box<dyn DoubleEndedIterator<Item = (Bytes, Bytes)> + '_> {
+        Box::new(
+            self.store
+                .scan(start, end)
+                .filter_map(|x| self.ok_or_record(x)),
+        )
+    }
+
+    fn len(&self) -> usize {
+        self.ok_or_record(self.store.len()).unwrap_or(0)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ok_or_record(self.store.is_empty()).unwrap_or(true)
+    }
+
+    fn size(&self) -> usize {
+        self.store.size()
+    }
+
+    fn export_all(&mut self) -> Bytes {
+        let result = self.store.export_all();
+        self.ok_or_record(result).unwrap_or_default()
+    }
+
+    fn import_all(&mut self, bytes: Bytes) -> Result<(), String> {
+        self.store.import_all(bytes).map_err(|e| e.to_string())
+    }
+
+    /// The cloned store lives in memory. It doesn't write to the directory.
+    fn clone_store(&self) -> Arc<Mutex<dyn KvStore>> {
+        Arc::new(Mutex::new(self.store.to_mem_store()))
+    }
+
+    fn flush(&mut self) -> LoroResult<()> {
+        if let Some(e) = self.read_error() {
+            return Err(e);
+        }
+        self.store.flush()
+    }
+
+    fn read_error(&self) -> Option<LoroError> {
+        self.read_error.lock().unwrap().clone()
+    }
+}
+
 mod default_binary_format {
     //! Default binary format for the key-value store.
     //!
@@ -19,6 +19,8 @@ mod change_meta;
 pub(crate) mod lock;
 use arena::SharedArena;
 use configure::Configure;
+use container::idx::ContainerIdx;
+use delta::TreeMoveRecord;
 use diff_calc::DiffCalculator;
 use lock::LoroMutex;
 
@@ -37,7 +39,7 @@ use pre_commit::{
 pub use rustc_hash::FxHashMap;
 pub use state::DocState;
 pub use state::{TreeNode, TreeNodeWithChildren, TreeParentId};
-use subscription::{LocalUpdateCallback, Observer, PeerIdUpdateCallback};
+use subscription::{LocalUpdateCallback, Observer, PeerIdUpdateCallback, TreeMoveConflictCallback};
 use txn::Transaction;
 pub use undo::UndoManager;
 pub use utils::subscription::SubscriberSetWithQueue;
@@ -67,6 +69,7 @@ mod error;
 pub mod fuzz;
 mod parent;
 pub mod pre_commit;
+pub mod signature;
 mod span;
 #[cfg(test)]
 pub mod tests;
@@ -167,6 +170,10 @@ pub struct LoroDocInner {
     first_commit_from_peer_subs:
         SubscriberSetWithQueue<(), FirstCommitFromPeerCallback, FirstCommitFromPeerPayload>,
     pre_commit_subs: SubscriberSetWithQueue<(), PreCommitCallback, PreCommitCallbackPayload>,
+    tree_move_conflict_subs:
+        SubscriberSetWithQueue<ContainerIdx, TreeMoveConflictCallback, Vec<TreeMoveRecord>>,
+    /// The directory the doc is persisted to. It's only set when the doc is opened by `open_dir`.
+    storage_dir: Option<std::path::PathBuf>,
 }
 
 /// The version of the loro crate
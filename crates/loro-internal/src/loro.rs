@@ -2,7 +2,7 @@ use crate::encoding::json_schema::{encode_change, export_json_in_id_span};
 pub use crate::encoding::ExportMode;
 use crate::pre_commit::{FirstCommitFromPeerCallback, FirstCommitFromPeerPayload};
 pub use crate::state::analyzer::{ContainerAnalysisInfo, DocAnalysis};
-use crate::sync::AtomicBool;
+use crate::sync::{AtomicBool, Mutex};
 pub(crate) use crate::LoroDocInner;
 use crate::{
     arena::SharedArena,
@@ -16,19 +16,24 @@ use crate::{
     dag::{Dag, DagUtils},
     diff_calc::DiffCalculator,
     encoding::{
-        self, decode_snapshot, export_fast_snapshot, export_fast_updates,
-        export_fast_updates_in_range, export_shallow_snapshot, export_snapshot_at,
+        self, compress_blob, decode_snapshot, decompress_blob, decrypt_blob, encrypt_blob,
+        export_fast_snapshot, export_fast_updates, export_fast_updates_in_range,
+        export_pending_changes, export_shallow_snapshot, export_snapshot_at,
         export_state_only_snapshot,
+        fast_snapshot::{decode_snapshot_inner, decode_snapshot_with_oplog_loader},
         json_schema::{encode_change_to_json, json::JsonSchema},
-        parse_header_and_body, EncodeMode, ImportBlobMetadata, ImportStatus, ParsedHeaderAndBody,
+        parse_header_and_body, Cipher, EncodeMode, ExportOptions, ImportBlobMetadata, ImportStatus,
+        ParsedHeaderAndBody, ReadBlob,
     },
     event::{str_to_path, EventTriggerKind, Index, InternalDocDiff},
     handler::{Handler, MovableListHandler, TextHandler, TreeHandler, ValueOrHandler},
     id::PeerID,
     json::JsonChange,
+    kv_store::MemKvStore,
     op::InnerContent,
-    oplog::{loro_dag::FrontiersNotIncluded, OpLog},
-    state::DocState,
+    oplog::{loro_dag::FrontiersNotIncluded, OpLog, PendingChangesSummary},
+    signature::{InvalidSignaturePolicy, Signer, Verifier},
+    state::{ContainerState, DocState, State},
     subscription::{LocalUpdateCallback, Observer, Subscriber},
     undo::DiffBatch,
     utils::subscription::{SubscriberSetWithQueue, Subscription},
@@ -42,11 +47,13 @@ use crate::{
     lock::{LoroLockGroup, LoroMutex},
     txn::Transaction,
 };
+use bytes::Bytes;
 use either::Either;
 use loro_common::{
     ContainerID, ContainerType, HasIdSpan, HasLamportSpan, IdSpan, LoroEncodeError, LoroResult,
     LoroValue, ID,
 };
+use loro_kv_store::mem_store::MemKvConfig;
 use rle::HasLength;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
@@ -54,6 +61,7 @@ use std::{
     cmp::Ordering,
     collections::{hash_map::Entry, BinaryHeap},
     ops::ControlFlow,
+    path::PathBuf,
     sync::{
         atomic::Ordering::{Acquire, Release},
         Arc,
@@ -98,7 +106,13 @@ impl LoroDoc {
     }
 
     pub fn new() -> Self {
-        let oplog = OpLog::new();
+        Self::new_with_oplog(OpLog::new(), None)
+    }
+
+    /// Create a doc on top of the given oplog.
+    ///
+    /// `storage_dir` is the directory the doc is persisted to, see [LoroDoc::open_dir].
+    pub(crate) fn new_with_oplog(oplog: OpLog, storage_dir: Option<PathBuf>) -> Self {
         let arena = oplog.arena.clone();
         let config: Configure = oplog.configure.clone();
         let lock_group = LoroLockGroup::new();
@@ -121,6 +135,8 @@ impl LoroDoc {
                 peer_id_change_subs: SubscriberSetWithQueue::new(),
                 pre_commit_subs: SubscriberSetWithQueue::new(),
                 first_commit_from_peer_subs: SubscriberSetWithQueue::new(),
+                tree_move_conflict_subs: SubscriberSetWithQueue::new(),
+                storage_dir,
             }
         });
         LoroDoc { inner }
@@ -450,6 +466,31 @@ impl LoroDoc {
         self.config.set_merge_interval(interval);
     }
 
+    /// Set the signer that signs the local changes when they are committed.
+    ///
+    /// A signed change is never merged with the other changes.
+    #[inline]
+    pub fn set_signer(&self, signer: Option<Arc<dyn Signer>>) {
+        self.config.set_signer(signer);
+    }
+
+    /// Set the verifier that checks the signatures of the imported changes,
+    /// and what to do with the changes whose signatures are invalid.
+    #[inline]
+    pub fn set_verifier(
+        &self,
+        verifier: Option<Arc<dyn Verifier>>,
+        policy: InvalidSignaturePolicy,
+    ) {
+        self.config.set_verifier(verifier, policy);
+    }
+
+    /// Get the signature of the change that contains the given id.
+    pub fn get_change_signature(&self, id: ID) -> Option<Arc<[u8]>> {
+        let oplog = self.oplog.lock().unwrap();
+        oplog.get_change_at(id)?.signature().cloned()
+    }
+
     pub fn can_edit(&self) -> bool {
         !self.is_detached() || self.config.detached_editing()
     }
@@ -460,7 +501,11 @@ impl LoroDoc {
 
     #[inline]
     pub fn config_text_style(&self, text_style: StyleConfigMap) {
-        self.config.text_style_config.try_write().unwrap().map = text_style.map;
+        self.config
+            .text_style_config
+            .try_write()
+            .unwrap()
+            .set_map(text_style.map);
     }
 
     #[inline]
@@ -469,11 +514,12 @@ impl LoroDoc {
             .text_style_config
             .try_write()
             .unwrap()
-            .default_style = text_style;
+            .set_default_style(text_style);
     }
     pub fn from_snapshot(bytes: &[u8]) -> LoroResult<Self> {
         let doc = Self::new();
-        let ParsedHeaderAndBody { mode, body, .. } = parse_header_and_body(bytes, true)?;
+        let bytes = decompress_blob(bytes)?;
+        let ParsedHeaderAndBody { mode, body, .. } = parse_header_and_body(&bytes, true)?;
         if mode.is_snapshot() {
             doc.with_barrier(|| -> Result<(), LoroError> {
                 decode_snapshot(&doc, mode, body, Default::default())?;
@@ -487,6 +533,91 @@ impl LoroDoc {
         }
     }
 
+    /// Create a doc from a snapshot, and only decode the states of the given
+    /// containers and their descendants.
+    ///
+    /// The states of the other containers stay in their encoded form, and they
+    /// are decoded lazily when they are accessed.
+    pub fn from_snapshot_with_roots(bytes: &[u8], roots: &[ContainerID]) -> LoroResult<Self> {
+        let doc = Self::from_snapshot(bytes)?;
+        doc.materialize_containers(roots)?;
+        Ok(doc)
+    }
+
+    /// Decode the states of the given containers and all their descendants.
+    ///
+    /// It returns [LoroError::ContainersNotFound] if any of the containers doesn't exist.
+    pub fn materialize_containers(&self, roots: &[ContainerID]) -> LoroResult<()> {
+        self.with_barrier(|| self.state.lock().unwrap().materialize_subtrees(roots))
+    }
+
+    /// Evict the least recently accessed containers back to their encoded form,
+    /// until the total encoded size of the decoded containers is within `budget` bytes.
+    ///
+    /// The evicted containers will be decoded again when they are accessed.
+    /// Return the number of the evicted containers.
+    pub fn evict_cold_containers(&self, budget: usize) -> usize {
+        self.with_barrier(|| self.state.lock().unwrap().evict_cold_containers(budget))
+    }
+
+    /// Create a doc from the kv entries of the change store.
+    ///
+    /// The entries are the ones returned by [LoroDoc::export_dirty_kv_entries]. They
+    /// should be given in the order they were exported, so that a newer value of
+    /// the same key overrides the older one. The state is calculated from the history.
+    ///
+    /// It cannot reconstruct a shallow doc, because the state of the shallow root
+    /// is not included in the entries.
+    pub fn from_kv_entries(entries: impl IntoIterator<Item = (Bytes, Bytes)>) -> LoroResult<Self> {
+        let mut kv = MemKvStore::new(MemKvConfig::default());
+        for (k, v) in entries {
+            kv.set(&k, v);
+        }
+
+        let doc = Self::new_with_oplog(OpLog::new_with_kv(Arc::new(Mutex::new(kv))), None);
+        if doc
+            .oplog
+            .lock()
+            .unwrap()
+            .change_store()
+            .external_kv_frontiers()?
+            .is_none()
+        {
+            return Ok(doc);
+        }
+
+        doc.with_barrier(|| {
+            decode_snapshot_with_oplog_loader(
+                &doc,
+                |oplog| {
+                    oplog.load_change_store_from_kv()?;
+                    if oplog.is_shallow() {
+                        return Err(LoroError::DecodeError(
+                            "Cannot create a shallow doc from kv entries".into(),
+                        ));
+                    }
+                    Ok(())
+                },
+                None,
+                Bytes::new(),
+                Default::default(),
+            )
+        })?;
+        Ok(doc)
+    }
+
+    /// Export the kv entries of the change store that have changed since the last call.
+    ///
+    /// The keys are the ones documented on [crate::oplog::ChangeStore]: the blocks keyed by
+    /// PeerID + Counter, plus the `vv` and `fr` keys. All the entries are returned on the
+    /// first call, or after a snapshot is imported into an empty doc.
+    ///
+    /// The pending transaction is committed first. The entries can be
+    /// used to recreate the doc with [LoroDoc::from_kv_entries].
+    pub fn export_dirty_kv_entries(&self) -> Vec<(Bytes, Bytes)> {
+        self.with_barrier(|| self.oplog.lock().unwrap().export_dirty_kv_entries())
+    }
+
     /// Is the document empty? (no ops)
     #[inline(always)]
     pub fn can_reset_with_snapshot(&self) -> bool {
@@ -499,6 +630,11 @@ impl LoroDoc {
             return false;
         }
 
+        // The changes in the snapshot need to be verified one by one
+        if oplog.configure.signature_config().verifier.is_some() {
+            return false;
+        }
+
         oplog.is_empty() && self.state.lock().unwrap().can_import_snapshot()
     }
 
@@ -574,6 +710,56 @@ impl LoroDoc {
         self.with_barrier(|| self._import_with(bytes, origin))
     }
 
+    /// Import the blob read from the reader.
+    ///
+    /// A fast snapshot is read part by part, and the reader is not read beyond
+    /// the end of it. The blocks of fast updates are read and decoded one by one.
+    /// Blobs in the other modes are read until the end of the reader.
+    pub fn import_from_reader<R: std::io::Read>(
+        &self,
+        r: &mut R,
+    ) -> Result<ImportStatus, LoroError> {
+        let s = debug_span!("import_from_reader", peer = self.peer_id());
+        let _e = s.enter();
+        self.with_barrier(|| self._import_from_reader(r, Default::default()))
+    }
+
+    fn _import_from_reader<R: std::io::Read>(
+        &self,
+        r: &mut R,
+        origin: InternalString,
+    ) -> Result<ImportStatus, LoroError> {
+        let snapshot = match encoding::read_blob_from(r)? {
+            ReadBlob::Snapshot(snapshot) => snapshot,
+            ReadBlob::Updates { checksum } => {
+                let result = self.update_oplog_and_apply_delta_to_state_if_needed(
+                    |oplog| encoding::decode_updates_from_reader(oplog, r, checksum),
+                    origin,
+                );
+                self.emit_events();
+                return result;
+            }
+            ReadBlob::Other(bytes) => return self._import_with(&bytes, origin),
+        };
+
+        let result = if self.can_reset_with_snapshot() {
+            loro_common::info!("Init by fast snapshot from reader {}", self.peer_id());
+            decode_snapshot_inner(snapshot, self, origin).map(|_| ImportStatus {
+                success: VersionRange::from_vv(&self.oplog_vv()),
+                pending: None,
+                quarantined: None,
+            })
+        } else {
+            self.update_oplog_and_apply_delta_to_state_if_needed(
+                |oplog| encoding::decode_snapshot_oplog(oplog, snapshot.oplog_bytes),
+                origin,
+            )
+        };
+
+        self.emit_events();
+        result
+    }
+
     #[tracing::instrument(skip_all)]
     fn _import_with(
         &self,
@@ -581,7 +767,8 @@ impl LoroDoc {
         origin: InternalString,
     ) -> Result<ImportStatus, LoroError> {
         ensure_cov::notify_cov("loro_internal::import");
-        let parsed = parse_header_and_body(bytes, true)?;
+        let bytes = decompress_blob(bytes)?;
+        let parsed = parse_header_and_body(&bytes, true)?;
         loro_common::info!("Importing with mode={:?}", &parsed.mode);
         let result = match parsed.mode {
             EncodeMode::OutdatedRle => {
@@ -682,17 +869,21 @@ impl LoroDoc {
 
     fn emit_events(&self) {
         // we should not hold the lock when emitting events
-        let events = {
+        let (events, rejected_tree_moves) = {
             let mut state = self.state.lock().unwrap();
-            state.take_events()
+            (state.take_events(), state.take_rejected_tree_moves())
         };
         for event in events {
             self.observer.emit(event);
         }
+        for (tree, moves) in rejected_tree_moves {
+            self.tree_move_conflict_subs.emit(&tree, moves);
+        }
     }
 
     pub(crate) fn drop_pending_events(&self) -> Vec<DocDiff> {
         let mut state = self.state.lock().unwrap();
+        state.take_rejected_tree_moves();
         state.take_events()
     }
 
@@ -1191,6 +1382,7 @@ impl LoroDoc {
 
         let mut success = VersionRange::default();
         let mut pending = VersionRange::default();
+        let mut quarantined = VersionRange::default();
         let mut meta_arr = bytes
             .iter()
             .map(|b| Ok((LoroDoc::decode_import_blob_meta(b, false)?, b)))
@@ -1256,6 +1448,12 @@ impl LoroDoc {
                             }
                         }
                     }
+
+                    if let Some(q) = s.quarantined.as_ref() {
+                        for (&peer, &(start, end)) in q.iter() {
+                            quarantined.extends_to_include_id_span(IdSpan::new(peer, start, end));
+                        }
+                    }
                 }
                 Err(e) => {
                     err = Some(e);
@@ -1284,6 +1482,11 @@ impl LoroDoc {
             } else {
                 Some(pending)
             },
+            quarantined: if quarantined.is_empty() {
+                None
+            } else {
+                Some(quarantined)
+            },
         })
     }
 
@@ -1808,6 +2011,57 @@ impl LoroDoc {
         })
     }
 
+    /// Export the doc in the given mode with the given options.
+    ///
+    /// The compression is recorded in the header of the blob, so it can be
+    /// imported by [`LoroDoc::import`] directly.
+    pub fn export_with(
+        &self,
+        mode: ExportMode,
+        options: ExportOptions,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        let ans = self.export(mode)?;
+        compress_blob(ans, options.compression)
+    }
+
+    /// Export the doc in the given mode and encrypt it with the cipher.
+    ///
+    /// The metadata of the blob is still readable by [`LoroDoc::decode_import_blob_meta`]
+    /// without the key, but the content can only be imported by [`LoroDoc::import_encrypted`].
+    pub fn export_encrypted(
+        &self,
+        mode: ExportMode,
+        cipher: &dyn Cipher,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        let blob = self.export(mode)?;
+        let meta = LoroDoc::decode_import_blob_meta(&blob, false)
+            .map_err(|e| LoroEncodeError::EncryptionError(e.to_string()))?;
+        encrypt_blob(&blob, meta, cipher)
+    }
+
+    /// Decrypt the blob exported by [`LoroDoc::export_encrypted`] and import it.
+    pub fn import_encrypted(
+        &self,
+        bytes: &[u8],
+        cipher: &dyn Cipher,
+    ) -> Result<ImportStatus, LoroError> {
+        let blob = decrypt_blob(bytes, cipher)?;
+        self.import(&blob)
+    }
+
+    /// Export the doc in the given mode into the writer.
+    ///
+    /// The snapshot modes write the parts of the snapshot one by one instead of
+    /// concatenating them into a single buffer.
+    #[instrument(skip(self, w))]
+    pub fn export_to_writer<W: std::io::Write>(
+        &self,
+        mode: ExportMode,
+        w: &mut W,
+    ) -> Result<(), LoroEncodeError> {
+        self.with_barrier(|| encoding::export_to_writer(self, mode, w))
+    }
+
     /// The doc only contains the history since the shallow history start version vector.
     ///
     /// This is empty if the doc is not shallow.
@@ -1842,6 +2096,103 @@ impl LoroDoc {
         }
     }
 
+    /// Get the version of the state at the last commit, i.e. without the ops in the
+    /// pending transaction.
+    pub(crate) fn committed_frontiers(&self) -> Frontiers {
+        let txn = self.txn.lock().unwrap();
+        match txn.as_ref() {
+            Some(txn) => txn.frontiers().clone(),
+            None => self.state.lock().unwrap().frontiers.clone(),
+        }
+    }
+
+    /// Call `f` with a copy of the container state at the last commit, i.e. without the
+    /// ops in the pending transaction.
+    ///
+    /// The events of the pending ops are emitted when they are committed, so a subscriber
+    /// that keeps its own view of a container should build it from this state.
+    pub(crate) fn with_committed_state<R>(
+        &self,
+        idx: ContainerIdx,
+        f: impl FnOnce(&mut State) -> R,
+    ) -> R {
+        let txn = self.txn.lock().unwrap();
+        let oplog = self.oplog.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        let frontiers = match txn.as_ref() {
+            Some(txn) => txn.frontiers().clone(),
+            None => state.frontiers.clone(),
+        };
+        let mut committed = state.fork_committed_state(idx, &frontiers, &oplog);
+        drop(state);
+        drop(oplog);
+        drop(txn);
+        f(&mut committed)
+    }
+
+    /// Get the deep value of the container at the last commit, see [LoroDoc::with_committed_state].
+    pub(crate) fn get_committed_deep_value(&self, idx: ContainerIdx) -> LoroValue {
+        let value = self.with_committed_state(idx, |state| state.get_value());
+        self.resolve_committed_containers(value)
+    }
+
+    fn resolve_committed_containers(&self, value: LoroValue) -> LoroValue {
+        match value {
+            LoroValue::Container(id) => {
+                self.get_committed_deep_value(self.arena.register_container(&id))
+            }
+            LoroValue::List(list) => LoroValue::List(
+                list.iter()
+                    .map(|x| self.resolve_committed_containers(x.clone()))
+                    .collect(),
+            ),
+            LoroValue::Map(map) => LoroValue::Map(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.resolve_committed_containers(v.clone())))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    /// Discard the ops in the pending transaction.
+    ///
+    /// The [DocState] is reverted to the version before these ops, and their op ids
+    /// will be reused by the next commit. No events are emitted for them, so the
+    /// subscribers and the undo manager never see the discarded changes.
+    ///
+    /// The next-commit options (message, origin, timestamp) are discarded as well.
+    /// It only has effect when auto commit is enabled.
+    pub fn rollback_pending(&self) {
+        let mut txn_guard = self.txn.lock().unwrap();
+        let Some(txn) = txn_guard.take() else {
+            return;
+        };
+
+        txn.abort();
+        self._renew_txn_if_auto_commit_with_guard(None, txn_guard);
+    }
+
+    /// Get the summary of the imported changes that can't be applied because their
+    /// dependencies are missing.
+    pub fn pending_changes_summary(&self) -> PendingChangesSummary {
+        self.oplog.lock().unwrap().pending_changes_summary()
+    }
+
+    /// Export the imported changes that can't be applied because their dependencies
+    /// are missing, in the updates format.
+    ///
+    /// The exported bytes can be imported later (e.g. after a restart) to restore them.
+    pub fn export_pending(&self) -> Vec<u8> {
+        export_pending_changes(&self.oplog.lock().unwrap())
+    }
+
+    /// Drop the imported changes that can't be applied because their dependencies
+    /// are missing, and return their range.
+    pub fn clear_pending(&self) -> VersionRange {
+        self.oplog.lock().unwrap().clear_pending_changes()
+    }
+
     #[inline]
     pub fn find_id_spans_between(&self, from: &Frontiers, to: &Frontiers) -> VersionVectorDiff {
         self.oplog().lock().unwrap().dag.find_path(from, to)
@@ -11,6 +11,7 @@ use crate::{
     container::{
         list::list_op::{InnerListOp, ListOp},
         map::MapSet,
+        richtext::EMBED_STYLE_KEY,
         tree::tree_op::TreeOp,
     },
     encoding::OwnedValue,
@@ -45,7 +46,11 @@ impl InnerContent {
                 InnerListOp::Move { .. } => {}
                 InnerListOp::InsertText { .. } => {}
                 InnerListOp::Delete(_) => {}
-                InnerListOp::StyleStart { .. } => {}
+                InnerListOp::StyleStart { key, value, .. } => {
+                    if let (EMBED_STYLE_KEY, LoroValue::Container(c)) = (&**key, value) {
+                        f(c);
+                    }
+                }
                 InnerListOp::StyleEnd => {}
             },
             crate::op::InnerContent::Map(m) => {
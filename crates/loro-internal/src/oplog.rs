@@ -22,17 +22,20 @@ use crate::encoding::decode_oplog;
 use crate::encoding::{ImportStatus, ParsedHeaderAndBody};
 use crate::history_cache::ContainerHistoryCache;
 use crate::id::{Counter, PeerID, ID};
+use crate::kv_store::KvStore;
 use crate::op::{FutureInnerContent, ListSlice, RawOpContent, RemoteOp, RichOp};
 use crate::span::{HasCounterSpan, HasLamportSpan};
 use crate::version::{Frontiers, ImVersionVector, VersionVector};
 use crate::LoroError;
 use change_store::BlockOpRef;
-use loro_common::{HasIdSpan, IdLp, IdSpan};
+use loro_common::{HasIdSpan, IdLp, IdSpan, LoroResult};
 use rle::{HasLength, RleVec, Sliceable};
 use smallvec::SmallVec;
 
 pub use self::loro_dag::{AppDag, AppDagNode, FrontiersNotIncluded};
+pub(crate) use change_store::{encode_change_as_block, ExportedBlocks};
 pub use change_store::{BlockChangeRef, ChangeStore};
+pub use pending_changes::PendingChangesSummary;
 
 /// [OpLog] store all the ops i.e. the history.
 /// It allows multiple [AppState] to attach to it.
@@ -74,6 +77,24 @@ impl OpLog {
         let arena = SharedArena::new();
         let cfg = Configure::default();
         let change_store = ChangeStore::new_mem(&arena, cfg.merge_interval_in_s.clone());
+        Self::new_with_change_store(arena, cfg, change_store)
+    }
+
+    /// Create an oplog whose changes are stored in the given kv store.
+    ///
+    /// [OpLog::load_change_store_from_kv] should be called if the kv store is not empty.
+    pub(crate) fn new_with_kv(kv: Arc<Mutex<dyn KvStore>>) -> Self {
+        let arena = SharedArena::new();
+        let cfg = Configure::default();
+        let change_store = ChangeStore::new_with_kv(&arena, cfg.merge_interval_in_s.clone(), kv);
+        Self::new_with_change_store(arena, cfg, change_store)
+    }
+
+    fn new_with_change_store(
+        arena: SharedArena,
+        cfg: Configure,
+        change_store: ChangeStore,
+    ) -> Self {
         Self {
             history_cache: Mutex::new(ContainerHistoryCache::new(change_store.clone(), None)),
             dag: AppDag::new(change_store.clone()),
@@ -375,6 +396,17 @@ impl OpLog {
         self.change_store.export_blocks_in_range(spans, w)
     }
 
+    #[inline(always)]
+    pub(crate) fn collect_blocks_from(&self, vv: &VersionVector) -> ExportedBlocks {
+        self.change_store
+            .collect_blocks_from(vv, self.shallow_since_vv(), self.vv())
+    }
+
+    #[inline(always)]
+    pub(crate) fn collect_blocks_in_range(&self, spans: &[IdSpan]) -> ExportedBlocks {
+        self.change_store.collect_blocks_in_range(spans)
+    }
+
     pub(crate) fn fork_changes_up_to(&self, frontiers: &Frontiers) -> Option<Bytes> {
         let vv = self.dag.frontiers_to_vv(frontiers)?;
         Some(
@@ -594,6 +626,26 @@ impl OpLog {
             .encode_all(self.dag.vv(), self.dag.frontiers())
     }
 
+    /// Load the history that already exists in the kv store of the change store.
+    pub(crate) fn load_change_store_from_kv(&mut self) -> LoroResult<()> {
+        let v = self.change_store.load_from_external_kv()?;
+        self.dag.set_version_by_fast_snapshot_import(v);
+        Ok(())
+    }
+
+    /// Return the kv entries of the change store that have changed since the last call.
+    pub(crate) fn export_dirty_kv_entries(&self) -> Vec<(Bytes, Bytes)> {
+        self.change_store
+            .export_dirty_kv_entries(self.dag.vv(), self.dag.frontiers())
+    }
+
+    /// Write the cached changes into the kv store of the change store and persist it.
+    pub(crate) fn persist_change_store(&self) -> LoroResult<()> {
+        self.change_store
+            .flush_and_compact(self.dag.vv(), self.dag.frontiers());
+        self.change_store.persist_external_kv()
+    }
+
     pub fn check_dag_correctness(&self) {
         self.dag.check_dag_correctness();
     }
@@ -649,6 +701,7 @@ pub(crate) fn convert_change_to_remote(
         lamport: change.lamport,
         timestamp: change.timestamp,
         commit_msg: change.commit_msg.clone(),
+        signature: change.signature.clone(),
     }
 }
 
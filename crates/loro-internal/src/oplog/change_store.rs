@@ -24,7 +24,7 @@ use rle::{HasLength, Mergable, RlePush, RleVec, Sliceable};
 use std::sync::atomic::AtomicI64;
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     ops::{Bound, Deref},
     sync::Arc,
 };
@@ -80,6 +80,9 @@ struct ChangeStoreInner {
     start_frontiers: Frontiers,
     /// It's more like a parsed cache for binary_kv.
     mem_parsed_kv: BTreeMap<ID, Arc<ChangesBlock>>,
+    /// The ids of the blocks written into the external kv since the last
+    /// [ChangeStore::export_dirty_kv_entries]. `None` means all the entries are dirty.
+    dirty_block_ids: Option<BTreeSet<ID>>,
 }
 
 #[derive(Debug, Clone)]
@@ -114,16 +117,33 @@ pub const FRONTIERS_KEY: &[u8] = b"fr";
 
 impl ChangeStore {
     pub fn new_mem(a: &SharedArena, merge_interval: Arc<AtomicI64>) -> Self {
+        Self::new_with_kv(
+            a,
+            merge_interval,
+            Arc::new(Mutex::new(MemKvStore::new(MemKvConfig::default()))),
+            // Arc::new(Mutex::new(BTreeMap::default())),
+        )
+    }
+
+    /// Create a change store on top of the given kv store.
+    ///
+    /// If the kv store is not empty, [ChangeStore::load_from_external_kv] should be
+    /// called before using the store.
+    pub fn new_with_kv(
+        a: &SharedArena,
+        merge_interval: Arc<AtomicI64>,
+        external_kv: Arc<Mutex<dyn KvStore>>,
+    ) -> Self {
         Self {
             inner: Arc::new(Mutex::new(ChangeStoreInner {
                 start_vv: ImVersionVector::new(),
                 start_frontiers: Frontiers::default(),
                 mem_parsed_kv: BTreeMap::new(),
+                dirty_block_ids: None,
             })),
             arena: a.clone(),
             external_vv: Arc::new(Mutex::new(VersionVector::new())),
-            external_kv: Arc::new(Mutex::new(MemKvStore::new(MemKvConfig::default()))),
-            // external_kv: Arc::new(Mutex::new(BTreeMap::default())),
+            external_kv,
             merge_interval,
         }
     }
@@ -163,7 +183,7 @@ impl ChangeStore {
                     continue;
                 }
 
-                let ch = c.slice(start, end);
+                let ch = slice_for_export(&c, start, end);
                 new_store.insert_change(ch, false, false);
             }
         }
@@ -177,6 +197,11 @@ impl ChangeStore {
     }
 
     pub(super) fn export_blocks_in_range<W: std::io::Write>(&self, spans: &[IdSpan], w: &mut W) {
+        self.collect_blocks_in_range(spans).write_to(w).unwrap();
+    }
+
+    /// Collect the changes in the given spans into the blocks to be exported.
+    pub(crate) fn collect_blocks_in_range(&self, spans: &[IdSpan]) -> ExportedBlocks {
         let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
         for span in spans {
             let mut span = *span;
@@ -195,7 +220,34 @@ impl ChangeStore {
             }
         }
 
-        encode_blocks_in_store(new_store, &self.arena, w);
+        ExportedBlocks(new_store)
+    }
+
+    /// Encode the changes as blocks, which is the body of the updates format.
+    ///
+    /// The changes should be sorted by id and should not overlap.
+    pub(crate) fn export_changes_as_blocks<'a, W: std::io::Write>(
+        &self,
+        changes: impl IntoIterator<Item = &'a Change>,
+        w: &mut W,
+    ) {
+        let mut new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
+        let mut last_end: Option<ID> = None;
+        for c in changes {
+            // The changes of the same peer in a store should be continuous
+            if last_end.is_some_and(|end| end.peer == c.id.peer && end.counter != c.id.counter) {
+                let store = std::mem::replace(
+                    &mut new_store,
+                    ChangeStore::new_mem(&self.arena, self.merge_interval.clone()),
+                );
+                encode_blocks_in_store(&store, &self.arena, w).unwrap();
+            }
+
+            last_end = Some(c.id_end());
+            new_store.insert_change(c.clone(), false, false);
+        }
+
+        encode_blocks_in_store(&new_store, &self.arena, w).unwrap();
     }
 
     fn encode_from(
@@ -234,7 +286,13 @@ impl ChangeStore {
 
             let change_end = c.ctr_end();
             if change_end > cnt_threshold {
-                changes.push(c.slice((cnt_threshold - c.id.counter) as usize, c.atom_len()));
+                // The signature needs the whole change to be verified. The known part
+                // is trimmed when it's imported into the oplog
+                if c.signature.is_some() {
+                    changes.push(c.clone());
+                } else {
+                    changes.push(c.slice((cnt_threshold - c.id.counter) as usize, c.atom_len()));
+                }
             }
         });
 
@@ -256,7 +314,11 @@ impl ChangeStore {
             if c.id.counter >= start {
                 true
             } else if c.ctr_end() > start {
-                *c = c.slice((start - c.id.counter) as usize, c.atom_len());
+                // The signature needs the whole change to be verified. The known part
+                // is trimmed when it's imported into the oplog
+                if c.signature.is_none() {
+                    *c = c.slice((start - c.id.counter) as usize, c.atom_len());
+                }
                 true
             } else {
                 false
@@ -474,6 +536,7 @@ impl ChangeStore {
                 start_vv: inner.start_vv.clone(),
                 start_frontiers: inner.start_frontiers.clone(),
                 mem_parsed_kv: BTreeMap::new(),
+                dirty_block_ids: None,
             })),
             arena,
             external_vv: Arc::new(Mutex::new(self.external_vv.lock().unwrap().clone())),
@@ -482,6 +545,17 @@ impl ChangeStore {
         }
     }
 
+    /// The frontiers stored in the external kv store.
+    ///
+    /// It's `None` if the kv store doesn't contain any history.
+    pub(crate) fn external_kv_frontiers(&self) -> LoroResult<Option<Frontiers>> {
+        let kv = self.external_kv.lock().unwrap();
+        match kv.get(FRONTIERS_KEY) {
+            Some(bytes) => Ok(Frontiers::decode(&bytes).ok()),
+            None => kv.read_error().map_or(Ok(None), Err),
+        }
+    }
+
     pub fn kv_size(&self) -> usize {
         self.external_kv
             .lock()
@@ -498,6 +572,18 @@ impl ChangeStore {
         latest_vv: &VersionVector,
         w: &mut W,
     ) {
+        self.collect_blocks_from(start_vv, shallow_since_vv, latest_vv)
+            .write_to(w)
+            .unwrap();
+    }
+
+    /// Collect the changes between `start_vv` and `latest_vv` into the blocks to be exported.
+    pub(crate) fn collect_blocks_from(
+        &self,
+        start_vv: &VersionVector,
+        shallow_since_vv: &ImVersionVector,
+        latest_vv: &VersionVector,
+    ) -> ExportedBlocks {
         let new_store = ChangeStore::new_mem(&self.arena, self.merge_interval.clone());
         for mut span in latest_vv.sub_iter(start_vv) {
             let counter_lower_bound = shallow_since_vv.get(&span.peer).copied().unwrap_or(0);
@@ -518,13 +604,12 @@ impl ChangeStore {
                     .min(c.atom_len());
 
                 assert_ne!(start, end);
-                let ch = c.slice(start, end);
+                let ch = slice_for_export(&c, start, end);
                 new_store.insert_change(ch, false, false);
             }
         }
 
-        let arena = &self.arena;
-        encode_blocks_in_store(new_store, arena, w);
+        ExportedBlocks(new_store)
     }
 
     pub(crate) fn fork_changes_up_to(
@@ -562,17 +647,48 @@ impl ChangeStore {
     }
 }
 
+/// Slice the change to be exported from the given start.
+///
+/// A signed change is exported as a whole if it reaches the end, because its signature
+/// is only valid for the whole change. The importer skips the part it already has.
+fn slice_for_export(c: &Change, start: usize, end: usize) -> Change {
+    if c.signature.is_some() && end == c.atom_len() {
+        c.slice(0, end)
+    } else {
+        c.slice(start, end)
+    }
+}
+
+/// Encode a single change as a block, e.g. to get the bytes to be signed.
+pub(crate) fn encode_change_as_block(change: &Change, arena: &SharedArena) -> Vec<u8> {
+    encode_block(std::slice::from_ref(change), arena)
+}
+
+/// The blocks of the changes to be exported, which is the body of the updates format.
+pub(crate) struct ExportedBlocks(ChangeStore);
+
+impl ExportedBlocks {
+    /// Write the blocks with their lengths, one block at a time.
+    ///
+    /// The encoded blocks are cached in place of the parsed changes, so writing the
+    /// blocks again, e.g. after calculating their checksum, doesn't encode them again.
+    pub(crate) fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        encode_blocks_in_store(&self.0, &self.0.arena, w)
+    }
+}
+
 fn encode_blocks_in_store<W: std::io::Write>(
-    new_store: ChangeStore,
+    new_store: &ChangeStore,
     arena: &SharedArena,
     w: &mut W,
-) {
+) -> std::io::Result<()> {
     let mut inner = new_store.inner.lock().unwrap();
     for (_id, block) in inner.mem_parsed_kv.iter_mut() {
         let bytes = block.to_bytes(arena);
-        leb128::write::unsigned(w, bytes.bytes.len() as u64).unwrap();
-        w.write_all(&bytes.bytes).unwrap();
+        leb128::write::unsigned(w, bytes.bytes.len() as u64)?;
+        w.write_all(&bytes.bytes)?;
     }
+    Ok(())
 }
 
 mod mut_external_kv {
@@ -592,6 +708,15 @@ mod mut_external_kv {
             kv_store
                 .import_all(bytes)
                 .map_err(|e| LoroError::DecodeError(e.into_boxed_str()))?;
+            drop(kv_store);
+            self.inner.lock().unwrap().dirty_block_ids = None;
+            self.load_from_external_kv()
+        }
+
+        /// Load the version info from the changes that already exist in the external kv store.
+        pub(crate) fn load_from_external_kv(&self) -> Result<BatchDecodeInfo, LoroError> {
+            #[allow(unused_mut)]
+            let mut kv_store = self.external_kv.lock().unwrap();
             let vv_bytes = kv_store.get(VV_KEY).unwrap_or_default();
             let vv = VersionVector::decode(&vv_bytes).unwrap();
             let start_vv_bytes = kv_store.get(START_VV_KEY).unwrap_or_default();
@@ -620,12 +745,19 @@ mod mut_external_kv {
             } else {
                 Frontiers::decode(&start_frontiers).unwrap()
             };
+            if let Some(e) = kv_store.read_error() {
+                return Err(e);
+            }
 
             let mut max_lamport = None;
             let mut max_timestamp = 0;
             drop(kv_store);
             for id in frontiers.iter() {
-                let c = self.get_change(id).unwrap();
+                let Some(c) = self.get_change(id) else {
+                    return Err(self.external_kv.lock().unwrap().read_error().unwrap_or(
+                        LoroError::DecodeError("The change of the frontiers is missing".into()),
+                    ));
+                };
                 debug_assert_ne!(c.atom_len(), 0);
                 let l = c.lamport_last();
                 if let Some(x) = max_lamport {
@@ -656,12 +788,57 @@ mod mut_external_kv {
             })
         }
 
+        /// Flush the cached changes and return the kv entries that have changed since the last call.
+        ///
+        /// All the entries are returned on the first call, or after the kv store is
+        /// replaced by [ChangeStore::import_all].
+        pub(crate) fn export_dirty_kv_entries(
+            &self,
+            vv: &VersionVector,
+            frontiers: &Frontiers,
+        ) -> Vec<(Bytes, Bytes)> {
+            self.flush_and_compact(vv, frontiers);
+            let mut inner = self.inner.lock().unwrap();
+            let store = self.external_kv.lock().unwrap();
+            match inner.dirty_block_ids.replace(BTreeSet::new()) {
+                None => store.scan(Bound::Unbounded, Bound::Unbounded).collect(),
+                Some(ids) => {
+                    let mut ans = Vec::with_capacity(ids.len() + 2);
+                    for id in ids {
+                        let key = id.to_bytes();
+                        if let Some(value) = store.get(&key) {
+                            ans.push((Bytes::copy_from_slice(&key), value));
+                        }
+                    }
+                    for key in [VV_KEY, FRONTIERS_KEY] {
+                        if let Some(value) = store.get(key) {
+                            ans.push((Bytes::from_static(key), value));
+                        }
+                    }
+                    ans
+                }
+            }
+        }
+
+        /// Persist the external kv store if it's backed by a durable storage.
+        ///
+        /// [ChangeStore::flush_and_compact] should be called first to write the
+        /// cached changes into the kv store.
+        pub(crate) fn persist_external_kv(&self) -> LoroResult<()> {
+            self.external_kv.lock().unwrap().flush()
+        }
+
         /// Flush the cached change to kv_store
         pub(crate) fn flush_and_compact(&self, vv: &VersionVector, frontiers: &Frontiers) {
             let mut inner = self.inner.lock().unwrap();
             let mut store = self.external_kv.lock().unwrap();
             let mut external_vv = self.external_vv.lock().unwrap();
-            for (id, block) in inner.mem_parsed_kv.iter_mut() {
+            let ChangeStoreInner {
+                mem_parsed_kv,
+                dirty_block_ids,
+                ..
+            } = &mut *inner;
+            for (id, block) in mem_parsed_kv.iter_mut() {
                 if !block.flushed {
                     let id_bytes = id.to_bytes();
                     let counter_start = external_vv.get(&id.peer).copied().unwrap_or(0);
@@ -679,6 +856,9 @@ mod mut_external_kv {
                     let bytes = block.to_bytes(&self.arena);
                     store.set(&id_bytes, bytes.bytes);
                     Arc::make_mut(block).flushed = true;
+                    if let Some(dirty) = dirty_block_ids.as_mut() {
+                        dirty.insert(*id);
+                    }
                 }
             }
 
@@ -718,7 +898,8 @@ mod mut_inner_kv {
             let s = info_span!("change_store insert_change", id = ?change.id);
             let _e = s.enter();
             let estimated_size = change.estimate_storage_size();
-            if estimated_size > MAX_BLOCK_SIZE && split_when_exceeds {
+            // A signed change is kept whole, because its signature is only valid for the whole change
+            if estimated_size > MAX_BLOCK_SIZE && split_when_exceeds && change.signature.is_none() {
                 self.split_change_then_insert(change);
                 return;
             }
@@ -919,6 +1100,8 @@ mod mut_inner_kv {
                 lamport: change.lamport,
                 timestamp: change.timestamp,
                 commit_msg: change.commit_msg.clone(),
+                // The signature is not valid for the split parts
+                signature: None,
             };
 
             let mut total_len = 0;
@@ -992,6 +1175,7 @@ mod mut_inner_kv {
                 lamport: next_lamport,
                 timestamp: new_change.timestamp,
                 commit_msg: new_change.commit_msg.clone(),
+                signature: None,
             };
 
             self.insert_change(new_change, false, false);
@@ -29,6 +29,9 @@
 //! ┌────────────────────────────────┬─────────────────────────────┐
 //! │    N Rle Commit Msg Lengths    │       Commit Messages       │
 //! └────────────────────────────────┴─────────────────────────────┘
+//! ┌────────────────────────────────┬─────────────────────────────┐
+//! │ N Rle Signature Lengths (Opt)  │      Signatures (Opt)       │
+//! └────────────────────────────────┴─────────────────────────────┘
 //!
 //!  ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ Encoded Operations ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─
 //!
@@ -555,6 +558,20 @@ pub fn decode_block(
     let commit_msg_len_decoder = AnyRleDecoder::<u32>::new(bytes);
     let (commit_msg_lens, commit_msgs) = commit_msg_len_decoder.take_n_finalize(n_changes).unwrap();
     let mut commit_msg_index = 0;
+    let commit_msgs_len = commit_msg_lens.iter().map(|&x| x as usize).sum::<usize>();
+    if commit_msgs_len > commit_msgs.len() {
+        return Err(LoroError::DecodeDataCorruptionError);
+    }
+
+    let signature_bytes = &commit_msgs[commit_msgs_len..];
+    let (signature_lens, signatures) = if signature_bytes.is_empty() {
+        (Vec::new(), signature_bytes)
+    } else {
+        AnyRleDecoder::<u32>::new(signature_bytes)
+            .take_n_finalize(n_changes)
+            .map_err(|_| LoroError::DecodeDataCorruptionError)?
+    };
+    let mut signature_index = 0;
     let keys = header.keys.get_or_init(|| decode_keys(&keys));
     let decode_arena = ValueDecodeArena {
         peers: &header.peers,
@@ -603,6 +620,17 @@ pub fn decode_block(
                 }
             }
         };
+        let signature: Option<Arc<[u8]>> = match signature_lens.get(i) {
+            Some(&len) if len > 0 => {
+                let end = signature_index + len as usize;
+                let Some(signature) = signatures.get(signature_index..end) else {
+                    return LoroResult::Err(LoroError::DecodeDataCorruptionError);
+                };
+                signature_index = end;
+                Some(Arc::from(signature))
+            }
+            _ => None,
+        };
         changes.push(Change {
             ops: Default::default(),
             deps: header.deps_groups[i].clone(),
@@ -610,6 +638,7 @@ pub fn decode_block(
             lamport: header.lamports[i],
             timestamp: timestamps[i] as Timestamp,
             commit_msg,
+            signature,
         })
     }
 
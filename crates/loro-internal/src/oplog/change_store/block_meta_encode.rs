@@ -83,6 +83,22 @@ pub(crate) fn encode_changes(
     meta.append(&mut t);
     meta.append(&mut cml);
     meta.append(&mut cms);
+    // The signatures are optional and appended to the end, so that the blocks
+    // without signatures are encoded the same as before
+    if block.iter().any(|c| c.signature.is_some()) {
+        let mut signature_len_encoder = AnyRleEncoder::<u32>::new();
+        let mut signatures = Vec::new();
+        for c in block.iter() {
+            let signature = c.signature.as_deref().unwrap_or_default();
+            signature_len_encoder
+                .append(signature.len() as u32)
+                .unwrap();
+            signatures.extend_from_slice(signature);
+        }
+
+        meta.append(&mut signature_len_encoder.finish().unwrap());
+        meta.append(&mut signatures);
+    }
 
     (ans, meta)
 }
@@ -5,7 +5,7 @@ use crate::span::{HasId, HasLamport};
 use crate::sync::Mutex;
 use crate::version::{shrink_frontiers, Frontiers, ImVersionVector, VersionVector};
 use rustc_hash::FxHashSet;
-use loro_common::{HasCounter, HasCounterSpan, HasIdSpan, HasLamportSpan, PeerID};
+use loro_common::{HasCounter, HasCounterSpan, HasIdSpan, HasLamportSpan, IdSpan, PeerID};
 use once_cell::sync::OnceCell;
 use rle::{HasIndex, HasLength, Mergable, Sliceable};
 use smallvec::SmallVec;
@@ -695,6 +695,23 @@ impl AppDag {
         }
     }
 
+    /// Drop the version info recorded by [`Self::update_version_on_new_local_op`]
+    /// for an aborted txn, so that its op ids can be reused.
+    ///
+    /// `frontiers` is the latest frontiers before the txn started.
+    pub(crate) fn revert_pending_txn(&mut self, frontiers: Frontiers) {
+        let Some(node) = self.pending_txn_node.take() else {
+            return;
+        };
+
+        self.vv.shrink_to_exclude(IdSpan::new(
+            node.peer,
+            node.cnt,
+            node.cnt + node.len as Counter,
+        ));
+        self.frontiers = frontiers;
+    }
+
     pub(crate) fn latest_vv_contains_peer(&self, peer: PeerID) -> bool {
         self.vv.contains_key(&peer) && *self.vv.get(&peer).unwrap() > 0
     }
@@ -33,6 +33,77 @@ pub(crate) struct PendingChanges {
     changes: FxHashMap<PeerID, BTreeMap<Counter, Vec<PendingChange>>>,
 }
 
+/// The summary of the changes that can't be applied because their dependencies are missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingChangesSummary {
+    /// The first missing dependency of each pending change, grouped by peer.
+    ///
+    /// The counters of each peer are sorted in ascending order.
+    pub missing_deps: FxHashMap<PeerID, Vec<Counter>>,
+    /// The range of the pending changes
+    pub pending: VersionRange,
+    /// The number of the pending changes
+    pub change_num: usize,
+}
+
+impl PendingChangesSummary {
+    /// Whether there are no pending changes
+    pub fn is_empty(&self) -> bool {
+        self.change_num == 0
+    }
+}
+
+impl OpLog {
+    pub(crate) fn pending_changes_summary(&self) -> PendingChangesSummary {
+        let mut ans = PendingChangesSummary::default();
+        for (&peer, tree) in self.pending_changes.changes.iter() {
+            ans.missing_deps
+                .insert(peer, tree.keys().copied().collect());
+            for change in tree.values().flatten() {
+                ans.change_num += 1;
+                ans.pending.extends_to_include_id_span(change.id_span());
+            }
+        }
+
+        ans
+    }
+
+    /// Encode the pending changes as blocks, which is the body of the updates format.
+    pub(crate) fn export_pending_changes<W: std::io::Write>(&self, w: &mut W) {
+        let mut changes: Vec<&Change> = self
+            .pending_changes
+            .changes
+            .values()
+            .flat_map(|tree| tree.values().flatten())
+            .map(|c| c.deref())
+            .collect();
+        // The same change, or overlapping parts of it, may be imported multiple times
+        // before its dependencies arrive
+        changes.sort_unstable_by(|a, b| a.id.cmp(&b.id).then(b.ctr_end().cmp(&a.ctr_end())));
+        let mut ans: Vec<Change> = Vec::with_capacity(changes.len());
+        for c in changes {
+            match ans.last() {
+                Some(last) if last.id.peer == c.id.peer && last.ctr_end() > c.id.counter => {
+                    // Only the part that is not covered by the previous change is kept
+                    if c.ctr_end() > last.ctr_end() {
+                        let start = (last.ctr_end() - c.id.counter) as usize;
+                        ans.push(c.slice(start, c.atom_len()));
+                    }
+                }
+                _ => ans.push(c.clone()),
+            }
+        }
+        self.change_store.export_changes_as_blocks(&ans, w);
+    }
+
+    /// Drop all the pending changes and return their range.
+    pub(crate) fn clear_pending_changes(&mut self) -> VersionRange {
+        let range = self.pending_changes_summary().pending;
+        self.pending_changes = PendingChanges::default();
+        range
+    }
+}
+
 impl OpLog {
     pub(super) fn extend_pending_changes_with_unknown_lamport(
         &mut self,
@@ -37,6 +37,7 @@ pub struct ChangeModifier(Arc<Mutex<ChangeModifierInner>>);
 struct ChangeModifierInner {
     new_msg: Option<Arc<str>>,
     new_timestamp: Option<Timestamp>,
+    new_signature: Option<Arc<[u8]>>,
 }
 
 impl ChangeModifier {
@@ -55,6 +56,15 @@ impl ChangeModifier {
         self
     }
 
+    /// Set the signature of the change.
+    ///
+    /// The change won't be signed by the [crate::signature::Signer] in the config if
+    /// the signature is set here.
+    pub fn set_signature(&self, signature: &[u8]) -> &Self {
+        self.0.lock().unwrap().new_signature = Some(Arc::from(signature));
+        self
+    }
+
     pub(crate) fn modify_change(&self, change: &mut Change) {
         let m = self.0.lock().unwrap();
         if let Some(msg) = &m.new_msg {
@@ -64,5 +74,9 @@ impl ChangeModifier {
         if let Some(timestamp) = m.new_timestamp {
             change.timestamp = timestamp;
         }
+
+        if let Some(signature) = &m.new_signature {
+            change.signature = Some(signature.clone());
+        }
     }
 }
@@ -0,0 +1,120 @@
+//! Signed changes.
+//!
+//! A [Signer] signs every local change when it's committed, and the signature is
+//! stored with the change in the change store and exported with it. A [Verifier]
+//! checks the signatures of the imported changes, e.g. whether a change claiming
+//! `PeerID` X is actually signed by the key of X.
+//!
+//! The signature is calculated over `signing_payload`, which is the encoded content
+//! and deps of the change. It's only valid for the whole change, so a signed change
+//! is never split in the change store, and it's exported as a whole even if the
+//! receiver already has a part of it. A change still loses its signature when its
+//! end is cut off, e.g. by [crate::loro::ExportMode::UpdatesInRange].
+use std::sync::Arc;
+
+use loro_common::{HasIdSpan, LoroError, LoroResult};
+
+use crate::{
+    arena::SharedArena, change::Change, oplog::encode_change_as_block, version::VersionRange,
+    ChangeMeta,
+};
+
+/// Signs the local changes.
+pub trait Signer: Send + Sync {
+    /// Sign the payload of a local change. The returned signature is stored with the change.
+    fn sign(&self, meta: &ChangeMeta, payload: &[u8]) -> Vec<u8>;
+}
+
+/// Verifies the signatures of the imported changes.
+pub trait Verifier: Send + Sync {
+    /// Return whether the signature of the change is valid.
+    ///
+    /// `signature` is `None` if the change is not signed. The peer that claims to be
+    /// the author of the change is `meta.id.peer`.
+    fn verify(&self, meta: &ChangeMeta, payload: &[u8], signature: Option<&[u8]>) -> bool;
+}
+
+/// What to do with the imported changes whose signatures are invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidSignaturePolicy {
+    /// Fail the whole import with [LoroError::InvalidSignature].
+    #[default]
+    Reject,
+    /// Skip the invalid changes and import the others. The skipped changes are
+    /// reported in [crate::encoding::ImportStatus::quarantined].
+    ///
+    /// The changes that depend on the skipped ones become pending.
+    Quarantine,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct SignatureConfig {
+    pub(crate) signer: Option<Arc<dyn Signer>>,
+    pub(crate) verifier: Option<Arc<dyn Verifier>>,
+    pub(crate) policy: InvalidSignaturePolicy,
+}
+
+impl std::fmt::Debug for SignatureConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureConfig")
+            .field("signer", &self.signer.is_some())
+            .field("verifier", &self.verifier.is_some())
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+/// The bytes to be signed for the change.
+///
+/// It's the change encoded as a single block without its signature.
+pub(crate) fn signing_payload(change: &Change, arena: &SharedArena) -> Vec<u8> {
+    let mut change = change.clone();
+    change.signature = None;
+    encode_change_as_block(&change, arena)
+}
+
+/// Sign the change with the signer in the config, if there is one.
+pub(crate) fn sign_change(config: &SignatureConfig, change: &mut Change, arena: &SharedArena) {
+    let Some(signer) = config.signer.as_ref() else {
+        return;
+    };
+
+    let payload = signing_payload(change, arena);
+    let signature = signer.sign(&ChangeMeta::from_change(change), &payload);
+    change.signature = Some(signature.into());
+}
+
+/// Verify the signatures of the imported changes with the verifier in the config.
+///
+/// Return the changes that pass the verification and the range of the quarantined changes.
+pub(crate) fn verify_changes(
+    config: &SignatureConfig,
+    changes: Vec<Change>,
+    arena: &SharedArena,
+) -> LoroResult<(Vec<Change>, VersionRange)> {
+    let mut quarantined = VersionRange::new();
+    let Some(verifier) = config.verifier.as_ref() else {
+        return Ok((changes, quarantined));
+    };
+
+    let mut ans = Vec::with_capacity(changes.len());
+    for change in changes {
+        let payload = signing_payload(&change, arena);
+        let meta = ChangeMeta::from_change(&change);
+        if verifier.verify(&meta, &payload, change.signature.as_deref()) {
+            ans.push(change);
+            continue;
+        }
+
+        match config.policy {
+            InvalidSignaturePolicy::Reject => {
+                return Err(LoroError::InvalidSignature(change.id));
+            }
+            InvalidSignaturePolicy::Quarantine => {
+                quarantined.extends_to_include_id_span(change.id_span());
+            }
+        }
+    }
+
+    Ok((ans, quarantined))
+}
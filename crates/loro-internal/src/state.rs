@@ -15,9 +15,12 @@ use tracing::{info_span, instrument, warn};
 
 use crate::{
     configure::{Configure, DefaultRandom, SecureRandomGenerator},
-    container::{idx::ContainerIdx, richtext::config::StyleConfigMap},
+    container::{
+        idx::ContainerIdx,
+        richtext::{config::StyleConfigMap, EMBED_STYLE_KEY},
+    },
     cursor::Cursor,
-    delta::TreeExternalDiff,
+    delta::{TreeExternalDiff, TreeMoveRecord},
     diff_calc::{DiffCalculator, DiffMode},
     event::{Diff, EventTriggerKind, Index, InternalContainerDiff, InternalDiff},
     fx_map,
@@ -75,6 +78,9 @@ pub struct DocState {
     event_recorder: EventRecorder,
 
     dead_containers_cache: DeadContainersCache,
+    /// The imported tree moves rejected because of cycles, which are taken when the
+    /// events are emitted
+    rejected_tree_moves: Vec<(ContainerIdx, Vec<TreeMoveRecord>)>,
 }
 
 impl std::fmt::Debug for DocState {
@@ -341,6 +347,7 @@ impl DocState {
                 changed_idx_in_txn: FxHashSet::default(),
                 event_recorder: Default::default(),
                 dead_containers_cache: Default::default(),
+                rejected_tree_moves: Vec::new(),
             },
             crate::lock::LockKind::DocState,
         ))
@@ -365,6 +372,7 @@ impl DocState {
             changed_idx_in_txn: FxHashSet::default(),
             event_recorder: Default::default(),
             dead_containers_cache: Default::default(),
+            rejected_tree_moves: Vec::new(),
         }))
     }
 
@@ -404,6 +412,11 @@ impl DocState {
         std::mem::take(&mut self.event_recorder.events)
     }
 
+    /// Take the imported tree moves that are rejected because they would create cycles.
+    pub(crate) fn take_rejected_tree_moves(&mut self) -> Vec<(ContainerIdx, Vec<TreeMoveRecord>)> {
+        std::mem::take(&mut self.rejected_tree_moves)
+    }
+
     /// Record the next diff.
     /// Caller should call [pre_txn] before calling this.
     ///
@@ -625,6 +638,13 @@ impl DocState {
                                 },
                             );
                         }
+
+                        if let State::TreeState(tree) = state {
+                            let rejected = tree.take_rejected_moves();
+                            if !rejected.is_empty() {
+                                self.rejected_tree_moves.push((idx, rejected));
+                            }
+                        }
                     });
                 }
                 crate::event::DiffVariant::External(_) => unreachable!(),
@@ -692,12 +712,108 @@ impl DocState {
     pub(crate) fn start_txn(&mut self, origin: InternalString, trigger: EventTriggerKind) {
         self.pre_txn(origin, trigger);
         self.in_txn = true;
+        self.changed_idx_in_txn.clear();
     }
 
     pub(crate) fn abort_txn(&mut self) {
         self.in_txn = false;
     }
 
+    /// Abort the current txn and revert the local ops applied in it.
+    ///
+    /// The containers changed in the txn are rebuilt from the ops in the oplog
+    /// (starting from the shallow root state if the doc is shallow), so the
+    /// uncommitted ops must not be in the oplog. No event is recorded.
+    pub(crate) fn rollback_txn(&mut self, frontiers: Frontiers, oplog: &OpLog) {
+        self.in_txn = false;
+        let changed = std::mem::take(&mut self.changed_idx_in_txn);
+        if changed.is_empty() {
+            self.frontiers = frontiers;
+            return;
+        }
+
+        self.dead_containers_cache.clear();
+        for &idx in changed.iter() {
+            let state = self
+                .store
+                .fork_shallow_root_state(idx)
+                .unwrap_or_else(|| create_state_(idx, &self.config, self.peer_id()));
+            self.store.reset_container(idx, state);
+        }
+
+        let from_vv = oplog.shallow_since_vv().to_vv();
+        let from_frontiers = oplog.shallow_since_frontiers().clone();
+        let to_vv = oplog.dag().frontiers_to_vv(&frontiers).unwrap();
+        let mut diff_calc = DiffCalculator::new(false);
+        let (diffs, _diff_mode) = diff_calc.calc_diff_internal(
+            oplog,
+            &from_vv,
+            &from_frontiers,
+            &to_vv,
+            &frontiers,
+            Some(&|idx| changed.contains(&idx)),
+        );
+
+        // The reverted ops were never visible to the users, so the rebuilding
+        // should not be recorded as events
+        let recorder = std::mem::take(&mut self.event_recorder);
+        self.apply_diff(
+            InternalDocDiff {
+                origin: Default::default(),
+                by: EventTriggerKind::Local,
+                diff: diffs.into(),
+                new_version: Cow::Owned(frontiers),
+            },
+            DiffMode::Checkout,
+        );
+        self.event_recorder = recorder;
+    }
+
+    /// Get a copy of the container state without the local ops of the current txn.
+    ///
+    /// A container changed in the txn is rebuilt from the ops in the oplog like
+    /// [DocState::rollback_txn], so `frontiers` must be the version at the start of the txn.
+    pub(crate) fn fork_committed_state(
+        &mut self,
+        idx: ContainerIdx,
+        frontiers: &Frontiers,
+        oplog: &OpLog,
+    ) -> State {
+        if !self.in_txn || !self.changed_idx_in_txn.contains(&idx) {
+            return self.store.get_or_create_imm(idx).fork(&self.config);
+        }
+
+        let mut state = self
+            .store
+            .fork_shallow_root_state(idx)
+            .unwrap_or_else(|| create_state_(idx, &self.config, self.peer_id()));
+        let from_vv = oplog.shallow_since_vv().to_vv();
+        let from_frontiers = oplog.shallow_since_frontiers().clone();
+        let to_vv = oplog.dag().frontiers_to_vv(frontiers).unwrap();
+        let mut diff_calc = DiffCalculator::new(false);
+        let (diffs, _diff_mode) = diff_calc.calc_diff_internal(
+            oplog,
+            &from_vv,
+            &from_frontiers,
+            &to_vv,
+            frontiers,
+            Some(&|x| x == idx),
+        );
+        for diff in diffs {
+            if let crate::event::DiffVariant::Internal(diff) = diff.diff {
+                state.apply_diff(
+                    diff,
+                    DiffApplyContext {
+                        mode: DiffMode::Checkout,
+                        doc: &self.doc,
+                    },
+                );
+            }
+        }
+
+        state
+    }
+
     pub fn iter_and_decode_all(&mut self) -> impl Iterator<Item = &mut State> {
         self.store.iter_and_decode_all()
     }
@@ -724,6 +840,47 @@ impl DocState {
         self.store.contains_id(id)
     }
 
+    /// Decode the states of the given containers and all their descendants.
+    ///
+    /// Other containers are kept in their encoded form until they are accessed.
+    pub(crate) fn materialize_subtrees(&mut self, roots: &[ContainerID]) -> LoroResult<()> {
+        let missing: Vec<ContainerID> = roots
+            .iter()
+            .filter(|id| !self.does_container_exist(id))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(LoroError::ContainersNotFound {
+                containers: Box::new(missing),
+            });
+        }
+
+        let mut visited = FxHashSet::default();
+        let mut stack: Vec<ContainerID> = roots.to_vec();
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id.clone()) {
+                continue;
+            }
+
+            let idx = self.arena.register_container(&id);
+            // An empty root container may not be in the store yet
+            if let Some(state) = self.store.get_container(idx) {
+                stack.extend(state.get_child_containers());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evict the least recently accessed containers back to their encoded form
+    /// until the decoded containers fit in `budget` bytes.
+    ///
+    /// Return the number of the evicted containers.
+    pub(crate) fn evict_cold_containers(&mut self, budget: usize) -> usize {
+        assert!(!self.in_txn);
+        self.store.evict_cold_containers(budget)
+    }
+
     pub(crate) fn commit_txn(&mut self, new_frontiers: Frontiers, diff: Option<InternalDocDiff>) {
         self.in_txn = false;
         self.frontiers = new_frontiers;
@@ -1660,6 +1817,15 @@ fn trigger_on_new_container(
                 }
             }
         }
+        Diff::Text(text) => {
+            for delta in text.iter() {
+                if let DeltaItem::Replace { attr, .. } = delta {
+                    if let Some(LoroValue::Container(id)) = attr.0.get(EMBED_STYLE_KEY) {
+                        listener(arena.register_container(id));
+                    }
+                }
+            }
+        }
         Diff::Tree(tree) => {
             for item in tree.iter() {
                 if matches!(item.action, TreeExternalDiff::Create { .. }) {
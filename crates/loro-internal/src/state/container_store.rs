@@ -94,7 +94,6 @@ impl ContainerStore {
             .map(|x| x.get_state_mut(idx, ctx!(self)))
     }
 
-    #[allow(unused)]
     pub fn get_container(&mut self, idx: ContainerIdx) -> Option<&State> {
         self.store
             .get_mut(idx)
@@ -119,6 +118,12 @@ impl ContainerStore {
         self.store.flush()
     }
 
+    /// Evict the least recently accessed containers back to their encoded form
+    /// until the decoded containers fit in `budget` bytes.
+    pub(crate) fn evict_cold_containers(&mut self, budget: usize) -> usize {
+        self.store.evict_cold_containers(budget)
+    }
+
     pub fn shallow_root_frontiers(&self) -> Option<&Frontiers> {
         self.shallow_root_store
             .as_ref()
@@ -203,6 +208,23 @@ impl ContainerStore {
             .get_state_mut(idx, ctx!(self))
     }
 
+    /// Replace the state of the container with the given one, e.g. when the
+    /// uncommitted changes of a txn are reverted
+    pub(super) fn reset_container(&mut self, idx: ContainerIdx, state: State) {
+        self.store.insert(idx, ContainerWrapper::new(state, &self.arena));
+    }
+
+    /// Get a copy of the container state at the shallow root version.
+    ///
+    /// Return `None` if the doc is not shallow or the container doesn't exist at that version.
+    pub(super) fn fork_shallow_root_state(&self, idx: ContainerIdx) -> Option<State> {
+        let shallow_root_store = self.shallow_root_store.as_ref()?;
+        let mut store = shallow_root_store.store.lock().unwrap();
+        store
+            .get_mut(idx)
+            .map(|c| c.get_state(idx, ctx!(self)).fork(&self.conf))
+    }
+
     pub(crate) fn ensure_container(&mut self, id: &loro_common::ContainerID) {
         let idx = self.arena.register_container(id);
         self.store.ensure_container(idx, || {
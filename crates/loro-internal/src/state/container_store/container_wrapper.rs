@@ -30,6 +30,11 @@ pub(crate) struct ContainerWrapper {
     bytes_offset_for_state: Option<usize>,
     state: Option<State>,
     flushed: bool,
+    /// The value of the access clock of the store when the container is accessed last time
+    last_access: u64,
+    /// The length of the bytes when the container is encoded or decoded last time.
+    /// It's kept after the bytes are dropped, as an approximate size of the container.
+    encoded_size: usize,
 }
 
 impl ContainerWrapper {
@@ -49,6 +54,8 @@ impl ContainerWrapper {
             bytes_offset_for_state: None,
             bytes_offset_for_value: None,
             flushed: false,
+            last_access: 0,
+            encoded_size: 0,
         }
     }
 
@@ -115,6 +122,7 @@ impl ContainerWrapper {
             .unwrap()
             .encode_snapshot_fast(&mut output);
         let ans: Bytes = output.into();
+        self.encoded_size = ans.len();
         self.bytes = Some(ans.clone());
         ans
     }
@@ -143,6 +151,8 @@ impl ContainerWrapper {
             bytes_offset_for_value: Some(size),
             bytes_offset_for_state: None,
             flushed: true,
+            last_access: 0,
+            encoded_size: bytes.len(),
         }
     }
 
@@ -270,6 +280,26 @@ impl ContainerWrapper {
         self.flushed = flushed;
     }
 
+    pub(crate) fn touch(&mut self, clock: u64) {
+        self.last_access = clock;
+    }
+
+    pub(crate) fn last_access(&self) -> u64 {
+        self.last_access
+    }
+
+    /// The approximate size of the container, without encoding it.
+    ///
+    /// It's exact if the container is flushed.
+    pub(crate) fn encoded_size(&self) -> usize {
+        self.encoded_size
+    }
+
+    /// Whether the value or the state is decoded from the bytes
+    pub(crate) fn is_decoded(&self) -> bool {
+        self.value.is_some() || self.state.is_some()
+    }
+
     #[allow(unused)]
     pub(crate) fn parent(&self) -> Option<&ContainerID> {
         self.parent.as_ref()
@@ -21,6 +21,9 @@ pub(crate) struct InnerStore {
     kv: KvWrapper,
     all_loaded: bool,
     config: Configure,
+    /// It's increased every time a container is accessed, so that the cold containers
+    /// can be found when evicting
+    access_clock: u64,
 }
 
 impl std::fmt::Debug for InnerStore {
@@ -36,22 +39,22 @@ impl InnerStore {
         idx: ContainerIdx,
         f: impl FnOnce() -> ContainerWrapper,
     ) -> &mut ContainerWrapper {
-        match self.store.entry(idx) {
+        self.access_clock += 1;
+        let c = match self.store.entry(idx) {
             std::collections::hash_map::Entry::Vacant(e) => {
                 let id = self.arena.get_container_id(idx).unwrap();
                 let key = id.to_bytes();
-                if !self.all_loaded {
-                    if let Some(v) = self.kv.get(&key) {
-                        let c = ContainerWrapper::new_from_bytes(v);
-                        return e.insert(c);
-                    }
-                }
-
-                let c = f();
-                e.insert(c)
+                let c = if self.all_loaded {
+                    None
+                } else {
+                    self.kv.get(&key).map(ContainerWrapper::new_from_bytes)
+                };
+                e.insert(c.unwrap_or_else(f))
             }
             std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
-        }
+        };
+        c.touch(self.access_clock);
+        c
     }
 
     pub(super) fn ensure_container(
@@ -77,6 +80,11 @@ impl InnerStore {
         self.store.insert(idx, c);
     }
 
+    /// The inserted container will override the one in `kv` when flushed
+    pub(super) fn insert(&mut self, idx: ContainerIdx, c: ContainerWrapper) {
+        self.store.insert(idx, c);
+    }
+
     pub(crate) fn get_mut(&mut self, idx: ContainerIdx) -> Option<&mut ContainerWrapper> {
         if let std::collections::hash_map::Entry::Vacant(e) = self.store.entry(idx) {
             if !self.all_loaded {
@@ -89,7 +97,10 @@ impl InnerStore {
             }
         }
 
-        self.store.get_mut(&idx)
+        self.access_clock += 1;
+        let c = self.store.get_mut(&idx)?;
+        c.touch(self.access_clock);
+        Some(c)
     }
 
     pub(crate) fn contains_id(&mut self, id: &ContainerID) -> bool {
@@ -152,6 +163,42 @@ impl InnerStore {
             }));
     }
 
+    /// Evict the least recently accessed decoded containers until the total encoded
+    /// size of the decoded containers is within `budget` bytes.
+    ///
+    /// The evicted containers are dropped from `store`, so they will be decoded from `kv`
+    /// again when they are accessed. Return the number of the evicted containers.
+    pub(crate) fn evict_cold_containers(&mut self, budget: usize) -> usize {
+        self.flush();
+        let mut decoded: Vec<(u64, usize, ContainerIdx)> = self
+            .store
+            .iter()
+            // A container that is not flushed is not in `kv`
+            .filter(|(_, c)| c.is_decoded() && c.is_flushed())
+            .map(|(idx, c)| (c.last_access(), c.encoded_size(), *idx))
+            .collect();
+        let mut total: usize = decoded.iter().map(|(_, size, _)| size).sum();
+        if total <= budget {
+            return 0;
+        }
+
+        decoded.sort_unstable();
+        let mut evicted = 0;
+        for (_, size, idx) in decoded {
+            if total <= budget {
+                break;
+            }
+
+            self.store.remove(&idx);
+            total -= size;
+            evicted += 1;
+        }
+
+        // `kv` is up to date after flushing, so the evicted containers can be loaded from it
+        self.all_loaded = false;
+        evicted
+    }
+
     pub(crate) fn get_kv_clone(&self) -> KvWrapper {
         self.kv.clone()
     }
@@ -253,6 +300,7 @@ impl InnerStore {
             kv: KvWrapper::new_mem(),
             all_loaded: true,
             config,
+            access_clock: 0,
         }
     }
 
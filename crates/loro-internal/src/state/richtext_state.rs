@@ -11,11 +11,11 @@ use crate::{
         idx::ContainerIdx,
         list::list_op,
         richtext::{
-            config::StyleConfigMap,
+            config::{OverlapRules, StyleConfigMap},
             richtext_state::{
                 DrainInfo, EntityRangeInfo, IterRangeItem, PosType, RichtextStateChunk,
             },
-            AnchorType, RichtextState as InnerState, StyleOp, Styles,
+            AnchorType, RichtextState as InnerState, StyleKey, StyleOp, Styles, EMBED_STYLE_KEY,
         },
     },
     delta::{StyleMeta, StyleMetaItem},
@@ -54,6 +54,11 @@ impl RichtextState {
         }
     }
 
+    #[inline]
+    fn overlap_rules(&self) -> Option<Arc<OverlapRules>> {
+        self.config.read().unwrap().overlap_rules()
+    }
+
     #[inline]
     fn update_version(&mut self) {
         self.version_id = self.version_id.wrapping_add(1);
@@ -136,6 +141,48 @@ impl RichtextState {
         }
     }
 
+    /// Get the containers embedded in the text with their event indexes.
+    ///
+    /// A container is embedded if the style that embeds it covers an alive embed char.
+    fn embedded_containers(&self) -> Vec<(ContainerID, usize)> {
+        let elements = match &self.state {
+            LazyLoad::Dst(s) => {
+                return s
+                    .iter_embedded_containers()
+                    .map(|(id, index)| (id.clone(), index))
+                    .collect();
+            }
+            LazyLoad::Src(s) => &s.elements,
+        };
+
+        // The state is not loaded yet, so the elements have to be scanned
+        let mut ans = Vec::new();
+        let mut open: Vec<&ContainerID> = Vec::new();
+        let mut index = 0;
+        for c in elements {
+            match c {
+                RichtextStateChunk::Style { style, anchor_type } => {
+                    let Some(id) = style.embedded_container() else {
+                        continue;
+                    };
+                    match anchor_type {
+                        AnchorType::Start => open.push(id),
+                        AnchorType::End => open.retain(|x| *x != id),
+                    }
+                }
+                RichtextStateChunk::Text(text) => {
+                    if !open.is_empty() && text.len() > 0 {
+                        ans.extend(open.drain(..).map(|id| (id.clone(), index)));
+                    }
+
+                    index += text.event_len() as usize;
+                }
+            }
+        }
+
+        ans
+    }
+
     fn get_style_start(
         &mut self,
         style_starts: &mut FxHashMap<Arc<StyleOp>, Pos>,
@@ -256,10 +303,11 @@ impl RichtextState {
         let mut delta = Vec::new();
         // TODO: merge last
         for span in self.state.get_mut().iter() {
-            delta.push(TextDelta::Insert {
-                insert: span.text.as_str().to_string(),
-                attributes: span.attributes.to_option_map(),
-            })
+            TextDelta::push_insert(
+                &mut delta,
+                span.text.as_str(),
+                span.attributes.to_option_map(),
+            );
         }
         delta
     }
@@ -297,6 +345,7 @@ impl ContainerState for RichtextState {
 
         // tracing::info!("Self state = {:#?}", &self);
         // PERF: compose delta
+        let rules = self.overlap_rules();
         let mut ans: TextDiff = TextDiff::new();
         let mut style_delta: TextDiff = TextDiff::new();
         let mut style_starts: FxHashMap<Arc<StyleOp>, Pos> = FxHashMap::default();
@@ -456,6 +505,7 @@ impl ContainerState for RichtextState {
                                             self.state.get_mut().annotate_style_range_with_event(
                                                 start_entity_index..entity_index + 1,
                                                 style.clone(),
+                                                rules.clone(),
                                             );
                                         for (s, l) in event {
                                             delta.push_retain(
@@ -491,6 +541,7 @@ impl ContainerState for RichtextState {
             unreachable!()
         };
 
+        let rules = self.overlap_rules();
         let mut style_starts: FxHashMap<Arc<StyleOp>, usize> = FxHashMap::default();
         let mut entity_index = 0;
         for span in richtext.iter() {
@@ -555,6 +606,7 @@ impl ContainerState for RichtextState {
                                     self.state.get_mut().annotate_style_range(
                                         start_pos..entity_index + 1,
                                         style.clone(),
+                                        rules.clone(),
                                     );
                                 }
                             }
@@ -570,6 +622,7 @@ impl ContainerState for RichtextState {
 
     fn apply_local_op(&mut self, r_op: &RawOp, op: &Op) -> LoroResult<ApplyLocalOpReturn> {
         self.update_version();
+        let mut ans = ApplyLocalOpReturn::default();
         match &op.content {
             crate::op::InnerContent::List(l) => match l {
                 list_op::InnerListOp::Insert { slice: _, pos: _ } => {
@@ -588,11 +641,27 @@ impl ContainerState for RichtextState {
                     );
                 }
                 list_op::InnerListOp::Delete(del) => {
-                    self.state.get_mut().drain_by_entity_index(
-                        del.start() as usize,
-                        rle::HasLength::atom_len(&del),
-                        None,
-                    );
+                    let start = del.start() as usize;
+                    let len = rle::HasLength::atom_len(&del);
+                    // The text covered by an embed style is its embed char
+                    let embed_key = StyleKey::Key(EMBED_STYLE_KEY.into());
+                    for IterRangeItem { chunk, styles, .. } in
+                        self.state.get_mut().iter_range(start..start + len)
+                    {
+                        if !matches!(chunk, RichtextStateChunk::Text(_)) {
+                            continue;
+                        }
+
+                        if let Some(id) = styles
+                            .get(&embed_key)
+                            .and_then(|v| v.get())
+                            .and_then(|style| style.embedded_container())
+                        {
+                            ans.deleted_containers.push(id.clone());
+                        }
+                    }
+
+                    self.state.get_mut().drain_by_entity_index(start, len, None);
                 }
                 list_op::InnerListOp::StyleStart {
                     start,
@@ -610,6 +679,7 @@ impl ContainerState for RichtextState {
                     // We can assume StyleStart and StyleEnd are always appear in a pair
                     // for apply_local_op. (Because for local behavior, when we mark,
                     // we always create a pair of style ops.)
+                    let rules = self.overlap_rules();
                     self.state.get_mut().mark_with_entity_index(
                         *start as usize..*end as usize,
                         Arc::new(StyleOp {
@@ -620,6 +690,7 @@ impl ContainerState for RichtextState {
                             value: value.clone(),
                             info: *info,
                         }),
+                        rules,
                     );
                 }
                 list_op::InnerListOp::Set { .. } => {}
@@ -630,7 +701,7 @@ impl ContainerState for RichtextState {
         }
 
         // self.check_consistency_between_content_and_style_ranges();
-        Ok(Default::default())
+        Ok(ans)
     }
 
     fn to_diff(&mut self, _doc: &Weak<LoroDocInner>) -> Diff {
@@ -651,18 +722,22 @@ impl ContainerState for RichtextState {
     }
 
     #[doc = r" Get the index of the child container"]
-    #[allow(unused)]
     fn get_child_index(&self, id: &ContainerID) -> Option<Index> {
-        None
+        self.embedded_containers()
+            .into_iter()
+            .find(|(child, _)| child == id)
+            .map(|(_, index)| Index::Seq(index))
     }
 
-    #[allow(unused)]
     fn get_child_containers(&self) -> Vec<ContainerID> {
-        Vec::new()
+        self.embedded_containers()
+            .into_iter()
+            .map(|(child, _)| child)
+            .collect()
     }
 
-    fn contains_child(&self, _id: &ContainerID) -> bool {
-        false
+    fn contains_child(&self, id: &ContainerID) -> bool {
+        self.get_child_index(id).is_some()
     }
 
     fn fork(&self, config: &crate::configure::Configure) -> Self {
@@ -721,6 +796,19 @@ impl RichtextState {
         self.state.get_mut().len_unicode()
     }
 
+    #[inline]
+    pub fn len_grapheme(&mut self) -> usize {
+        self.state.get_mut().len_grapheme()
+    }
+
+    #[inline]
+    pub(crate) fn grapheme_range_to_unicode(
+        &mut self,
+        range: Range<usize>,
+    ) -> LoroResult<Range<usize>> {
+        self.state.get_mut().grapheme_range_to_unicode(range)
+    }
+
     #[inline]
     pub(crate) fn get_entity_index_for_text_insert(
         &mut self,
@@ -750,6 +838,21 @@ impl RichtextState {
             .get_entity_range_and_text_styles_at_range(range, pos_type)
     }
 
+    /// Get the keys of the styles in the entity range that marking `key` over it would trim
+    pub(crate) fn get_conflicting_style_keys(
+        &mut self,
+        range: Range<usize>,
+        key: &str,
+    ) -> Vec<InternalString> {
+        let Some(rules) = self.overlap_rules() else {
+            return Vec::new();
+        };
+
+        self.state
+            .get_mut()
+            .get_conflicting_style_keys(range, key, &rules)
+    }
+
     #[inline]
     pub(crate) fn get_styles_at_entity_index(&mut self, entity_index: usize) -> StyleMeta {
         self.state
@@ -773,6 +876,35 @@ impl RichtextState {
         self.state.get_mut().get_richtext_value()
     }
 
+    #[inline]
+    pub(crate) fn get_style_ranges(
+        &mut self,
+        range: Range<usize>,
+        pos_type: PosType,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.state.get_mut().get_style_ranges(range, pos_type)
+    }
+
+    #[inline]
+    pub(crate) fn get_annotation_styles(&mut self) -> Vec<(Arc<StyleOp>, Range<usize>)> {
+        self.state.get_mut().get_annotation_styles()
+    }
+
+    #[inline]
+    pub(crate) fn line_count(&mut self) -> usize {
+        self.state.get_mut().line_count()
+    }
+
+    #[inline]
+    pub(crate) fn get_line_start(&mut self, line: usize, pos_type: PosType) -> Option<usize> {
+        self.state.get_mut().get_line_start(line, pos_type)
+    }
+
+    #[inline]
+    pub(crate) fn get_line_col(&mut self, pos: usize, pos_type: PosType) -> Option<(usize, usize)> {
+        self.state.get_mut().get_line_col(pos, pos_type)
+    }
+
     #[inline]
     pub(crate) fn get_stable_position(
         &mut self,
@@ -812,6 +944,7 @@ pub(crate) struct RichtextStateLoader {
     elements: Vec<RichtextStateChunk>,
     style_ranges: Vec<(Arc<StyleOp>, Range<usize>)>,
     entity_index: usize,
+    overlap_rules: Option<Arc<OverlapRules>>,
 }
 
 impl From<RichtextStateLoader> for InnerState {
@@ -845,7 +978,7 @@ impl RichtextStateLoader {
     pub fn into_state(self) -> InnerState {
         let mut state = InnerState::from_chunks(self.elements.into_iter());
         for (style, range) in self.style_ranges {
-            state.annotate_style_range(range, style);
+            state.annotate_style_range(range, style, self.overlap_rules.clone());
         }
 
         if cfg!(debug_assertions) {
@@ -1022,7 +1155,15 @@ mod snapshot {
             Self: Sized,
         {
             let mut text = RichtextState::new(idx, ctx.configure.text_style_config.clone());
-            let mut loader = RichtextStateLoader::default();
+            let mut loader = RichtextStateLoader {
+                overlap_rules: ctx
+                    .configure
+                    .text_style_config
+                    .read()
+                    .unwrap()
+                    .overlap_rules(),
+                ..Default::default()
+            };
             let peer_num = leb128::read::unsigned(&mut bytes).unwrap() as usize;
             let mut peers = Vec::with_capacity(peer_num);
             for _ in 0..peer_num {
@@ -17,7 +17,7 @@ use std::sync::Weak;
 use super::{ApplyLocalOpReturn, ContainerState, DiffApplyContext};
 use crate::configure::Configure;
 use crate::container::idx::ContainerIdx;
-use crate::delta::{TreeDiff, TreeDiffItem, TreeExternalDiff};
+use crate::delta::{TreeDiff, TreeDiffItem, TreeExternalDiff, TreeMoveRecord};
 use crate::diff_calc::DiffMode;
 use crate::event::InternalDiff;
 use crate::op::Op;
@@ -48,6 +48,9 @@ pub struct TreeState {
     children: TreeChildrenCache,
     fractional_index_config: TreeFractionalIndexConfigInner,
     peer_id: PeerID,
+    /// The imported moves rejected because of cycles since the last
+    /// [TreeState::take_rejected_moves]
+    rejected_moves: Vec<TreeMoveRecord>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -667,6 +670,36 @@ impl TreeState {
                 rng: Box::new(rand::rngs::StdRng::seed_from_u64(0)),
             },
             peer_id,
+            rejected_moves: Vec::new(),
+        }
+    }
+
+    /// Take the imported moves that are rejected because they would create cycles
+    pub(crate) fn take_rejected_moves(&mut self) -> Vec<TreeMoveRecord> {
+        std::mem::take(&mut self.rejected_moves)
+    }
+
+    /// Move the node of an imported op, and record it if it's rejected because of a cycle
+    fn mov_imported(
+        &mut self,
+        target: TreeID,
+        parent: TreeParentId,
+        id: IdFull,
+        position: Option<FractionalIndex>,
+    ) -> bool {
+        match self.mov(target, parent, id, position, true) {
+            Ok(()) => true,
+            Err(LoroError::TreeError(LoroTreeError::CyclicMoveError)) => {
+                self.rejected_moves.push(TreeMoveRecord {
+                    id: id.id(),
+                    lamport: id.lamport,
+                    target,
+                    parent,
+                    applied: false,
+                });
+                false
+            }
+            Err(_) => false,
         }
     }
 
@@ -970,6 +1003,16 @@ impl TreeState {
         self.trees.get(target).and_then(|x| x.position.clone())
     }
 
+    /// Get the position of the node among its siblings, which decides the order of the children
+    pub(crate) fn get_node_position(&self, target: &TreeID) -> Option<NodePosition> {
+        self.trees.get(target).map(|x| {
+            NodePosition::new(
+                x.position.clone().unwrap_or_default(),
+                x.last_move_op.idlp(),
+            )
+        })
+    }
+
     pub(crate) fn get_index_by_tree_id(&self, target: &TreeID) -> Option<usize> {
         let parent = self.parent(target)?;
         (!parent.is_deleted())
@@ -1061,6 +1104,7 @@ impl ContainerState for TreeState {
         let need_check = !matches!(ctx.mode, DiffMode::Checkout | DiffMode::Linear);
         let mut ans = vec![];
         if let InternalDiff::Tree(tree) = &diff {
+            self.rejected_moves.extend(tree.rejected.iter().cloned());
             // assert never cause cycle move
             for diff in tree.diff.iter() {
                 let last_move_op = diff.last_effective_move_op_id;
@@ -1094,10 +1138,12 @@ impl ContainerState for TreeState {
                         let old_index = self.get_index_by_tree_id(&target);
                         let was_alive = !self.is_node_deleted(&target).unwrap();
                         if need_check {
-                            if self
-                                .mov(target, *parent, last_move_op, Some(position.clone()), true)
-                                .is_ok()
-                            {
+                            if self.mov_imported(
+                                target,
+                                *parent,
+                                last_move_op,
+                                Some(position.clone()),
+                            ) {
                                 if self.is_node_deleted(&target).unwrap() {
                                     if was_alive {
                                         // delete event
@@ -1228,6 +1274,7 @@ impl ContainerState for TreeState {
     // So be careful when you modify this function.
     fn apply_diff(&mut self, diff: InternalDiff, ctx: DiffApplyContext) {
         if let InternalDiff::Tree(tree) = &diff {
+            self.rejected_moves.extend(tree.rejected.iter().cloned());
             let need_check = !matches!(ctx.mode, DiffMode::Checkout | DiffMode::Linear);
             // assert never cause cycle move
             for diff in tree.diff.iter() {
@@ -1240,8 +1287,12 @@ impl ContainerState for TreeState {
                         parent, position, ..
                     } => {
                         if need_check {
-                            self.mov(target, *parent, last_move_op, Some(position.clone()), true)
-                                .unwrap_or_default();
+                            self.mov_imported(
+                                target,
+                                *parent,
+                                last_move_op,
+                                Some(position.clone()),
+                            );
                         } else {
                             self.mov(target, *parent, last_move_op, Some(position.clone()), false)
                                 .unwrap();
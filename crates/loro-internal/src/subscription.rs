@@ -3,8 +3,8 @@ use super::{
     event::{DiffEvent, DocDiff},
 };
 use crate::{
-    container::idx::ContainerIdx, utils::subscription::SubscriberSet, ContainerDiff, LoroDoc,
-    Subscription,
+    container::idx::ContainerIdx, delta::TreeMoveRecord, utils::subscription::SubscriberSet,
+    ContainerDiff, LoroDoc, Subscription,
 };
 use rustc_hash::FxHashMap;
 use loro_common::{ContainerID, ID};
@@ -16,6 +16,9 @@ use crate::sync::Mutex;
 pub type LocalUpdateCallback = Box<dyn Fn(&Vec<u8>) -> bool + Send + Sync + 'static>;
 /// The callback of the peer id change. The second argument is the next counter for the peer.
 pub type PeerIdUpdateCallback = Box<dyn Fn(&ID) -> bool + Send + Sync + 'static>;
+/// The callback of the imported tree moves that are rejected because of cycles.
+pub type TreeMoveConflictCallback =
+    Box<dyn Fn(&Vec<TreeMoveRecord>) -> bool + Send + Sync + 'static>;
 #[allow(clippy::unused_unit)]
 pub type Subscriber = Arc<dyn for<'a> Fn(DiffEvent<'a>) + Send + Sync>;
 
@@ -26,6 +29,17 @@ impl LoroDoc {
         enable();
         s
     }
+
+    /// Subscribe to the imported moves of the tree that are rejected because of cycles.
+    pub(crate) fn subscribe_tree_move_conflicts(
+        &self,
+        tree: ContainerIdx,
+        callback: TreeMoveConflictCallback,
+    ) -> Subscription {
+        let (s, enable) = self.tree_move_conflict_subs.inner().insert(tree, callback);
+        enable();
+        s
+    }
 }
 
 struct ObserverInner {
@@ -30,6 +30,7 @@ use crate::{
     loro::CommitOptions,
     op::{Op, RawOp, RawOpContent},
     pre_commit::{ChangeModifier, PreCommitCallbackPayload},
+    signature::sign_change,
     span::HasIdSpan,
     version::Frontiers,
     ChangeMeta, InternalString, LoroDoc, LoroDocInner, LoroError, LoroValue,
@@ -153,6 +154,8 @@ pub struct Transaction {
     next_lamport: Lamport,
     doc: Weak<LoroDocInner>,
     frontiers: Frontiers,
+    /// The latest frontiers of the oplog when the txn started, used by [Transaction::abort]
+    oplog_frontiers: Frontiers,
     local_ops: RleVec<[Op; 1]>, // TODO: use a more efficient data structure
     event_hints: FxHashMap<ContainerIdx, Vec<EventHint>>,
     pub(super) arena: SharedArena,
@@ -197,6 +200,8 @@ pub(super) enum EventHint {
         start: u32,
         end: u32,
         style: Style,
+        /// The keys of the styles trimmed by this mark, see [crate::configure::OverlapPolicy]
+        trimmed_keys: Vec<InternalString>,
     },
     InsertText {
         /// pos is a Unicode index. If wasm, it's a UTF-16 index.
@@ -347,6 +352,7 @@ impl Transaction {
         let next_counter = oplog_lock.next_id(peer).counter;
         let next_lamport = oplog_lock.dag.frontiers_to_next_lamport(&frontiers);
         let latest_timestamp = oplog_lock.get_greatest_timestamp(&frontiers);
+        let oplog_frontiers = oplog_lock.frontiers().clone();
         oplog_lock
             .check_change_greater_than_last_peer_id(peer, next_counter, &frontiers)
             .unwrap();
@@ -357,6 +363,7 @@ impl Transaction {
             doc: Arc::downgrade(&doc),
             arena,
             frontiers,
+            oplog_frontiers,
             timestamp: None,
             next_counter,
             next_lamport,
@@ -448,6 +455,7 @@ impl Transaction {
                     .unwrap_or_else(|| doc.oplog.lock().unwrap().get_timestamp_for_next_txn()),
             ),
             commit_msg: take(&mut self.msg),
+            signature: None,
         };
 
         let change_meta = ChangeMeta::from_change(&change);
@@ -472,6 +480,11 @@ impl Transaction {
 
         let mut change = oplog.uncommitted_change.take().unwrap();
         modifier.modify_change(&mut change);
+        if change.signature.is_none() {
+            let config = oplog.configure.signature_config();
+            sign_change(&config, &mut change, &oplog.arena);
+        }
+
         let diff = if state.is_recording() {
             Some(change_to_diff(
                 &change,
@@ -517,6 +530,41 @@ impl Transaction {
         Ok(None)
     }
 
+    /// Abort the transaction and drop all the local ops created in it.
+    ///
+    /// The [DocState] is reverted to the version at the start of the txn,
+    /// and the op ids of the dropped ops will be reused by the next txn.
+    /// No event is emitted for the aborted ops.
+    pub fn abort(mut self) {
+        self._abort()
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn _abort(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let Some(doc) = self.doc.upgrade() else {
+            return;
+        };
+        self.finished = true;
+        self.on_commit = None;
+        self.event_hints.clear();
+        let ops = std::mem::take(&mut self.local_ops);
+        let mut oplog = doc.oplog.lock().unwrap();
+        let mut state = doc.state.lock().unwrap();
+        if ops.is_empty() {
+            state.abort_txn();
+            return;
+        }
+
+        oplog.dag.revert_pending_txn(take(&mut self.oplog_frontiers));
+        state.rollback_txn(take(&mut self.frontiers), &oplog);
+        self.next_counter = self.start_counter;
+        self.next_lamport = self.start_lamport;
+    }
+
     fn take_options(&self) -> CommitOptions {
         let mut options = CommitOptions::new();
         if !self.origin.is_empty() {
@@ -790,7 +838,12 @@ fn change_to_diff(
 
             // Generate diff based on hint type
             match hint {
-                EventHint::Mark { start, end, style } => {
+                EventHint::Mark {
+                    start,
+                    end,
+                    style,
+                    trimmed_keys,
+                } => {
                     let mut meta = StyleMeta::default();
                     meta.insert(
                         style.key.clone(),
@@ -800,6 +853,16 @@ fn change_to_diff(
                             value: style.data,
                         },
                     );
+                    for key in trimmed_keys {
+                        meta.insert(
+                            key,
+                            StyleMetaItem {
+                                lamport,
+                                peer: change.id.peer,
+                                value: LoroValue::Null,
+                            },
+                        );
+                    }
                     let diff = DeltaRopeBuilder::new()
                         .retain(start as usize, Default::default())
                         .retain(
@@ -1244,14 +1244,16 @@ fn import_status() -> LoroResult<()> {
         status1,
         ImportStatus {
             success: Default::default(),
-            pending: Some(VersionRange::from_map(fx_map!(1=>(1, 2))))
+            pending: Some(VersionRange::from_map(fx_map!(1=>(1, 2)))),
+            quarantined: None
         }
     );
     assert_eq!(
         status2,
         ImportStatus {
             success: VersionRange::from_map(fx_map!(1=>(0, 2))),
-            pending: None
+            pending: None,
+            quarantined: None
         }
     );
 
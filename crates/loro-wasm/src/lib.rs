@@ -13,9 +13,10 @@ use convert::{
 use js_sys::{Array, Object, Promise, Reflect, Uint8Array};
 use loro_internal::{
     change::Lamport,
-    configure::{StyleConfig, StyleConfigMap},
+    configure::{OverlapPolicy, StyleConfig, StyleConfigMap},
     container::{richtext::ExpandType, ContainerID},
     cursor::{self, CannotFindRelativePosition, Side},
+    diff::diff_impl::UpdateGranularity,
     encoding::ImportBlobMetadata,
     event::Index,
     handler::{
@@ -179,7 +180,9 @@ extern "C" {
     pub type JsLoroTreeOrUndefined;
     #[wasm_bindgen(typescript_type = "[string, Value | Container]")]
     pub type MapEntry;
-    #[wasm_bindgen(typescript_type = "{[key: string]: { expand: 'before'|'after'|'none'|'both' }}")]
+    #[wasm_bindgen(
+        typescript_type = "{[key: string]: { expand: 'before'|'after'|'none'|'both', overlap?: 'allow'|'exclusive', incompatible?: string[] }}"
+    )]
     pub type JsTextStyles;
     #[wasm_bindgen(typescript_type = "{ expand: 'before'|'after'|'none'|'both' } | undefined")]
     pub type JsTextStyle;
@@ -539,6 +542,12 @@ impl LoroDoc {
     /// - `none`: the mark will not be expanded to include the inserted text at the boundaries
     /// - `both`: when inserting text either right before or right after the given range, the mark will be expanded to include the inserted text
     ///
+    /// You can also specify which marks can't overlap. Where they overlap, the latest one wins
+    /// and the older ones are trimmed. All the peers must use the same config.
+    ///
+    /// - `overlap`: `exclusive` if the marks of the style (e.g. `link`, `link:a` and `link:b`) can't overlap. `allow` by default
+    /// - `incompatible`: the styles that can't overlap with this style, e.g. `code` can be incompatible with `bold`
+    ///
     /// @example
     /// ```ts
     /// const doc = new LoroDoc();
@@ -580,14 +589,42 @@ impl LoroDoc {
             let expand_str = expand
                 .as_string()
                 .ok_or_else(|| JsError::new("`expand` must be a string"))?;
-            // read allowOverlap value from value
+            let expand = ExpandType::try_from_str(&expand_str).ok_or_else(|| {
+                JsError::new("`expand` must be one of `none`, `start`, `end`, `both`")
+            })?;
+            // read overlap value from value
+            let overlap = Reflect::get(&value, &"overlap".into())?;
+            let overlap = if overlap.is_undefined() {
+                OverlapPolicy::Allow
+            } else {
+                overlap
+                    .as_string()
+                    .and_then(|x| OverlapPolicy::try_from_str(&x))
+                    .ok_or_else(|| JsError::new("`overlap` must be one of `allow`, `exclusive`"))?
+            };
+            // read incompatible value from value
+            let incompatible = Reflect::get(&value, &"incompatible".into())?;
+            let incompatible = if incompatible.is_undefined() {
+                Vec::new()
+            } else {
+                if !Array::is_array(&incompatible) {
+                    return Err(JsError::new("`incompatible` must be an array of strings").into());
+                }
+                Array::from(&incompatible)
+                    .iter()
+                    .map(|x| {
+                        x.as_string().ok_or_else(|| {
+                            JsError::new("`incompatible` must be an array of strings")
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            };
             style_config.insert(
                 key.into(),
-                StyleConfig {
-                    expand: ExpandType::try_from_str(&expand_str).ok_or_else(|| {
-                        JsError::new("`expand` must be one of `none`, `start`, `end`, `both`")
-                    })?,
-                },
+                StyleConfig::new()
+                    .expand(expand)
+                    .overlap(overlap)
+                    .incompatible(incompatible),
             );
         }
 
@@ -2553,15 +2590,22 @@ impl LoroText {
     #[wasm_bindgen(skip_typescript)]
     pub fn update(&self, text: &str, options: JsValue) -> JsResult<()> {
         let options = if options.is_null() || options.is_undefined() {
-            UpdateOptions {
-                timeout_ms: None,
-                use_refined_diff: true,
-            }
+            UpdateOptions::default()
         } else {
             let opts = match js_sys::Object::try_from(&options) {
                 Some(o) => o,
                 None => return Err(JsError::new("Invalid options").into()),
             };
+            let granularity = match js_sys::Reflect::get(opts, &"granularity".into())
+                .ok()
+                .and_then(|v| v.as_string())
+                .as_deref()
+            {
+                None | Some("char") => UpdateGranularity::Char,
+                Some("word") => UpdateGranularity::Word,
+                Some("sentence") => UpdateGranularity::Sentence,
+                Some(x) => return Err(JsError::new(&format!("Invalid granularity: {x}")).into()),
+            };
             UpdateOptions {
                 timeout_ms: js_sys::Reflect::get(opts, &"timeoutMs".into())
                     .ok()
@@ -2570,6 +2614,7 @@ impl LoroText {
                     .ok()
                     .and_then(|v| v.as_bool())
                     .unwrap_or(true),
+                granularity,
             }
         };
         self.handler
@@ -2583,10 +2628,7 @@ impl LoroText {
     #[wasm_bindgen(js_name = "updateByLine", skip_typescript)]
     pub fn update_by_line(&self, text: &str, options: JsValue) -> JsResult<()> {
         let options = if options.is_null() || options.is_undefined() {
-            UpdateOptions {
-                timeout_ms: None,
-                use_refined_diff: true,
-            }
+            UpdateOptions::default()
         } else {
             let opts = match js_sys::Object::try_from(&options) {
                 Some(o) => o,
@@ -2600,6 +2642,7 @@ impl LoroText {
                     .ok()
                     .and_then(|v| v.as_bool())
                     .unwrap_or(true),
+                ..Default::default()
             }
         };
         self.handler
@@ -6185,6 +6228,10 @@ export type JsonChange = {
 export interface TextUpdateOptions {
     timeoutMs?: number,
     useRefinedDiff?: boolean,
+    /**
+     * The unit of the diff, "char" by default. It's ignored by `updateByLine`.
+     */
+    granularity?: "char" | "word" | "sentence",
 }
 
 export type ExportMode = {
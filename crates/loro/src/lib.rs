@@ -29,7 +29,7 @@ use loro_internal::{
     TextHandler as InnerTextHandler, TreeHandler as InnerTreeHandler,
     UnknownHandler as InnerUnknownHandler,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::cmp::Ordering;
 use std::ops::ControlFlow;
 use std::ops::Deref;
@@ -37,25 +37,35 @@ use std::ops::Range;
 use std::sync::Arc;
 use tracing::info;
 
-pub use loro_internal::diff::diff_impl::UpdateOptions;
-pub use loro_internal::diff::diff_impl::UpdateTimeoutError;
+pub use loro_internal::diff::diff_impl::{UpdateGranularity, UpdateOptions};
 pub use loro_internal::subscription::LocalUpdateCallback;
 pub use loro_internal::subscription::PeerIdUpdateCallback;
 pub use loro_internal::ChangeMeta;
 pub use loro_internal::LORO_VERSION;
 pub mod event;
+pub mod sync_session;
 pub use loro_internal::awareness;
 pub use loro_internal::change::Timestamp;
 pub use loro_internal::configure::Configure;
-pub use loro_internal::configure::{StyleConfig, StyleConfigMap};
+pub use loro_internal::configure::{OverlapPolicy, StyleConfig, StyleConfigMap};
 pub use loro_internal::container::richtext::ExpandType;
 pub use loro_internal::container::{ContainerID, ContainerType, IntoContainerId};
 pub use loro_internal::cursor;
-pub use loro_internal::delta::{TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff};
+pub use loro_internal::delta::{
+    TreeDeltaItem, TreeDiff, TreeDiffItem, TreeExternalDiff, TreeMoveRecord,
+};
+#[cfg(feature = "encryption")]
+pub use loro_internal::encoding::ChaCha20Poly1305Cipher;
 pub use loro_internal::encoding::ImportBlobMetadata;
-pub use loro_internal::encoding::{EncodedBlobMode, ExportMode};
+pub use loro_internal::encoding::{
+    Cipher, Compression, EncodedBlobMode, ExportMode, ExportOptions,
+};
 pub use loro_internal::event::{EventTriggerKind, Index};
-pub use loro_internal::handler::TextDelta;
+pub use loro_internal::handler::{
+    Annotation, AnnotationEvent, AnnotationSubscriber, LineChange, LineSubscriber,
+    SortedChildrenSubscriber, SortedChildrenView, TextDelta, TextPattern, TreeAncestors,
+    TreeDescendantsBfs, TreeDescendantsDfs, TreeMoveConflictSubscriber,
+};
 pub use loro_internal::json;
 pub use loro_internal::json::{
     FutureOp as JsonFutureOp, FutureOpWrapper as JsonFutureOpWrapper, JsonChange, JsonOp,
@@ -65,7 +75,8 @@ pub use loro_internal::json::{
 pub use loro_internal::kv_store::{KvStore, MemKvStore};
 pub use loro_internal::loro::CommitOptions;
 pub use loro_internal::loro::DocAnalysis;
-pub use loro_internal::oplog::FrontiersNotIncluded;
+pub use loro_internal::oplog::{FrontiersNotIncluded, PendingChangesSummary};
+pub use loro_internal::signature::{InvalidSignaturePolicy, Signer, Verifier};
 pub use loro_internal::undo;
 pub use loro_internal::version::{Frontiers, VersionRange, VersionVector, VersionVectorDiff};
 pub use loro_internal::ApplyDiff;
@@ -290,6 +301,71 @@ impl LoroDoc {
         self.doc.set_change_merge_interval(interval);
     }
 
+    /// Set the signer that signs the local changes when they are committed.
+    ///
+    /// The signature is stored with the change and exported with it. A signed
+    /// change is never merged with the other changes. The signature can also be
+    /// set in [`LoroDoc::subscribe_pre_commit`] by [`ChangeModifier::set_signature`].
+    ///
+    /// A signature is only valid for the whole change, so the signature is lost
+    /// when only a part of the change is exported.
+    #[inline]
+    pub fn set_signer(&self, signer: Option<Arc<dyn Signer>>) {
+        self.doc.set_signer(signer);
+    }
+
+    /// Set the verifier that checks the signatures of the imported changes.
+    ///
+    /// With [`InvalidSignaturePolicy::Reject`], the import fails if any change has an invalid
+    /// signature. With [`InvalidSignaturePolicy::Quarantine`], the invalid changes are skipped
+    /// and reported in [`ImportStatus::quarantined`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use loro::{ChangeMeta, InvalidSignaturePolicy, LoroDoc, Signer, Verifier};
+    /// use std::sync::Arc;
+    ///
+    /// struct PeerSigner;
+    /// impl Signer for PeerSigner {
+    ///     fn sign(&self, meta: &ChangeMeta, _payload: &[u8]) -> Vec<u8> {
+    ///         meta.id.peer.to_le_bytes().to_vec()
+    ///     }
+    /// }
+    ///
+    /// impl Verifier for PeerSigner {
+    ///     fn verify(&self, meta: &ChangeMeta, _payload: &[u8], signature: Option<&[u8]>) -> bool {
+    ///         signature == Some(&meta.id.peer.to_le_bytes()[..])
+    ///     }
+    /// }
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.set_signer(Some(Arc::new(PeerSigner)));
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.commit();
+    ///
+    /// let other = LoroDoc::new();
+    /// other.set_verifier(Some(Arc::new(PeerSigner)), InvalidSignaturePolicy::Reject);
+    /// other.import(&doc.export(loro::ExportMode::all_updates()).unwrap()).unwrap();
+    /// assert_eq!(other.get_text("text").to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn set_verifier(
+        &self,
+        verifier: Option<Arc<dyn Verifier>>,
+        policy: InvalidSignaturePolicy,
+    ) {
+        self.doc.set_verifier(verifier, policy);
+    }
+
+    /// Get the signature of the change that contains the given id.
+    ///
+    /// Return `None` if the change is not found or not signed.
+    #[inline]
+    pub fn get_change_signature(&self, id: ID) -> Option<Arc<[u8]>> {
+        self.doc.get_change_signature(id)
+    }
+
     /// Set the rich text format configuration of the document.
     ///
     /// Configure the `expand` behavior for marks used by [`LoroText::mark`]/[`LoroText::unmark`].
@@ -300,13 +376,26 @@ impl LoroDoc {
     /// - `both`: inserts on either side expand the mark
     /// - `none`: do not expand at boundaries
     ///
+    /// It also controls which marks can't overlap, see [`OverlapPolicy`] and
+    /// [`StyleConfig::incompatible()`]. All the peers must use the same config.
+    ///
     /// # Example
     /// ```
-    /// use loro::{LoroDoc, StyleConfigMap, StyleConfig, ExpandType};
+    /// use loro::{ExpandType, LoroDoc, OverlapPolicy, StyleConfig, StyleConfigMap};
     /// let doc = LoroDoc::new();
     /// let mut styles = StyleConfigMap::new();
-    /// styles.insert("bold".into(), StyleConfig { expand: ExpandType::After });
+    /// styles.insert("bold".into(), StyleConfig::new().expand(ExpandType::After));
+    /// styles.insert("link".into(), StyleConfig::new().overlap(OverlapPolicy::Exclusive));
+    /// styles.insert("code".into(), StyleConfig::new().incompatible(["bold"]));
     /// doc.config_text_style(styles);
+    ///
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// text.mark(0..5, "bold", true).unwrap();
+    /// // `code` clears `bold` in its range
+    /// text.mark(3..8, "code", true).unwrap();
+    /// assert!(text.get_styles_at(2).unwrap().contains_key("bold"));
+    /// assert!(!text.get_styles_at(3).unwrap().contains_key("bold"));
     /// ```
     #[inline]
     pub fn config_text_style(&self, text_style: StyleConfigMap) {
@@ -326,7 +415,7 @@ impl LoroDoc {
     /// ```
     /// use loro::{LoroDoc, StyleConfig, ExpandType};
     /// let doc = LoroDoc::new();
-    /// doc.config_default_text_style(Some(StyleConfig { expand: ExpandType::After }));
+    /// doc.config_default_text_style(Some(StyleConfig::new().expand(ExpandType::After)));
     /// ```
     pub fn config_default_text_style(&self, text_style: Option<StyleConfig>) {
         self.doc.config_default_text_style(text_style);
@@ -509,9 +598,9 @@ impl LoroDoc {
     /// - `doc.import(data)` is called.
     /// - `doc.checkout(version)` is called.
     ///
-    /// Note: Loro transactions are not ACID database transactions. There is no rollback or
-    /// isolation; they are a grouping mechanism for events/history. For interactive undo/redo,
-    /// use [`UndoManager`].
+    /// Note: Loro transactions are not ACID database transactions. There is no isolation;
+    /// they are a grouping mechanism for events/history. The pending transaction can be
+    /// discarded by [`LoroDoc::rollback_pending`]. For interactive undo/redo, use [`UndoManager`].
     ///
     /// Empty-commit behavior: this method is an explicit commit. If the pending
     /// transaction is empty, any previously set next-commit options (message/timestamp/origin)
@@ -540,6 +629,31 @@ impl LoroDoc {
         self.doc.commit_with(options);
     }
 
+    /// Discard the changes in the pending transaction.
+    ///
+    /// The document state is reverted to the version before these changes, and their
+    /// op ids will be reused by the next commit. No events are emitted for them, so
+    /// the subscribers and [`UndoManager`] never see the discarded changes.
+    ///
+    /// The options set by `set_next_commit_*` are discarded as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use loro::LoroDoc;
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// doc.commit();
+    /// text.insert(5, " world").unwrap();
+    /// doc.rollback_pending();
+    /// assert_eq!(text.to_string(), "Hello");
+    /// ```
+    #[inline]
+    pub fn rollback_pending(&self) {
+        self.doc.rollback_pending();
+    }
+
     /// Set commit message for the current uncommitted changes
     ///
     /// It will be persisted.
@@ -612,6 +726,141 @@ impl LoroDoc {
         Ok(Self::_new(inner))
     }
 
+    /// Creates a new LoroDoc from a snapshot, decoding only the given containers
+    /// and their descendants.
+    ///
+    /// The other containers stay encoded until they are accessed. It's useful when only
+    /// a small part of a large document is needed.
+    ///
+    /// Returns an error if any of the given containers doesn't exist in the snapshot.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{LoroDoc, ExportMode};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.get_map("map").insert("key", "value").unwrap();
+    /// let snapshot = doc.export(ExportMode::Snapshot).unwrap();
+    ///
+    /// let text_id = doc.get_text("text").id();
+    /// let restored = LoroDoc::from_snapshot_with_roots(&snapshot, &[text_id]).unwrap();
+    /// assert_eq!(restored.get_text("text").to_string(), "Hello");
+    /// // Other containers are decoded when accessed
+    /// assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+    /// ```
+    pub fn from_snapshot_with_roots(bytes: &[u8], roots: &[ContainerID]) -> LoroResult<Self> {
+        let inner = InnerLoroDoc::from_snapshot_with_roots(bytes, roots)?;
+        inner.start_auto_commit();
+        Ok(Self::_new(inner))
+    }
+
+    /// Decodes the states of the given containers and all their descendants.
+    ///
+    /// Returns an error if any of the given containers doesn't exist.
+    #[inline]
+    pub fn materialize_containers(&self, roots: &[ContainerID]) -> LoroResult<()> {
+        self.doc.materialize_containers(roots)
+    }
+
+    /// Evicts the least recently accessed containers back to their encoded form, until
+    /// the encoded size of the decoded containers is within `budget` bytes.
+    ///
+    /// The evicted containers are decoded again transparently when they are accessed.
+    /// Returns the number of the evicted containers.
+    #[inline]
+    pub fn evict_cold_containers(&self, budget: usize) -> usize {
+        self.doc.evict_cold_containers(budget)
+    }
+
+    /// Open a `LoroDoc` persisted in the given directory.
+    ///
+    /// An empty doc is created if the directory doesn't exist or is empty.
+    ///
+    /// The history is stored as SSTable files and read lazily, so only the blocks
+    /// of changes that are accessed are read from the disk. The new changes are not
+    /// written to the directory until [`LoroDoc::flush`] is called.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let dir = std::env::temp_dir().join("loro-open-dir-doc-example");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let doc = LoroDoc::open_dir(&dir).unwrap();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// doc.flush().unwrap();
+    ///
+    /// let reopened = LoroDoc::open_dir(&dir).unwrap();
+    /// assert_eq!(reopened.get_text("text").to_string(), "Hello");
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn open_dir(dir: impl AsRef<std::path::Path>) -> LoroResult<Self> {
+        let inner = InnerLoroDoc::open_dir(dir)?;
+        inner.start_auto_commit();
+        Ok(Self::_new(inner))
+    }
+
+    /// Write the changes and the latest state into the directory opened by [`LoroDoc::open_dir`].
+    ///
+    /// The pending transaction is committed first.
+    ///
+    /// It returns an error if the doc is not opened from a directory, or if the doc is a
+    /// shallow doc.
+    #[inline]
+    pub fn flush(&self) -> LoroResult<()> {
+        self.doc.flush()
+    }
+
+    /// Export the key-value entries of the history that have changed since the last call.
+    ///
+    /// The history is stored as key-value entries internally: the blocks of changes are
+    /// keyed by 12 bytes of PeerID + Counter, and the `vv` / `fr` keys store the version
+    /// vector and the frontiers. All the entries are returned on the first call, so
+    /// persisting the returned entries after each call is enough to rebuild the doc
+    /// with [`LoroDoc::from_kv_entries`].
+    ///
+    /// The pending transaction is committed first.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let mut storage = BTreeMap::new();
+    /// doc.get_text("text").insert(0, "Hello").unwrap();
+    /// storage.extend(doc.export_dirty_kv_entries());
+    /// doc.get_text("text").insert(5, " world").unwrap();
+    /// let delta = doc.export_dirty_kv_entries();
+    /// storage.extend(delta);
+    ///
+    /// let restored = LoroDoc::from_kv_entries(storage).unwrap();
+    /// assert_eq!(restored.get_text("text").to_string(), "Hello world");
+    /// ```
+    pub fn export_dirty_kv_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.doc
+            .export_dirty_kv_entries()
+            .into_iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect()
+    }
+
+    /// Create a `LoroDoc` from the entries exported by [`LoroDoc::export_dirty_kv_entries`].
+    ///
+    /// If a key appears more than once, the latter value overrides the former one.
+    /// The state is calculated from the history.
+    ///
+    /// It returns an error if the entries come from a shallow doc.
+    pub fn from_kv_entries(
+        entries: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) -> LoroResult<Self> {
+        let inner =
+            InnerLoroDoc::from_kv_entries(entries.into_iter().map(|(k, v)| (k.into(), v.into())))?;
+        inner.start_auto_commit();
+        Ok(Self::_new(inner))
+    }
+
     /// Import data exported by [`LoroDoc::export`].
     ///
     /// Use [`ExportMode::Snapshot`] for full-state snapshots, or
@@ -649,6 +898,83 @@ impl LoroDoc {
         self.doc.import_with(bytes, origin.into())
     }
 
+    /// Import data read from the reader, e.g. a file written by [`LoroDoc::export_to_writer`].
+    ///
+    /// A snapshot is read part by part into separate buffers, and the reader is not read
+    /// beyond the end of it. Updates are read one change block at a time into separate
+    /// buffers, and each block is released once it's decoded.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{ExportMode, LoroDoc};
+    ///
+    /// let a = LoroDoc::new();
+    /// a.get_text("text").insert(0, "Hello").unwrap();
+    /// let mut buf = Vec::new();
+    /// a.export_to_writer(ExportMode::Snapshot, &mut buf).unwrap();
+    ///
+    /// let b = LoroDoc::new();
+    /// b.import_from_reader(&mut buf.as_slice()).unwrap();
+    /// assert_eq!(a.get_deep_value(), b.get_deep_value());
+    /// ```
+    #[inline]
+    pub fn import_from_reader<R: std::io::Read>(
+        &self,
+        r: &mut R,
+    ) -> Result<ImportStatus, LoroError> {
+        self.doc.import_from_reader(r)
+    }
+
+    /// Get the summary of the imported changes that can't be applied because their
+    /// dependencies are missing.
+    ///
+    /// These are the changes reported by [`ImportStatus::pending`]. They are applied
+    /// automatically once their dependencies are imported.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{ExportMode, LoroDoc};
+    ///
+    /// let a = LoroDoc::new();
+    /// a.set_peer_id(1).unwrap();
+    /// let text = a.get_text("text");
+    /// text.insert(0, "Hello").unwrap();
+    /// a.commit();
+    /// let vv = a.oplog_vv();
+    /// text.insert(5, " world").unwrap();
+    /// a.commit();
+    ///
+    /// let b = LoroDoc::new();
+    /// b.import(&a.export(ExportMode::updates(&vv)).unwrap()).unwrap();
+    /// let summary = b.pending_changes_summary();
+    /// assert_eq!(summary.change_num, 1);
+    /// assert_eq!(summary.missing_deps[&1], vec![4]);
+    /// ```
+    #[inline]
+    pub fn pending_changes_summary(&self) -> PendingChangesSummary {
+        self.doc.pending_changes_summary()
+    }
+
+    /// Export the imported changes that can't be applied because their dependencies
+    /// are missing, in the updates format.
+    ///
+    /// The pending changes only live in memory. The exported bytes can be persisted
+    /// and imported after a restart to restore them.
+    #[inline]
+    pub fn export_pending(&self) -> Vec<u8> {
+        self.doc.export_pending()
+    }
+
+    /// Drop the imported changes that can't be applied because their dependencies
+    /// are missing, and return their range.
+    ///
+    /// This can be used to bound the memory when a peer keeps sending changes whose
+    /// dependencies never arrive.
+    #[inline]
+    pub fn clear_pending(&self) -> VersionRange {
+        self.doc.clear_pending()
+    }
+
     /// Import the json schema updates.
     ///
     /// # Example
@@ -1234,6 +1560,85 @@ impl LoroDoc {
         self.doc.export(mode)
     }
 
+    /// Export the document in the given mode with the given options.
+    ///
+    /// [`ExportOptions::compression`] selects the compression of the exported blob.
+    /// The compression is recorded in the header, so [`LoroDoc::import`] detects
+    /// and decompresses it automatically.
+    ///
+    /// The compressed blobs can't be imported by the versions without compression support.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::{Compression, ExportMode, ExportOptions, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// doc.get_text("text").insert(0, &"Hello world! ".repeat(100)).unwrap();
+    /// let options = ExportOptions::new().with_compression(Compression::Lz4);
+    /// let updates = doc.export_with(ExportMode::all_updates(), options).unwrap();
+    /// assert!(updates.len() < doc.export(ExportMode::all_updates()).unwrap().len());
+    ///
+    /// let new_doc = LoroDoc::new();
+    /// new_doc.import(&updates).unwrap();
+    /// assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    /// ```
+    #[inline]
+    pub fn export_with(
+        &self,
+        mode: ExportMode,
+        options: ExportOptions,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        self.doc.export_with(mode, options)
+    }
+
+    /// Export the document in the given mode and encrypt it with the cipher.
+    ///
+    /// The metadata of the blob, i.e. the [`ImportBlobMetadata`] returned by
+    /// [`LoroDoc::decode_import_blob_meta`], stays readable without the key, so a relay
+    /// server can route the blob without being able to read its content. The metadata
+    /// is authenticated by the cipher, so it can't be modified without being detected.
+    ///
+    /// Use [`LoroDoc::import_encrypted`] to import the blob. A default ChaCha20-Poly1305
+    /// cipher is available with the `encryption` feature.
+    #[inline]
+    pub fn export_encrypted(
+        &self,
+        mode: ExportMode,
+        cipher: &dyn Cipher,
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        self.doc.export_encrypted(mode, cipher)
+    }
+
+    /// Decrypt the blob exported by [`LoroDoc::export_encrypted`] and import it.
+    ///
+    /// Returns [`LoroError::DecryptionError`] if the blob is tampered with or the key is wrong.
+    #[inline]
+    pub fn import_encrypted(
+        &self,
+        bytes: &[u8],
+        cipher: &dyn Cipher,
+    ) -> Result<ImportStatus, LoroError> {
+        self.doc.import_encrypted(bytes, cipher)
+    }
+
+    /// Export the document in the given mode into the writer.
+    ///
+    /// The output is the same as [`LoroDoc::export`]. For the snapshot modes, the parts
+    /// of the snapshot are written one by one instead of being concatenated into a single
+    /// blob first. For the updates modes, the change blocks are written one block at a time.
+    ///
+    /// Each part of a snapshot is still encoded in memory.
+    ///
+    /// The writer is not flushed.
+    #[inline]
+    pub fn export_to_writer<W: std::io::Write>(
+        &self,
+        mode: ExportMode,
+        w: &mut W,
+    ) -> Result<(), LoroEncodeError> {
+        self.doc.export_to_writer(mode, w)
+    }
+
     /// Analyze the container info of the doc
     ///
     /// This is used for development and debugging. It can be slow.
@@ -2218,6 +2623,54 @@ impl LoroText {
         self.handler.insert_utf8(pos, s)
     }
 
+    /// Insert an inline embed (e.g. an image or a mention) at the given unicode position.
+    ///
+    /// The embed occupies one position in the text, which is the char `U+FFFC` in
+    /// [LoroText::to_string]. It shows up as [TextDelta::Embed] in [LoroText::to_delta]
+    /// and in the text events.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{LoroDoc, LoroValue, TextDelta};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "ab").unwrap();
+    /// text.insert_embed(1, "image.png").unwrap();
+    /// assert_eq!(text.len_unicode(), 3);
+    /// assert_eq!(
+    ///     text.to_delta()[1],
+    ///     TextDelta::Embed {
+    ///         embed: LoroValue::from("image.png"),
+    ///         attributes: None,
+    ///     }
+    /// );
+    /// ```
+    pub fn insert_embed(&self, pos: usize, value: impl Into<LoroValue>) -> LoroResult<()> {
+        self.handler.insert_embed(pos, value)
+    }
+
+    /// Insert a child container as an inline embed at the given unicode position.
+    ///
+    /// The child container is deleted when its embed is deleted.
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::{ContainerTrait, LoroDoc, LoroMap};
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "ab").unwrap();
+    /// let map = text.insert_embed_container(1, LoroMap::new()).unwrap();
+    /// map.insert("src", "image.png").unwrap();
+    /// text.delete(1, 1).unwrap();
+    /// assert!(map.is_deleted());
+    /// ```
+    pub fn insert_embed_container<C: ContainerTrait>(&self, pos: usize, child: C) -> LoroResult<C> {
+        Ok(C::from_handler(
+            self.handler
+                .insert_embed_container(pos, child.to_handler())?,
+        ))
+    }
+
     /// Delete a range of text at the given unicode position with unicode length.
     pub fn delete(&self, pos: usize, len: usize) -> LoroResult<()> {
         self.handler.delete_unicode(pos, len)
@@ -2228,11 +2681,43 @@ impl LoroText {
         self.handler.delete_utf8(pos, len)
     }
 
+    /// Delete `len` grapheme clusters at the given grapheme cluster position.
+    ///
+    /// A grapheme cluster is a character perceived by users, which may consist of several
+    /// unicode code points, like `"👨‍👩‍👧"` or `"e\u{301}"`. Deleting by grapheme clusters
+    /// removes such a character as a whole. It takes O(n).
+    ///
+    /// # Example
+    /// ```
+    /// # use loro::LoroDoc;
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "a👨‍👩‍👧e\u{301}").unwrap();
+    /// assert_eq!(text.len_unicode(), 8);
+    /// assert_eq!(text.len_grapheme(), 3);
+    /// text.delete_grapheme(1, 1).unwrap();
+    /// assert_eq!(text.to_string(), "ae\u{301}");
+    /// assert_eq!(text.slice_grapheme(1, 2).unwrap(), "e\u{301}");
+    /// ```
+    pub fn delete_grapheme(&self, pos: usize, len: usize) -> LoroResult<()> {
+        self.handler.delete_grapheme(pos, len)
+    }
+
+    /// Insert a string at the given grapheme cluster position. It takes O(n).
+    pub fn insert_grapheme(&self, pos: usize, s: &str) -> LoroResult<()> {
+        self.handler.insert_grapheme(pos, s)
+    }
+
     /// Get a string slice at the given Unicode range
     pub fn slice(&self, start_index: usize, end_index: usize) -> LoroResult<String> {
         self.handler.slice(start_index, end_index)
     }
 
+    /// Get a string slice at the given grapheme cluster range. It takes O(n).
+    pub fn slice_grapheme(&self, start_index: usize, end_index: usize) -> LoroResult<String> {
+        self.handler.slice_grapheme(start_index, end_index)
+    }
+
     /// Get the characters at given unicode position.
     pub fn char_at(&self, pos: usize) -> LoroResult<char> {
         self.handler.char_at(pos)
@@ -2263,6 +2748,11 @@ impl LoroText {
         self.handler.len_utf16()
     }
 
+    /// Get the number of the grapheme clusters in the text container. It takes O(n).
+    pub fn len_grapheme(&self) -> usize {
+        self.handler.len_grapheme()
+    }
+
     /// Update the current text based on the provided text.
     ///
     /// It will calculate the minimal difference and apply it to the current text.
@@ -2271,15 +2761,22 @@ impl LoroText {
     /// This could take a long time for large texts (e.g. > 50_000 characters).
     /// In that case, you should use `updateByLine` instead.
     ///
+    /// For prose, [UpdateGranularity::Word] or [UpdateGranularity::Sentence] can be used
+    /// to replace the changed words or sentences as a whole.
+    ///
     /// # Example
     /// ```rust
-    /// use loro::LoroDoc;
+    /// use loro::{LoroDoc, UpdateGranularity, UpdateOptions};
     ///
     /// let doc = LoroDoc::new();
     /// let text = doc.get_text("text");
     /// text.insert(0, "Hello").unwrap();
     /// text.update("Hello World", Default::default()).unwrap();
     /// assert_eq!(text.to_string(), "Hello World");
+    ///
+    /// let options = UpdateOptions::default().granularity(UpdateGranularity::Word);
+    /// text.update("Hi there World", options).unwrap();
+    /// assert_eq!(text.to_string(), "Hi there World");
     /// ```
     ///
     pub fn update(&self, text: &str, options: UpdateOptions) -> Result<(), UpdateTimeoutError> {
@@ -2297,6 +2794,40 @@ impl LoroText {
         self.handler.update_by_line(text, options)
     }
 
+    /// Update the text to `text`, which was edited from the text at the `base` version,
+    /// e.g. by an external tool editing a file on disk.
+    ///
+    /// Unlike [LoroText::update], the diff is calculated against the text at `base`, and
+    /// the edits are applied as if they were made concurrently at `base`. So the edits
+    /// made after `base` are merged instead of being overwritten.
+    ///
+    /// The edits are made by a doc forked at `base`, which uses a new random peer id. So
+    /// every call adds a new peer to the version vector of the doc.
+    ///
+    /// The pending transaction of the doc is committed first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello World").unwrap();
+    /// doc.commit();
+    /// let base = doc.state_frontiers();
+    /// text.insert(11, "!").unwrap();
+    /// // The file was edited from the text at `base`
+    /// text.update_from_base(&base, "Hello Loro World").unwrap();
+    /// assert_eq!(text.to_string(), "Hello Loro World!");
+    /// ```
+    pub fn update_from_base(
+        &self,
+        base: &Frontiers,
+        text: &str,
+    ) -> Result<(), UpdateFromBaseError> {
+        self.handler.update_from_base(base, text)
+    }
+
     /// Apply a [delta](https://quilljs.com/docs/delta/) to the text container.
     pub fn apply_delta(&self, delta: &[TextDelta]) -> LoroResult<()> {
         self.handler.apply_delta(delta)
@@ -2343,6 +2874,287 @@ impl LoroText {
         self.handler.unmark(range.start, range.end, key)
     }
 
+    /// Get the styles of the char at the given unicode position.
+    ///
+    /// The reserved styles of embeds and annotations are not included, see
+    /// [LoroText::get_annotations] for the annotations.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// text.mark(0..5, "bold", true).unwrap();
+    /// text.mark(3..8, "link", "https://loro.dev").unwrap();
+    /// let styles = text.get_styles_at(4).unwrap();
+    /// assert_eq!(styles.len(), 2);
+    /// assert_eq!(styles["bold"], true.into());
+    /// assert!(text.get_styles_at(9).unwrap().is_empty());
+    /// assert_eq!(
+    ///     text.find_style_ranges("link"),
+    ///     vec![(3..8, "https://loro.dev".into())]
+    /// );
+    /// assert_eq!(text.styles_in_range(4..6).unwrap().len(), 2);
+    /// ```
+    pub fn get_styles_at(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.handler.get_styles_at(pos)
+    }
+
+    /// Get the styles of the char at the given UTF-8 position.
+    pub fn get_styles_at_utf8(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.handler.get_styles_at_utf8(pos)
+    }
+
+    /// Get the styles of the char at the given UTF-16 position.
+    pub fn get_styles_at_utf16(&self, pos: usize) -> LoroResult<FxHashMap<String, LoroValue>> {
+        self.handler.get_styles_at_utf16(pos)
+    }
+
+    /// Find the unicode ranges where the style `key` is applied, along with its values.
+    ///
+    /// Neighbor ranges with the same value are merged.
+    pub fn find_style_ranges(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.handler.find_style_ranges(key)
+    }
+
+    /// Find the UTF-8 ranges where the style `key` is applied, along with its values.
+    pub fn find_style_ranges_utf8(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.handler.find_style_ranges_utf8(key)
+    }
+
+    /// Find the UTF-16 ranges where the style `key` is applied, along with its values.
+    pub fn find_style_ranges_utf16(&self, key: &str) -> Vec<(Range<usize>, LoroValue)> {
+        self.handler.find_style_ranges_utf16(key)
+    }
+
+    /// Get the styled spans overlapping the unicode `range`, clipped to `range`.
+    ///
+    /// Each span has the same styles; the text without styles is skipped.
+    pub fn styles_in_range(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.handler.styles_in_range(range)
+    }
+
+    /// Get the styled spans overlapping the UTF-8 `range`, clipped to `range`.
+    pub fn styles_in_range_utf8(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.handler.styles_in_range_utf8(range)
+    }
+
+    /// Get the styled spans overlapping the UTF-16 `range`, clipped to `range`.
+    pub fn styles_in_range_utf16(
+        &self,
+        range: Range<usize>,
+    ) -> LoroResult<Vec<(Range<usize>, FxHashMap<String, LoroValue>)>> {
+        self.handler.styles_in_range_utf16(range)
+    }
+
+    /// Annotate the text in the unicode `range` with `value`, and return the id of the
+    /// annotation.
+    ///
+    /// Unlike a mark, the annotation is kept when the annotated text is deleted: its range
+    /// collapses to an empty range. Text inserted at either end of the annotation isn't a
+    /// part of it. The text must be attached to a document.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "Hello world").unwrap();
+    /// let id = text.annotate(6..11, "Needs a citation").unwrap();
+    /// text.delete(5, 6).unwrap();
+    /// let annotations = text.get_annotations();
+    /// assert_eq!(annotations[0].id, id);
+    /// assert_eq!(annotations[0].range, 5..5);
+    /// assert_eq!(doc.get_cursor_pos(&annotations[0].end).unwrap().current.pos, 5);
+    /// ```
+    pub fn annotate(&self, range: Range<usize>, value: impl Into<LoroValue>) -> LoroResult<String> {
+        self.handler.annotate(range, value)
+    }
+
+    /// Remove the annotation with the given id.
+    pub fn remove_annotation(&self, id: &str) -> LoroResult<()> {
+        self.handler.remove_annotation(id)
+    }
+
+    /// Get the annotations with their current unicode ranges, sorted by the ranges.
+    pub fn get_annotations(&self) -> Vec<Annotation> {
+        self.handler.get_annotations()
+    }
+
+    /// Subscribe the changes of the annotations.
+    ///
+    /// The callback is invoked when annotations are created or removed, or when their
+    /// ranges or values are changed.
+    pub fn subscribe_annotations(
+        &self,
+        callback: AnnotationSubscriber,
+    ) -> LoroResult<Subscription> {
+        self.handler.subscribe_annotations(callback)
+    }
+
+    /// Find the non-overlapping unicode ranges that match `pattern`.
+    ///
+    /// The pattern is a `&str`, or a `&Regex` with the `regex` feature enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "你好 world, world").unwrap();
+    /// assert_eq!(text.find("world"), vec![3..8, 10..15]);
+    /// assert_eq!(text.find_utf8("world"), vec![7..12, 14..19]);
+    /// ```
+    pub fn find<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.handler.find(pattern)
+    }
+
+    /// Find the non-overlapping UTF-8 ranges that match `pattern`.
+    pub fn find_utf8<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.handler.find_utf8(pattern)
+    }
+
+    /// Find the non-overlapping UTF-16 ranges that match `pattern`.
+    pub fn find_utf16<'a>(&self, pattern: impl Into<TextPattern<'a>>) -> Vec<Range<usize>> {
+        self.handler.find_utf16(pattern)
+    }
+
+    /// Replace all the matches of `pattern` with `replacement` in one transaction, and
+    /// return the number of the matches.
+    ///
+    /// Only the part of each match that differs from its replacement is edited, so the
+    /// marks on the rest of the text are kept. With a regex pattern, the replacement can
+    /// refer to the capture groups like `$1`.
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "color, colors").unwrap();
+    /// text.mark(0..5, "bold", true).unwrap();
+    /// assert_eq!(text.replace_all("color", "colour").unwrap(), 2);
+    /// assert_eq!(text.to_string(), "colour, colours");
+    /// // Only "u" is inserted, inside the bold range
+    /// assert_eq!(text.find_style_ranges("bold"), vec![(0..6, true.into())]);
+    /// ```
+    pub fn replace_all<'a>(
+        &self,
+        pattern: impl Into<TextPattern<'a>>,
+        replacement: &str,
+    ) -> LoroResult<usize> {
+        self.handler.replace_all(pattern, replacement)
+    }
+
+    /// Get the number of lines, which is the number of `\n` plus one.
+    ///
+    /// The line queries run in O(log n).
+    ///
+    /// # Example
+    /// ```
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "fn main() {\n    println!(\"你好\");\n}").unwrap();
+    /// assert_eq!(text.line_count(), 3);
+    /// assert_eq!(text.line_to_pos(1), Some(12));
+    /// assert_eq!(text.line_to_pos(3), None);
+    /// assert_eq!(text.pos_to_line_col(28), Some((1, 16)));
+    /// assert_eq!(text.pos_to_line_col_utf8(32), Some((1, 20)));
+    /// assert_eq!(text.slice_lines(1..3).unwrap(), "    println!(\"你好\");\n}");
+    /// ```
+    pub fn line_count(&self) -> usize {
+        self.handler.line_count()
+    }
+
+    /// Get the unicode index of the start of the `line` (0-based), or None if there is
+    /// no such line.
+    pub fn line_to_pos(&self, line: usize) -> Option<usize> {
+        self.handler.line_to_pos(line)
+    }
+
+    /// Get the UTF-8 index of the start of the `line` (0-based).
+    pub fn line_to_pos_utf8(&self, line: usize) -> Option<usize> {
+        self.handler.line_to_pos_utf8(line)
+    }
+
+    /// Get the UTF-16 index of the start of the `line` (0-based).
+    pub fn line_to_pos_utf16(&self, line: usize) -> Option<usize> {
+        self.handler.line_to_pos_utf16(line)
+    }
+
+    /// Get the line (0-based) and the unicode column of the unicode index `pos`, or None
+    /// if `pos` is out of bound.
+    pub fn pos_to_line_col(&self, pos: usize) -> Option<(usize, usize)> {
+        self.handler.pos_to_line_col(pos)
+    }
+
+    /// Get the line (0-based) and the UTF-8 column of the UTF-8 index `pos`.
+    pub fn pos_to_line_col_utf8(&self, pos: usize) -> Option<(usize, usize)> {
+        self.handler.pos_to_line_col_utf8(pos)
+    }
+
+    /// Get the line (0-based) and the UTF-16 column of the UTF-16 index `pos`.
+    pub fn pos_to_line_col_utf16(&self, pos: usize) -> Option<(usize, usize)> {
+        self.handler.pos_to_line_col_utf16(pos)
+    }
+
+    /// Get the text of the `lines` (0-based), including their line breaks.
+    pub fn slice_lines(&self, lines: Range<usize>) -> LoroResult<String> {
+        self.handler.slice_lines(lines)
+    }
+
+    /// Subscribe the changes of the lines.
+    ///
+    /// The callback is invoked with the changed lines of each event, so an editor binding
+    /// can update only the changed lines. The text must be attached to a document.
+    ///
+    /// The lines start from the committed text, so the pending ops are reported when
+    /// they are committed.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use loro::{LineChange, LoroDoc};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let text = doc.get_text("text");
+    /// text.insert(0, "a\nb\nc").unwrap();
+    /// doc.commit();
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let changes_clone = changes.clone();
+    /// let _sub = text
+    ///     .subscribe_lines(Arc::new(move |e| {
+    ///         changes_clone.lock().unwrap().extend_from_slice(e);
+    ///     }))
+    ///     .unwrap();
+    /// // Replace "b" with "x\ny"
+    /// text.splice(2, 1, "x\ny").unwrap();
+    /// doc.commit();
+    /// assert_eq!(
+    ///     changes.lock().unwrap()[..],
+    ///     [LineChange {
+    ///         old_lines: 1..2,
+    ///         new_lines: 1..3
+    ///     }]
+    /// );
+    /// ```
+    pub fn subscribe_lines(&self, callback: LineSubscriber) -> LoroResult<Subscription> {
+        self.handler.subscribe_lines(callback)
+    }
+
     /// Get the text in [Delta](https://quilljs.com/docs/delta/) format.
     ///
     /// # Example
@@ -2389,10 +3201,17 @@ impl LoroText {
             .iter()
             .map(|x| {
                 let map = x.as_map().unwrap();
-                let insert = map.get("insert").unwrap().as_string().unwrap().to_string();
                 let attributes = map
                     .get("attributes")
                     .map(|v| v.as_map().unwrap().deref().clone());
+                if let Some(embed) = map.get("embed") {
+                    return TextDelta::Embed {
+                        embed: embed.clone(),
+                        attributes,
+                    };
+                }
+
+                let insert = map.get("insert").unwrap().as_string().unwrap().to_string();
                 TextDelta::Insert { insert, attributes }
             })
             .collect()
@@ -2469,6 +3288,14 @@ impl LoroText {
         self.handler.get_cursor(pos, side)
     }
 
+    /// Get the cursor at the given grapheme cluster position. It takes O(n).
+    ///
+    /// The cursor is the same as the one from [LoroText::get_cursor] at the start of
+    /// the grapheme cluster.
+    pub fn get_cursor_grapheme(&self, pos: usize, side: Side) -> Option<Cursor> {
+        self.handler.get_cursor_grapheme(pos, side)
+    }
+
     /// Whether the text container is deleted.
     pub fn is_deleted(&self) -> bool {
         self.handler.is_deleted()
@@ -2758,6 +3585,49 @@ impl LoroTree {
         self.handler.delete(target)
     }
 
+    /// Duplicate the `target` node and its descendants as a new subtree under `parent`
+    /// at `index`, in one transaction.
+    ///
+    /// The metadata maps of the nodes are deeply copied, including the containers in
+    /// them. Return the mapping from the duplicated nodes to the new nodes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::{loro_value, LoroDoc, LoroText, TreeParentId};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// // enable generate fractional index
+    /// tree.enable_fractional_index(0);
+    /// let root = tree.create(None).unwrap();
+    /// let child = tree.create(root).unwrap();
+    /// let title = tree
+    ///     .get_meta(child)
+    ///     .unwrap()
+    ///     .insert_container("title", LoroText::new())
+    ///     .unwrap();
+    /// title.insert(0, "Section").unwrap();
+    ///
+    /// let mapping = tree.duplicate_subtree(root, None, 1).unwrap();
+    /// let new_child = mapping[&child];
+    /// assert_eq!(tree.roots(), vec![root, mapping[&root]]);
+    /// assert_eq!(tree.parent(new_child), Some(TreeParentId::Node(mapping[&root])));
+    /// let new_meta = tree.get_meta(new_child).unwrap().get_deep_value();
+    /// assert_eq!(new_meta, loro_value!({"title": "Section"}));
+    /// ```
+    pub fn duplicate_subtree<T: Into<TreeParentId>>(
+        &self,
+        target: TreeID,
+        parent: T,
+        index: usize,
+    ) -> LoroResult<FxHashMap<TreeID, TreeID>> {
+        if !self.handler.is_fractional_index_enabled() {
+            return Err(LoroTreeError::FractionalIndexNotEnabled.into());
+        }
+        self.handler.duplicate_subtree(target, parent.into(), index)
+    }
+
     /// Get the associated metadata map handler of a tree node.
     ///
     /// # Example
@@ -2832,6 +3702,131 @@ impl LoroTree {
         self.handler.children_num(&parent)
     }
 
+    /// Get the view of the children of the target node sorted by the value at `path` in
+    /// their metadata maps.
+    ///
+    /// Every key of the path but the last one refers to a child map. A missing value is
+    /// treated as `Null`. The values are ordered by type first (`Null` < bool < number <
+    /// string < binary < list < map), and the children with equal values keep their order
+    /// in the tree.
+    ///
+    /// The view is stored and maintained incrementally by the events of the tree, so
+    /// [SortedChildrenView::children] doesn't sort the children again. Its subscribers
+    /// receive the changes of the view in the shape of the tree events, where the indexes
+    /// are in the sorted view and should be applied in order. A child whose sort value
+    /// changes is reported as a move within the parent.
+    ///
+    /// The view starts from the committed state. The tree must be attached to a document,
+    /// and the parent node must exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use loro::{LoroDoc, TreeExternalDiff};
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// let a = tree.create(root).unwrap();
+    /// let b = tree.create(root).unwrap();
+    /// tree.get_meta(a).unwrap().insert("name", "zoo").unwrap();
+    /// tree.get_meta(b).unwrap().insert("name", "apple").unwrap();
+    /// doc.commit();
+    /// let view = tree.children_sorted_by(root, &["name"]).unwrap();
+    /// assert_eq!(view.children(), vec![b, a]);
+    ///
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let changes_clone = changes.clone();
+    /// let _sub = view.subscribe(Arc::new(move |diff| {
+    ///     changes_clone.lock().unwrap().extend(diff.diff.clone());
+    /// }));
+    /// tree.get_meta(a).unwrap().insert("name", "ant").unwrap();
+    /// doc.commit();
+    /// assert_eq!(view.children(), vec![a, b]);
+    /// let changes = changes.lock().unwrap();
+    /// assert_eq!(changes.len(), 1);
+    /// assert_eq!(changes[0].target, a);
+    /// assert!(matches!(
+    ///     changes[0].action,
+    ///     TreeExternalDiff::Move {
+    ///         index: 0,
+    ///         old_index: 1,
+    ///         ..
+    ///     }
+    /// ));
+    /// ```
+    pub fn children_sorted_by<T: Into<TreeParentId>>(
+        &self,
+        parent: T,
+        path: &[&str],
+    ) -> LoroResult<SortedChildrenView> {
+        self.handler.children_sorted_by(&parent.into(), path)
+    }
+
+    /// Iterate the ancestors of the target node, from its parent to the root node.
+    ///
+    /// The node itself is not included. If the node does not exist, the iterator is empty.
+    pub fn ancestors(&self, target: TreeID) -> TreeAncestors {
+        self.handler.ancestors(&target)
+    }
+
+    /// Iterate the descendants of the parent in depth-first pre-order.
+    ///
+    /// The iterator is lazy: the children of a node are read from the tree when the node
+    /// is reached, and no value of the tree is built. The parent itself is not included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::LoroDoc;
+    ///
+    /// let doc = LoroDoc::new();
+    /// let tree = doc.get_tree("tree");
+    /// let root = tree.create(None).unwrap();
+    /// let a = tree.create(root).unwrap();
+    /// let b = tree.create(root).unwrap();
+    /// let a_1 = tree.create(a).unwrap();
+    /// assert_eq!(tree.descendants_dfs(root).collect::<Vec<_>>(), vec![a, a_1, b]);
+    /// assert_eq!(tree.descendants_bfs(root).collect::<Vec<_>>(), vec![a, b, a_1]);
+    /// assert_eq!(tree.ancestors(a_1).collect::<Vec<_>>(), vec![a, root]);
+    /// assert_eq!(tree.depth(a_1), Some(2));
+    /// assert_eq!(tree.subtree_size(root), Some(4));
+    /// assert_eq!(tree.lowest_common_ancestor(a_1, b), Some(root));
+    /// ```
+    pub fn descendants_dfs<T: Into<TreeParentId>>(&self, parent: T) -> TreeDescendantsDfs {
+        self.handler.descendants_dfs(&parent.into())
+    }
+
+    /// Iterate the descendants of the parent in breadth-first order.
+    ///
+    /// The iterator is lazy, and the parent itself is not included.
+    pub fn descendants_bfs<T: Into<TreeParentId>>(&self, parent: T) -> TreeDescendantsBfs {
+        self.handler.descendants_bfs(&parent.into())
+    }
+
+    /// Return the number of the ancestors of the target node, so a root node has depth 0.
+    ///
+    /// If the node does not exist, return `None`.
+    pub fn depth(&self, target: TreeID) -> Option<usize> {
+        self.handler.depth(&target)
+    }
+
+    /// Return the number of the nodes in the subtree of the target node, including itself.
+    ///
+    /// If the node does not exist, return `None`.
+    pub fn subtree_size(&self, target: TreeID) -> Option<usize> {
+        self.handler.subtree_size(&target)
+    }
+
+    /// Return the deepest node that is an ancestor of both nodes, where a node is regarded
+    /// as an ancestor of itself.
+    ///
+    /// Return `None` if the nodes are in different trees or one of them does not exist.
+    pub fn lowest_common_ancestor(&self, a: TreeID, b: TreeID) -> Option<TreeID> {
+        self.handler.lowest_common_ancestor(&a, &b)
+    }
+
     /// Return the fractional index of the target node with hex format.
     pub fn fractional_index(&self, target: TreeID) -> Option<String> {
         self.handler
@@ -2897,6 +3892,56 @@ impl LoroTree {
     pub fn get_last_move_id(&self, target: &TreeID) -> Option<ID> {
         self.handler.get_last_move_id(target)
     }
+
+    /// Get all the ops that create, move or delete the target node at the last commit,
+    /// in the order they are applied.
+    ///
+    /// Concurrent moves are applied in the order of their lamports and peers, and a move
+    /// that would create a cycle is rejected, which is marked by `applied: false`. The last
+    /// applied op is the one of [LoroTree::get_last_move_id].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use loro::{ExportMode, LoroDoc, TreeParentId};
+    ///
+    /// let doc_a = LoroDoc::new();
+    /// doc_a.set_peer_id(1).unwrap();
+    /// let tree_a = doc_a.get_tree("tree");
+    /// let x = tree_a.create(None).unwrap();
+    /// let y = tree_a.create(None).unwrap();
+    /// let doc_b = LoroDoc::new();
+    /// doc_b.set_peer_id(2).unwrap();
+    /// doc_b.import(&doc_a.export(ExportMode::all_updates()).unwrap()).unwrap();
+    ///
+    /// // Concurrent moves that would create a cycle
+    /// tree_a.mov(x, y).unwrap();
+    /// doc_b.get_tree("tree").mov(y, x).unwrap();
+    /// doc_a.import(&doc_b.export(ExportMode::all_updates()).unwrap()).unwrap();
+    /// assert_eq!(tree_a.parent(x), Some(TreeParentId::Node(y)));
+    /// let history = tree_a.history_of_node(y);
+    /// assert_eq!(history.len(), 2);
+    /// assert!(history[0].applied);
+    /// assert_eq!(history[1].parent, TreeParentId::Node(x));
+    /// assert!(!history[1].applied);
+    /// assert_eq!(tree_a.get_last_move_id(&y), Some(history[0].id));
+    /// ```
+    pub fn history_of_node(&self, target: TreeID) -> Vec<TreeMoveRecord> {
+        self.handler.history_of_node(&target)
+    }
+
+    /// Subscribe the moves that are rejected because they would create cycles.
+    ///
+    /// Local moves that would create cycles fail directly, so the rejected moves come from
+    /// the concurrent moves of other peers. The callback is invoked with the newly rejected
+    /// moves of an import after its events are emitted. The tree must be attached to a
+    /// document.
+    pub fn subscribe_move_conflicts(
+        &self,
+        callback: TreeMoveConflictSubscriber,
+    ) -> LoroResult<Subscription> {
+        self.handler.subscribe_move_conflicts(callback)
+    }
 }
 
 impl Default for LoroTree {
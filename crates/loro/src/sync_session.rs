@@ -0,0 +1,388 @@
+//! Synchronization between two [LoroDoc]s.
+//!
+//! [SyncSession] drives the synchronization with a remote peer by producing and
+//! consuming [SyncMessage]s. It's transport-agnostic: the messages can be sent through
+//! any channel, either as typed values or encoded by [SyncMessage::encode].
+//!
+//! # Protocol
+//!
+//! 1. Both sides send [SyncMessage::Hello] with their versions by [SyncSession::start].
+//!    A side that receives a hello before sending its own replies with one.
+//! 2. After receiving a hello, a side sends the updates the remote peer is missing.
+//! 3. After importing updates, a side replies with [SyncMessage::Ack] containing its
+//!    new version. If some changes can't be applied because their dependencies are
+//!    missing, it also requests the missing range by [SyncMessage::RequestMissing].
+//!    If the missing changes still don't arrive after a few requests, it sends a
+//!    new hello to resync from its current version.
+//! 4. The local changes made afterwards are sent by [SyncSession::poll].
+//!
+//! # Example
+//!
+//! ```
+//! use loro::{sync_session::SyncSession, LoroDoc};
+//!
+//! let a = LoroDoc::new();
+//! a.get_text("text").insert(0, "Hello").unwrap();
+//! let b = LoroDoc::new();
+//! b.get_map("map").insert("key", 1).unwrap();
+//!
+//! let mut session_a = SyncSession::new(&a);
+//! let mut session_b = SyncSession::new(&b);
+//! let mut to_b = vec![session_a.start()];
+//! let mut to_a = vec![];
+//! while !to_a.is_empty() || !to_b.is_empty() {
+//!     for msg in std::mem::take(&mut to_b) {
+//!         to_a.extend(session_b.handle(msg).unwrap());
+//!     }
+//!     for msg in std::mem::take(&mut to_a) {
+//!         to_b.extend(session_a.handle(msg).unwrap());
+//!     }
+//! }
+//!
+//! assert!(session_a.is_synced() && session_b.is_synced());
+//! assert_eq!(a.get_deep_value(), b.get_deep_value());
+//! ```
+use loro_common::{IdSpan, LoroError, LoroResult};
+
+use crate::{ExportMode, Frontiers, LoroDoc, VersionRange, VersionVector};
+
+/// The default value of [SyncSession::set_max_missing_requests].
+pub const DEFAULT_MAX_MISSING_REQUESTS: usize = 3;
+
+/// A message of the sync protocol. See the [module docs](self) for the protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncMessage {
+    /// The version of the sender. It's sent when a session starts.
+    ///
+    /// The receiver will reset what it knows about the sender to this version.
+    Hello {
+        /// The oplog version vector of the sender
+        vv: VersionVector,
+        /// The oplog frontiers of the sender
+        frontiers: Frontiers,
+    },
+    /// The updates exported by [ExportMode::Updates] or [ExportMode::UpdatesInRange]
+    Updates {
+        /// The encoded updates
+        updates: Vec<u8>,
+    },
+    /// The version of the sender after it imports the updates
+    Ack {
+        /// The oplog version vector of the sender
+        vv: VersionVector,
+    },
+    /// Request the updates in the range, which are the missing dependencies of
+    /// the pending changes of the sender
+    RequestMissing {
+        /// The requested range
+        range: VersionRange,
+    },
+}
+
+const HELLO_TAG: u8 = 0;
+const UPDATES_TAG: u8 = 1;
+const ACK_TAG: u8 = 2;
+const REQUEST_MISSING_TAG: u8 = 3;
+
+impl SyncMessage {
+    /// Encode the message into bytes.
+    ///
+    /// # Layout
+    ///
+    /// The first byte is the type of the message, followed by
+    ///
+    /// - Hello: u32 LE len + encoded vv, u32 LE len + encoded frontiers
+    /// - Updates: the updates bytes
+    /// - Ack: encoded vv
+    /// - RequestMissing: u32 LE count, then (u64 LE peer, i32 LE start, i32 LE end) for each peer
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            SyncMessage::Hello { vv, frontiers } => {
+                let vv = vv.encode();
+                let frontiers = frontiers.encode();
+                let mut ans = Vec::with_capacity(9 + vv.len() + frontiers.len());
+                ans.push(HELLO_TAG);
+                ans.extend_from_slice(&(vv.len() as u32).to_le_bytes());
+                ans.extend_from_slice(&vv);
+                ans.extend_from_slice(&(frontiers.len() as u32).to_le_bytes());
+                ans.extend_from_slice(&frontiers);
+                ans
+            }
+            SyncMessage::Updates { updates } => {
+                let mut ans = Vec::with_capacity(1 + updates.len());
+                ans.push(UPDATES_TAG);
+                ans.extend_from_slice(updates);
+                ans
+            }
+            SyncMessage::Ack { vv } => {
+                let mut ans = vec![ACK_TAG];
+                ans.extend_from_slice(&vv.encode());
+                ans
+            }
+            SyncMessage::RequestMissing { range } => {
+                let mut ans = vec![REQUEST_MISSING_TAG];
+                ans.extend_from_slice(&(range.inner().len() as u32).to_le_bytes());
+                for (peer, (start, end)) in range.iter() {
+                    ans.extend_from_slice(&peer.to_le_bytes());
+                    ans.extend_from_slice(&start.to_le_bytes());
+                    ans.extend_from_slice(&end.to_le_bytes());
+                }
+                ans
+            }
+        }
+    }
+
+    /// Decode the message encoded by [SyncMessage::encode].
+    pub fn decode(bytes: &[u8]) -> LoroResult<Self> {
+        let Some((&tag, mut r)) = bytes.split_first() else {
+            return Err(invalid_message());
+        };
+        match tag {
+            HELLO_TAG => {
+                let vv = read_len_prefixed(&mut r)?;
+                let frontiers = read_len_prefixed(&mut r)?;
+                Ok(SyncMessage::Hello {
+                    vv: VersionVector::decode(vv)?,
+                    frontiers: Frontiers::decode(frontiers)?,
+                })
+            }
+            UPDATES_TAG => Ok(SyncMessage::Updates {
+                updates: r.to_vec(),
+            }),
+            ACK_TAG => Ok(SyncMessage::Ack {
+                vv: VersionVector::decode(r)?,
+            }),
+            REQUEST_MISSING_TAG => {
+                let len = u32::from_le_bytes(read_array(&mut r)?);
+                let mut range = VersionRange::new();
+                for _ in 0..len {
+                    let peer = u64::from_le_bytes(read_array(&mut r)?);
+                    let start = i32::from_le_bytes(read_array(&mut r)?);
+                    let end = i32::from_le_bytes(read_array(&mut r)?);
+                    range.insert(peer, start, end);
+                }
+                Ok(SyncMessage::RequestMissing { range })
+            }
+            _ => Err(invalid_message()),
+        }
+    }
+}
+
+fn invalid_message() -> LoroError {
+    LoroError::DecodeError("Invalid sync message".into())
+}
+
+fn read_array<const N: usize>(r: &mut &[u8]) -> LoroResult<[u8; N]> {
+    if r.len() < N {
+        return Err(invalid_message());
+    }
+
+    let (bytes, rest) = r.split_at(N);
+    *r = rest;
+    Ok(bytes.try_into().unwrap())
+}
+
+fn read_len_prefixed<'a>(r: &mut &'a [u8]) -> LoroResult<&'a [u8]> {
+    let len = u32::from_le_bytes(read_array(r)?) as usize;
+    if r.len() < len {
+        return Err(invalid_message());
+    }
+
+    let (bytes, rest) = r.split_at(len);
+    *r = rest;
+    Ok(bytes)
+}
+
+/// The state machine of the sync protocol with a remote peer.
+///
+/// See the [module docs](self) for the protocol.
+#[derive(Debug)]
+pub struct SyncSession {
+    doc: LoroDoc,
+    /// The version the remote peer reported by its last hello or ack
+    remote_vv: Option<VersionVector>,
+    /// `remote_vv` plus the versions of the updates sent to the remote peer
+    sent_vv: VersionVector,
+    hello_sent: bool,
+    /// The changes that can't be applied because their dependencies are missing
+    pending: Option<VersionRange>,
+    missing_requests: usize,
+    max_missing_requests: usize,
+}
+
+impl SyncSession {
+    /// Create a session that syncs the doc with a remote peer.
+    pub fn new(doc: &LoroDoc) -> Self {
+        Self {
+            doc: doc.clone(),
+            remote_vv: None,
+            sent_vv: VersionVector::new(),
+            hello_sent: false,
+            pending: None,
+            missing_requests: 0,
+            max_missing_requests: DEFAULT_MAX_MISSING_REQUESTS,
+        }
+    }
+
+    /// Set how many times the missing dependencies of the pending changes are requested
+    /// before falling back to a new hello.
+    pub fn set_max_missing_requests(&mut self, max: usize) {
+        self.max_missing_requests = max;
+    }
+
+    /// Start the session by creating the hello message.
+    ///
+    /// It can be called again to resync with the remote peer, e.g. after reconnecting.
+    pub fn start(&mut self) -> SyncMessage {
+        self.hello_sent = true;
+        SyncMessage::Hello {
+            vv: self.doc.oplog_vv(),
+            frontiers: self.doc.oplog_frontiers(),
+        }
+    }
+
+    /// Handle the message from the remote peer and return the messages to reply.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of importing [SyncMessage::Updates].
+    pub fn handle(&mut self, msg: SyncMessage) -> LoroResult<Vec<SyncMessage>> {
+        let mut ans = Vec::new();
+        match msg {
+            SyncMessage::Hello { vv, frontiers } => {
+                self.sent_vv = vv.clone();
+                self.remote_vv = Some(vv);
+                if !self.hello_sent {
+                    ans.push(self.start());
+                }
+                if frontiers != self.doc.oplog_frontiers() {
+                    ans.extend(self.poll());
+                }
+            }
+            SyncMessage::Updates { updates } => {
+                let status = self.doc.import(&updates)?;
+                // The remote peer has the changes it sent
+                let imported = range_end_vv(&status.success);
+                self.sent_vv.merge(&imported);
+                if let Some(remote_vv) = self.remote_vv.as_mut() {
+                    remote_vv.merge(&imported);
+                }
+
+                let vv = self.doc.oplog_vv();
+                let mut pending = status.pending.unwrap_or_default();
+                for (&peer, &(start, end)) in self.pending.take().iter().flat_map(|p| p.iter()) {
+                    // The pending changes of the previous imports may be applied by this import
+                    if vv.get(&peer).copied().unwrap_or(0) < end {
+                        pending.extends_to_include_id_span(IdSpan::new(peer, start, end));
+                    }
+                }
+                self.pending = (!pending.is_empty()).then_some(pending);
+                ans.push(SyncMessage::Ack { vv });
+                if self.pending.is_none() {
+                    self.missing_requests = 0;
+                } else {
+                    ans.push(self.request_missing());
+                }
+            }
+            SyncMessage::Ack { vv } => {
+                self.sent_vv.merge(&vv);
+                match self.remote_vv.as_mut() {
+                    Some(remote_vv) => remote_vv.merge(&vv),
+                    None => self.remote_vv = Some(vv),
+                }
+            }
+            SyncMessage::RequestMissing { range } => {
+                let vv = self.doc.oplog_vv();
+                let spans: Vec<IdSpan> = range
+                    .iter()
+                    .filter_map(|(&peer, &(start, end))| {
+                        let end = end.min(vv.get(&peer).copied().unwrap_or(0));
+                        (start < end).then(|| IdSpan::new(peer, start, end))
+                    })
+                    .collect();
+                if !spans.is_empty() {
+                    ans.push(SyncMessage::Updates {
+                        // Exporting updates never fails
+                        updates: self
+                            .doc
+                            .export(ExportMode::updates_in_range(spans))
+                            .unwrap(),
+                    });
+                }
+            }
+        }
+
+        Ok(ans)
+    }
+
+    /// Return the updates of the local changes that haven't been sent to the remote peer.
+    ///
+    /// Returns `None` if there is nothing to send or the version of the remote peer
+    /// is unknown yet.
+    pub fn poll(&mut self) -> Option<SyncMessage> {
+        self.remote_vv.as_ref()?;
+        let vv = self.doc.oplog_vv();
+        if self.sent_vv.includes_vv(&vv) {
+            return None;
+        }
+
+        // Exporting updates never fails
+        let updates = self.doc.export(ExportMode::updates(&self.sent_vv)).unwrap();
+        self.sent_vv.merge(&vv);
+        Some(SyncMessage::Updates { updates })
+    }
+
+    /// Whether both peers have the same version and there are no pending changes.
+    pub fn is_synced(&self) -> bool {
+        self.pending.is_none() && self.remote_vv.as_ref() == Some(&self.doc.oplog_vv())
+    }
+
+    /// The version of the remote peer reported by its last hello or ack.
+    pub fn remote_vv(&self) -> Option<&VersionVector> {
+        self.remote_vv.as_ref()
+    }
+
+    /// The changes that are received but can't be applied because their
+    /// dependencies are missing.
+    pub fn pending(&self) -> Option<&VersionRange> {
+        self.pending.as_ref()
+    }
+
+    fn request_missing(&mut self) -> SyncMessage {
+        self.missing_requests += 1;
+        if self.missing_requests > self.max_missing_requests {
+            self.missing_requests = 0;
+            return self.start();
+        }
+
+        let vv = self.doc.oplog_vv();
+        let mut range = VersionRange::new();
+        for (&peer, &(start, _)) in self.pending.iter().flat_map(|p| p.iter()) {
+            let end = vv.get(&peer).copied().unwrap_or(0);
+            if end < start {
+                range.insert(peer, end, start);
+            }
+        }
+        if let Some(remote_vv) = self.remote_vv.as_ref() {
+            for span in remote_vv.sub_iter(&vv) {
+                range.extends_to_include_id_span(span);
+            }
+        }
+
+        if range.is_empty() {
+            // The dependencies are not known by the remote peer either
+            self.missing_requests = 0;
+            return self.start();
+        }
+
+        SyncMessage::RequestMissing { range }
+    }
+}
+
+fn range_end_vv(range: &VersionRange) -> VersionVector {
+    let mut vv = VersionVector::new();
+    for (&peer, &(_, end)) in range.iter() {
+        vv.insert(peer, end);
+    }
+    vv
+}
@@ -0,0 +1,78 @@
+use loro::{Compression, EncodedBlobMode, ExportMode, ExportOptions, LoroDoc, LoroError};
+
+fn doc_with_text() -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    for i in 0..100 {
+        text.insert(text.len_unicode(), &format!("line {i}\n"))?;
+        doc.commit();
+    }
+    Ok(doc)
+}
+
+fn compressions() -> Vec<Compression> {
+    vec![
+        Compression::Lz4,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level: 3 },
+    ]
+}
+
+#[test]
+fn compressed_updates_roundtrip() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let plain = doc.export(ExportMode::all_updates())?;
+    for compression in compressions() {
+        let options = ExportOptions::new().with_compression(compression);
+        let compressed = doc.export_with(ExportMode::all_updates(), options)?;
+        assert!(compressed.len() < plain.len(), "{compression:?}");
+
+        let meta = LoroDoc::decode_import_blob_meta(&compressed, true)?;
+        assert_eq!(meta.mode, EncodedBlobMode::Updates);
+        let new_doc = LoroDoc::new();
+        new_doc.import(&compressed)?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    }
+    Ok(())
+}
+
+#[test]
+fn compressed_snapshot_roundtrip() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    for compression in compressions() {
+        let options = ExportOptions::new().with_compression(compression);
+        let snapshot = doc.export_with(ExportMode::Snapshot, options)?;
+        let new_doc = LoroDoc::from_snapshot(&snapshot)?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+        let new_doc = LoroDoc::new();
+        new_doc.import_from_reader(&mut snapshot.as_slice())?;
+        assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+        assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    }
+    Ok(())
+}
+
+#[test]
+fn no_compression_is_the_same_as_export() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let bytes = doc.export_with(ExportMode::Snapshot, ExportOptions::new())?;
+    assert_eq!(bytes, doc.export(ExportMode::Snapshot)?);
+    Ok(())
+}
+
+#[test]
+fn corrupted_compressed_blob() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let options = ExportOptions::new().with_compression(Compression::Lz4);
+    let mut bytes = doc.export_with(ExportMode::all_updates(), options)?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    let new_doc = LoroDoc::new();
+    assert_eq!(
+        new_doc.import(&bytes).unwrap_err(),
+        LoroError::DecodeChecksumMismatchError
+    );
+    Ok(())
+}
@@ -42,6 +42,7 @@ fn allow_editing_on_detached_mode_when_detached_editing_is_enabled() {
                             loro::TextDelta::Delete { delete } => {
                                 s.replace_range(index..index + delete, "");
                             }
+                            loro::TextDelta::Embed { .. } => unreachable!(),
                         }
                     }
                 }
@@ -198,6 +199,7 @@ fn allow_editing_on_detached_mode_when_detached_editing_is_enabled_2() {
                             loro::TextDelta::Delete { delete } => {
                                 s.replace_range(index..index + delete, "");
                             }
+                            loro::TextDelta::Embed { .. } => unreachable!(),
                         }
                     }
                 }
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use loro::{ExportMode, LoroDoc, LoroError};
+
+use super::gen_action;
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("loro-dir-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn flush_and_reopen() -> anyhow::Result<()> {
+    let dir = test_dir("flush_and_reopen");
+    let doc = LoroDoc::open_dir(&dir)?;
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 123, 256);
+    doc.flush()?;
+    gen_action(&doc, 321, 64);
+    doc.flush()?;
+    let value = doc.get_deep_value();
+    let vv = doc.oplog_vv();
+    drop(doc);
+
+    let doc = LoroDoc::open_dir(&dir)?;
+    assert_eq!(doc.get_deep_value(), value);
+    assert_eq!(doc.oplog_vv(), vv);
+
+    // Keep editing the reopened doc
+    doc.set_peer_id(2)?;
+    gen_action(&doc, 42, 64);
+    doc.flush()?;
+    let value = doc.get_deep_value();
+    let updates = doc.export(ExportMode::all_updates())?;
+    drop(doc);
+
+    let doc = LoroDoc::open_dir(&dir)?;
+    assert_eq!(doc.get_deep_value(), value);
+    let new_doc = LoroDoc::new();
+    new_doc.import(&updates)?;
+    assert_eq!(new_doc.get_deep_value(), value);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn unflushed_changes_are_not_persisted() -> anyhow::Result<()> {
+    let dir = test_dir("unflushed");
+    let doc = LoroDoc::open_dir(&dir)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.flush()?;
+    text.insert(5, " world")?;
+    doc.commit();
+    drop(doc);
+
+    let doc = LoroDoc::open_dir(&dir)?;
+    assert_eq!(doc.get_text("text").to_string(), "Hello");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn import_snapshot_into_dir_doc() -> anyhow::Result<()> {
+    let src = LoroDoc::new();
+    src.set_peer_id(1)?;
+    gen_action(&src, 7, 128);
+    src.commit();
+
+    let dir = test_dir("import_snapshot");
+    let doc = LoroDoc::open_dir(&dir)?;
+    doc.import(&src.export(ExportMode::Snapshot)?)?;
+    doc.flush()?;
+    drop(doc);
+
+    let doc = LoroDoc::open_dir(&dir)?;
+    assert_eq!(doc.get_deep_value(), src.get_deep_value());
+    assert_eq!(doc.oplog_vv(), src.oplog_vv());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn stale_state_is_recalculated() -> anyhow::Result<()> {
+    let dir = test_dir("stale_state");
+    let doc = LoroDoc::open_dir(&dir)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.flush()?;
+    let state = std::fs::read(dir.join("state"))?;
+    doc.get_text("text").insert(5, "!")?;
+    doc.flush()?;
+    drop(doc);
+
+    // Simulate a crash after the oplog is written but before the state is written
+    std::fs::write(dir.join("state"), state)?;
+    let doc = LoroDoc::open_dir(&dir)?;
+    assert_eq!(doc.get_text("text").to_string(), "Hello!");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn flush_without_dir() {
+    let doc = LoroDoc::new();
+    assert!(matches!(doc.flush(), Err(LoroError::ArgErr(_))));
+}
+
+#[test]
+fn corrupted_oplog_is_reported() -> anyhow::Result<()> {
+    let dir = test_dir("corrupted_oplog");
+    let doc = LoroDoc::open_dir(&dir)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.flush()?;
+    drop(doc);
+
+    for entry in std::fs::read_dir(dir.join("oplog"))? {
+        let path = entry?.path();
+        if path.extension() == Some("sst".as_ref()) {
+            let mut bytes = std::fs::read(&path)?;
+            bytes[10] ^= 1;
+            std::fs::write(&path, &bytes)?;
+        }
+    }
+
+    assert!(matches!(
+        LoroDoc::open_dir(&dir),
+        Err(LoroError::DecodeChecksumMismatchError)
+    ));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use loro::{Cipher, EncodedBlobMode, ExportMode, LoroDoc, LoroEncodeError, LoroError, LoroResult};
+
+/// A toy cipher for testing. It's NOT secure.
+///
+/// The associated data is appended to the ciphertext and checked when decrypting.
+struct XorCipher(u8);
+
+impl Cipher for XorCipher {
+    fn encrypt(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, LoroEncodeError> {
+        let mut ans: Vec<u8> = plaintext.iter().map(|b| b ^ self.0).collect();
+        ans.extend_from_slice(associated_data);
+        Ok(ans)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> LoroResult<Vec<u8>> {
+        let (body, ad) = ciphertext.split_at(ciphertext.len() - associated_data.len());
+        if ad != associated_data {
+            return Err(LoroError::DecryptionError(
+                "Associated data mismatch".into(),
+            ));
+        }
+
+        Ok(body.iter().map(|b| b ^ self.0).collect())
+    }
+}
+
+fn doc_with_text() -> anyhow::Result<LoroDoc> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "secret message")?;
+    doc.commit();
+    Ok(doc)
+}
+
+#[test]
+fn encrypted_updates_roundtrip() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let cipher = XorCipher(0x5a);
+    let plain = doc.export(ExportMode::all_updates())?;
+    let encrypted = doc.export_encrypted(ExportMode::all_updates(), &cipher)?;
+
+    // The metadata is readable without the key
+    let meta = LoroDoc::decode_import_blob_meta(&encrypted, true)?;
+    let plain_meta = LoroDoc::decode_import_blob_meta(&plain, true)?;
+    assert_eq!(meta.mode, EncodedBlobMode::Updates);
+    assert_eq!(meta.partial_end_vv, plain_meta.partial_end_vv);
+    assert_eq!(meta.change_num, plain_meta.change_num);
+    assert_eq!(meta.start_frontiers, plain_meta.start_frontiers);
+
+    let new_doc = LoroDoc::new();
+    assert_eq!(
+        new_doc.import(&encrypted).unwrap_err(),
+        LoroError::ImportEncryptedBlob
+    );
+    new_doc.import_encrypted(&encrypted, &cipher)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn encrypted_snapshot_roundtrip() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let cipher = XorCipher(0x5a);
+    let encrypted = doc.export_encrypted(ExportMode::Snapshot, &cipher)?;
+    let meta = LoroDoc::decode_import_blob_meta(&encrypted, true)?;
+    assert_eq!(meta.mode, EncodedBlobMode::Snapshot);
+
+    let new_doc = LoroDoc::new();
+    new_doc.import_encrypted(&encrypted, &cipher)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn tampered_blob_is_rejected() -> anyhow::Result<()> {
+    let doc = doc_with_text()?;
+    let cipher = XorCipher(0x5a);
+    let mut encrypted = doc.export_encrypted(ExportMode::all_updates(), &cipher)?;
+    encrypted[30] ^= 1;
+    assert_eq!(
+        LoroDoc::decode_import_blob_meta(&encrypted, true).unwrap_err(),
+        LoroError::DecodeChecksumMismatchError
+    );
+    assert_eq!(
+        LoroDoc::new()
+            .import_encrypted(&encrypted, &cipher)
+            .unwrap_err(),
+        LoroError::DecodeChecksumMismatchError
+    );
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn chacha20_poly1305_cipher() -> anyhow::Result<()> {
+    use loro::ChaCha20Poly1305Cipher;
+
+    let doc = doc_with_text()?;
+    let cipher = ChaCha20Poly1305Cipher::new(&[7; 32]);
+    let encrypted = doc.export_encrypted(ExportMode::all_updates(), &cipher)?;
+    let needle = b"secret message";
+    assert!(!encrypted.windows(needle.len()).any(|w| w == needle));
+
+    let new_doc = LoroDoc::new();
+    new_doc.import_encrypted(&encrypted, &cipher)?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+
+    let wrong_key = ChaCha20Poly1305Cipher::new(&[8; 32]);
+    assert!(matches!(
+        LoroDoc::new().import_encrypted(&encrypted, &wrong_key),
+        Err(LoroError::DecryptionError(_))
+    ));
+    Ok(())
+}
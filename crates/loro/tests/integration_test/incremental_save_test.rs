@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use loro::{ExportMode, LoroDoc};
+
+use super::gen_action;
+
+#[test]
+fn incremental_save_and_restore() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let mut storage = BTreeMap::new();
+    for seed in 0..10 {
+        gen_action(&doc, seed, 64);
+        storage.extend(doc.export_dirty_kv_entries());
+        let restored = LoroDoc::from_kv_entries(storage.clone())?;
+        assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+        assert_eq!(restored.oplog_vv(), doc.oplog_vv());
+    }
+    Ok(())
+}
+
+#[test]
+fn dirty_entries_only_contain_changed_blocks() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 1, 1000);
+    let all = doc.export_dirty_kv_entries();
+    assert!(all.len() >= 3);
+
+    // Nothing changed
+    let entries = doc.export_dirty_kv_entries();
+    let keys: Vec<_> = entries.iter().map(|(k, _)| k.as_slice()).collect();
+    assert_eq!(keys, vec![b"vv".as_slice(), b"fr".as_slice()]);
+
+    // Only the last block of the peer is touched
+    doc.set_peer_id(2)?;
+    doc.get_text("text").insert(0, "a")?;
+    let entries = doc.export_dirty_kv_entries();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(&entries[0].0[..8], &2u64.to_be_bytes());
+    Ok(())
+}
+
+#[test]
+fn incremental_save_after_import_and_export() -> anyhow::Result<()> {
+    let src = LoroDoc::new();
+    src.set_peer_id(1)?;
+    gen_action(&src, 7, 128);
+    src.commit();
+
+    let doc = LoroDoc::new();
+    doc.import(&src.export(ExportMode::Snapshot)?)?;
+    let mut storage = BTreeMap::new();
+    storage.extend(doc.export_dirty_kv_entries());
+
+    doc.set_peer_id(2)?;
+    gen_action(&doc, 8, 64);
+    // Exporting a snapshot flushes the blocks too, they must still be reported as dirty
+    doc.export(ExportMode::Snapshot)?;
+    storage.extend(doc.export_dirty_kv_entries());
+
+    let restored = LoroDoc::from_kv_entries(storage)?;
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn from_empty_kv_entries() -> anyhow::Result<()> {
+    let doc = LoroDoc::from_kv_entries(Vec::new())?;
+    assert!(doc.get_deep_value().as_map().unwrap().is_empty());
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    assert_eq!(doc.get_text("text").to_string(), "Hello");
+    Ok(())
+}
@@ -1,18 +1,43 @@
-use loro::LoroDoc;
+use loro::{LoroDoc, LoroValue};
+use rustc_hash::FxHashMap;
 
+mod compression_test;
 mod detached_editing_test;
+mod dir_storage_test;
+mod encryption_test;
 mod event_test;
+mod incremental_save_test;
 #[cfg(feature = "jsonpath")]
 mod jsonpath_test;
+mod partial_checkout_test;
+mod pending_changes_test;
 mod redact_test;
+mod rollback_test;
 mod shallow_snapshot_test;
+mod signature_test;
 mod snapshot_at_test;
+mod streaming_test;
+mod style_overlap_test;
+mod style_query_test;
+mod sync_session_test;
+mod text_annotation_test;
+mod text_embed_test;
+mod text_grapheme_test;
+mod text_lines_test;
+mod text_search_test;
 mod text_update_test;
+mod tree_test;
 mod undo_test;
 
+fn attrs(pairs: &[(&str, LoroValue)]) -> FxHashMap<String, LoroValue> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect()
+}
+
 fn gen_action(doc: &LoroDoc, seed: u64, mut ops_len: usize) {
     let mut rng = StdRng::seed_from_u64(seed);
-    use loro::LoroValue;
     use rand::prelude::*;
 
     let root_map = doc.get_map("root");
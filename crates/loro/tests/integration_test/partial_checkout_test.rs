@@ -0,0 +1,90 @@
+use loro::{ContainerID, ContainerType, ExportMode, LoroDoc, LoroError, LoroMap, ID};
+
+fn doc_with_nested_containers() -> anyhow::Result<(LoroDoc, Vec<u8>)> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let map = doc.get_map("map");
+    let child = map.insert_container("child", LoroMap::new())?;
+    child.insert("key", "value")?;
+    doc.get_text("text").insert(0, "Hello world")?;
+    let list = doc.get_list("list");
+    for i in 0..100 {
+        list.push(i)?;
+    }
+    doc.commit();
+    let snapshot = doc.export(ExportMode::Snapshot)?;
+    Ok((doc, snapshot))
+}
+
+#[test]
+fn from_snapshot_with_roots() -> anyhow::Result<()> {
+    let (doc, snapshot) = doc_with_nested_containers()?;
+    let restored = LoroDoc::from_snapshot_with_roots(&snapshot, &[doc.get_map("map").id()])?;
+    assert_eq!(
+        restored.get_map("map").get_deep_value(),
+        doc.get_map("map").get_deep_value()
+    );
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+
+    // The partially loaded doc can be edited and exported as usual
+    restored.get_text("text").insert(0, ">")?;
+    restored.commit();
+    let new_doc = LoroDoc::from_snapshot(&restored.export(ExportMode::Snapshot)?)?;
+    assert_eq!(new_doc.get_text("text").to_string(), ">Hello world");
+    Ok(())
+}
+
+#[test]
+fn from_snapshot_with_missing_roots() -> anyhow::Result<()> {
+    let (_, snapshot) = doc_with_nested_containers()?;
+    let missing = ContainerID::new_normal(ID::new(2, 0), ContainerType::Map);
+    let err = LoroDoc::from_snapshot_with_roots(&snapshot, &[missing.clone()]).unwrap_err();
+    assert_eq!(
+        err,
+        LoroError::ContainersNotFound {
+            containers: Box::new(vec![missing])
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn evict_cold_containers() -> anyhow::Result<()> {
+    let (doc, snapshot) = doc_with_nested_containers()?;
+    let restored = LoroDoc::from_snapshot(&snapshot)?;
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+    // Nothing is evicted if the budget is large enough
+    assert_eq!(restored.evict_cold_containers(usize::MAX), 0);
+    assert!(restored.evict_cold_containers(0) > 0);
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+
+    // The edits before eviction are kept
+    restored.get_list("list").push(100)?;
+    restored.get_map("map").insert("new", 1)?;
+    assert!(restored.evict_cold_containers(0) > 0);
+    assert_eq!(restored.get_list("list").len(), 101);
+    let v = restored.get_map("map").get("new").unwrap();
+    assert_eq!(v.into_value().unwrap().into_i64().unwrap(), 1);
+
+    doc.import(&restored.export(ExportMode::updates(&doc.oplog_vv()))?)?;
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn evict_keeps_hot_containers() -> anyhow::Result<()> {
+    let (doc, snapshot) = doc_with_nested_containers()?;
+    let restored = LoroDoc::from_snapshot(&snapshot)?;
+    restored.materialize_containers(&[
+        doc.get_map("map").id(),
+        doc.get_text("text").id(),
+        doc.get_list("list").id(),
+    ])?;
+    // The text is the most recently accessed container
+    assert_eq!(restored.get_text("text").to_string(), "Hello world");
+    let evicted = restored.evict_cold_containers(64);
+    assert!(evicted > 0);
+    assert_eq!(restored.get_text("text").to_string(), "Hello world");
+    assert_eq!(restored.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
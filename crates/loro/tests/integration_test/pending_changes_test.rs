@@ -0,0 +1,133 @@
+use loro::{ExportMode, IdSpan, LoroDoc, VersionRange, VersionVector};
+
+/// Returns a doc with two changes of peer 1, and the updates of the first change and
+/// the second change.
+fn two_changes() -> anyhow::Result<(LoroDoc, Vec<u8>, Vec<u8>)> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let vv = doc.oplog_vv();
+    text.insert(5, " world")?;
+    doc.commit();
+    let first = doc.export(ExportMode::updates_till(&vv))?;
+    let second = doc.export(ExportMode::updates(&vv))?;
+    Ok((doc, first, second))
+}
+
+#[test]
+fn pending_changes_summary() -> anyhow::Result<()> {
+    let (_, first, second) = two_changes()?;
+    let doc = LoroDoc::new();
+    assert!(doc.pending_changes_summary().is_empty());
+    let status = doc.import(&second)?;
+    assert!(status.pending.is_some());
+
+    let summary = doc.pending_changes_summary();
+    assert_eq!(summary.change_num, 1);
+    assert_eq!(summary.missing_deps.len(), 1);
+    assert_eq!(summary.missing_deps[&1], vec![4]);
+    let mut expected = VersionRange::new();
+    expected.insert(1, 5, 11);
+    assert_eq!(summary.pending, expected);
+
+    doc.import(&first)?;
+    assert!(doc.pending_changes_summary().is_empty());
+    assert_eq!(doc.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn export_pending_and_restore() -> anyhow::Result<()> {
+    let (_, first, second) = two_changes()?;
+    let doc = LoroDoc::new();
+    doc.import(&second)?;
+    // importing the same changes again doesn't duplicate them in the export
+    doc.import(&second)?;
+    let pending = doc.export_pending();
+
+    let restored = LoroDoc::new();
+    let status = restored.import(&pending)?;
+    assert!(status.success.is_empty());
+    assert_eq!(restored.oplog_vv(), VersionVector::default());
+    assert_eq!(
+        restored.pending_changes_summary().pending,
+        doc.pending_changes_summary().pending
+    );
+    restored.import(&first)?;
+    assert_eq!(restored.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn export_overlapping_pending_changes() -> anyhow::Result<()> {
+    let (doc, first, _) = two_changes()?;
+    let pending_doc = LoroDoc::new();
+    for span in [IdSpan::new(1, 5, 8), IdSpan::new(1, 7, 11)] {
+        pending_doc.import(&doc.export(ExportMode::updates_in_range(vec![span]))?)?;
+    }
+
+    let restored = LoroDoc::new();
+    restored.import(&pending_doc.export_pending())?;
+    let mut expected = VersionRange::new();
+    expected.insert(1, 5, 11);
+    assert_eq!(restored.pending_changes_summary().pending, expected);
+    restored.import(&first)?;
+    assert_eq!(restored.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn export_pending_of_multiple_peers() -> anyhow::Result<()> {
+    let a = LoroDoc::new();
+    a.set_peer_id(1)?;
+    let text_a = a.get_text("text");
+    for (i, s) in ["a", "b", "c", "d"].into_iter().enumerate() {
+        text_a.insert(i, s)?;
+        a.commit();
+    }
+    let b = LoroDoc::new();
+    b.set_peer_id(2)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    b.get_text("text").insert(0, "e")?;
+    b.commit();
+
+    let doc = LoroDoc::new();
+    // The pending changes of peer 1 are not continuous
+    for span in [IdSpan::new(1, 1, 2), IdSpan::new(1, 3, 4)] {
+        doc.import(&a.export(ExportMode::updates_in_range(vec![span]))?)?;
+    }
+    doc.import(&b.export(ExportMode::updates(&a.oplog_vv()))?)?;
+    let summary = doc.pending_changes_summary();
+    assert_eq!(summary.change_num, 3);
+    assert_eq!(summary.missing_deps[&1], vec![0, 2, 3]);
+
+    let restored = LoroDoc::new();
+    restored.import(&doc.export_pending())?;
+    assert_eq!(restored.pending_changes_summary().pending, summary.pending);
+    for span in [IdSpan::new(1, 0, 1), IdSpan::new(1, 2, 3)] {
+        restored.import(&a.export(ExportMode::updates_in_range(vec![span]))?)?;
+    }
+    assert!(restored.pending_changes_summary().is_empty());
+    assert_eq!(restored.get_deep_value(), b.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn clear_pending() -> anyhow::Result<()> {
+    let (_, first, second) = two_changes()?;
+    let doc = LoroDoc::new();
+    doc.import(&second)?;
+    let range = doc.clear_pending();
+    let mut expected = VersionRange::new();
+    expected.insert(1, 5, 11);
+    assert_eq!(range, expected);
+    assert!(doc.pending_changes_summary().is_empty());
+    assert!(doc.export_pending().len() < 32);
+
+    doc.import(&first)?;
+    assert_eq!(doc.get_text("text").to_string(), "Hello");
+    assert!(doc.clear_pending().is_empty());
+    Ok(())
+}
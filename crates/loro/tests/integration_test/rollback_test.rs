@@ -0,0 +1,141 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use loro::{ExportMode, LoroDoc, LoroList, ToJson, UndoManager, ID};
+use loro_internal::vv;
+use serde_json::json;
+
+use super::gen_action;
+
+#[test]
+fn rollback_reverts_pending_changes() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    let map = doc.get_map("map");
+    let list = doc.get_list("list");
+    text.insert(0, "Hello")?;
+    map.insert("a", 1)?;
+    list.push(1)?;
+    doc.commit();
+    let value = doc.get_deep_value();
+    let vv = doc.oplog_vv();
+
+    text.insert(5, " world")?;
+    text.delete(0, 2)?;
+    map.insert("a", 2)?;
+    map.insert("b", 3)?;
+    list.delete(0, 1)?;
+    list.push(2)?;
+    let sub_list = list.insert_container(0, LoroList::new())?;
+    sub_list.push("nested")?;
+    doc.rollback_pending();
+
+    assert_eq!(doc.get_pending_txn_len(), 0);
+    assert_eq!(doc.get_deep_value(), value);
+    assert_eq!(doc.oplog_vv(), vv);
+    assert_eq!(doc.state_vv(), vv);
+    Ok(())
+}
+
+#[test]
+fn rollback_reuses_op_ids() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "abc")?;
+    doc.commit();
+    text.insert(3, "def")?;
+    doc.rollback_pending();
+    text.insert(3, "g")?;
+    doc.commit();
+    assert_eq!(doc.oplog_vv(), vv!(1 => 4));
+    assert_eq!(doc.oplog_frontiers(), ID::new(1, 3).into());
+
+    let new_doc = LoroDoc::new();
+    new_doc.import(&doc.export(ExportMode::all_updates())?)?;
+    assert_eq!(new_doc.get_text("text").to_string(), "abcg");
+    Ok(())
+}
+
+#[test]
+fn rollback_emits_no_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let count = Arc::new(AtomicUsize::new(0));
+    let count_clone = count.clone();
+    let _sub = doc.subscribe_root(Arc::new(move |_| {
+        count_clone.fetch_add(1, Ordering::SeqCst);
+    }));
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.rollback_pending();
+    doc.commit();
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+
+    text.insert(0, "Hi")?;
+    doc.commit();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn rollback_is_invisible_to_undo_manager() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let mut undo = UndoManager::new(&doc);
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    text.insert(5, " world")?;
+    doc.rollback_pending();
+    text.insert(5, "!")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "Hello!");
+    undo.undo()?;
+    assert_eq!(text.to_string(), "Hello");
+    undo.undo()?;
+    assert_eq!(text.to_string(), "");
+    assert!(!undo.can_undo());
+    undo.redo()?;
+    undo.redo()?;
+    assert_eq!(text.to_string(), "Hello!");
+    Ok(())
+}
+
+#[test]
+fn rollback_on_shallow_doc() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 123, 64);
+    doc.commit();
+    let frontiers = doc.oplog_frontiers();
+    gen_action(&doc, 321, 16);
+    doc.commit();
+
+    let shallow_doc = LoroDoc::new();
+    shallow_doc.import(&doc.export(ExportMode::shallow_snapshot(&frontiers))?)?;
+    shallow_doc.set_peer_id(2)?;
+    let value = shallow_doc.get_deep_value();
+    gen_action(&shallow_doc, 42, 32);
+    shallow_doc.rollback_pending();
+    assert_eq!(shallow_doc.get_deep_value(), value);
+    assert_eq!(shallow_doc.oplog_vv(), doc.oplog_vv());
+    Ok(())
+}
+
+#[test]
+fn rollback_with_nothing_pending() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    doc.rollback_pending();
+    assert_eq!(doc.get_deep_value().to_json_value(), json!({"text": "Hello"}));
+    text.insert(5, "!")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "Hello!");
+    Ok(())
+}
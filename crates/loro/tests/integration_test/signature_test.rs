@@ -0,0 +1,175 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use loro::{
+    ChangeMeta, ExportMode, InvalidSignaturePolicy, LoroDoc, LoroError, Signer, Verifier,
+    VersionRange, VersionVector, ID,
+};
+
+/// A toy signature scheme: the signature is the hash of the key of the peer and the payload.
+struct KeyedHashSigner {
+    key: u64,
+}
+
+fn keyed_hash(key: u64, payload: &[u8]) -> Vec<u8> {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    payload.hash(&mut hasher);
+    hasher.finish().to_le_bytes().to_vec()
+}
+
+impl Signer for KeyedHashSigner {
+    fn sign(&self, _meta: &ChangeMeta, payload: &[u8]) -> Vec<u8> {
+        keyed_hash(self.key, payload)
+    }
+}
+
+/// Each peer's key is `peer * 100`.
+struct PeerKeyVerifier;
+
+impl Verifier for PeerKeyVerifier {
+    fn verify(&self, meta: &ChangeMeta, payload: &[u8], signature: Option<&[u8]>) -> bool {
+        signature == Some(&keyed_hash(meta.id.peer * 100, payload)[..])
+    }
+}
+
+fn signed_doc(peer: u64, key: u64) -> LoroDoc {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(peer).unwrap();
+    doc.set_signer(Some(Arc::new(KeyedHashSigner { key })));
+    doc
+}
+
+fn verifying_doc(policy: InvalidSignaturePolicy) -> LoroDoc {
+    let doc = LoroDoc::new();
+    doc.set_verifier(Some(Arc::new(PeerKeyVerifier)), policy);
+    doc
+}
+
+#[test]
+fn signed_changes_roundtrip() -> anyhow::Result<()> {
+    let doc = signed_doc(1, 100);
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    text.insert(5, " world")?;
+    doc.commit();
+
+    // Signed changes are not merged
+    assert_eq!(doc.len_changes(), 2);
+    let signature = doc.get_change_signature(ID::new(1, 0)).unwrap();
+
+    let updates = verifying_doc(InvalidSignaturePolicy::Reject);
+    let status = updates.import(&doc.export(ExportMode::all_updates())?)?;
+    assert!(status.quarantined.is_none());
+    assert_eq!(updates.get_text("text").to_string(), "Hello world");
+    assert_eq!(updates.get_change_signature(ID::new(1, 0)), Some(signature));
+
+    let snapshot = verifying_doc(InvalidSignaturePolicy::Reject);
+    snapshot.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(snapshot.get_text("text").to_string(), "Hello world");
+    assert!(snapshot.get_change_signature(ID::new(1, 5)).is_some());
+    Ok(())
+}
+
+#[test]
+fn signed_changes_are_exported_whole() -> anyhow::Result<()> {
+    let doc = signed_doc(1, 100);
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    // Large enough to be split into several blocks if it wasn't signed
+    let long = "a".repeat(64 * 1024);
+    text.insert(5, &long)?;
+    doc.commit();
+
+    // Exporting from the middle of a signed change exports the whole change
+    let mut vv = VersionVector::new();
+    vv.set_end(ID::new(1, 3));
+    let partial = verifying_doc(InvalidSignaturePolicy::Reject);
+    let status = partial.import(&doc.export(ExportMode::updates(&vv))?)?;
+    assert!(status.quarantined.is_none());
+    assert_eq!(partial.get_text("text").len_unicode(), 5 + long.len());
+
+    // The receiver skips the part it already has
+    let mut first = VersionVector::new();
+    first.set_end(ID::new(1, 5));
+    let a = verifying_doc(InvalidSignaturePolicy::Reject);
+    a.import(&doc.export(ExportMode::updates_till(&first))?)?;
+    let status = a.import(&doc.export(ExportMode::updates(&vv))?)?;
+    assert!(status.quarantined.is_none());
+    assert_eq!(a.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn reject_invalid_signatures() -> anyhow::Result<()> {
+    // Peer 2 signs with a key that isn't its own
+    let forged = signed_doc(2, 100);
+    forged.get_text("text").insert(0, "forged")?;
+    forged.commit();
+    let unsigned = LoroDoc::new();
+    unsigned.set_peer_id(3)?;
+    unsigned.get_text("text").insert(0, "unsigned")?;
+    unsigned.commit();
+
+    let doc = verifying_doc(InvalidSignaturePolicy::Reject);
+    let err = doc
+        .import(&forged.export(ExportMode::all_updates())?)
+        .unwrap_err();
+    assert_eq!(err, LoroError::InvalidSignature(ID::new(2, 0)));
+    let err = doc
+        .import(&unsigned.export(ExportMode::all_updates())?)
+        .unwrap_err();
+    assert_eq!(err, LoroError::InvalidSignature(ID::new(3, 0)));
+    assert!(doc.oplog_vv().is_empty());
+    assert_eq!(doc.get_text("text").to_string(), "");
+    Ok(())
+}
+
+#[test]
+fn quarantine_invalid_signatures() -> anyhow::Result<()> {
+    let a = signed_doc(1, 100);
+    a.get_text("text").insert(0, "a")?;
+    a.commit();
+    let b = signed_doc(2, 100);
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    b.get_text("text").insert(1, "b")?;
+    b.commit();
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    a.get_text("text").insert(2, "c")?;
+    a.commit();
+
+    let doc = verifying_doc(InvalidSignaturePolicy::Quarantine);
+    let status = doc.import(&a.export(ExportMode::all_updates())?)?;
+    let mut quarantined = VersionRange::new();
+    quarantined.insert(2, 0, 1);
+    assert_eq!(status.quarantined, Some(quarantined));
+    // The change depending on the quarantined change is pending
+    let mut pending = VersionRange::new();
+    pending.insert(1, 1, 2);
+    assert_eq!(status.pending, Some(pending));
+    assert_eq!(doc.get_text("text").to_string(), "a");
+    Ok(())
+}
+
+#[test]
+fn set_signature_in_pre_commit() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.set_signer(Some(Arc::new(KeyedHashSigner { key: 100 })));
+    let _sub = doc.subscribe_pre_commit(Box::new(|e| {
+        e.modifier.set_signature(b"manual");
+        true
+    }));
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    assert_eq!(
+        doc.get_change_signature(ID::new(1, 0)).as_deref(),
+        Some(&b"manual"[..])
+    );
+    Ok(())
+}
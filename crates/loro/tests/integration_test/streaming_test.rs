@@ -0,0 +1,145 @@
+use std::io::Read;
+
+use loro::{ExportMode, LoroDoc, LoroError};
+
+use super::gen_action;
+
+#[test]
+fn streamed_snapshot_is_same_as_exported() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 123, 256);
+    doc.commit();
+    let frontiers = doc.oplog_frontiers();
+    gen_action(&doc, 321, 64);
+    doc.commit();
+
+    for mode in [
+        ExportMode::Snapshot,
+        ExportMode::all_updates(),
+        ExportMode::shallow_snapshot(&frontiers),
+        ExportMode::state_only(None),
+        ExportMode::snapshot_at(&frontiers),
+    ] {
+        let mut streamed = Vec::new();
+        doc.export_to_writer(mode.clone(), &mut streamed)?;
+        assert_eq!(streamed, doc.export(mode)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn import_snapshot_from_reader() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 42, 128);
+    doc.commit();
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+
+    let new_doc = LoroDoc::new();
+    new_doc.import_from_reader(&mut buf.as_slice())?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    assert_eq!(new_doc.oplog_vv(), doc.oplog_vv());
+    Ok(())
+}
+
+#[test]
+fn import_snapshot_from_reader_into_non_empty_doc() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    gen_action(&doc, 42, 128);
+    doc.commit();
+
+    let other = LoroDoc::new();
+    other.set_peer_id(2)?;
+    gen_action(&other, 7, 32);
+    other.commit();
+
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+    other.import_from_reader(&mut buf.as_slice())?;
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    assert_eq!(other.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn reader_is_not_read_beyond_snapshot() -> anyhow::Result<()> {
+    let a = LoroDoc::new();
+    a.get_text("text").insert(0, "Hello")?;
+    let b = LoroDoc::new();
+    b.get_map("map").insert("key", 1)?;
+
+    let mut buf = Vec::new();
+    a.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+    b.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+    buf.extend_from_slice(b"trailing");
+
+    let mut reader = buf.as_slice();
+    let new_a = LoroDoc::new();
+    new_a.import_from_reader(&mut reader)?;
+    let new_b = LoroDoc::new();
+    new_b.import_from_reader(&mut reader)?;
+    assert_eq!(new_a.get_deep_value(), a.get_deep_value());
+    assert_eq!(new_b.get_deep_value(), b.get_deep_value());
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest)?;
+    assert_eq!(rest, b"trailing");
+    Ok(())
+}
+
+#[test]
+fn import_updates_from_reader() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    gen_action(&doc, 1, 64);
+    doc.commit();
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::all_updates(), &mut buf)?;
+
+    let new_doc = LoroDoc::new();
+    new_doc.import_from_reader(&mut buf.as_slice())?;
+    assert_eq!(new_doc.get_deep_value(), doc.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn import_corrupted_snapshot_from_reader() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.get_text("text").insert(0, "Hello")?;
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::Snapshot, &mut buf)?;
+
+    let new_doc = LoroDoc::new();
+    assert!(new_doc
+        .import_from_reader(&mut &buf[..buf.len() - 1])
+        .is_err());
+    // flip a byte in the oplog bytes
+    buf[30] ^= 0xff;
+    assert_eq!(
+        new_doc.import_from_reader(&mut buf.as_slice()),
+        Err(LoroError::DecodeChecksumMismatchError)
+    );
+    assert!(new_doc.oplog_frontiers().is_empty());
+    Ok(())
+}
+
+#[test]
+fn import_corrupted_updates_from_reader() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    gen_action(&doc, 1, 64);
+    doc.commit();
+    let mut buf = Vec::new();
+    doc.export_to_writer(ExportMode::all_updates(), &mut buf)?;
+
+    let new_doc = LoroDoc::new();
+    assert!(new_doc
+        .import_from_reader(&mut &buf[..buf.len() - 1])
+        .is_err());
+    let last = buf.len() - 1;
+    buf[last] ^= 0xff;
+    assert!(new_doc.import_from_reader(&mut buf.as_slice()).is_err());
+    // Nothing is imported before the checksum of all the blocks is checked
+    assert!(new_doc.oplog_frontiers().is_empty());
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{
+    event::Diff, ExportMode, LoroDoc, LoroValue, OverlapPolicy, StyleConfig, StyleConfigMap,
+    TextDelta,
+};
+use pretty_assertions::assert_eq;
+
+use super::attrs;
+
+fn new_doc() -> LoroDoc {
+    let mut styles = StyleConfigMap::default_rich_text_config();
+    styles.insert(
+        "link".into(),
+        StyleConfig::new().overlap(OverlapPolicy::Exclusive),
+    );
+    styles.insert("code".into(), StyleConfig::new().incompatible(["bold"]));
+    let doc = LoroDoc::new();
+    doc.config_text_style(styles);
+    doc
+}
+
+fn sync(a: &LoroDoc, b: &LoroDoc) -> anyhow::Result<()> {
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+    Ok(())
+}
+
+#[test]
+fn exclusive_marks_trim_older_ones() -> anyhow::Result<()> {
+    let doc = new_doc();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    text.mark(0..5, "link:a", "a")?;
+    text.mark(3..8, "link:b", "b")?;
+    assert_eq!(text.find_style_ranges("link:a"), vec![(0..3, "a".into())]);
+    assert_eq!(text.find_style_ranges("link:b"), vec![(3..8, "b".into())]);
+
+    text.mark(6..11, "link", "c")?;
+    assert_eq!(text.find_style_ranges("link:b"), vec![(3..6, "b".into())]);
+    assert_eq!(text.find_style_ranges("link"), vec![(6..11, "c".into())]);
+
+    // Marks of the other keys can still overlap
+    text.mark(0..5, "comment:a", "a")?;
+    text.mark(3..8, "comment:b", "b")?;
+    assert_eq!(
+        text.get_styles_at(4)?,
+        attrs(&[
+            ("link:b", "b".into()),
+            ("comment:a", "a".into()),
+            ("comment:b", "b".into())
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn incompatible_marks_trim_older_ones() -> anyhow::Result<()> {
+    let doc = new_doc();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    text.mark(0..11, "bold", true)?;
+    text.mark(3..6, "code", true)?;
+    assert_eq!(
+        text.find_style_ranges("bold"),
+        vec![(0..3, true.into()), (6..11, true.into())]
+    );
+    assert_eq!(text.find_style_ranges("code"), vec![(3..6, true.into())]);
+
+    // It's symmetric, the latest one wins
+    text.mark(4..5, "bold", true)?;
+    assert_eq!(
+        text.find_style_ranges("code"),
+        vec![(3..4, true.into()), (5..6, true.into())]
+    );
+
+    // Unmarking the newer style doesn't bring the trimmed one back
+    text.unmark(3..6, "code")?;
+    assert!(text.find_style_ranges("code").is_empty());
+    assert_eq!(
+        text.find_style_ranges("bold"),
+        vec![
+            (0..3, true.into()),
+            (4..5, true.into()),
+            (6..11, true.into())
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn concurrent_incompatible_marks_converge() -> anyhow::Result<()> {
+    let a = new_doc();
+    a.set_peer_id(1)?;
+    let b = new_doc();
+    b.set_peer_id(2)?;
+    a.get_text("text").insert(0, "Hello world")?;
+    sync(&a, &b)?;
+
+    a.get_text("text").mark(0..11, "bold", true)?;
+    b.get_text("text").mark(3..6, "code", true)?;
+    sync(&a, &b)?;
+
+    // The marks have the same lamport, so the one of the larger peer wins
+    for doc in [&a, &b] {
+        let text = doc.get_text("text");
+        assert_eq!(
+            text.find_style_ranges("bold"),
+            vec![(0..3, true.into()), (6..11, true.into())]
+        );
+        assert_eq!(text.find_style_ranges("code"), vec![(3..6, true.into())]);
+    }
+    assert_eq!(a.get_text("text").to_delta(), b.get_text("text").to_delta());
+
+    let snapshot = new_doc();
+    snapshot.import(&a.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        snapshot.get_text("text").to_delta(),
+        a.get_text("text").to_delta()
+    );
+    Ok(())
+}
+
+#[test]
+fn trimmed_styles_in_events() -> anyhow::Result<()> {
+    let doc = new_doc();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    text.mark(0..5, "bold", true)?;
+    doc.commit();
+    let other = new_doc();
+    other.import(&doc.export(ExportMode::Snapshot)?)?;
+
+    let deltas = Arc::new(Mutex::new(Vec::new()));
+    let mut subs = Vec::new();
+    for doc in [&doc, &other] {
+        let deltas = deltas.clone();
+        subs.push(doc.subscribe_root(Arc::new(move |e| {
+            for e in e.events {
+                if let Diff::Text(delta) = e.diff {
+                    deltas.lock().unwrap().push(delta);
+                }
+            }
+        })));
+    }
+
+    text.mark(2..4, "code", true)?;
+    doc.commit();
+    // Remote peers receive the same event
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+    let expected = vec![
+        TextDelta::Retain {
+            retain: 2,
+            attributes: None,
+        },
+        TextDelta::Retain {
+            retain: 2,
+            attributes: Some(attrs(&[("code", true.into()), ("bold", LoroValue::Null)])),
+        },
+    ];
+    assert_eq!(
+        deltas.lock().unwrap().as_slice(),
+        &[expected.clone(), expected]
+    );
+    Ok(())
+}
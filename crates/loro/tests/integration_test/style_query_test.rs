@@ -0,0 +1,162 @@
+use loro::{LoroDoc, LoroError, LoroText};
+use pretty_assertions::assert_eq;
+
+use super::attrs;
+
+#[test]
+fn query_styles_in_different_indexes() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "a😀中b")?;
+    text.mark(1..3, "bold", true)?;
+    text.mark(2..4, "link", "https://loro.dev")?;
+
+    assert_eq!(text.find_style_ranges("bold"), vec![(1..3, true.into())]);
+    assert_eq!(
+        text.find_style_ranges_utf8("bold"),
+        vec![(1..8, true.into())]
+    );
+    assert_eq!(
+        text.find_style_ranges_utf16("bold"),
+        vec![(1..4, true.into())]
+    );
+    assert_eq!(
+        text.find_style_ranges_utf16("link"),
+        vec![(3..5, "https://loro.dev".into())]
+    );
+    assert!(text.find_style_ranges("italic").is_empty());
+
+    assert_eq!(text.get_styles_at(0)?, attrs(&[]));
+    assert_eq!(text.get_styles_at(1)?, attrs(&[("bold", true.into())]));
+    assert_eq!(
+        text.get_styles_at_utf8(6)?,
+        attrs(&[("bold", true.into()), ("link", "https://loro.dev".into())])
+    );
+    assert_eq!(
+        text.get_styles_at_utf16(4)?,
+        attrs(&[("link", "https://loro.dev".into())])
+    );
+
+    assert_eq!(
+        text.styles_in_range(0..3)?,
+        vec![
+            (1..2, attrs(&[("bold", true.into())])),
+            (
+                2..3,
+                attrs(&[("bold", true.into()), ("link", "https://loro.dev".into())])
+            ),
+        ]
+    );
+    assert_eq!(
+        text.styles_in_range_utf8(3..9)?,
+        vec![
+            (3..5, attrs(&[("bold", true.into())])),
+            (
+                5..8,
+                attrs(&[("bold", true.into()), ("link", "https://loro.dev".into())])
+            ),
+            (8..9, attrs(&[("link", "https://loro.dev".into())])),
+        ]
+    );
+    assert_eq!(
+        text.styles_in_range_utf16(4..5)?,
+        vec![(4..5, attrs(&[("link", "https://loro.dev".into())]))]
+    );
+    Ok(())
+}
+
+#[test]
+fn style_ranges_merge_by_value() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "abcdef")?;
+    text.mark(0..2, "link", "x")?;
+    text.mark(2..4, "link", "x")?;
+    text.mark(4..6, "link", "y")?;
+    assert_eq!(
+        text.find_style_ranges("link"),
+        vec![(0..4, "x".into()), (4..6, "y".into())]
+    );
+
+    // Unmarked text doesn't have the style anymore
+    text.unmark(1..3, "link")?;
+    assert_eq!(
+        text.find_style_ranges("link"),
+        vec![(0..1, "x".into()), (3..4, "x".into()), (4..6, "y".into())]
+    );
+    assert_eq!(text.get_styles_at(2)?, attrs(&[]));
+    assert_eq!(
+        text.styles_in_range(0..4)?,
+        vec![
+            (0..1, attrs(&[("link", "x".into())])),
+            (3..4, attrs(&[("link", "x".into())])),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn query_styles_of_embeds_and_annotations() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "ab\n")?;
+    text.insert_embed(1, "image.png")?;
+    text.mark(0..3, "bold", true)?;
+    text.annotate(0..2, "comment")?;
+
+    // The reserved styles are not included
+    assert_eq!(text.get_styles_at(1)?, attrs(&[("bold", true.into())]));
+    assert_eq!(text.get_styles_at(3)?, attrs(&[]));
+    assert!(text.find_style_ranges("$embed").is_empty());
+    assert_eq!(
+        text.styles_in_range(0..4)?,
+        vec![(0..3, attrs(&[("bold", true.into())]))]
+    );
+    Ok(())
+}
+
+#[test]
+fn query_styles_of_detached_text() -> anyhow::Result<()> {
+    let text = LoroText::new();
+    text.insert(0, "Hello world")?;
+    text.mark(0..5, "bold", true)?;
+    assert_eq!(text.find_style_ranges("bold"), vec![(0..5, true.into())]);
+    assert_eq!(text.get_styles_at(4)?, attrs(&[("bold", true.into())]));
+
+    let doc = LoroDoc::new();
+    let text = doc.get_map("map").insert_container("text", text)?;
+    assert_eq!(text.find_style_ranges("bold"), vec![(0..5, true.into())]);
+    assert_eq!(
+        text.styles_in_range(3..7)?,
+        vec![(3..5, attrs(&[("bold", true.into())]))]
+    );
+    Ok(())
+}
+
+#[test]
+fn query_styles_out_of_bound() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "a😀")?;
+    assert!(matches!(
+        text.get_styles_at(2),
+        Err(LoroError::OutOfBound { .. })
+    ));
+    assert!(text.get_styles_at_utf8(4).is_ok());
+    assert!(matches!(
+        text.get_styles_at_utf8(5),
+        Err(LoroError::OutOfBound { .. })
+    ));
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 2..1;
+    assert!(matches!(
+        text.styles_in_range(reversed),
+        Err(LoroError::EndIndexLessThanStartIndex { .. })
+    ));
+    assert!(matches!(
+        text.styles_in_range_utf16(0..4),
+        Err(LoroError::OutOfBound { .. })
+    ));
+    assert!(text.styles_in_range_utf16(0..3)?.is_empty());
+    Ok(())
+}
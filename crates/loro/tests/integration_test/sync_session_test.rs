@@ -0,0 +1,202 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use loro::{
+    sync_session::{SyncMessage, SyncSession},
+    ExportMode, LoroDoc, VersionRange, ID,
+};
+
+use super::gen_action;
+
+/// Deliver the messages between the two sessions through an in-process channel pair
+/// until both sides are idle.
+fn run(
+    a: &mut SyncSession,
+    b: &mut SyncSession,
+    (a_tx, a_rx): &(Sender<Vec<u8>>, Receiver<Vec<u8>>),
+    (b_tx, b_rx): &(Sender<Vec<u8>>, Receiver<Vec<u8>>),
+) -> anyhow::Result<()> {
+    loop {
+        let mut idle = true;
+        while let Ok(bytes) = a_rx.try_recv() {
+            idle = false;
+            for msg in a.handle(SyncMessage::decode(&bytes)?)? {
+                b_tx.send(msg.encode())?;
+            }
+        }
+        while let Ok(bytes) = b_rx.try_recv() {
+            idle = false;
+            for msg in b.handle(SyncMessage::decode(&bytes)?)? {
+                a_tx.send(msg.encode())?;
+            }
+        }
+        if idle {
+            return Ok(());
+        }
+    }
+}
+
+#[test]
+fn sync_two_docs_through_channels() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    gen_action(&doc_a, 1, 64);
+    doc_a.commit();
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    gen_action(&doc_b, 2, 64);
+    doc_b.commit();
+
+    let to_a = channel();
+    let to_b = channel();
+    let mut a = SyncSession::new(&doc_a);
+    let mut b = SyncSession::new(&doc_b);
+    to_b.0.send(a.start().encode())?;
+    run(&mut a, &mut b, &to_a, &to_b)?;
+    assert!(a.is_synced());
+    assert!(b.is_synced());
+    assert_eq!(doc_a.get_deep_value(), doc_b.get_deep_value());
+
+    // local changes after the handshake
+    gen_action(&doc_a, 3, 32);
+    doc_a.commit();
+    assert!(!a.is_synced());
+    to_b.0.send(a.poll().unwrap().encode())?;
+    assert!(a.poll().is_none());
+    run(&mut a, &mut b, &to_a, &to_b)?;
+    assert!(a.is_synced());
+    assert!(b.is_synced());
+    assert_eq!(doc_a.get_deep_value(), doc_b.get_deep_value());
+    Ok(())
+}
+
+#[test]
+fn request_missing_dependencies() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let text = doc_a.get_text("text");
+    text.insert(0, "Hello")?;
+    doc_a.commit();
+    let vv = doc_a.oplog_vv();
+    text.insert(5, " world")?;
+    doc_a.commit();
+
+    let doc_b = LoroDoc::new();
+    let mut a = SyncSession::new(&doc_a);
+    let mut b = SyncSession::new(&doc_b);
+    let hello = b.start();
+    a.handle(hello)?;
+    // The first part of the history is lost
+    let replies = b.handle(SyncMessage::Updates {
+        updates: doc_a.export(ExportMode::updates(&vv))?,
+    })?;
+    assert!(b.pending().is_some());
+    let Some(SyncMessage::RequestMissing { range }) = replies.last().cloned() else {
+        panic!("expected a request for the missing range, got {replies:?}");
+    };
+    let mut expected = VersionRange::new();
+    expected.insert(1, 0, 5);
+    assert_eq!(range, expected);
+
+    for msg in a.handle(SyncMessage::RequestMissing { range })? {
+        b.handle(msg)?;
+    }
+    assert!(b.pending().is_none());
+    assert_eq!(doc_b.get_text("text").to_string(), "Hello world");
+    Ok(())
+}
+
+#[test]
+fn pending_ranges_are_merged() -> anyhow::Result<()> {
+    let mut docs = Vec::new();
+    for peer in [1, 3] {
+        let doc = LoroDoc::new();
+        doc.set_peer_id(peer)?;
+        let text = doc.get_text("text");
+        text.insert(0, "Hello")?;
+        doc.commit();
+        let vv = doc.oplog_vv();
+        text.insert(5, " world")?;
+        doc.commit();
+        docs.push((doc, vv));
+    }
+
+    let doc_b = LoroDoc::new();
+    let mut b = SyncSession::new(&doc_b);
+    for (doc, vv) in docs.iter() {
+        b.handle(SyncMessage::Updates {
+            updates: doc.export(ExportMode::updates(vv))?,
+        })?;
+    }
+
+    // The pending changes of both imports are kept
+    let pending = b.pending().unwrap();
+    assert!(pending.get(&1).is_some());
+    assert!(pending.get(&3).is_some());
+    Ok(())
+}
+
+#[test]
+fn resync_after_too_many_missing_requests() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let text = doc_a.get_text("text");
+    text.insert(0, "Hello")?;
+    doc_a.commit();
+    let vv = doc_a.oplog_vv();
+    text.insert(5, " world")?;
+    doc_a.commit();
+    let updates = doc_a.export(ExportMode::updates(&vv))?;
+
+    let doc_b = LoroDoc::new();
+    let mut b = SyncSession::new(&doc_b);
+    b.set_max_missing_requests(1);
+    b.start();
+    b.handle(SyncMessage::Hello {
+        vv: doc_a.oplog_vv(),
+        frontiers: doc_a.oplog_frontiers(),
+    })?;
+    let replies = b.handle(SyncMessage::Updates {
+        updates: updates.clone(),
+    })?;
+    assert!(matches!(
+        replies.last(),
+        Some(SyncMessage::RequestMissing { .. })
+    ));
+    let replies = b.handle(SyncMessage::Updates { updates })?;
+    assert_eq!(
+        replies.last(),
+        Some(&SyncMessage::Hello {
+            vv: Default::default(),
+            frontiers: Default::default(),
+        })
+    );
+    assert!(!b.is_synced());
+    Ok(())
+}
+
+#[test]
+fn encode_and_decode_messages() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    doc.get_text("text").insert(0, "Hello")?;
+    doc.commit();
+    let mut range = VersionRange::new();
+    range.insert(1, 0, 5);
+    range.insert(2, 3, 10);
+    for msg in [
+        SyncMessage::Hello {
+            vv: doc.oplog_vv(),
+            frontiers: ID::new(1, 4).into(),
+        },
+        SyncMessage::Updates {
+            updates: doc.export(ExportMode::all_updates())?,
+        },
+        SyncMessage::Ack { vv: doc.oplog_vv() },
+        SyncMessage::RequestMissing { range },
+    ] {
+        assert_eq!(SyncMessage::decode(&msg.encode())?, msg);
+    }
+    assert!(SyncMessage::decode(&[]).is_err());
+    assert!(SyncMessage::decode(&[0, 1, 2]).is_err());
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{AnnotationEvent, ExportMode, LoroDoc, LoroError, LoroText, LoroValue};
+use pretty_assertions::assert_eq;
+
+fn ranges(text: &LoroText) -> Vec<(std::ops::Range<usize>, LoroValue)> {
+    text.get_annotations()
+        .into_iter()
+        .map(|x| (x.range, x.value))
+        .collect()
+}
+
+#[test]
+fn annotation_collapses_when_text_is_deleted() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    let id = text.annotate(6..11, "comment")?;
+    assert_eq!(ranges(&text), vec![(6..11, "comment".into())]);
+
+    // Text inserted at the ends is not annotated, but text inserted inside is
+    text.insert(11, "!")?;
+    text.insert(6, "big ")?;
+    text.insert(12, "_")?;
+    assert_eq!(text.to_string(), "Hello big wo_rld!");
+    assert_eq!(ranges(&text), vec![(10..16, "comment".into())]);
+
+    text.delete(8, 9)?;
+    assert_eq!(text.to_string(), "Hello bi");
+    let annotations = text.get_annotations();
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].id, id);
+    assert_eq!(annotations[0].range, 8..8);
+    assert_eq!(doc.get_cursor_pos(&annotations[0].start)?.current.pos, 8);
+    assert_eq!(doc.get_cursor_pos(&annotations[0].end)?.current.pos, 8);
+
+    // A collapsed annotation stays collapsed
+    text.insert(8, "g")?;
+    assert_eq!(text.get_annotations()[0].range.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn annotation_survives_concurrent_deletion() -> anyhow::Result<()> {
+    let a = LoroDoc::new();
+    a.set_peer_id(1)?;
+    a.get_text("text").insert(0, "Hello world")?;
+    a.commit();
+    let b = a.fork();
+    b.set_peer_id(2)?;
+
+    a.get_text("text").annotate(0..5, true)?;
+    b.get_text("text").delete(0, 6)?;
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+
+    assert_eq!(a.get_text("text").to_string(), "world");
+    assert_eq!(ranges(&a.get_text("text")), vec![(0..0, true.into())]);
+    assert_eq!(
+        a.get_text("text").get_annotations(),
+        b.get_text("text").get_annotations()
+    );
+
+    let snapshot = LoroDoc::new();
+    snapshot.import(&a.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        snapshot.get_text("text").get_annotations(),
+        a.get_text("text").get_annotations()
+    );
+    Ok(())
+}
+
+#[test]
+fn remove_annotation() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    let hello = text.annotate(0..5, "a")?;
+    let world = text.annotate(6..11, "b")?;
+    assert_eq!(ranges(&text), vec![(0..5, "a".into()), (6..11, "b".into())]);
+
+    text.remove_annotation(&hello)?;
+    assert_eq!(ranges(&text), vec![(6..11, "b".into())]);
+
+    // Collapsed annotations can be removed too
+    text.delete(5, 6)?;
+    assert_eq!(ranges(&text), vec![(5..5, "b".into())]);
+    text.remove_annotation(&world)?;
+    assert!(text.get_annotations().is_empty());
+
+    // The annotation keys are reserved
+    assert!(matches!(
+        text.mark(0..1, &format!("$annotation:{world}"), "c"),
+        Err(LoroError::ArgErr(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn annotation_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    doc.commit();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+    let _sub = text.subscribe_annotations(Arc::new(move |e| {
+        events_clone.lock().unwrap().push(e.to_vec());
+    }))?;
+
+    let id = text.annotate(6..11, "comment")?;
+    doc.commit();
+    let take = || std::mem::take(&mut *events.lock().unwrap());
+    let batches = take();
+    assert_eq!(batches.len(), 1);
+    assert!(matches!(
+        &batches[0][..],
+        [AnnotationEvent::Updated(x)] if x.id == id && x.range == (6..11)
+    ));
+
+    // Edits that don't move the annotation emit nothing
+    text.insert(11, "!")?;
+    doc.commit();
+    assert!(take().is_empty());
+
+    text.delete(0, 11)?;
+    doc.commit();
+    let batches = take();
+    assert!(matches!(
+        &batches[..],
+        [batch] if matches!(&batch[..], [AnnotationEvent::Updated(x)] if x.range == (0..0))
+    ));
+
+    // Remote changes emit events too
+    let other = LoroDoc::new();
+    other.import(&doc.export(ExportMode::Snapshot)?)?;
+    other.get_text("text").remove_annotation(&id)?;
+    other.commit();
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    assert_eq!(take(), vec![vec![AnnotationEvent::Removed(id)]]);
+    Ok(())
+}
+
+#[test]
+fn detached_text_has_no_annotations() -> anyhow::Result<()> {
+    let text = LoroText::new();
+    text.insert(0, "Hello")?;
+    assert!(matches!(
+        text.annotate(0..5, "comment"),
+        Err(LoroError::MisuseDetachedContainer { .. })
+    ));
+    assert!(text.get_annotations().is_empty());
+    Ok(())
+}
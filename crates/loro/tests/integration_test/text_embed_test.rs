@@ -0,0 +1,214 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{
+    event::Diff, ContainerTrait, ExportMode, Index, LoroDoc, LoroError, LoroMap, LoroValue,
+    TextDelta, ToJson, VersionVector,
+};
+use pretty_assertions::assert_eq;
+use rustc_hash::FxHashMap;
+use serde_json::json;
+
+fn embed(value: impl Into<LoroValue>) -> TextDelta {
+    TextDelta::Embed {
+        embed: value.into(),
+        attributes: None,
+    }
+}
+
+fn insert(s: &str) -> TextDelta {
+    TextDelta::Insert {
+        insert: s.to_string(),
+        attributes: None,
+    }
+}
+
+#[test]
+fn insert_embed() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    text.insert_embed(1, "image.png")?;
+    assert_eq!(text.to_string(), "a\u{FFFC}b");
+    assert_eq!(text.len_unicode(), 3);
+    assert_eq!(
+        text.to_delta(),
+        vec![insert("a"), embed("image.png"), insert("b")]
+    );
+    assert_eq!(
+        text.get_richtext_value().to_json_value(),
+        json!([{ "insert": "a" }, { "embed": "image.png" }, { "insert": "b" }])
+    );
+
+    // Adjacent embeds are not merged, and other styles still apply to them
+    text.insert_embed(2, "video.mp4")?;
+    text.mark(0..4, "bold", true)?;
+    let bold = Some(FxHashMap::from_iter([("bold".to_string(), true.into())]));
+    assert_eq!(
+        text.to_delta(),
+        vec![
+            TextDelta::Insert {
+                insert: "a".to_string(),
+                attributes: bold.clone(),
+            },
+            TextDelta::Embed {
+                embed: "image.png".into(),
+                attributes: bold.clone(),
+            },
+            TextDelta::Embed {
+                embed: "video.mp4".into(),
+                attributes: bold.clone(),
+            },
+            TextDelta::Insert {
+                insert: "b".to_string(),
+                attributes: bold,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn embed_style_key_is_reserved() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    assert!(matches!(
+        text.mark(0..1, "$embed", "image.png"),
+        Err(LoroError::ArgErr(_))
+    ));
+    assert!(matches!(
+        text.unmark(0..1, "$embed"),
+        Err(LoroError::ArgErr(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn embed_survives_concurrent_edits() -> anyhow::Result<()> {
+    let a = LoroDoc::new();
+    a.set_peer_id(1)?;
+    a.get_text("text").insert(0, "ab")?;
+    a.commit();
+    let b = a.fork();
+    b.set_peer_id(2)?;
+
+    a.get_text("text").insert_embed(1, "image.png")?;
+    b.get_text("text").insert(0, "X")?;
+    b.get_text("text").insert(3, "Y")?;
+    a.import(&b.export(ExportMode::all_updates())?)?;
+    b.import(&a.export(ExportMode::all_updates())?)?;
+
+    let expected = vec![insert("Xa"), embed("image.png"), insert("bY")];
+    assert_eq!(a.get_text("text").to_delta(), expected);
+    assert_eq!(b.get_text("text").to_delta(), expected);
+
+    // Text inserted right after the embed doesn't become a part of it
+    a.get_text("text").insert(3, "W")?;
+    assert_eq!(
+        a.get_text("text").to_delta(),
+        vec![insert("Xa"), embed("image.png"), insert("WbY")]
+    );
+    Ok(())
+}
+
+#[test]
+fn embed_roundtrip() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world")?;
+    text.insert_embed(5, LoroValue::from(vec![1, 2, 3]))?;
+    text.mark(0..6, "bold", true)?;
+    doc.commit();
+    let expected = text.to_delta();
+
+    let snapshot = LoroDoc::new();
+    snapshot.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(snapshot.get_text("text").to_delta(), expected);
+
+    let json = LoroDoc::new();
+    json.import_json_updates(doc.export_json_updates(&VersionVector::default(), &doc.oplog_vv()))?;
+    assert_eq!(json.get_text("text").to_delta(), expected);
+    Ok(())
+}
+
+#[test]
+fn embed_in_text_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let deltas = Arc::new(Mutex::new(Vec::new()));
+    let deltas_clone = deltas.clone();
+    let _sub = doc.subscribe_root(Arc::new(move |e| {
+        for e in e.events {
+            if let Diff::Text(delta) = e.diff {
+                deltas_clone.lock().unwrap().push(delta);
+            }
+        }
+    }));
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    doc.commit();
+    text.insert_embed(1, "image.png")?;
+    doc.commit();
+    assert_eq!(
+        deltas.lock().unwrap().last().unwrap(),
+        &vec![
+            TextDelta::Retain {
+                retain: 1,
+                attributes: None,
+            },
+            embed("image.png"),
+        ]
+    );
+
+    // Applying the event to another doc reproduces the embed
+    let other = LoroDoc::new();
+    let other_text = other.get_text("text");
+    for delta in deltas.lock().unwrap().iter() {
+        other_text.apply_delta(delta)?;
+    }
+    assert_eq!(other_text.to_delta(), text.to_delta());
+    Ok(())
+}
+
+#[test]
+fn embed_container() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "ab")?;
+    let map = text.insert_embed_container(1, LoroMap::new())?;
+    map.insert("src", "image.png")?;
+    doc.commit();
+    assert_eq!(
+        text.to_delta(),
+        vec![insert("a"), embed(map.id()), insert("b")]
+    );
+    assert_eq!(
+        doc.get_path_to_container(&map.id()).unwrap(),
+        vec![
+            (text.id(), Index::Key("text".into())),
+            (map.id(), Index::Seq(1))
+        ]
+    );
+
+    let other = LoroDoc::new();
+    other.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        other.get_path_to_container(&map.id()).unwrap(),
+        vec![
+            (text.id(), Index::Key("text".into())),
+            (map.id(), Index::Seq(1))
+        ]
+    );
+    assert_eq!(other.get_text("text").to_delta(), text.to_delta());
+    assert_eq!(
+        other.get_map(map.id()).get_deep_value().to_json_value(),
+        json!({ "src": "image.png" })
+    );
+
+    // Deleting the embed char deletes the embedded container
+    text.delete(1, 1)?;
+    doc.commit();
+    assert!(map.is_deleted());
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+    assert!(other.get_map(map.id()).is_deleted());
+    Ok(())
+}
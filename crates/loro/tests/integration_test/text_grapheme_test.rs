@@ -0,0 +1,116 @@
+use loro::{cursor::Side, ExportMode, LoroDoc, LoroText};
+use pretty_assertions::assert_eq;
+
+const FAMILY: &str = "👨\u{200d}👩\u{200d}👧";
+
+#[test]
+fn edit_by_grapheme() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    assert_eq!(text.len_grapheme(), 0);
+    text.insert(0, &format!("a{FAMILY}e\u{301}b"))?;
+    assert_eq!(text.len_unicode(), 9);
+    assert_eq!(text.len_grapheme(), 4);
+    assert_eq!(text.slice_grapheme(1, 2)?, FAMILY);
+    assert_eq!(text.slice_grapheme(2, 4)?, "e\u{301}b");
+
+    text.insert_grapheme(2, "!")?;
+    assert_eq!(text.to_string(), format!("a{FAMILY}!e\u{301}b"));
+    text.delete_grapheme(3, 1)?;
+    assert_eq!(text.to_string(), format!("a{FAMILY}!b"));
+    text.delete_grapheme(0, 2)?;
+    assert_eq!(text.to_string(), "!b");
+    Ok(())
+}
+
+#[test]
+fn grapheme_across_style_anchors() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, &format!("ae\u{301}{FAMILY}b"))?;
+    // The style anchors split the text chunks inside the clusters
+    text.mark(1..2, "bold", true)?;
+    text.mark(3..5, "italic", true)?;
+    assert_eq!(text.len_grapheme(), 4);
+    assert_eq!(text.slice_grapheme(1, 3)?, format!("e\u{301}{FAMILY}"));
+    text.insert_grapheme(3, "!")?;
+    assert_eq!(text.to_string(), format!("ae\u{301}{FAMILY}!b"));
+    text.delete_grapheme(1, 2)?;
+    assert_eq!(text.to_string(), "a!b");
+    Ok(())
+}
+
+#[test]
+fn grapheme_out_of_bound() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, &format!("a{FAMILY}"))?;
+    assert!(text.insert_grapheme(3, "x").is_err());
+    assert!(text.delete_grapheme(1, 2).is_err());
+    assert!(text.slice_grapheme(0, 3).is_err());
+    assert!(text.slice_grapheme(2, 1).is_err());
+    assert!(text.get_cursor_grapheme(3, Side::Left).is_none());
+    assert_eq!(text.to_string(), format!("a{FAMILY}"));
+    Ok(())
+}
+
+#[test]
+fn grapheme_cursor() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, &format!("a{FAMILY}b"))?;
+    let cursor = text.get_cursor_grapheme(2, Side::Left).unwrap();
+    assert_eq!(doc.get_cursor_pos(&cursor)?.current.pos, 6);
+    text.insert_grapheme(0, FAMILY)?;
+    assert_eq!(doc.get_cursor_pos(&cursor)?.current.pos, 11);
+    Ok(())
+}
+
+#[test]
+fn grapheme_in_detached_text() -> anyhow::Result<()> {
+    let text = LoroText::new();
+    text.insert(0, &format!("{FAMILY}{FAMILY}"))?;
+    assert_eq!(text.len_grapheme(), 2);
+    text.insert_grapheme(1, "x")?;
+    text.delete_grapheme(0, 1)?;
+    assert_eq!(text.to_string(), format!("x{FAMILY}"));
+    assert_eq!(text.slice_grapheme(1, 2)?, FAMILY);
+    assert!(text.get_cursor_grapheme(0, Side::Left).is_none());
+    assert!(text.insert_grapheme(3, "x").is_err());
+    assert!(text.delete_grapheme(1, 2).is_err());
+    assert_eq!(text.to_string(), format!("x{FAMILY}"));
+    Ok(())
+}
+
+#[test]
+fn concurrent_edits_split_grapheme() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let text_a = doc_a.get_text("text");
+    text_a.insert(0, &format!("a{FAMILY}b"))?;
+    doc_a.commit();
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    let text_b = doc_b.get_text("text");
+
+    // B inserts into the middle of the ZWJ sequence while A edits around it
+    text_b.insert(3, "x")?;
+    doc_b.commit();
+    text_a.insert_grapheme(2, "c")?;
+    doc_a.commit();
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    let expected = "a👨\u{200d}x👩\u{200d}👧cb";
+    assert_eq!(text_a.to_string(), expected);
+    assert_eq!(text_b.to_string(), expected);
+
+    // The split parts are separate clusters now
+    assert_eq!(text_a.len_grapheme(), 6);
+    assert_eq!(text_a.slice_grapheme(3, 4)?, "👩\u{200d}👧");
+    text_a.delete_grapheme(1, 1)?;
+    assert_eq!(text_a.to_string(), "ax👩\u{200d}👧cb");
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    assert_eq!(text_b.to_string(), text_a.to_string());
+    Ok(())
+}
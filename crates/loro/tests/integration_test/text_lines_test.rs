@@ -0,0 +1,200 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{ExportMode, LineChange, LoroDoc, LoroText};
+use pretty_assertions::assert_eq;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn lines_of(text: &LoroText) -> Vec<String> {
+    (0..text.line_count())
+        .map(|i| text.slice_lines(i..i + 1).unwrap())
+        .collect()
+}
+
+fn check_lines(text: &LoroText) {
+    let s = text.to_string();
+    assert_eq!(text.line_count(), s.matches('\n').count() + 1);
+    let mut line = 0;
+    let mut col = 0;
+    let mut col_utf8 = 0;
+    let mut col_utf16 = 0;
+    let (mut pos, mut pos_utf8, mut pos_utf16) = (0, 0, 0);
+    assert_eq!(text.line_to_pos(0), Some(0));
+    for c in s.chars().chain(std::iter::once('\0')) {
+        assert_eq!(text.pos_to_line_col(pos), Some((line, col)));
+        assert_eq!(text.pos_to_line_col_utf8(pos_utf8), Some((line, col_utf8)));
+        assert_eq!(
+            text.pos_to_line_col_utf16(pos_utf16),
+            Some((line, col_utf16))
+        );
+        if c == '\0' {
+            break;
+        }
+
+        pos += 1;
+        pos_utf8 += c.len_utf8();
+        pos_utf16 += c.len_utf16();
+        if c == '\n' {
+            line += 1;
+            (col, col_utf8, col_utf16) = (0, 0, 0);
+            assert_eq!(text.line_to_pos(line), Some(pos));
+            assert_eq!(text.line_to_pos_utf8(line), Some(pos_utf8));
+            assert_eq!(text.line_to_pos_utf16(line), Some(pos_utf16));
+        } else {
+            col += 1;
+            col_utf8 += c.len_utf8();
+            col_utf16 += c.len_utf16();
+        }
+    }
+
+    assert_eq!(text.line_to_pos(line + 1), None);
+    assert_eq!(text.pos_to_line_col(pos + 1), None);
+    assert_eq!(lines_of(text).concat(), s);
+}
+
+#[test]
+fn line_queries() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    check_lines(&text);
+    assert_eq!(text.line_count(), 1);
+    assert_eq!(text.slice_lines(0..1)?, "");
+    assert!(text.slice_lines(0..2).is_err());
+
+    text.insert(0, "a\n")?;
+    assert_eq!(text.line_count(), 2);
+    assert_eq!(text.pos_to_line_col(2), Some((1, 0)));
+    assert_eq!(lines_of(&text), vec!["a\n", ""]);
+
+    text.insert(2, "😀 b\n\nc")?;
+    text.mark(1..5, "bold", true)?;
+    check_lines(&text);
+    assert_eq!(text.pos_to_line_col_utf16(6), Some((1, 4)));
+    assert_eq!(lines_of(&text), vec!["a\n", "😀 b\n", "\n", "c"]);
+    Ok(())
+}
+
+#[test]
+fn line_queries_after_random_edits() -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    let pieces = ["\n", "ab", "\n\n", "你好\n", "😀", "x\ny"];
+    for i in 0..300 {
+        let len = text.len_unicode();
+        if len > 0 && rng.gen_bool(0.3) {
+            let pos = rng.gen_range(0..len);
+            let del = rng.gen_range(1..=(len - pos).min(5));
+            text.delete(pos, del)?;
+        } else {
+            let pos = rng.gen_range(0..=len);
+            text.insert(pos, pieces[rng.gen_range(0..pieces.len())])?;
+        }
+        if i % 50 == 0 && text.len_unicode() > 2 {
+            text.mark(1..text.len_unicode() - 1, "bold", i % 100 == 0)?;
+        }
+    }
+
+    check_lines(&text);
+    let snapshot = LoroDoc::new();
+    snapshot.import(&doc.export(ExportMode::Snapshot)?)?;
+    check_lines(&snapshot.get_text("text"));
+    Ok(())
+}
+
+#[test]
+fn line_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "one\ntwo\nthree\nfour")?;
+    let other = LoroDoc::new();
+    other.set_peer_id(2)?;
+    other.import(&doc.export(ExportMode::all_updates())?)?;
+
+    let changes = Arc::new(Mutex::new(Vec::<Vec<LineChange>>::new()));
+    let changes_clone = changes.clone();
+    let _sub = text.subscribe_lines(Arc::new(move |e| {
+        changes_clone.lock().unwrap().push(e.to_vec());
+    }))?;
+    let mut mirror = lines_of(&text);
+    let mut check = |expected: Option<Vec<LineChange>>| {
+        let batches = std::mem::take(&mut *changes.lock().unwrap());
+        for batch in batches.iter() {
+            for change in batch.iter().rev() {
+                let new_lines = change
+                    .new_lines
+                    .clone()
+                    .map(|i| text.slice_lines(i..i + 1).unwrap());
+                mirror.splice(change.old_lines.clone(), new_lines);
+            }
+        }
+        assert_eq!(mirror, lines_of(&text));
+        if let Some(expected) = expected {
+            assert_eq!(batches, vec![expected]);
+        }
+    };
+
+    // Several edits in one transaction
+    text.insert(0, "zero\n")?;
+    text.delete(9, 4)?;
+    text.insert(text.len_unicode(), "!")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "zero\none\nthree\nfour!");
+    check(Some(vec![
+        LineChange {
+            old_lines: 0..1,
+            new_lines: 0..2,
+        },
+        LineChange {
+            old_lines: 1..3,
+            new_lines: 2..3,
+        },
+        LineChange {
+            old_lines: 3..4,
+            new_lines: 3..4,
+        },
+    ]));
+
+    // Edits on the same line are merged
+    text.insert(6, "n")?;
+    text.insert(1, "\n")?;
+    text.insert(8, "\n")?;
+    doc.commit();
+    check(None);
+
+    // Style changes don't change the lines
+    text.mark(0..3, "bold", true)?;
+    doc.commit();
+    assert!(changes.lock().unwrap().is_empty());
+
+    // Remote changes
+    other.get_text("text").insert(0, "a\nb")?;
+    other.get_text("text").delete(6, 5)?;
+    other.commit();
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    check(None);
+    Ok(())
+}
+
+#[test]
+fn line_events_of_pending_ops() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "a\nb")?;
+    let changes = Arc::new(Mutex::new(Vec::<LineChange>::new()));
+    let changes_clone = changes.clone();
+    let _sub = text.subscribe_lines(Arc::new(move |e| {
+        changes_clone.lock().unwrap().extend_from_slice(e);
+    }))?;
+    // Subscribing doesn't commit the pending ops
+    assert_eq!(doc.get_pending_txn_len(), 3);
+    doc.commit();
+    assert_eq!(
+        *changes.lock().unwrap(),
+        vec![LineChange {
+            old_lines: 0..1,
+            new_lines: 0..2,
+        }]
+    );
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use loro::{LoroDoc, LoroText};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn find_in_different_indexes() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "😀 ab ab, aab")?;
+    assert_eq!(text.find("ab"), vec![2..4, 5..7, 10..12]);
+    assert_eq!(text.find_utf8("ab"), vec![5..7, 8..10, 13..15]);
+    assert_eq!(text.find_utf16("ab"), vec![3..5, 6..8, 11..13]);
+    assert_eq!(text.find("b, a"), vec![6..10]);
+    assert!(text.find("abc").is_empty());
+    assert!(text.find("").is_empty());
+
+    // An inline embed occupies one position
+    text.insert_embed(0, "image")?;
+    assert_eq!(text.find("ab"), vec![3..5, 6..8, 11..13]);
+    Ok(())
+}
+
+#[test]
+fn replace_all_keeps_marks_outside_replaced_ranges() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "The cat sat on the cat mat")?;
+    text.mark(0..7, "bold", true)?;
+    text.mark(15..26, "italic", true)?;
+    doc.commit();
+
+    let events = Arc::new(AtomicUsize::new(0));
+    let events_clone = events.clone();
+    let _sub = doc.subscribe_root(Arc::new(move |_| {
+        events_clone.fetch_add(1, Ordering::SeqCst);
+    }));
+    let ops = doc.len_ops();
+    assert_eq!(text.replace_all("cat", "dog")?, 2);
+    doc.commit();
+    assert_eq!(text.to_string(), "The dog sat on the dog mat");
+    assert_eq!(events.load(Ordering::SeqCst), 1);
+    // Each match is replaced by one deletion and one insertion
+    assert_eq!(doc.len_ops() - ops, 2 * (3 + 3));
+    assert_eq!(text.find_style_ranges("bold"), vec![(0..7, true.into())]);
+    assert_eq!(
+        text.find_style_ranges("italic"),
+        vec![(15..26, true.into())]
+    );
+
+    // The common prefix and suffix of a match and its replacement are kept
+    let ops = doc.len_ops();
+    assert_eq!(text.replace_all("dog", "dig")?, 2);
+    doc.commit();
+    assert_eq!(text.to_string(), "The dig sat on the dig mat");
+    assert_eq!(doc.len_ops() - ops, 2 * 2);
+
+    // Nothing changes if the replacement is the same
+    let ops = doc.len_ops();
+    assert_eq!(text.replace_all("dig", "dig")?, 2);
+    assert_eq!(text.replace_all("fox", "dog")?, 0);
+    doc.commit();
+    assert_eq!(doc.len_ops(), ops);
+    Ok(())
+}
+
+#[test]
+fn replace_all_with_undo() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    let mut undo = loro::UndoManager::new(&doc);
+    text.insert(0, "a-b-c")?;
+    doc.commit();
+    text.replace_all("-", ", ")?;
+    doc.commit();
+    assert_eq!(text.to_string(), "a, b, c");
+    undo.undo()?;
+    assert_eq!(text.to_string(), "a-b-c");
+    Ok(())
+}
+
+#[test]
+fn replace_all_in_detached_text() -> anyhow::Result<()> {
+    let text = LoroText::new();
+    text.insert(0, "one two one")?;
+    assert_eq!(text.replace_all("one", "1")?, 2);
+    assert_eq!(text.to_string(), "1 two 1");
+    assert_eq!(text.find("two"), vec![2..5]);
+
+    let doc = LoroDoc::new();
+    let text = doc.get_map("map").insert_container("text", text)?;
+    assert_eq!(text.to_string(), "1 two 1");
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn find_and_replace_with_regex() -> anyhow::Result<()> {
+    use loro::regex::Regex;
+
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "2024-01-02 and 2025-12-31")?;
+    text.mark(11..14, "bold", true)?;
+    let re = Regex::new(r"(\d{4})-(\d{2})-(\d{2})")?;
+    assert_eq!(text.find(&re), vec![0..10, 15..25]);
+
+    let expected = re.replace_all(&text.to_string(), "$3/$2/$1").to_string();
+    assert_eq!(text.replace_all(&re, "$3/$2/$1")?, 2);
+    assert_eq!(text.to_string(), expected);
+    assert_eq!(text.to_string(), "02/01/2024 and 31/12/2025");
+    assert_eq!(text.find_style_ranges("bold"), vec![(11..14, true.into())]);
+
+    // Empty matches insert the replacement
+    let re = Regex::new(r"\b")?;
+    text.delete(0, text.len_unicode())?;
+    text.insert(0, "a b")?;
+    let expected = re.replace_all(&text.to_string(), "|").to_string();
+    text.replace_all(&re, "|")?;
+    assert_eq!(text.to_string(), expected);
+    assert_eq!(text.to_string(), "|a| |b|");
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use loro::LoroDoc;
+use loro::{LoroDoc, LoroError, UpdateFromBaseError};
 
 #[test]
 fn test_text_update() -> anyhow::Result<()> {
@@ -157,3 +157,135 @@ fn test_failed_case_2() -> anyhow::Result<()> {
     assert_eq!(&text.to_string(), new1);
     Ok(())
 }
+
+#[test]
+fn test_text_update_from_base() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let text = doc.get_text("text");
+    text.insert(0, "one\ntwo\nthree\n")?;
+    doc.commit();
+    let base = doc.state_frontiers();
+
+    // A concurrent edit from another peer after the file was read
+    let other = LoroDoc::new();
+    other.set_peer_id(2)?;
+    other.import(&doc.export(loro::ExportMode::all_updates())?)?;
+    other.get_text("text").delete(4, 3)?;
+    other.get_text("text").insert(4, "TWO")?;
+    doc.import(&other.export(loro::ExportMode::all_updates())?)?;
+    assert_eq!(text.to_string(), "one\nTWO\nthree\n");
+
+    // The file edited from `base` is merged with the concurrent edit
+    text.update_from_base(&base, "zero\none\ntwo\nthree\nfour\n")?;
+    assert_eq!(text.to_string(), "zero\none\nTWO\nthree\nfour\n");
+    assert_eq!(doc.peer_id(), 1);
+    // The edits are made by a new peer
+    assert_eq!(doc.oplog_vv().len(), 3);
+    other.import(&doc.export(loro::ExportMode::all_updates())?)?;
+    assert_eq!(other.get_text("text").to_string(), text.to_string());
+
+    // An unchanged file doesn't revert the edits after `base`
+    text.update_from_base(&base, "one\ntwo\nthree\n")?;
+    assert_eq!(text.to_string(), "zero\none\nTWO\nthree\nfour\n");
+
+    // Updating from the latest version is the same as `update`
+    text.update_from_base(&doc.state_frontiers(), "one\n")?;
+    assert_eq!(text.to_string(), "one\n");
+    Ok(())
+}
+
+#[test]
+fn test_text_update_from_invalid_base() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello")?;
+    doc.commit();
+    let base = loro::Frontiers::from_id(loro::ID::new(42, 0));
+    assert!(matches!(
+        text.update_from_base(&base, "World"),
+        Err(UpdateFromBaseError::Loro(LoroError::FrontiersNotFound(_)))
+    ));
+    assert!(matches!(
+        loro::LoroText::new().update_from_base(&doc.state_frontiers(), "World"),
+        Err(UpdateFromBaseError::Loro(
+            LoroError::MisuseDetachedContainer { .. }
+        ))
+    ));
+    assert_eq!(text.to_string(), "Hello");
+    Ok(())
+}
+
+#[test]
+fn test_text_update_by_word_and_sentence() -> anyhow::Result<()> {
+    use loro::{UpdateGranularity, UpdateOptions};
+
+    let word = UpdateOptions::default().granularity(UpdateGranularity::Word);
+    let sentence = UpdateOptions::default().granularity(UpdateGranularity::Sentence);
+    let doc = LoroDoc::new();
+    let text = doc.get_text("text");
+    for target in [
+        "the cat sat",
+        "a dog sat",
+        "",
+        "你好，世界！ 😀 Hello world.",
+        "Hello there. How are you? Fine.",
+        "Hello there. How old are you? Fine.\n",
+    ] {
+        for options in [word.clone(), sentence.clone()] {
+            text.update("The quick brown fox.", Default::default())?;
+            text.update(target, options)?;
+            assert_eq!(text.to_string(), target);
+        }
+    }
+
+    // The tiny unchanged space is merged, so the phrase is replaced as a whole
+    text.update("the cat sat", Default::default())?;
+    doc.commit();
+    let ops = doc.len_ops();
+    text.update("a dog sat", word.clone())?;
+    doc.commit();
+    assert_eq!(text.to_string(), "a dog sat");
+    assert_eq!(doc.len_ops() - ops, "the cat".len() + "a dog".len());
+
+    // The changed sentence is replaced as a whole
+    text.update("Hello there. How are you? Fine.", Default::default())?;
+    doc.commit();
+    let ops = doc.len_ops();
+    text.update("Hello there. How old are you? Fine.", sentence)?;
+    doc.commit();
+    assert_eq!(
+        doc.len_ops() - ops,
+        "How are you? ".len() + "How old are you? ".len()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_concurrent_text_update_by_word() -> anyhow::Result<()> {
+    use loro::{ExportMode, UpdateGranularity, UpdateOptions};
+
+    let options = UpdateOptions::default().granularity(UpdateGranularity::Word);
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    doc_a.get_text("text").insert(0, "The color sky")?;
+    doc_a.commit();
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+
+    doc_a
+        .get_text("text")
+        .update("The colour sky", options.clone())?;
+    doc_b.get_text("text").update("The hue sky", options)?;
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    let result = doc_a.get_text("text").to_string();
+    assert_eq!(result, doc_b.get_text("text").to_string());
+    // The words are not interleaved
+    assert!(
+        result == "The colourhue sky" || result == "The huecolour sky",
+        "{result}"
+    );
+    Ok(())
+}
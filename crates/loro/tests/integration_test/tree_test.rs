@@ -0,0 +1,564 @@
+use std::sync::{Arc, Mutex};
+
+use loro::{
+    ContainerTrait, ExportMode, LoroDoc, LoroList, LoroMap, LoroText, LoroTree, LoroValue, ToJson,
+    TreeDiff, TreeExternalDiff, TreeID, TreeMoveRecord, TreeParentId, UndoManager,
+};
+use pretty_assertions::assert_eq;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// The deep value of the subtree under `node`, without the tree ids
+fn subtree_value(tree: &LoroTree, node: TreeID) -> serde_json::Value {
+    let children = tree
+        .children(node)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| subtree_value(tree, child))
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "meta": tree.get_meta(node).unwrap().get_deep_value().to_json_value(),
+        "children": children,
+    })
+}
+
+#[test]
+fn duplicate_subtree_with_meta_containers() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    let section = tree.create(None)?;
+    let para_1 = tree.create(section)?;
+    let para_2 = tree.create(section)?;
+    let leaf = tree.create(para_2)?;
+    let other = tree.create(None)?;
+
+    let meta = tree.get_meta(section)?;
+    meta.insert("heading", true)?;
+    let title = meta.insert_container("title", LoroText::new())?;
+    title.insert(0, "Intro")?;
+    title.mark(0..5, "bold", true)?;
+    let tags = tree
+        .get_meta(para_1)?
+        .insert_container("tags", LoroList::new())?;
+    tags.push("a")?;
+    let nested = tags.push_container(LoroMap::new())?;
+    nested.insert("key", "value")?;
+    tree.get_meta(leaf)?.insert("depth", 2)?;
+    doc.commit();
+
+    let mut undo = UndoManager::new(&doc);
+    let before = tree.get_value();
+    let mapping = tree.duplicate_subtree(section, None, 1)?;
+    doc.commit();
+    assert_eq!(mapping.len(), 4);
+    let new_section = mapping[&section];
+    assert_eq!(tree.roots(), vec![section, new_section, other]);
+    assert_eq!(
+        tree.children(new_section),
+        Some(vec![mapping[&para_1], mapping[&para_2]])
+    );
+    assert_eq!(tree.children(mapping[&para_2]), Some(vec![mapping[&leaf]]));
+    assert_eq!(
+        subtree_value(&tree, new_section),
+        subtree_value(&tree, section)
+    );
+
+    // The copied containers are independent
+    let new_title = tree
+        .get_meta(new_section)?
+        .get("title")
+        .unwrap()
+        .into_container()
+        .unwrap()
+        .into_text()
+        .unwrap();
+    assert_ne!(new_title.id(), title.id());
+    assert_eq!(new_title.get_richtext_value(), title.get_richtext_value());
+    new_title.insert(0, "New ")?;
+    assert_eq!(title.to_string(), "Intro");
+
+    // Other peers see the same copy
+    let other_doc = LoroDoc::new();
+    other_doc.import(&doc.export(ExportMode::Snapshot)?)?;
+    assert_eq!(
+        other_doc.get_tree("tree").get_value_with_meta(),
+        tree.get_value_with_meta()
+    );
+
+    // It's undone in one step
+    doc.commit();
+    undo.undo()?;
+    undo.undo()?;
+    assert_eq!(tree.get_value(), before);
+    Ok(())
+}
+
+#[test]
+fn duplicate_subtree_into_itself() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    let root = tree.create(None)?;
+    let child = tree.create(root)?;
+    tree.get_meta(child)?.insert("name", "child")?;
+
+    let mapping = tree.duplicate_subtree(root, child, 0)?;
+    assert_eq!(mapping.len(), 2);
+    assert_eq!(tree.children(child), Some(vec![mapping[&root]]));
+    assert_eq!(tree.children(mapping[&root]), Some(vec![mapping[&child]]));
+    assert!(tree
+        .children(mapping[&child])
+        .unwrap_or_default()
+        .is_empty());
+    assert_eq!(
+        tree.get_meta(mapping[&child])?.get_deep_value(),
+        tree.get_meta(child)?.get_deep_value()
+    );
+    Ok(())
+}
+
+#[test]
+fn duplicate_subtree_errors() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let root = tree.create(None)?;
+    // Fractional index is required to insert at the index
+    assert!(tree.duplicate_subtree(root, None, 0).is_err());
+
+    tree.enable_fractional_index(0);
+    let deleted = tree.create(None)?;
+    tree.delete(deleted)?;
+    assert!(tree.duplicate_subtree(deleted, None, 0).is_err());
+    assert!(tree.duplicate_subtree(root, deleted, 0).is_err());
+    assert!(tree.duplicate_subtree(root, None, 2).is_err());
+    assert!(tree
+        .duplicate_subtree(root, TreeParentId::Deleted, 0)
+        .is_err());
+    assert_eq!(tree.roots(), vec![root]);
+
+    let detached = LoroTree::new();
+    let node = detached.create(None)?;
+    assert!(detached.duplicate_subtree(node, None, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn children_sorted_by_meta_value() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let root = tree.create(None)?;
+    let nodes: Vec<TreeID> = (0..6).map(|_| tree.create(root).unwrap()).collect();
+    let info = |i: usize| {
+        tree.get_meta(nodes[i])
+            .unwrap()
+            .insert_container("info", LoroMap::new())
+            .unwrap()
+    };
+    info(0).insert("rank", 2.5)?;
+    info(1).insert("rank", "b")?;
+    info(2).insert("rank", 1)?;
+    // No rank
+    info(3);
+    info(4).insert("rank", 2.5)?;
+    let title = info(5).insert_container("rank", LoroText::new())?;
+    title.insert(0, "a")?;
+    doc.commit();
+
+    // Null < number < string, and the ties keep the order of the tree
+    assert_eq!(
+        tree.children_sorted_by(root, &["info", "rank"])?.children(),
+        vec![nodes[3], nodes[2], nodes[0], nodes[4], nodes[5], nodes[1]]
+    );
+    // A missing path sorts nothing
+    assert_eq!(
+        tree.children_sorted_by(root, &["missing"])?.children(),
+        tree.children(root).unwrap()
+    );
+    assert_eq!(
+        tree.children_sorted_by(root, &[])?.children(),
+        tree.children(root).unwrap()
+    );
+
+    // The view needs the events of a doc and an existing parent
+    let detached = LoroTree::new();
+    detached.create(None)?;
+    assert!(detached.children_sorted_by(None, &["name"]).is_err());
+    let other = LoroDoc::new().get_tree("tree");
+    assert!(other.children_sorted_by(root, &["name"]).is_err());
+    Ok(())
+}
+
+#[test]
+fn children_sorted_by_mixed_numbers() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    let root = tree.create(None)?;
+    let nodes: Vec<TreeID> = (0..7).map(|_| tree.create(root).unwrap()).collect();
+    let values: [LoroValue; 7] = [
+        // 2^53 + 1 is rounded to 2^53 as a double
+        9_007_199_254_740_993i64.into(),
+        9_007_199_254_740_992f64.into(),
+        9_007_199_254_740_992i64.into(),
+        0.5.into(),
+        0i64.into(),
+        (-0.0).into(),
+        0.0.into(),
+    ];
+    for (node, value) in nodes.iter().zip(values) {
+        tree.get_meta(*node)?.insert("n", value)?;
+    }
+    doc.commit();
+
+    // The integers and the doubles are compared exactly, and the ties keep the order of
+    // the tree
+    assert_eq!(
+        tree.children_sorted_by(root, &["n"])?.children(),
+        vec![nodes[5], nodes[4], nodes[6], nodes[3], nodes[1], nodes[2], nodes[0]]
+    );
+    Ok(())
+}
+
+#[test]
+fn sorted_children_events() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1)?;
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    let root = tree.create(None)?;
+    let other_root = tree.create(None)?;
+    let nodes: Vec<TreeID> = (0..4).map(|_| tree.create(root).unwrap()).collect();
+    for (i, name) in ["d", "b", "c", "a"].iter().enumerate() {
+        tree.get_meta(nodes[i])?.insert("name", *name)?;
+    }
+    let title = tree
+        .get_meta(nodes[0])?
+        .insert_container("title", LoroText::new())?;
+    doc.commit();
+
+    let diffs = Arc::new(Mutex::new(Vec::<TreeDiff>::new()));
+    let diffs_clone = diffs.clone();
+    let view = tree.children_sorted_by(root, &["name"])?;
+    let _sub = view.subscribe(Arc::new(move |diff| {
+        diffs_clone.lock().unwrap().push(diff.clone());
+    }));
+    let mut mirror = view.children();
+    assert_eq!(mirror, vec![nodes[3], nodes[1], nodes[2], nodes[0]]);
+    let mut check = || {
+        let mut count = 0;
+        for diff in std::mem::take(&mut *diffs.lock().unwrap()) {
+            for item in diff.diff {
+                count += 1;
+                match item.action {
+                    TreeExternalDiff::Create { index, .. } => mirror.insert(index, item.target),
+                    TreeExternalDiff::Move {
+                        index, old_index, ..
+                    } => {
+                        assert_eq!(mirror.remove(old_index), item.target);
+                        mirror.insert(index, item.target);
+                    }
+                    TreeExternalDiff::Delete { old_index, .. } => {
+                        assert_eq!(mirror.remove(old_index), item.target);
+                    }
+                }
+            }
+        }
+        assert_eq!(mirror, view.children());
+        assert_eq!(
+            mirror,
+            tree.children_sorted_by(root, &["name"]).unwrap().children()
+        );
+        count
+    };
+
+    // A change of the sort value moves the node in the view
+    tree.get_meta(nodes[0])?.insert("name", "0")?;
+    doc.commit();
+    assert_eq!(check(), 1);
+
+    // The changes that don't affect the view are skipped
+    title.insert(0, "title")?;
+    tree.get_meta(nodes[1])?.insert("other", 1)?;
+    tree.create(other_root)?;
+    // Reordering in the tree doesn't change the view
+    tree.mov_before(nodes[3], nodes[1])?;
+    doc.commit();
+    assert_eq!(check(), 0);
+
+    // Create, move in, move out and delete in one transaction
+    let new = tree.create(root)?;
+    tree.get_meta(new)?.insert("name", "bb")?;
+    let moved = tree.create(other_root)?;
+    tree.mov(moved, root)?;
+    tree.mov(nodes[2], other_root)?;
+    tree.delete(nodes[1])?;
+    doc.commit();
+    assert_eq!(check(), 4);
+
+    // Remote changes
+    let other = LoroDoc::new();
+    other.set_peer_id(2)?;
+    other.import(&doc.export(ExportMode::Snapshot)?)?;
+    let other_tree = other.get_tree("tree");
+    other_tree.get_meta(nodes[3])?.insert("name", "z")?;
+    other_tree.create(root)?;
+    doc.import(&other.export(ExportMode::all_updates())?)?;
+    assert_eq!(check(), 2);
+    assert_eq!(view.children()[4], nodes[3]);
+
+    // The pending ops are reported when they are committed
+    drop(_sub);
+    tree.get_meta(nodes[3])?.insert("name", "")?;
+    let view = tree.children_sorted_by(root, &["name"])?;
+    let diffs_clone = diffs.clone();
+    let _sub = view.subscribe(Arc::new(move |diff| {
+        diffs_clone.lock().unwrap().push(diff.clone());
+    }));
+    assert_eq!(view.children()[4], nodes[3]);
+    doc.commit();
+    assert_eq!(view.children()[0], nodes[3]);
+    assert_eq!(diffs.lock().unwrap().len(), 1);
+    Ok(())
+}
+
+/// The descendants in depth-first pre-order, collected by `children`
+fn dfs_by_children(tree: &LoroTree, parent: TreeParentId, ans: &mut Vec<TreeID>) {
+    for child in tree.children(parent).unwrap_or_default() {
+        ans.push(child);
+        dfs_by_children(tree, TreeParentId::Node(child), ans);
+    }
+}
+
+#[test]
+fn tree_traversal() -> anyhow::Result<()> {
+    let doc = LoroDoc::new();
+    let tree = doc.get_tree("tree");
+    tree.enable_fractional_index(0);
+    let root = tree.create(None)?;
+    let a = tree.create(root)?;
+    let b = tree.create(root)?;
+    let a_1 = tree.create(a)?;
+    let a_2 = tree.create(a)?;
+    let a_1_1 = tree.create(a_1)?;
+    let b_1 = tree.create(b)?;
+    let other_root = tree.create(None)?;
+    tree.mov_before(b, a)?;
+
+    assert_eq!(
+        tree.descendants_dfs(root).collect::<Vec<_>>(),
+        vec![b, b_1, a, a_1, a_1_1, a_2]
+    );
+    assert_eq!(
+        tree.descendants_bfs(root).collect::<Vec<_>>(),
+        vec![b, a, b_1, a_1, a_2, a_1_1]
+    );
+    let mut expected = Vec::new();
+    dfs_by_children(&tree, TreeParentId::Root, &mut expected);
+    assert_eq!(tree.descendants_dfs(None).collect::<Vec<_>>(), expected);
+    assert_eq!(tree.descendants_bfs(None).count(), expected.len());
+    assert_eq!(tree.descendants_dfs(a_2).count(), 0);
+
+    assert_eq!(
+        tree.ancestors(a_1_1).collect::<Vec<_>>(),
+        vec![a_1, a, root]
+    );
+    assert_eq!(tree.ancestors(root).count(), 0);
+    assert_eq!(tree.depth(root), Some(0));
+    assert_eq!(tree.depth(a_1_1), Some(3));
+    assert_eq!(tree.subtree_size(root), Some(7));
+    assert_eq!(tree.subtree_size(a), Some(4));
+    assert_eq!(tree.subtree_size(a_2), Some(1));
+
+    assert_eq!(tree.lowest_common_ancestor(a_1_1, a_2), Some(a));
+    assert_eq!(tree.lowest_common_ancestor(a_1_1, b_1), Some(root));
+    assert_eq!(tree.lowest_common_ancestor(a, a_1_1), Some(a));
+    assert_eq!(tree.lowest_common_ancestor(a_2, a_2), Some(a_2));
+    assert_eq!(tree.lowest_common_ancestor(a, other_root), None);
+
+    // The deleted nodes keep their subtree
+    tree.delete(a)?;
+    assert_eq!(tree.descendants_dfs(root).collect::<Vec<_>>(), vec![b, b_1]);
+    assert_eq!(tree.ancestors(a_1_1).collect::<Vec<_>>(), vec![a_1, a]);
+    assert_eq!(tree.subtree_size(a), Some(4));
+    assert_eq!(tree.lowest_common_ancestor(a_1_1, b_1), None);
+
+    // The nodes that don't exist
+    let unknown = TreeID::new(100, 0);
+    assert_eq!(tree.ancestors(unknown).count(), 0);
+    assert_eq!(tree.depth(unknown), None);
+    assert_eq!(tree.subtree_size(unknown), None);
+    assert_eq!(tree.lowest_common_ancestor(unknown, root), None);
+    Ok(())
+}
+
+#[test]
+fn tree_traversal_in_detached_tree() -> anyhow::Result<()> {
+    let tree = LoroTree::new();
+    let root = tree.create(None)?;
+    let a = tree.create(root)?;
+    let b = tree.create(root)?;
+    let a_1 = tree.create(a)?;
+    assert_eq!(
+        tree.descendants_dfs(root).collect::<Vec<_>>(),
+        vec![a, a_1, b]
+    );
+    assert_eq!(
+        tree.descendants_bfs(None).collect::<Vec<_>>(),
+        vec![root, a, b, a_1]
+    );
+    assert_eq!(tree.depth(a_1), Some(2));
+    assert_eq!(tree.subtree_size(root), Some(4));
+    assert_eq!(tree.lowest_common_ancestor(a_1, b), Some(root));
+    Ok(())
+}
+
+#[test]
+fn move_conflicts() -> anyhow::Result<()> {
+    let doc_a = LoroDoc::new();
+    doc_a.set_peer_id(1)?;
+    let tree_a = doc_a.get_tree("tree");
+    let x = tree_a.create(None)?;
+    let y = tree_a.create(None)?;
+    let z = tree_a.create(None)?;
+    let doc_b = LoroDoc::new();
+    doc_b.set_peer_id(2)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    let tree_b = doc_b.get_tree("tree");
+
+    let subscribe = |tree: &LoroTree| {
+        let conflicts = Arc::new(Mutex::new(Vec::<TreeMoveRecord>::new()));
+        let conflicts_clone = conflicts.clone();
+        let sub = tree
+            .subscribe_move_conflicts(Arc::new(move |e| {
+                conflicts_clone.lock().unwrap().extend_from_slice(e);
+            }))
+            .unwrap();
+        (conflicts, sub)
+    };
+    let (conflicts_a, _sub_a) = subscribe(&tree_a);
+    let (conflicts_b, _sub_b) = subscribe(&tree_b);
+
+    // A local move that would create a cycle fails
+    tree_a.mov(x, y)?;
+    assert!(tree_a.mov(y, x).is_err());
+    doc_a.commit();
+    assert!(conflicts_a.lock().unwrap().is_empty());
+    let before_sync = doc_a.oplog_frontiers();
+
+    tree_b.mov(y, x)?;
+    doc_b.commit();
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    doc_b.import(&doc_a.export(ExportMode::all_updates())?)?;
+    assert_eq!(tree_a.get_value(), tree_b.get_value());
+    assert_eq!(tree_a.parent(x), Some(TreeParentId::Node(y)));
+    assert_eq!(tree_a.parent(y), Some(TreeParentId::Root));
+
+    // The move of peer 2 has a greater peer, so it's applied later and rejected
+    let rejected = TreeMoveRecord {
+        id: tree_b.history_of_node(y)[1].id,
+        lamport: 3,
+        target: y,
+        parent: TreeParentId::Node(x),
+        applied: false,
+    };
+    assert_eq!(rejected.id.peer, 2);
+    assert_eq!(*conflicts_a.lock().unwrap(), vec![rejected.clone()]);
+    assert_eq!(*conflicts_b.lock().unwrap(), vec![rejected.clone()]);
+    assert_eq!(
+        tree_a.history_of_node(y),
+        vec![
+            TreeMoveRecord {
+                id: tree_a.get_last_move_id(&y).unwrap(),
+                lamport: 1,
+                target: y,
+                parent: TreeParentId::Root,
+                applied: true,
+            },
+            rejected
+        ]
+    );
+    assert_eq!(tree_a.history_of_node(y), tree_b.history_of_node(y));
+
+    // The conflicts are reported once
+    conflicts_a.lock().unwrap().clear();
+    tree_b.mov(z, x)?;
+    doc_a.import(&doc_b.export(ExportMode::all_updates())?)?;
+    assert!(conflicts_a.lock().unwrap().is_empty());
+
+    // The history is relative to the version of the state, and checkouts don't report
+    // the conflicts again
+    assert!(LoroTree::new().history_of_node(x).is_empty());
+    doc_a.checkout(&before_sync)?;
+    assert_eq!(tree_a.history_of_node(y).len(), 1);
+    doc_a.checkout_to_latest();
+    assert_eq!(tree_a.history_of_node(y).len(), 2);
+    assert!(conflicts_a.lock().unwrap().is_empty());
+
+    // The pending ops are not in the history until they are committed
+    tree_a.mov(y, z)?;
+    assert_eq!(tree_a.history_of_node(y).len(), 2);
+    doc_a.commit();
+    assert_eq!(tree_a.history_of_node(y).len(), 3);
+
+    // After a shallow snapshot, the history starts from the shallow root
+    let shallow = LoroDoc::new();
+    shallow.import(&doc_a.export(ExportMode::shallow_snapshot(&doc_a.oplog_frontiers()))?)?;
+    let shallow_tree = shallow.get_tree("tree");
+    let history = shallow_tree.history_of_node(y);
+    assert_eq!(history.len(), 1);
+    assert!(history[0].applied);
+    assert_eq!(history[0].parent, TreeParentId::Node(z));
+    assert_eq!(Some(history[0].id), shallow_tree.get_last_move_id(&y));
+    Ok(())
+}
+
+#[test]
+fn history_of_node_matches_tree_state() -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(7);
+    let docs: Vec<LoroDoc> = (0..3)
+        .map(|i| {
+            let doc = LoroDoc::new();
+            doc.set_peer_id(i + 1).unwrap();
+            doc
+        })
+        .collect();
+    let mut nodes = Vec::new();
+    for _ in 0..8 {
+        nodes.push(docs[0].get_tree("tree").create(None)?);
+    }
+    for round in 0..20 {
+        for doc in docs.iter() {
+            if round > 0 {
+                doc.import(&docs[0].export(ExportMode::all_updates())?)?;
+            }
+            let tree = doc.get_tree("tree");
+            for _ in 0..3 {
+                let target = nodes[rng.gen_range(0..nodes.len())];
+                let parent = nodes[rng.gen_range(0..nodes.len())];
+                if rng.gen_bool(0.1) {
+                    let _ = tree.delete(target);
+                } else if rng.gen_bool(0.2) {
+                    let _ = tree.mov(target, None);
+                } else {
+                    // Cycles fail locally
+                    let _ = tree.mov(target, parent);
+                }
+            }
+            doc.commit();
+        }
+        for doc in docs[1..].iter() {
+            docs[0].import(&doc.export(ExportMode::all_updates())?)?;
+        }
+    }
+
+    let tree = docs[0].get_tree("tree");
+    let mut rejected = 0;
+    for node in nodes.iter() {
+        let history = tree.history_of_node(*node);
+        let last = history.iter().rev().find(|x| x.applied).unwrap();
+        assert_eq!(Some(last.id), tree.get_last_move_id(node));
+        assert_eq!(Some(last.parent), tree.parent(*node));
+        rejected += history.iter().filter(|x| !x.applied).count();
+    }
+    assert!(rejected > 0);
+    Ok(())
+}
@@ -635,6 +635,7 @@ fn undo_richtext_conflict_set_style() -> LoroResult<()> {
         "color".into(),
         StyleConfig {
             expand: loro::ExpandType::After,
+            ..Default::default()
         },
     );
     doc_a.config_text_style(config.clone());
@@ -799,6 +799,28 @@ fn get_cursor() {
     assert_eq!(pos_info.current.pos, 5); // should not be affected by rich text mark
 }
 
+#[test]
+fn get_cursor_of_style_anchors() {
+    let doc = LoroDoc::new();
+    doc.set_peer_id(1).unwrap();
+    let text = doc.get_text("text");
+    text.insert(0, "Hello world").unwrap();
+    // The style start op is 1@11 and the style end op is 1@12
+    text.mark(0..5, "bold", true).unwrap();
+    text.insert(0, "Hi, ").unwrap();
+    doc.commit();
+    let cursor_at = |counter| {
+        loro_internal::cursor::Cursor::new(
+            Some(ID::new(1, counter)),
+            text.id(),
+            loro_internal::cursor::Side::Middle,
+            0,
+        )
+    };
+    assert_eq!(doc.get_cursor_pos(&cursor_at(11)).unwrap().current.pos, 4);
+    assert_eq!(doc.get_cursor_pos(&cursor_at(12)).unwrap().current.pos, 9);
+}
+
 #[test]
 fn get_cursor_at_the_end() {
     let doc = LoroDoc::new();
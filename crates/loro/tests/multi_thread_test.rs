@@ -49,6 +49,7 @@ mod loom_test {
                         match &v {
                             loro::TextDelta::Retain { .. } => unreachable!(),
                             loro::TextDelta::Delete { .. } => unreachable!(),
+                            loro::TextDelta::Embed { .. } => unreachable!(),
                             loro::TextDelta::Insert { insert, .. } => {
                                 count_clone.fetch_add(insert.len(), Ordering::SeqCst);
                             }